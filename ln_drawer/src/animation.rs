@@ -145,6 +145,8 @@ impl<T: AnimationEasingType> Element for Animation<T> {
                 })
             })),
             draw: None,
+            bounding: None,
+            clip: None,
         });
 
         world.dependency(control, this);
@@ -1,2 +1,26 @@
 pub mod transform;
 pub mod luni;
+
+/// Which way text runs, and so where the logical start and end of a layout are. Spread
+/// down a layout tree by [`WidgetDirection`].
+///
+/// [`WidgetDirection`]: crate::widgets::WidgetDirection
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum WritingDirection {
+    #[default]
+    LeftToRight,
+    RightToLeft,
+}
+
+impl WritingDirection {
+    pub fn is_rtl(self) -> bool {
+        self == WritingDirection::RightToLeft
+    }
+
+    pub fn flip(self) -> WritingDirection {
+        match self {
+            WritingDirection::LeftToRight => WritingDirection::RightToLeft,
+            WritingDirection::RightToLeft => WritingDirection::LeftToRight,
+        }
+    }
+}
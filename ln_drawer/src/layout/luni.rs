@@ -1,13 +1,27 @@
+use hashbrown::HashMap;
 use ln_world::{Element, Handle, World};
 
 use crate::{
-    measures::Rectangle,
-    widgets::{WidgetAnimatedRectangle, WidgetRectangle},
+    layout::WritingDirection,
+    measures::{Rectangle, Size},
+    widgets::{WidgetAnimatedRectangle, WidgetDirection, WidgetRectangle},
 };
 
+/// Nested resizes of one flex by its children before they are taken as a cycle.
+const MAX_PASSES: u32 = 16;
+
+/// Lays the children out in the rectangle of the parent, sent to it by [`WidgetRectangle`].
+///
+/// Children hugging their own children, see [`LuniHug`], are sized after them instead of
+/// their `basis` and `cross`.
 pub struct LuniFlex {
     pub parent: (Handle, LuniParent),
     pub children: Vec<(Handle, LuniChild)>,
+    inherited: WritingDirection,
+    rect: Option<Rectangle>,
+    intrinsic: HashMap<Handle, LuniIntrinsic>,
+    reported: Option<LuniIntrinsic>,
+    passes: u32,
 }
 
 #[derive(Default)]
@@ -16,6 +30,10 @@ pub struct LuniParent {
     pub distribution: LuniDistribution,
     pub padding: LuniRect,
     pub template: LuniChildTemplate,
+    /// Overrides the direction the parent is sent by [`WidgetDirection`], for the children
+    /// and everything laid out within them.
+    pub direction: Option<WritingDirection>,
+    pub hug: Option<LuniHug>,
 }
 
 #[derive(Default)]
@@ -44,8 +62,10 @@ pub struct LuniChild {
 
 #[derive(Default, Clone, Copy)]
 pub enum LuniAxis {
+    /// From the start to the end, left to right unless the direction is right to left.
     #[default]
     Row,
+    /// From the end to the start.
     RowReverse,
     Column,
     ColumnReverse,
@@ -62,14 +82,20 @@ pub enum LuniDistribution {
     SpaceEvenly,
 }
 
+/// `start` and `end` are added to `left` and `right`, or the other way around when right to
+/// left.
 #[derive(Default, Clone, Copy)]
 pub struct LuniRect {
     pub left: i32,
     pub bottom: i32,
     pub right: i32,
     pub top: i32,
+    pub start: i32,
+    pub end: i32,
 }
 
+/// Where children are placed across the axis when not stretched, `cross` long. Rows start
+/// across at their top, columns at their start side.
 #[derive(Default, Clone, Copy)]
 pub enum LuniAlign {
     #[default]
@@ -79,69 +105,319 @@ pub enum LuniAlign {
     Center,
 }
 
-/// TODO specific certain data for parent to adjust itself based on its children
-pub struct LuniHug;
+/// Sizes the parent after its children: along the axis their lengths with margins and the
+/// padding, across it the largest of them. The hugged rectangle is sent back to the parent
+/// by [`WidgetRectangle`], and to the flex the parent is a child of by [`LuniIntrinsic`].
+#[derive(Default, Clone, Copy)]
+pub struct LuniHug {
+    pub main: bool,
+    pub cross: bool,
+    /// The point of the parent kept in place, `(0.0, 0.0)` for its left down corner.
+    pub anchor: (f32, f32),
+    pub min: Size,
+    pub max: Option<Size>,
+}
+
+/// Sent to the parent of a hugging [`LuniFlex`] once the size it hugs changes, `None` for
+/// the axes it doesn't hug.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LuniIntrinsic {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
+
+/// Sent by a [`LuniFlex`] to its children once inserted, hugging flexes of the children
+/// answer with [`LuniIntrinsic`].
+pub struct LuniMeasure;
 
 impl LuniFlex {
+    pub fn new(parent: (Handle, LuniParent), children: Vec<(Handle, LuniChild)>) -> Self {
+        LuniFlex {
+            parent,
+            children,
+            inherited: WritingDirection::default(),
+            rect: None,
+            intrinsic: HashMap::new(),
+            reported: None,
+            passes: 0,
+        }
+    }
+
     fn compute(&self, rect: Rectangle) -> Vec<(Handle, Rectangle)> {
         let mut result = Vec::with_capacity(self.children.len());
-        let mut lengths = Vec::with_capacity(self.children.len());
 
         let (_, parent) = &self.parent;
-
-        let mut grow_sum = 0.0;
-        let mut shrink_sum = 0.0;
-        let mut available =
-            rect_main_lenth(rect, parent.axis) - rect_main_padding(parent.padding, parent.axis);
-        for (_, child) in &self.children {
-            let child = child.apply(&parent.template);
-            grow_sum += child.grow;
-            shrink_sum += child.shrink * child.basis as f32;
-            available -= child.basis + rect_main_margin(child.margin, parent.axis);
-            lengths.push(child.basis);
+        let direction = self.direction();
+        let axis = parent.axis.resolve(direction);
+        let padding = parent.padding.resolve(direction);
+        let children = self.resolve_children(direction);
+
+        let mut space = rect_main_lenth(rect, axis) - rect_main_padding(padding, axis);
+        for child in &children {
+            space -= rect_main_margin(child.margin, axis);
         }
 
-        log::debug!("before: {lengths:?}, available: {available:?}");
+        let mut lengths = children.iter().map(|child| child.basis).collect::<Vec<_>>();
+        let mut frozen = vec![false; children.len()];
+
+        log::debug!("before: {lengths:?}, space: {space:?}");
+
+        // children held by their min or max are frozen there and the rest of the space is
+        // shared again, each pass freezes one at least
+        for _ in 0..=children.len() {
+            let mut available = space;
+            let mut grow_sum = 0.0;
+            let mut shrink_sum = 0.0;
+            for (i, child) in children.iter().enumerate() {
+                if frozen[i] {
+                    available -= lengths[i];
+                    continue;
+                }
 
-        let mut i = 0;
-        while i < self.children.len() {
-            let (_, child) = &self.children[i];
-            let child = child.apply(&parent.template);
+                available -= child.basis;
+                grow_sum += child.grow;
+                shrink_sum += child.shrink * child.basis as f32;
+            }
+
+            let mut clamped = false;
+            for (i, child) in children.iter().enumerate() {
+                if frozen[i] {
+                    continue;
+                }
 
-            if available > 0 && child.grow > 0.0 {
-                lengths[i] += (available as f32 * (child.grow / grow_sum)).round() as i32;
-            } else if available < 0 && child.shrink > 0.0 {
-                lengths[i] += (available as f32 * (child.shrink * child.basis as f32 / shrink_sum))
-                    .round() as i32;
+                let mut length = child.basis;
+                if available > 0 && child.grow > 0.0 {
+                    length += (available as f32 * (child.grow / grow_sum)).round() as i32;
+                } else if available < 0 && child.shrink > 0.0 {
+                    length += (available as f32 * (child.shrink * child.basis as f32 / shrink_sum))
+                        .round() as i32;
+                }
+
+                lengths[i] = child.clamp(length);
+                if lengths[i] != length {
+                    frozen[i] = true;
+                    clamped = true;
+                }
             }
 
-            i += 1;
+            if !clamped {
+                break;
+            }
         }
 
         log::debug!("after: {lengths:?}");
 
-        let mut cursor = rect_main_start(parent.padding, parent.axis);
-        for (i, (handle, child)) in self.children.iter().enumerate() {
-            let child = child.apply(&parent.template);
+        let mut cursor = rect_main_start(padding, axis);
+        for (i, (handle, _)) in self.children.iter().enumerate() {
+            let child = &children[i];
             let length = lengths[i];
             result.push((
                 *handle,
-                cursor_assign(
-                    cursor,
-                    rect,
-                    parent.padding,
-                    child.margin,
-                    length,
-                    child.align,
-                    parent.axis,
-                ),
+                cursor_assign(cursor, rect, padding, child, length, axis, direction),
             ));
 
-            cursor += rect_main_margin(child.margin, parent.axis) + length;
+            cursor += rect_main_margin(child.margin, axis) + length;
         }
 
         result
     }
+
+    /// The direction of the children, to be sent to them.
+    fn direction(&self) -> WritingDirection {
+        self.parent.1.direction.unwrap_or(self.inherited)
+    }
+
+    /// The children applied to the template, sized after their own children if they hug.
+    fn resolve_children(&self, direction: WritingDirection) -> Vec<LuniChildTemplate> {
+        let (_, parent) = &self.parent;
+
+        (self.children.iter())
+            .map(|(handle, child)| {
+                let mut child = child.apply(&parent.template);
+                child.margin = child.margin.resolve(direction);
+
+                if let Some(intrinsic) = self.intrinsic.get(handle) {
+                    let (main, cross) = match parent.axis {
+                        LuniAxis::Row | LuniAxis::RowReverse => (intrinsic.width, intrinsic.height),
+                        LuniAxis::Column | LuniAxis::ColumnReverse => {
+                            (intrinsic.height, intrinsic.width)
+                        }
+                    };
+                    child.basis = main.unwrap_or(child.basis);
+                    child.cross = cross.unwrap_or(child.cross);
+                }
+                child
+            })
+            .collect()
+    }
+
+    /// The size hugging the children, `None` if the flex doesn't hug.
+    fn intrinsic(&self) -> Option<LuniIntrinsic> {
+        let (_, parent) = &self.parent;
+        let hug = parent.hug?;
+
+        let direction = self.direction();
+        let padding = parent.padding.resolve(direction);
+        let children = self.resolve_children(direction);
+
+        let main = (children.iter())
+            .map(|child| child.clamp(child.basis) + rect_main_margin(child.margin, parent.axis))
+            .sum::<i32>()
+            + rect_main_padding(padding, parent.axis);
+
+        let cross = (children.iter())
+            .map(|child| child.cross + rect_cross_margin(child.margin, parent.axis))
+            .max()
+            .unwrap_or_default()
+            + rect_cross_padding(padding, parent.axis);
+
+        let (main, cross) = (hug.main.then_some(main), hug.cross.then_some(cross));
+        let (width, height) = match parent.axis {
+            LuniAxis::Row | LuniAxis::RowReverse => (main, cross),
+            LuniAxis::Column | LuniAxis::ColumnReverse => (cross, main),
+        };
+
+        let max = hug.max.unwrap_or(Size::splat(u32::MAX / 2));
+        Some(LuniIntrinsic {
+            width: width.map(|width| width.min(max.w as i32).max(hug.min.w as i32)),
+            height: height.map(|height| height.min(max.h as i32).max(hug.min.h as i32)),
+        })
+    }
+
+    /// `rect` resized to hug the children around the anchor.
+    fn hug(&self, rect: Rectangle) -> Rectangle {
+        let (Some(hug), Some(intrinsic)) = (self.parent.1.hug, self.intrinsic()) else {
+            return rect;
+        };
+
+        let resize = |origin: i32, extend: u32, length: Option<i32>, anchor: f32| {
+            let Some(length) = length else {
+                return (origin, origin + extend as i32);
+            };
+
+            // the anchor is rounded the same way both times, so hugging twice changes nothing
+            let point = origin + (extend as f32 * anchor).round() as i32;
+            let origin = point - (length as f32 * anchor).round() as i32;
+            (origin, origin + length)
+        };
+
+        let (left, right) = resize(rect.left(), rect.extend.w, intrinsic.width, hug.anchor.0);
+        let (down, up) = resize(rect.down(), rect.extend.h, intrinsic.height, hug.anchor.1);
+        Rectangle::new(left, down, right, up)
+    }
+
+    /// Hugs the children first if asked to, then lays them out in the parent.
+    fn layout(world: &World, this: Handle<Self>, rect: Rectangle, animated: bool) {
+        let mut flex = world.fetch_mut(this).unwrap();
+        let parent = flex.parent.0;
+
+        let hugged = flex.hug(rect);
+        if hugged != rect {
+            drop(flex);
+
+            // delivered after the rectangle being sent now, to everything observing it
+            match animated {
+                true => world.batch_trigger(parent, WidgetAnimatedRectangle(hugged)),
+                false => world.batch_trigger(parent, WidgetRectangle(hugged)),
+            }
+            return;
+        }
+
+        flex.rect = Some(rect);
+        let targets = flex.compute(rect);
+        drop(flex);
+
+        for (child, target) in targets {
+            match animated {
+                true => world.batch_trigger(child, WidgetAnimatedRectangle(target)),
+                false => world.batch_trigger(child, WidgetRectangle(target)),
+            }
+        }
+    }
+
+    /// A hugging child changed its size, which may change the size of the flex too.
+    fn child_resized(world: &World, this: Handle<Self>, child: Handle, intrinsic: LuniIntrinsic) {
+        let mut flex = world.fetch_mut(this).unwrap();
+        if flex.intrinsic.get(&child) == Some(&intrinsic) {
+            return;
+        }
+
+        if flex.passes >= MAX_PASSES {
+            log::warn!("cyclic layout through {child}, stopped after {MAX_PASSES} passes");
+            return;
+        }
+
+        flex.intrinsic.insert(child, intrinsic);
+        flex.passes += 1;
+        let rect = flex.rect;
+        drop(flex);
+
+        if let Some(rect) = rect {
+            LuniFlex::layout(world, this, rect, false);
+        }
+        LuniFlex::report(world, this);
+
+        // flushed after everything the report leads to, so passes only pile up in a cycle
+        world.queue(move |world| {
+            if let Ok(mut flex) = world.fetch_mut(this) {
+                flex.passes -= 1;
+            }
+        });
+    }
+
+    /// Tells the flex the parent is a child of about its new size, queued as it may be this
+    /// flex again.
+    fn report(world: &World, this: Handle<Self>) {
+        let mut flex = world.fetch_mut(this).unwrap();
+        let Some(intrinsic) = flex.intrinsic() else {
+            return;
+        };
+
+        if flex.reported == Some(intrinsic) {
+            return;
+        }
+
+        flex.reported = Some(intrinsic);
+        let parent = flex.parent.0;
+        drop(flex);
+
+        // a newer size may be reported before this one is delivered
+        world.queue(move |world| {
+            let latest = (world.fetch(this)).is_ok_and(|flex| flex.reported == Some(intrinsic));
+            if latest {
+                world.trigger(parent, &intrinsic);
+            }
+        });
+    }
+}
+
+impl LuniAxis {
+    /// The axis in absolute terms, rows reversed when right to left.
+    fn resolve(self, direction: WritingDirection) -> LuniAxis {
+        match (self, direction.is_rtl()) {
+            (LuniAxis::Row, true) => LuniAxis::RowReverse,
+            (LuniAxis::RowReverse, true) => LuniAxis::Row,
+            (axis, _) => axis,
+        }
+    }
+}
+
+impl LuniRect {
+    /// The rectangle in absolute terms, `start` and `end` added to their sides.
+    fn resolve(self, direction: WritingDirection) -> LuniRect {
+        let (left, right) = match direction {
+            WritingDirection::LeftToRight => (self.start, self.end),
+            WritingDirection::RightToLeft => (self.end, self.start),
+        };
+
+        LuniRect {
+            left: self.left + left,
+            right: self.right + right,
+            start: 0,
+            end: 0,
+            ..self
+        }
+    }
 }
 
 fn rect_main_lenth(rect: Rectangle, axis: LuniAxis) -> i32 {
@@ -165,6 +441,20 @@ fn rect_main_margin(margin: LuniRect, axis: LuniAxis) -> i32 {
     }
 }
 
+fn rect_cross_padding(padding: LuniRect, axis: LuniAxis) -> i32 {
+    match axis {
+        LuniAxis::Column | LuniAxis::ColumnReverse => padding.left + padding.right,
+        LuniAxis::Row | LuniAxis::RowReverse => padding.top + padding.bottom,
+    }
+}
+
+fn rect_cross_margin(margin: LuniRect, axis: LuniAxis) -> i32 {
+    match axis {
+        LuniAxis::Column | LuniAxis::ColumnReverse => margin.left + margin.right,
+        LuniAxis::Row | LuniAxis::RowReverse => margin.top + margin.bottom,
+    }
+}
+
 fn rect_main_start(padding: LuniRect, axis: LuniAxis) -> i32 {
     match axis {
         LuniAxis::Row => padding.left,
@@ -174,23 +464,47 @@ fn rect_main_start(padding: LuniRect, axis: LuniAxis) -> i32 {
     }
 }
 
+/// The rectangle of a child along `axis` and the padding, which must be resolved already.
 fn cursor_assign(
     cursor: i32,
     rect: Rectangle,
     padding: LuniRect,
-    margin: LuniRect,
+    child: &LuniChildTemplate,
     length: i32,
-    align: LuniAlign,
     axis: LuniAxis,
+    direction: WritingDirection,
 ) -> Rectangle {
-    let (start, end) = match align {
-        LuniAlign::Stretch => match axis {
-            LuniAxis::Column | LuniAxis::ColumnReverse => (padding.left, padding.right),
-            LuniAxis::Row | LuniAxis::RowReverse => (padding.top, padding.bottom),
-        },
-        LuniAlign::FlexStart => todo!(),
-        LuniAlign::FlexEnd => todo!(),
-        LuniAlign::Center => todo!(),
+    let margin = child.margin;
+
+    // the cross axis, from the left or down side and from the other one
+    let (extent, low, high, margin_low, margin_high) = match axis {
+        LuniAxis::Column | LuniAxis::ColumnReverse => (
+            rect.width() as i32,
+            padding.left,
+            padding.right,
+            margin.left,
+            margin.right,
+        ),
+        LuniAxis::Row | LuniAxis::RowReverse => (
+            rect.height() as i32,
+            padding.bottom,
+            padding.top,
+            margin.bottom,
+            margin.top,
+        ),
+    };
+
+    let free = extent - low - high - margin_low - margin_high - child.cross;
+    let start_low = match axis {
+        LuniAxis::Column | LuniAxis::ColumnReverse => !direction.is_rtl(),
+        LuniAxis::Row | LuniAxis::RowReverse => false,
+    };
+
+    let (start, end) = match (child.align, start_low) {
+        (LuniAlign::Stretch, _) => (low, high),
+        (LuniAlign::Center, _) => (low + free / 2, high + free - free / 2),
+        (LuniAlign::FlexStart, true) | (LuniAlign::FlexEnd, false) => (low, high + free),
+        (LuniAlign::FlexStart, false) | (LuniAlign::FlexEnd, true) => (low + free, high),
     };
 
     match axis {
@@ -214,9 +528,9 @@ fn cursor_assign(
         ),
         LuniAxis::ColumnReverse => Rectangle::new(
             rect.left() + start + margin.left,
-            rect.down() + cursor + margin.top,
+            rect.down() + cursor + margin.bottom,
             rect.right() - end - margin.right,
-            rect.down() + cursor + margin.top + length,
+            rect.down() + cursor + margin.bottom + length,
         ),
     }
 }
@@ -236,34 +550,376 @@ impl LuniChild {
     }
 }
 
+impl LuniChildTemplate {
+    /// Within `min` and `max`, `min` winning if they cross. Never negative.
+    fn clamp(&self, length: i32) -> i32 {
+        length
+            .min(self.max.unwrap_or(i32::MAX))
+            .max(self.min.unwrap_or_default())
+            .max(0)
+    }
+}
+
 impl Element for LuniFlex {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
-        let ob = world.observer(self.parent.0, move |&WidgetRectangle(rect), world| {
-            let this = world.fetch(this).unwrap();
-            let targets = this.compute(rect);
+        let parent = self.parent.0;
+
+        let ob = world.observer(parent, move |&WidgetRectangle(rect), world| {
+            LuniFlex::layout(world, this, rect, false);
+        });
+
+        let oba = world.observer(parent, move |&WidgetAnimatedRectangle(rect), world| {
+            LuniFlex::layout(world, this, rect, true);
+        });
+
+        let obd = world.observer(parent, move |&WidgetDirection(inherited), world| {
+            let mut this = world.fetch_mut(this).unwrap();
+            this.inherited = inherited;
+            let direction = this.direction();
 
-            for (child, target) in targets {
-                world.queue_trigger(child, WidgetRectangle(target));
+            for (child, _) in &this.children {
+                world.broadcast(*child, &WidgetDirection(direction));
             }
         });
 
-        let oba = world.observer(
-            self.parent.0,
-            move |&WidgetAnimatedRectangle(rect), world| {
-                let this = world.fetch(this).unwrap();
-                let targets = this.compute(rect);
+        // asked again by a flex inserted later
+        let obm = world.observer(parent, move |LuniMeasure, world| {
+            world.fetch_mut(this).unwrap().reported = None;
+            LuniFlex::report(world, this);
+        });
 
-                for (child, target) in targets {
-                    world.queue_trigger(child, WidgetAnimatedRectangle(target));
-                }
+        world.dependency(ob, this);
+        world.dependency(oba, this);
+        world.dependency(obd, this);
+        world.dependency(obm, this);
+        world.dependency(this, parent);
+
+        for &(child, _) in &self.children {
+            let ob = world.observer(child, move |&intrinsic: &LuniIntrinsic, world| {
+                LuniFlex::child_resized(world, this, child, intrinsic);
+            });
+            world.dependency(ob, this);
+            world.dependency(this, child);
+        }
+
+        world.queue(move |world| {
+            let Ok(flex) = world.fetch(this) else {
+                return;
+            };
+            let children = flex
+                .children
+                .iter()
+                .map(|&(child, _)| child)
+                .collect::<Vec<_>>();
+            drop(flex);
+
+            for child in children {
+                world.trigger(child, &LuniMeasure);
+            }
+            LuniFlex::report(world, this);
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    /// Each child with the last rectangle sent to it.
+    type Sent = Vec<(Handle, Rc<RefCell<Option<Rectangle>>>)>;
+
+    /// A flex laying out new elements, returns its parent and what the children are sent.
+    fn flex(world: &mut World, parent: LuniParent, children: Vec<LuniChild>) -> (Handle, Sent) {
+        let handle = world.insert(()).untyped();
+        let mut sent = Sent::new();
+        let children = (children.into_iter())
+            .map(|child| {
+                let sink = Rc::new(RefCell::new(None));
+                let element = world.insert(()).untyped();
+                sent.push((element, sink.clone()));
+
+                world.observer(element, move |&WidgetRectangle(rect), _| {
+                    *sink.borrow_mut() = Some(rect);
+                });
+                (element, child)
+            })
+            .collect();
+
+        world.insert(LuniFlex::new((handle, parent), children));
+        world.flush();
+        (handle, sent)
+    }
+
+    fn layout(world: &mut World, parent: Handle, rect: Rectangle) {
+        world.trigger(parent, &WidgetRectangle(rect));
+        world.flush();
+    }
+
+    fn rects(sent: &Sent) -> Vec<Rectangle> {
+        (sent.iter())
+            .map(|(_, sink)| sink.borrow().unwrap())
+            .collect()
+    }
+
+    fn basis(basis: i32) -> LuniChild {
+        LuniChild {
+            basis: Some(basis),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn row_mirrored() {
+        let mut world = World::default();
+        let parent = LuniParent {
+            padding: LuniRect {
+                start: 5,
+                ..Default::default()
             },
+            ..Default::default()
+        };
+        let (parent, sent) = flex(&mut world, parent, vec![basis(20), basis(30)]);
+
+        let rect = Rectangle::new(0, 0, 100, 10);
+        layout(&mut world, parent, rect);
+        assert_eq!(
+            rects(&sent),
+            [Rectangle::new(5, 0, 25, 10), Rectangle::new(25, 0, 55, 10)]
         );
 
-        world.dependency(ob, this);
-        world.dependency(oba, this);
-        world.dependency(this, self.parent.0);
-        for (child, _) in &self.children {
-            world.dependency(this, *child);
+        // from the right, the padding at the start too
+        world.trigger(parent, &WidgetDirection(WritingDirection::RightToLeft));
+        layout(&mut world, parent, rect);
+        assert_eq!(
+            rects(&sent),
+            [Rectangle::new(75, 0, 95, 10), Rectangle::new(45, 0, 75, 10)]
+        );
+    }
+
+    #[test]
+    fn column_mirrored() {
+        let mut world = World::default();
+        let parent = LuniParent {
+            axis: LuniAxis::Column,
+            template: LuniChildTemplate {
+                align: LuniAlign::FlexStart,
+                basis: 10,
+                cross: 20,
+                ..Default::default()
+            },
+            direction: Some(WritingDirection::RightToLeft),
+            ..Default::default()
+        };
+        let (parent, sent) = flex(&mut world, parent, vec![LuniChild::default()]);
+
+        let child = sent[0].0;
+        let direction = Rc::new(RefCell::new(None));
+        let sink = direction.clone();
+        world.observer(child, move |&WidgetDirection(direction), _| {
+            *sink.borrow_mut() = Some(direction);
+        });
+        world.flush();
+
+        // overridden for the children
+        world.trigger(parent, &WidgetDirection(WritingDirection::LeftToRight));
+        assert_eq!(*direction.borrow(), Some(WritingDirection::RightToLeft));
+
+        // starting across at the right side
+        layout(&mut world, parent, Rectangle::new(0, 0, 100, 50));
+        assert_eq!(rects(&sent), [Rectangle::new(80, 40, 100, 50)]);
+    }
+
+    #[test]
+    fn direction_broadcast_once() {
+        let mut world = World::default();
+        let (parent, sent) = flex(&mut world, Default::default(), vec![basis(10)]);
+
+        // a flex within the child, overriding, with something depending on its child
+        let inner = LuniParent {
+            direction: Some(WritingDirection::RightToLeft),
+            ..Default::default()
+        };
+        let child = world.insert(()).untyped();
+        let dependent = world.insert(()).untyped();
+        world.insert(LuniFlex::new(
+            (sent[0].0, inner),
+            vec![(child, LuniChild::default())],
+        ));
+        world.dependency(dependent, child);
+
+        let told = Rc::new(RefCell::new(vec![]));
+        for handle in [child, dependent] {
+            let sink = told.clone();
+            world.observer(handle, move |&WidgetDirection(direction), _| {
+                sink.borrow_mut().push((handle, direction));
+            });
         }
+        world.flush();
+
+        world.broadcast(parent, &WidgetDirection(WritingDirection::LeftToRight));
+        assert_eq!(
+            *told.borrow(),
+            [
+                (child, WritingDirection::RightToLeft),
+                (dependent, WritingDirection::RightToLeft)
+            ]
+        );
+    }
+
+    #[test]
+    fn min_max_clamped() {
+        let mut world = World::default();
+        let grow = |max| LuniChild {
+            grow: Some(1.0),
+            max: Some(max),
+            ..Default::default()
+        };
+        let (parent, sent) = flex(
+            &mut world,
+            Default::default(),
+            vec![grow(Some(20)), grow(None)],
+        );
+        layout(&mut world, parent, Rectangle::new(0, 0, 100, 10));
+        assert_eq!(
+            rects(&sent),
+            [Rectangle::new(0, 0, 20, 10), Rectangle::new(20, 0, 100, 10)]
+        );
+
+        let shrink = |min| LuniChild {
+            basis: Some(50),
+            shrink: Some(1.0),
+            min: Some(min),
+            ..Default::default()
+        };
+        let (parent, sent) = flex(
+            &mut world,
+            Default::default(),
+            vec![shrink(Some(45)), shrink(None)],
+        );
+        layout(&mut world, parent, Rectangle::new(0, 0, 60, 10));
+        assert_eq!(
+            rects(&sent),
+            [Rectangle::new(0, 0, 45, 10), Rectangle::new(45, 0, 60, 10)]
+        );
+
+        // hugging within its own bounds
+        let hugging = |min, max| {
+            let hug = LuniHug {
+                main: true,
+                min,
+                max,
+                ..Default::default()
+            };
+            let parent = LuniParent {
+                hug: Some(hug),
+                ..Default::default()
+            };
+            let children = vec![
+                (world.insert(()).untyped(), basis(30)),
+                (world.insert(()).untyped(), basis(20)),
+            ];
+            LuniFlex::new((world.insert(()).untyped(), parent), children).intrinsic()
+        };
+        let width = |width| {
+            Some(LuniIntrinsic {
+                width: Some(width),
+                height: None,
+            })
+        };
+        assert_eq!(hugging(Size::default(), None), width(50));
+        assert_eq!(hugging(Size::default(), Some(Size::new(40, 40))), width(40));
+        assert_eq!(hugging(Size::new(80, 0), None), width(80));
+    }
+
+    #[test]
+    fn nested_hug() {
+        let mut world = World::default();
+        let hug = LuniHug {
+            main: true,
+            ..Default::default()
+        };
+
+        let inner = LuniParent {
+            padding: LuniRect {
+                left: 5,
+                right: 5,
+                ..Default::default()
+            },
+            hug: Some(hug),
+            ..Default::default()
+        };
+        let (inner, sent) = flex(&mut world, inner, vec![basis(30), basis(20)]);
+
+        let outer = world.insert(()).untyped();
+        let reported = Rc::new(RefCell::new(Vec::new()));
+        let sink = reported.clone();
+        world.observer(outer, move |&intrinsic: &LuniIntrinsic, _| {
+            sink.borrow_mut().push(intrinsic);
+        });
+
+        let parent = LuniParent {
+            padding: LuniRect {
+                left: 10,
+                ..Default::default()
+            },
+            hug: Some(hug),
+            ..Default::default()
+        };
+        world.insert(LuniFlex::new((outer, parent), vec![(inner, basis(0))]));
+        world.flush();
+
+        // the children, the inner padding and the outer one
+        let intrinsic = LuniIntrinsic {
+            width: Some(70),
+            height: None,
+        };
+        assert_eq!(reported.borrow().last(), Some(&intrinsic));
+
+        // hugged all the way down
+        layout(&mut world, outer, Rectangle::new(0, 0, 200, 10));
+        assert_eq!(
+            rects(&sent),
+            [Rectangle::new(15, 0, 45, 10), Rectangle::new(45, 0, 65, 10)]
+        );
+    }
+
+    #[test]
+    fn cycle_stopped() {
+        let mut world = World::default();
+        let (a, b) = (world.insert(()).untyped(), world.insert(()).untyped());
+
+        let reported = Rc::new(RefCell::new(Vec::new()));
+        let sink = reported.clone();
+        world.observer(a, move |&intrinsic: &LuniIntrinsic, _| {
+            sink.borrow_mut().push(intrinsic);
+        });
+
+        // each hugs the other and a padding, growing without end
+        let parent = || LuniParent {
+            padding: LuniRect {
+                left: 5,
+                ..Default::default()
+            },
+            hug: Some(LuniHug {
+                main: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let first = world.insert(LuniFlex::new((a, parent()), vec![(b, basis(0))]));
+        let second = world.insert(LuniFlex::new((b, parent()), vec![(a, basis(0))]));
+        world.flush();
+
+        let reported = reported.borrow();
+        let width = reported.last().unwrap().width.unwrap();
+        // once when inserted and once measured, then a pass each
+        assert!(reported.len() <= MAX_PASSES as usize + 2);
+        assert!(width <= 10 * (MAX_PASSES as i32 + 1));
+
+        // settled, ready to lay out again
+        assert_eq!(world.fetch(first).unwrap().passes, 0);
+        assert_eq!(world.fetch(second).unwrap().passes, 0);
     }
 }
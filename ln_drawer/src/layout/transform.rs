@@ -1,7 +1,15 @@
+use std::{cell::Cell, rc::Rc};
+
 use ln_world::{Element, Handle, World};
 
-use crate::{measures::Rectangle, widgets::WidgetRectangle};
+use crate::{
+    layout::WritingDirection,
+    measures::Rectangle,
+    widgets::{WidgetDirection, WidgetRectangle},
+};
 
+/// Places `target` relative to the rectangle of `source`, and passes the writing direction
+/// of `source` on to it.
 pub struct Transform {
     pub value: TransformValue,
     pub source: Handle,
@@ -14,6 +22,8 @@ pub struct TransformValue {
     pub down: TransformEdge,
     pub right: TransformEdge,
     pub up: TransformEdge,
+    /// `left` and `right` are the start and end edges, mirrored when right to left.
+    pub logical: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -41,6 +51,7 @@ impl TransformValue {
                 anchor: 1.0,
                 offset: 0,
             },
+            logical: false,
         }
     }
 
@@ -62,6 +73,7 @@ impl TransformValue {
                 anchor: anchor.1,
                 offset: rect.up(),
             },
+            logical: false,
         }
     }
 
@@ -83,6 +95,7 @@ impl TransformValue {
                 anchor: 1.0,
                 offset: -height,
             },
+            logical: false,
         }
     }
 
@@ -104,9 +117,37 @@ impl TransformValue {
                 anchor: 0.5 + height * 0.5,
                 offset: 0,
             },
+            logical: false,
+        }
+    }
+
+    /// The same value with `left` and `right` taken as the start and end edges.
+    pub const fn logical(self) -> TransformValue {
+        TransformValue {
+            logical: true,
+            ..self
         }
     }
 
+    /// Mirrors a logical value for right to left, where its start edge is the right one.
+    pub fn resolve(&self, direction: WritingDirection) -> TransformValue {
+        if !self.logical || !direction.is_rtl() {
+            return *self;
+        }
+
+        let mirror = |edge: TransformEdge| TransformEdge {
+            anchor: 1.0 - edge.anchor,
+            offset: -edge.offset,
+        };
+
+        TransformValue {
+            left: mirror(self.right),
+            right: mirror(self.left),
+            ..*self
+        }
+    }
+
+    /// Where the edges are within `source`, logical values are taken as left to right.
     pub fn compute(&self, source: Rectangle) -> Rectangle {
         let left = source.extend.w as f32 * self.left.anchor;
         let left = source.origin.x + left.round() as i32 + self.left.offset;
@@ -126,15 +167,93 @@ impl TransformValue {
 
 impl Element for Transform {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let direction = Rc::new(Cell::new(WritingDirection::default()));
+
+        let inherited = direction.clone();
         let ob = world.observer(self.source, move |&WidgetRectangle(rect), world| {
             let this = world.fetch(this).unwrap();
-            let target = this.value.compute(rect);
+            let target = this.value.resolve(inherited.get()).compute(rect);
 
-            world.queue_trigger(this.target, WidgetRectangle(target));
+            world.batch_trigger(this.target, WidgetRectangle(target));
+        });
+
+        let obd = world.observer(self.source, move |&WidgetDirection(inherited), world| {
+            direction.set(inherited);
+            let target = world.fetch(this).unwrap().target;
+            world.broadcast(target, &WidgetDirection(inherited));
         });
 
         world.dependency(ob, this);
+        world.dependency(obd, this);
         world.dependency(this, self.source);
         world.dependency(this, self.target);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[test]
+    fn mirrored_values() {
+        let source = Rectangle::new(0, 0, 200, 100);
+        let value = TransformValue::anchor((0.0, 1.0), Rectangle::new(10, -30, 60, -10));
+        let compute = |value: TransformValue, direction| value.resolve(direction).compute(source);
+
+        let ltr = Rectangle::new(10, 70, 60, 90);
+        assert_eq!(compute(value, WritingDirection::RightToLeft), ltr);
+
+        // the start edge is the right one
+        let rtl = Rectangle::new(140, 70, 190, 90);
+        assert_eq!(compute(value.logical(), WritingDirection::LeftToRight), ltr);
+        assert_eq!(compute(value.logical(), WritingDirection::RightToLeft), rtl);
+    }
+
+    #[test]
+    fn direction_passed_on() {
+        let mut world = World::default();
+        let source = world.insert(()).untyped();
+        let target = world.insert(()).untyped();
+
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let sink = sent.clone();
+        world.observer(target, move |&WidgetRectangle(rect), _| {
+            sink.borrow_mut().push(rect);
+        });
+        let direction = Rc::new(Cell::new(WritingDirection::LeftToRight));
+        let sink = direction.clone();
+        world.observer(target, move |&WidgetDirection(direction), _| {
+            sink.set(direction)
+        });
+
+        let value = TransformValue::shrink(10, 0).logical();
+        let value = TransformValue {
+            right: TransformEdge {
+                anchor: 0.5,
+                offset: 0,
+            },
+            ..value
+        };
+        world.insert(Transform {
+            value,
+            source,
+            target,
+        });
+        world.flush();
+
+        let rect = Rectangle::new(0, 0, 100, 20);
+        world.trigger(source, &WidgetRectangle(rect));
+        world.flush();
+        world.trigger(source, &WidgetDirection(WritingDirection::RightToLeft));
+        world.trigger(source, &WidgetRectangle(rect));
+        world.flush();
+
+        assert_eq!(direction.get(), WritingDirection::RightToLeft);
+        assert_eq!(
+            *sent.borrow(),
+            [Rectangle::new(10, 0, 50, 20), Rectangle::new(50, 0, 90, 20)]
+        );
+    }
+}
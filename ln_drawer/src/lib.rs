@@ -1,25 +1,82 @@
 pub mod animation;
 pub mod layout;
 pub mod lnwin;
+pub mod locale;
 pub mod measures;
+pub mod metrics;
+pub mod note;
+pub mod plugin;
 pub mod render;
+pub mod replay;
 pub mod save;
+pub mod script;
 pub mod stroke;
 pub mod theme;
 pub mod tools;
 pub mod widgets;
 
+/// Accepts `--trace <file>` for recording the world, `--record <file>` for recording window
+/// events and `--replay <file>` for playing them back on a board kept in memory.
+/// `--enable-plugin <name>` and `--disable-plugin <name>` are saved for later launches.
+/// `--metrics <file>` dumps the [`metrics::Metrics`] of each window on close.
+/// `--render <file>` draws the board into an image without opening a window.
 pub fn desktop_main() {
-    use winit::event_loop::EventLoop;
+    use ln_world::trace::Recorder;
+    use winit::{event::WindowEvent, event_loop::EventLoop};
 
     env_logger::init();
 
     log::info!("This is LnDrawer. Welcome!");
 
-    let lnwin = lnwin::Lnwin::default();
+    let mut lnwin = lnwin::Lnwin::default();
+    let mut replay = None;
+    let mut render = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(path) = args.next() else {
+            log::error!("missing value after {arg}");
+            break;
+        };
+
+        match &arg[..] {
+            "--trace" => match Recorder::create(&path) {
+                Ok(recorder) => {
+                    lnwin.world.record(recorder.with_payload::<WindowEvent>());
+                }
+                Err(err) => log::error!("failed to trace into {path}: {err}"),
+            },
+            "--record" => match replay::ReplayRecorder::create(&path) {
+                Ok(recorder) => lnwin.recorder = Some(recorder),
+                Err(err) => log::error!("failed to record into {path}: {err}"),
+            },
+            "--replay" => replay = Some(path),
+            "--render" => render = Some(path),
+            "--metrics" => {
+                lnwin.world.insert(metrics::MetricsDump(path.into()));
+            }
+            "--enable-plugin" => lnwin.plugins.request_enabled(&path, true),
+            "--disable-plugin" => lnwin.plugins.request_enabled(&path, false),
+            _ => log::warn!("unknown argument {arg}"),
+        }
+    }
+
+    if let Some(path) = render {
+        let size = winit::dpi::PhysicalSize::new(1920, 1080);
+        if let Err(err) = lnwin.render_board(path.as_ref(), size) {
+            log::error!("failed to render the board into {path}: {err}");
+        }
+        return;
+    }
 
     let event_loop = EventLoop::builder().build().unwrap();
-    event_loop.run_app(lnwin).unwrap();
+    match replay {
+        Some(path) => match replay::Replay::open(lnwin, &path) {
+            Ok(replay) => event_loop.run_app(replay).unwrap(),
+            Err(err) => log::error!("failed to open the replay {path}: {err}"),
+        },
+        None => event_loop.run_app(lnwin).unwrap(),
+    }
 }
 
 #[cfg(target_os = "android")]
@@ -1,4 +1,10 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    cell::{Cell, RefCell},
+    path::Path,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
 
 use hashbrown::HashMap;
 use ln_world::{Element, Handle, ViewOptions, World};
@@ -7,7 +13,7 @@ use palette::{Hsla, IntoColor, RgbHue, Srgba};
 use winit::platform::android::activity::AndroidApp;
 use winit::{
     application::ApplicationHandler,
-    dpi::PhysicalPosition,
+    dpi::{PhysicalPosition, PhysicalSize},
     event::WindowEvent,
     event_loop::ActiveEventLoop,
     window::{Window, WindowAttributes, WindowId},
@@ -15,50 +21,260 @@ use winit::{
 
 use crate::{
     layout::{
-        luni::{LuniAxis, LuniChild, LuniChildTemplate, LuniFlex, LuniParent, LuniRect},
+        luni::{LuniAxis, LuniChild, LuniChildTemplate, LuniFlex, LuniHug, LuniParent, LuniRect},
         transform::{Transform, TransformEdge, TransformValue},
     },
-    measures::{Position, PositionFract, Rectangle, Size},
+    locale::{Locale, LocaleChanged},
+    measures::{Fract, Position, PositionFract, Rectangle, Size},
+    metrics::Metrics,
+    note::NoteBoard,
+    plugin::{Plugin, PluginManager, Toolbar},
     render::{
         Render,
         camera::{Camera, CameraUtils, MainCamera, UICamera},
         canvas::CanvasManagerDescriptor,
         rectangle::RectangleMesh,
         rounded::RoundedRect,
-        text::TextManagerDescriptor,
+        settings::RenderSettings,
+        text::{Text, TextDescriptor, TextManagerDescriptor},
     },
+    replay::ReplayRecorder,
     save::{Autosave, AutosaveScheduler, SaveDatabase},
-    stroke::{StrokeLayer, modifier::Modifier},
-    theme::ColorScheme,
+    script::ScriptHost,
+    stroke::{StrokeColorPicked, StrokeLayer, StrokeView, modifier::Modifier},
+    theme::{Attach, ColorScheme, ThemeManager, luni::Luni},
     tools::{
-        collider::ToolColliderDispatcher, focus::Focus, modifiers::ModifiersTool, mouse::MouseTool,
-        pointer::PointerTool, touch::MultiTouchTool,
+        clipboard::Clipboard,
+        collider::ToolColliderDispatcher,
+        focus::Focus,
+        keymap::{Keymap, Shortcut},
+        modifiers::ModifiersTool,
+        mouse::MouseTool,
+        pointer::PointerTool,
+        touch::MultiTouchTool,
     },
     widgets::{
-        WidgetClick, WidgetEnabled, WidgetHsla, WidgetRectangle,
-        button::{Button, ButtonAnim, ButtonChecked, ButtonColor, ButtonImage},
+        WidgetButton, WidgetClick, WidgetDirection, WidgetEnabled, WidgetExpanded, WidgetHover,
+        WidgetHsla, WidgetRectangle, WidgetSelect,
+        button::{Button, ButtonAnim, ButtonChecked, ButtonColor},
+        context_menu::ContextMenu,
+        headless::{
+            check_button::{CheckButton, Switch},
+            slider::{Leap, Slide, Slider},
+        },
+        inspector::Inspector,
+        menu::{MenuDescriptor, MenuEntry, MenuEntryDescriptor},
+        metrics::MetricsOverlay,
+        minimap::Minimap,
         palette::hsl::{PaletteHsl, PaletteHslMaterial},
         renderer::grid::{Grid, GridMaterial},
     },
 };
 
+/// How far the rotate shortcuts turn the board.
+const ROTATE_STEP: f32 = std::f32::consts::PI / 12.0;
+/// Zoom change of the zoom shortcuts, in powers of two.
+const ZOOM_STEP: f64 = 0.25;
+/// How far the pan shortcuts move the board, in half screens.
+const PAN_STEP: f64 = 0.25;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderBoardError {
+    #[error("the format of the render target cannot be read back")]
+    Unsupported,
+
+    #[error("failed to write the image: {0}")]
+    Image(#[from] image::ImageError),
+}
+
 #[derive(Default)]
 pub struct Lnwin {
     pub world: World,
     pub windows: HashMap<WindowId, Handle>,
+    pub recorder: Option<ReplayRecorder>,
+    /// Windows opened so far, numbering the next one.
+    opened: usize,
+    /// Moved into the world once the first window is created.
+    pub plugins: PluginManager,
+}
+
+impl Lnwin {
+    pub fn with_plugin(mut self, plugin: impl Plugin + 'static) -> Self {
+        self.plugins.register(plugin);
+        self
+    }
+
+    /// Draw the board through the camera saved by the primary window into an image at
+    /// `path`, without opening a window. The board is drawn as in the window without its
+    /// interface, once the chunks in sight are loaded or a few seconds have passed.
+    pub fn render_board(
+        &mut self,
+        path: &Path,
+        size: PhysicalSize<u32>,
+    ) -> Result<(), RenderBoardError> {
+        self.init_session();
+        self.world.flush();
+
+        // stands in for the window, as a slot of it would
+        let root = self.world.here();
+        let slot = self.world.insert(());
+        self.world.enter(slot, || {
+            self.world.option(ViewOptions { refs: vec![root] });
+            self.world.queue(move |world| {
+                let db = world.single_fetch::<SaveDatabase>().unwrap();
+                let settings = RenderSettings::load(&db);
+                drop(db);
+
+                world.insert(pollster::block_on(Render::new_offscreen(size, &settings)));
+                world.insert(settings);
+                world.flush();
+                Lnwindow::init_renderers(world);
+            });
+        });
+        self.world.flush();
+
+        let camera = self
+            .world
+            .enter(slot, || Camera::build_from_save(&self.world, "camera1"));
+        self.world.flush();
+        Lnwindow::attach_stroke_layer(&self.world, camera, slot, None);
+        self.world.flush();
+        self.world.enter(camera, || {
+            self.world.queue(|world| {
+                world.insert(StrokeView::overview(world, -100));
+            });
+            self.world.queue(Lnwindow::board);
+        });
+        self.world.flush();
+
+        // the chunks in sight are asked for while preparing a frame
+        self.world.enter(slot, || self.world.queue(Render::redraw));
+        self.world.flush();
+        let loaded = self.world.enter(camera, || {
+            let mut stroke = self.world.single_fetch_mut::<StrokeLayer>().unwrap();
+            stroke.wait_loaded(&self.world, Duration::from_secs(5))
+        });
+        if !loaded {
+            log::warn!("rendering the board before the chunks in sight are loaded");
+        }
+        self.world.flush();
+
+        let image = Rc::new(RefCell::new(None));
+        let captured = image.clone();
+        self.world.enter(slot, || {
+            self.world.queue(move |world| {
+                *captured.borrow_mut() = Render::capture(world);
+            });
+        });
+        self.world.flush();
+
+        let _ = self.world.remove(slot);
+        self.world.flush();
+
+        let image = image.take().ok_or(RenderBoardError::Unsupported)?;
+        image.save(path)?;
+        Ok(())
+    }
+
+    /// What the windows share, set up before the first one opens or the board is rendered.
+    fn init_session(&mut self) {
+        if self.world.single::<PluginManager>().is_err() {
+            self.world.insert(std::mem::take(&mut self.plugins));
+        }
+
+        if self.world.single::<Clipboard>().is_err() {
+            self.world.insert(Clipboard::default());
+        }
+
+        self.world.queue(|world| {
+            if world.single::<SaveDatabase>().is_err() {
+                SaveDatabase::init(world);
+            }
+
+            if world.single::<Locale>().is_err() {
+                world.insert(Locale::default());
+            }
+
+            let db = world.single_fetch::<SaveDatabase>().unwrap().clone();
+            let mut plugins = world.single_fetch_mut::<PluginManager>().unwrap();
+            if let Err(err) = plugins.load(&db) {
+                log::error!("failed to load plugins: {err}");
+            }
+        });
+    }
+
+    /// Every window is put alone into a slot referring to the root, so that the singletons
+    /// in its view, like [`Render`] or [`MainCamera`], are never seen by the others.
+    fn open_window(&mut self, event_loop: &dyn ActiveEventLoop, primary: Option<Handle<Lnwindow>>) {
+        let root = self.world.here();
+        let slot = self.world.insert(());
+        self.world.enter(slot, || {
+            self.world.option(ViewOptions { refs: vec![root] });
+        });
+
+        let lnwindow = Lnwindow::new(event_loop, primary, self.opened);
+        self.opened += 1;
+        let window_id = lnwindow.window.id();
+        let lnwindow = self.world.enter_insert(slot, lnwindow);
+        self.windows.insert(window_id, lnwindow.untyped());
+    }
+
+    /// The [`Lnwindow::index`] of an open window.
+    pub fn window_index(&self, window_id: WindowId) -> Option<usize> {
+        let &view = self.windows.get(&window_id)?;
+        self.world.enter(view, || {
+            let lnwindow = self.world.single_fetch::<Lnwindow>().ok()?;
+            Some(lnwindow.index)
+        })
+    }
+
+    /// The open window numbered `index`, see [`Lnwindow::index`].
+    pub fn window_of(&self, index: usize) -> Option<WindowId> {
+        (self.windows.keys().copied())
+            .find(|&window_id| self.window_index(window_id) == Some(index))
+    }
+
+    /// Forget the closed windows and open the requested ones. Secondary windows are closed
+    /// together with the primary one.
+    fn update_windows(&mut self, event_loop: &dyn ActiveEventLoop) {
+        let mut requests = Vec::new();
+        let mut primary_alive = false;
+        self.windows.retain(|_, &mut view| {
+            self.world.enter(view, || {
+                let Ok(mut lnwindow) = self.world.single_fetch_mut::<Lnwindow>() else {
+                    return false;
+                };
+
+                primary_alive |= lnwindow.primary.is_none();
+                let primary = lnwindow.primary.unwrap_or(lnwindow.handle());
+                for _ in 0..std::mem::take(&mut lnwindow.requested) {
+                    requests.push(primary);
+                }
+                true
+            })
+        });
+
+        if !primary_alive {
+            self.world.clear();
+            self.world.flush();
+            self.windows.clear();
+            return;
+        }
+
+        if !requests.is_empty() {
+            for primary in requests {
+                self.open_window(event_loop, Some(primary));
+            }
+            self.world.flush();
+        }
+    }
 }
 
 impl ApplicationHandler for Lnwin {
     fn can_create_surfaces(&mut self, event_loop: &dyn ActiveEventLoop) {
         if self.windows.is_empty() {
-            let lnwindow = Lnwindow::new(event_loop);
-            let root = self.world.here();
-            let window_id = lnwindow.window.id();
-            let lnwindow = self.world.insert(lnwindow);
-            self.windows.insert(window_id, lnwindow.untyped());
-            self.world.enter(lnwindow, || {
-                self.world.option(ViewOptions { refs: vec![root] });
-            });
+            self.init_session();
+            self.open_window(event_loop, None);
         } else {
             for &view in self.windows.values() {
                 self.world.enter(view, || {
@@ -78,24 +294,37 @@ impl ApplicationHandler for Lnwin {
         window_id: WindowId,
         event: WindowEvent,
     ) {
+        if let Some(index) = self.window_index(window_id)
+            && let Some(recorder) = &mut self.recorder
+        {
+            recorder.record(index, &event);
+        }
+
         if let Some(&view) = self.windows.get(&window_id) {
             self.world.enter(view, || {
                 if let Ok(lnwindow) = self.world.single::<Lnwindow>() {
                     self.world.trigger(lnwindow, &event);
-                } else {
-                    self.windows.remove(&window_id);
                 }
             });
 
             self.world.flush();
+            self.update_windows(event_loop);
         }
 
         if self.windows.is_empty() {
+            if let Some(recorder) = &mut self.recorder {
+                recorder.finish();
+            }
+
             event_loop.exit()
         }
     }
 
     fn suspended(&mut self, _event_loop: &dyn ActiveEventLoop) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.finish();
+        }
+
         for &view in self.windows.values() {
             self.world.enter(view, || {
                 Autosave::autosave_all(&self.world);
@@ -104,45 +333,65 @@ impl ApplicationHandler for Lnwin {
     }
 }
 
-/// The main window.
+/// A window onto the board. The primary one owns the [`StrokeLayer`], secondary ones are
+/// opened with `Ctrl+N` and show the same layer through their own cameras. Closing the
+/// primary window ends the session, the secondary ones are closed with it.
 pub struct Lnwindow {
     pub window: Arc<dyn Window>,
+    /// `None` for the primary window.
+    pub primary: Option<Handle<Lnwindow>>,
+    /// The order it was opened in during the session, 0 for the primary window.
+    pub index: usize,
+    requested: usize,
 }
 
 impl Element for Lnwindow {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
-        world.observer(this, move |event: &WindowEvent, world| {
-            if let WindowEvent::CloseRequested = event {
+        let slot = world.here();
+        let primary = self.primary;
+
+        world.enter(this, || {
+            world.option(ViewOptions { refs: vec![slot] });
+            Lnwindow::setup(world, this, slot, primary);
+        });
+    }
+}
+
+impl Lnwindow {
+    fn setup(world: &World, this: Handle<Self>, slot: Handle, primary: Option<Handle<Self>>) {
+        world.observer(this, move |event: &WindowEvent, world| match event {
+            WindowEvent::CloseRequested => {
                 Autosave::autosave_all(world);
-                world.queue(|world| {
-                    world.clear();
+                world.queue(move |world| {
+                    let _ = world.remove(slot);
                 });
             }
+            _ => {}
         });
 
         world.queue(move |world| {
-            let lnwindow = world.fetch_mut(this).unwrap();
-            world.insert(pollster::block_on(Render::new(&lnwindow)));
-        });
+            let lnwindow = world.fetch(this).unwrap();
+            let db = world.single_fetch::<SaveDatabase>().unwrap();
+            let settings = RenderSettings::load(&db);
+            let render = match primary {
+                None => pollster::block_on(Render::new(&lnwindow, &settings)),
+                Some(primary) => {
+                    let shared = world.enter_single_fetch::<Render>(primary).unwrap();
+                    Render::new_shared(&lnwindow, &settings, &shared)
+                }
+            };
+            world.insert(Metrics::new(&render));
+            world.insert(render);
+            world.insert(settings);
 
-        world.queue(|world| {
-            SaveDatabase::init(world);
-            world.insert(AutosaveScheduler {
-                autosave_duration: Duration::from_secs(10),
-            });
+            if primary.is_none() {
+                world.insert(AutosaveScheduler {
+                    autosave_duration: Duration::from_secs(10),
+                });
+            }
         });
 
-        world.queue(|world| {
-            Camera::init(world);
-            world.flush();
-
-            world.build(CanvasManagerDescriptor);
-            world.build(TextManagerDescriptor);
-            RoundedRect::init(world);
-            RectangleMesh::<PaletteHslMaterial>::init(world);
-            RectangleMesh::<GridMaterial>::init(world);
-            world.insert(ColorScheme::default());
-        });
+        world.queue(Lnwindow::init_renderers);
 
         world.queue(|world| {
             world.insert(ToolColliderDispatcher);
@@ -151,33 +400,53 @@ impl Element for Lnwindow {
             world.insert(MultiTouchTool::default());
             world.insert(Focus::default());
             world.insert(ModifiersTool::default());
+            world.insert(Keymap);
         });
 
-        world.queue(|world| {
+        world.queue(move |world| Lnwindow::shortcuts(world, this));
+
+        world.queue(move |world| {
             let here = world.here();
 
-            let camera1 = Camera::build_from_save(world, "camera1");
+            let (camera1, camera2) = match primary {
+                None => (
+                    Camera::build_from_save(world, "camera1"),
+                    Camera::build_from_save(world, "camera2"),
+                ),
+                // start where the primary window is looking at
+                Some(primary) => {
+                    let (center, zoom) = world.enter(primary, || {
+                        let main_camera = world.single_fetch::<MainCamera>().unwrap();
+                        let camera = world.fetch(main_camera.0).unwrap();
+                        (camera.center, camera.zoom)
+                    });
+                    (
+                        Camera::build_unsaved(world, center, zoom),
+                        Camera::build_unsaved(world, PositionFract::ZERO, Fract::ZERO),
+                    )
+                }
+            };
             world.insert(MainCamera(camera1));
-
-            let camera2 = Camera::build_from_save(world, "camera2");
             world.insert(UICamera(camera2));
 
             world.flush();
 
-            world.enter(camera1, || {
-                world.option(ViewOptions { refs: vec![here] });
-            });
             world.enter(camera2, || {
                 world.option(ViewOptions { refs: vec![here] });
             });
 
+            let shared = primary.map(|primary| {
+                world.enter(primary, || world.single_fetch::<MainCamera>().unwrap().0)
+            });
+            Lnwindow::attach_stroke_layer(world, camera1, here, shared);
+
             world.flush();
             world.enter(camera1, || {
                 world.queue(|world| {
-                    world.insert(StrokeLayer::new(world));
-                    world.insert(Grid);
+                    world.insert(StrokeView::new(world));
                     world.insert(CameraUtils::default());
                 });
+                world.queue(Lnwindow::board);
             });
 
             world.flush();
@@ -203,6 +472,18 @@ impl Element for Lnwindow {
                 });
 
                 world.queue(side_panel);
+                world.queue(Lnwindow::follow_direction);
+                world.queue(move |world| {
+                    world.insert(Inspector::default());
+                    world.insert(MetricsOverlay::default());
+                    world.insert(Minimap::new(world));
+                    world.insert(ContextMenu::default());
+
+                    // scripts run once for the session, not again for every window
+                    if primary.is_none() {
+                        world.insert(ScriptHost::default());
+                    }
+                });
             });
         });
     }
@@ -218,48 +499,14 @@ fn side_panel(world: &mut World) {
         press_color: Srgba::new(0.863, 0.863, 0.863, 1.0),
         ..Default::default()
     });
+    let theme = world.single::<ThemeManager>().unwrap();
+    world.queue_trigger(theme, Attach::here(world, parent));
 
-    let child0 = world.insert(Button {
-        order: 10,
-        color: Srgba::new(0.5, 0.5, 0.5, 0.0),
-        active_color: Srgba::new(0.5, 0.5, 0.5, 0.2),
-        press_color: Srgba::new(0.5, 0.5, 0.5, 0.3),
-        shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
-        image: Some(ButtonImage {
-            transform: TransformValue::anchor(
-                (0.5, 0.5),
-                Rectangle::new_half(Position::ZERO, Size::splat(12)),
-            ),
-            bytes: include_bytes!("../res/interface/pen.png"),
-        }),
-        ..Default::default()
-    });
-
-    let child1 = world.insert(Button {
-        order: 10,
-        color: Srgba::new(0.5, 0.5, 0.5, 0.0),
-        active_color: Srgba::new(0.5, 0.5, 0.5, 0.2),
-        press_color: Srgba::new(0.5, 0.5, 0.5, 0.3),
-        shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
-        image: Some(ButtonImage {
-            transform: TransformValue::anchor(
-                (0.5, 0.5),
-                Rectangle::new_half(Position::ZERO, Size::splat(12)),
-            ),
-            bytes: include_bytes!("../res/interface/brush.png"),
-        }),
-        ..Default::default()
-    });
-
-    let child2 = world.insert(Button {
-        order: 10,
-        color: Srgba::new(0.5, 0.5, 0.5, 0.0),
-        active_color: Srgba::new(0.5, 0.5, 0.5, 0.2),
-        press_color: Srgba::new(0.5, 0.5, 0.5, 0.3),
-        shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
-        image: None,
-        ..Default::default()
-    });
+    let mut toolbar = Toolbar::default();
+    let child0 = toolbar.button(world, Some(include_bytes!("../res/interface/pen.png")));
+    let child1 = toolbar.button(world, Some(include_bytes!("../res/interface/brush.png")));
+    let presets = toolbar.button(world, Some(include_bytes!("../res/interface/pencil.png")));
+    let child2 = toolbar.button(world, None);
 
     let child2_color = world.insert(Button {
         order: 11,
@@ -269,23 +516,28 @@ fn side_panel(world: &mut World) {
         ..Default::default()
     });
 
+    world
+        .single_fetch::<PluginManager>()
+        .unwrap()
+        .interface(world, &mut toolbar);
+
+    // switches for the plugins, if there are any
+    let plugins = (world.single_fetch::<PluginManager>().unwrap().plugins())
+        .next()
+        .is_some()
+        .then(|| toolbar.button(world, Some(include_bytes!("../res/interface/plugin.png"))));
+
     let elastic_blank = world.insert(());
+    toolbar.push(
+        elastic_blank,
+        LuniChild {
+            basis: Some(0),
+            grow: Some(1.0),
+            ..Default::default()
+        },
+    );
 
-    let child3 = world.insert(Button {
-        order: 10,
-        color: Srgba::new(0.5, 0.5, 0.5, 0.0),
-        active_color: Srgba::new(0.5, 0.5, 0.5, 0.2),
-        press_color: Srgba::new(0.5, 0.5, 0.5, 0.3),
-        shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
-        image: Some(ButtonImage {
-            transform: TransformValue::anchor(
-                (0.5, 0.5),
-                Rectangle::new_half(Position::ZERO, Size::splat(12)),
-            ),
-            bytes: include_bytes!("../res/interface/compass.png"),
-        }),
-        ..Default::default()
-    });
+    let child3 = toolbar.button(world, Some(include_bytes!("../res/interface/compass.png")));
 
     world.insert(Transform {
         value: TransformValue {
@@ -305,13 +557,15 @@ fn side_panel(world: &mut World) {
                 anchor: 0.5,
                 offset: -150,
             },
+            logical: true,
         },
         source: lnwindow.untyped(),
         target: parent.untyped(),
     });
 
-    world.insert(LuniFlex {
-        parent: (
+    // as tall as the buttons, around the middle of the window
+    world.insert(LuniFlex::new(
+        (
             parent.untyped(),
             LuniParent {
                 axis: LuniAxis::Column,
@@ -322,6 +576,7 @@ fn side_panel(world: &mut World) {
                         bottom: 2,
                         right: 2,
                         top: 2,
+                        ..Default::default()
                     },
                     ..Default::default()
                 },
@@ -330,53 +585,18 @@ fn side_panel(world: &mut World) {
                     bottom: 6,
                     right: 6,
                     top: 6,
+                    ..Default::default()
                 },
+                hug: Some(LuniHug {
+                    main: true,
+                    anchor: (0.5, 0.5),
+                    ..Default::default()
+                }),
                 ..Default::default()
             },
         ),
-        children: vec![
-            (
-                child0.untyped(),
-                LuniChild {
-                    basis: Some(54),
-                    shrink: Some(1.0),
-                    ..Default::default()
-                },
-            ),
-            (
-                child1.untyped(),
-                LuniChild {
-                    basis: Some(54),
-                    shrink: Some(1.0),
-                    ..Default::default()
-                },
-            ),
-            (
-                child2.untyped(),
-                LuniChild {
-                    basis: Some(54),
-                    shrink: Some(1.0),
-                    ..Default::default()
-                },
-            ),
-            (
-                elastic_blank.untyped(),
-                LuniChild {
-                    basis: Some(0),
-                    grow: Some(1.0),
-                    ..Default::default()
-                },
-            ),
-            (
-                child3.untyped(),
-                LuniChild {
-                    basis: Some(54),
-                    shrink: Some(1.0),
-                    ..Default::default()
-                },
-            ),
-        ],
-    });
+        toolbar.entries,
+    ));
 
     world.insert(Transform {
         value: TransformValue::anchor(
@@ -419,25 +639,39 @@ fn side_panel(world: &mut World) {
         };
     });
 
+    let pen = Shortcut::new("brush.pen", &["KeyP"]);
+    Shortcut::register(world, pen, move |world| {
+        world.trigger(child0, &WidgetClick);
+    });
+
+    let brush = Shortcut::new("brush.brush", &["KeyB"]);
+    Shortcut::register(world, brush, move |world| {
+        world.trigger(child1, &WidgetClick);
+    });
+
     world.observer(child3, move |&WidgetClick, world| {
-        let main_camera = world.single_fetch::<MainCamera>().unwrap();
-        let mut camera = world
-            .enter_single_fetch_mut::<Camera>(main_camera.0)
-            .unwrap();
-        camera.center = PositionFract::ZERO;
+        Lnwindow::main_camera(world, |utils| {
+            let zoom = world.single_fetch::<Camera>().unwrap().zoom;
+            utils.fly_to(world, PositionFract::ZERO, zoom);
+        });
     });
 
     let main_panel_transform = TransformValue::anchor(
         (1.0, 0.5),
         Rectangle::new_half(Position::new(220, 0), Size::splat(180)),
-    );
+    )
+    .logical();
 
     let main_panel_transform_start = TransformValue::anchor(
         (1.0, 0.5),
         Rectangle::new_half(Position::new(110, 0), Size::splat(90)),
-    );
+    )
+    .logical();
 
-    let palette_transform = TransformValue::scale(0.8, 0.8);
+    let palette_transform =
+        TransformValue::anchor((0.5, 1.0), Rectangle::new(-144, -264, 144, -20));
+    let size_transform = TransformValue::anchor((0.5, 0.0), Rectangle::new(-144, 52, 144, 76));
+    let flow_transform = TransformValue::anchor((0.5, 0.0), Rectangle::new(-144, 20, 144, 44));
 
     let main_panel = world.insert(Button {
         attach_pointer: false,
@@ -466,35 +700,336 @@ fn side_panel(world: &mut World) {
         target: palette.untyped(),
     });
 
+    let modifier = world.single_fetch::<StrokeLayer>().unwrap().modifier;
+    let size = world.insert(Slider {
+        enabled: false,
+        value: modifier.max_size,
+        min: 1.0,
+        max: 50.0,
+        step: 0.5,
+        ..Default::default()
+    });
+    let flow = world.insert(Slider {
+        enabled: false,
+        value: modifier.max_flow,
+        min: 0.05,
+        max: 1.0,
+        step: 0.05,
+        ..Default::default()
+    });
+
+    world.dependency(size, main_panel);
+    world.dependency(flow, main_panel);
+
+    world.queue_trigger(theme, Attach::here(world, main_panel));
+    world.queue_trigger(theme, Attach::here(world, size));
+    world.queue_trigger(theme, Attach::here(world, flow));
+
+    world.insert(Transform {
+        value: size_transform,
+        source: main_panel.untyped(),
+        target: size.untyped(),
+    });
+
+    world.insert(Transform {
+        value: flow_transform,
+        source: main_panel.untyped(),
+        target: flow.untyped(),
+    });
+
+    world.observer(size, move |&Slide(value), world| {
+        let mut layer = world.single_fetch_mut::<StrokeLayer>().unwrap();
+        layer.modifier.max_size = value;
+    });
+
+    world.observer(size, move |&Leap(value), world| {
+        let mut layer = world.single_fetch_mut::<StrokeLayer>().unwrap();
+        layer.modifier.max_size = value;
+    });
+
+    world.observer(flow, move |&Slide(value), world| {
+        let mut layer = world.single_fetch_mut::<StrokeLayer>().unwrap();
+        layer.modifier.max_flow = value;
+    });
+
+    world.observer(flow, move |&Leap(value), world| {
+        let mut layer = world.single_fetch_mut::<StrokeLayer>().unwrap();
+        layer.modifier.max_flow = value;
+    });
+
+    // the brushes bring sizes and flows of their own
+    for child in [child0, child1] {
+        world.observer(child, move |&WidgetClick, world| {
+            let modifier = world.single_fetch::<StrokeLayer>().unwrap().modifier;
+            world.fetch_mut(size).unwrap().value = modifier.max_size;
+            world.fetch_mut(flow).unwrap().value = modifier.max_flow;
+        });
+    }
+
+    // brush presets, spread to the right of the toolbar
+
+    let preset_menu = world.build(MenuDescriptor {
+        tile: Size::splat(54),
+        ..Default::default()
+    });
+
+    world.observer(presets, move |&WidgetRectangle(rect), world| {
+        world.trigger(preset_menu, &WidgetRectangle(rect));
+    });
+
+    // told the direction of the button along with it
+    world.dependency(preset_menu, presets);
+
+    world.observer(presets, move |&WidgetClick, world| {
+        let expanded = world.fetch(preset_menu).unwrap().expanded;
+        world.trigger(preset_menu, &WidgetExpanded(!expanded));
+    });
+
+    let mut index = 0;
+    while let Some(preset) = brush_preset(index, modifier) {
+        let entry = world.build(MenuEntryDescriptor {
+            menu: preset_menu,
+            order: 20,
+        });
+
+        let frame = world.insert(Button {
+            attach_pointer: false,
+            enabled: false,
+            order: 20,
+            ..Default::default()
+        });
+        world.queue_trigger(theme, Attach::here(world, frame));
+
+        // a dot as large as the brush
+        let dot_size = (preset.max_size as u32).clamp(4, 36) / 2;
+        let dot = world.insert(Button {
+            attach_pointer: false,
+            enabled: false,
+            order: 21,
+            color: Srgba::new(0.2, 0.2, 0.2, 1.0),
+            shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
+            roundness: dot_size as f32,
+            ..Default::default()
+        });
+
+        world.dependency(frame, entry);
+        world.dependency(dot, entry);
+
+        world.insert(Transform {
+            value: TransformValue::copy(),
+            source: entry.untyped(),
+            target: frame.untyped(),
+        });
+
+        world.insert(Transform {
+            value: TransformValue::anchor(
+                (0.5, 0.5),
+                Rectangle::new_half(Position::ZERO, Size::splat(dot_size)),
+            ),
+            source: entry.untyped(),
+            target: dot.untyped(),
+        });
+
+        world.observer(entry, move |event: &WidgetEnabled, world| {
+            world.trigger(frame, event);
+            world.trigger(dot, event);
+        });
+
+        world.observer(entry, move |event: &WidgetHover, world| {
+            world.trigger(frame, event);
+        });
+
+        world.observer(entry, move |event: &WidgetButton, world| {
+            world.trigger(frame, event);
+        });
+
+        index += 1;
+    }
+
+    world.observer(preset_menu, move |&WidgetSelect(index), world| {
+        let mut stroke = world.single_fetch_mut::<StrokeLayer>().unwrap();
+        let Some(preset) = index.and_then(|index| brush_preset(index as usize, stroke.modifier))
+        else {
+            return;
+        };
+        stroke.modifier = preset;
+        drop(stroke);
+
+        world.trigger(child0, &ButtonChecked(false));
+        world.trigger(child1, &ButtonChecked(false));
+        world.fetch_mut(size).unwrap().value = preset.max_size;
+        world.fetch_mut(flow).unwrap().value = preset.max_flow;
+    });
+
     world.observer(palette, move |&WidgetHsla(color), world| {
         let mut layer = world.single_fetch_mut::<StrokeLayer>().unwrap();
         layer.modifier.color = color.into_color();
         world.queue_trigger(child2_color, ButtonColor(color.into_color()));
     });
 
+    // colors picked from the board, the layer outlives the window
+    let stroke = world.single::<StrokeLayer>().unwrap();
+    let ob = world.observer(stroke, move |&StrokeColorPicked(color), world| {
+        let color: Hsla = color.into_color();
+        world.fetch_mut(palette).unwrap().color = color;
+        world.trigger(palette, &WidgetHsla(color));
+    });
+    world.dependency(ob, palette);
+
     world.observer(child2, move |&WidgetClick, world| {
         let main_panel = world.fetch(main_panel).unwrap();
         let child2 = world.fetch(child2).unwrap();
         world.queue_trigger(main_panel.handle(), WidgetEnabled(!main_panel.enabled));
         world.queue_trigger(palette, WidgetEnabled(!main_panel.enabled));
+        world.queue_trigger(size, WidgetEnabled(!main_panel.enabled));
+        world.queue_trigger(flow, WidgetEnabled(!main_panel.enabled));
 
         if !main_panel.enabled {
+            let direction = world.single_fetch::<Locale>().unwrap().direction();
             world.queue_trigger(
                 main_panel.handle(),
                 ButtonAnim {
-                    src: main_panel_transform_start.compute(child2.rect),
-                    dst: main_panel_transform.compute(child2.rect),
+                    src: main_panel_transform_start
+                        .resolve(direction)
+                        .compute(child2.rect),
+                    dst: main_panel_transform.resolve(direction).compute(child2.rect),
                     hidden_after_finished: false,
                 },
             );
         }
     });
 
+    if let Some(plugins) = plugins {
+        plugin_menu(world, plugins);
+    }
+
     world.queue_trigger(parent, WidgetRectangle(Rectangle::new(0, 0, 500, 100)));
 }
 
+/// A row for each plugin to the end of `button`, whose check box saves whether the plugin
+/// is enabled from the next launch on.
+fn plugin_menu(world: &World, button: Handle<Button>) {
+    let plugins = (world.single_fetch::<PluginManager>().unwrap().plugins()).collect::<Vec<_>>();
+
+    let menu = world.build(MenuDescriptor {
+        tile: Size::new(200, 54),
+        collapse_on_select: false,
+        ..Default::default()
+    });
+
+    world.observer(button, move |&WidgetRectangle(rect), world| {
+        world.trigger(menu, &WidgetRectangle(rect));
+    });
+
+    // told the direction of the button along with it
+    world.dependency(menu, button);
+
+    world.observer(button, move |&WidgetClick, world| {
+        let expanded = world.fetch(menu).unwrap().expanded;
+        world.trigger(menu, &WidgetExpanded(!expanded));
+    });
+
+    let theme = world.single::<ThemeManager>().unwrap();
+    for &(name, enabled) in &plugins {
+        let entry = world.build(MenuEntryDescriptor { menu, order: 20 });
+
+        let frame = world.insert(Button {
+            attach_pointer: false,
+            enabled: false,
+            order: 20,
+            ..Default::default()
+        });
+        let check = world.insert(CheckButton {
+            order: 21,
+            enabled: false,
+            checked: enabled,
+            ..Default::default()
+        });
+        world.queue_trigger(theme, Attach::here(world, frame));
+        world.queue_trigger(theme, Attach::here(world, check));
+
+        world.dependency(frame, entry);
+        world.dependency(check, entry);
+
+        world.insert(Transform {
+            value: TransformValue::copy(),
+            source: entry.untyped(),
+            target: frame.untyped(),
+        });
+
+        world.insert(Transform {
+            value: TransformValue::anchor((0.0, 0.5), Rectangle::new(15, -12, 39, 12)).logical(),
+            source: entry.untyped(),
+            target: check.untyped(),
+        });
+
+        let text = Rc::new(Cell::new(None));
+        let name_text = text.clone();
+        world.observer(entry, move |event: &WidgetEnabled, world| {
+            world.trigger(frame, event);
+            world.trigger(check, event);
+            plugin_name(world, entry, frame, name, &name_text);
+        });
+
+        world.observer(entry, move |&WidgetRectangle(_), world| {
+            plugin_name(world, entry, frame, name, &text);
+        });
+
+        world.observer(entry, move |event: &WidgetHover, world| {
+            world.trigger(frame, event);
+        });
+
+        world.observer(check, move |&Switch(enabled), world| {
+            let db = world.single_fetch::<SaveDatabase>().unwrap();
+            let plugins = world.single_fetch::<PluginManager>().unwrap();
+            if let Err(err) = plugins.save_enabled(&db, name, enabled) {
+                log::error!("failed to save plugin {name}: {err}");
+            }
+        });
+    }
+}
+
+/// Lays the name of a plugin out again over its row, dropping the last layout. Texts are
+/// laid out once, so the name is put down again as the row moves.
+fn plugin_name(
+    world: &World,
+    entry: Handle<MenuEntry>,
+    frame: Handle<Button>,
+    name: &str,
+    text: &Cell<Option<Handle<Text>>>,
+) {
+    if let Some(text) = text.take() {
+        let _ = world.remove(text);
+    }
+    if !world.fetch(frame).unwrap().enabled {
+        return;
+    }
+
+    let rect = world.fetch(entry).unwrap().rect;
+    let built = world.build(TextDescriptor {
+        text: name,
+        spans: &[],
+        rect: Rectangle::new(
+            rect.left() + 48,
+            rect.down() + 17,
+            rect.right() - 48,
+            rect.up() - 17,
+        ),
+        metrics: cosmic_text::Metrics::new(14.0, 20.0),
+        order: 21,
+        visible: true,
+        clip: None,
+    });
+    world.dependency(built, entry);
+    text.set(Some(built));
+}
+
 impl Lnwindow {
-    fn new(event_loop: &dyn ActiveEventLoop) -> Lnwindow {
+    fn new(
+        event_loop: &dyn ActiveEventLoop,
+        primary: Option<Handle<Lnwindow>>,
+        index: usize,
+    ) -> Lnwindow {
         let win_attr = WindowAttributes::default()
             .with_transparent(true)
             .with_title("LnDrawer");
@@ -502,7 +1037,257 @@ impl Lnwindow {
         let window = event_loop.create_window(win_attr).unwrap();
         let window = Arc::from(window);
 
-        Lnwindow { window }
+        Lnwindow {
+            window,
+            primary,
+            index,
+            requested: 0,
+        }
+    }
+
+    /// Open a secondary window once the current event is handled. It lives as long as the
+    /// primary window, which owns the board and ends the session when closed.
+    pub fn open_secondary(&mut self) {
+        self.requested += 1;
+    }
+
+    /// Put a new [`StrokeLayer`] into `camera`, or let it see the one of the `shared` camera.
+    /// What draws in the view of a window, for all its cameras.
+    fn init_renderers(world: &mut World) {
+        Camera::init(world);
+        world.flush();
+
+        world.build(CanvasManagerDescriptor);
+        world.build(TextManagerDescriptor);
+        RoundedRect::init(world);
+        RectangleMesh::<PaletteHslMaterial>::init(world);
+        RectangleMesh::<GridMaterial>::init(world);
+        world.insert(ThemeManager::new(Luni::new(ColorScheme::user(world))));
+        world.flush();
+
+        world.single_fetch::<PluginManager>().unwrap().init(world);
+    }
+
+    /// What is on the board besides the strokes, in the view of the main camera.
+    fn board(world: &mut World) {
+        world.insert(NoteBoard::default());
+        world.insert(Grid);
+        world.flush();
+
+        world.single_fetch::<PluginManager>().unwrap().canvas(world);
+    }
+
+    fn attach_stroke_layer(
+        world: &World,
+        camera: Handle<Camera>,
+        here: Handle,
+        shared: Option<Handle<Camera>>,
+    ) {
+        match shared {
+            None => world.enter(camera, || {
+                world.option(ViewOptions { refs: vec![here] });
+                world.queue(|world| {
+                    world.insert(StrokeLayer::new(world));
+                });
+            }),
+            Some(shared) => {
+                let stroke = world.enter(shared, || world.single::<StrokeLayer>().unwrap());
+                world.enter(camera, || {
+                    world.option(ViewOptions {
+                        refs: vec![here, stroke.untyped()],
+                    });
+                });
+            }
+        }
+    }
+
+    /// The shortcuts of the window itself and of its main camera.
+    fn shortcuts(world: &World, this: Handle<Self>) {
+        let new_window = Shortcut::new("window.new", &["Ctrl+KeyN"]);
+        Shortcut::register(world, new_window, move |world| {
+            world.fetch_mut(this).unwrap().open_secondary();
+        });
+
+        let rotate = [
+            ("camera.rotate_left", "Ctrl+BracketLeft", ROTATE_STEP),
+            ("camera.rotate_right", "Ctrl+BracketRight", -ROTATE_STEP),
+        ];
+        for (name, chord, step) in rotate {
+            let rotate = Shortcut::new(name, &[chord]).repeat();
+            Shortcut::register(world, rotate, move |world| {
+                Lnwindow::main_camera(world, |utils| utils.rotate_delta(world, step));
+            });
+        }
+
+        let flip = Shortcut::new("camera.flip", &["Ctrl+KeyH"]);
+        Shortcut::register(world, flip, |world| {
+            Lnwindow::main_camera(world, |utils| utils.flip(world));
+        });
+
+        let zoom = [
+            (
+                "camera.zoom_in",
+                ["Ctrl+Equal", "Ctrl+NumpadAdd"],
+                ZOOM_STEP,
+            ),
+            (
+                "camera.zoom_out",
+                ["Ctrl+Minus", "Ctrl+NumpadSubtract"],
+                -ZOOM_STEP,
+            ),
+        ];
+        for (name, chords, step) in zoom {
+            let zoom = Shortcut::new(name, &chords).repeat();
+            Shortcut::register(world, zoom, move |world| {
+                Lnwindow::main_camera(world, |utils| {
+                    utils.zoom_delta(world, Fract::from_f64(step));
+                });
+            });
+        }
+
+        let pan = [
+            ("camera.pan_left", "ArrowLeft", [-PAN_STEP, 0.0]),
+            ("camera.pan_right", "ArrowRight", [PAN_STEP, 0.0]),
+            ("camera.pan_down", "ArrowDown", [0.0, -PAN_STEP]),
+            ("camera.pan_up", "ArrowUp", [0.0, PAN_STEP]),
+        ];
+        for (name, chord, delta) in pan {
+            let pan = Shortcut::new(name, &[chord]).repeat();
+            Shortcut::register(world, pan, move |world| {
+                Lnwindow::main_camera(world, |utils| {
+                    let camera = world.single_fetch::<Camera>().unwrap();
+                    let center = camera.center + camera.screen_to_world_relative(delta);
+                    drop(camera);
+                    utils.move_to(world, center);
+                });
+            });
+        }
+
+        let fit = Shortcut::new("camera.fit", &["Ctrl+Digit0"]);
+        Shortcut::register(world, fit, |world| {
+            Lnwindow::main_camera(world, |utils| {
+                let stroke = world.single_fetch::<StrokeLayer>().unwrap();
+                match stroke.content_bounds() {
+                    Some(bounds) => utils.fly_to_fit(world, bounds),
+                    None => utils.fly_to(world, PositionFract::ZERO, Fract::ZERO),
+                }
+            });
+        });
+
+        for digit in 1..=9 {
+            let jump = Shortcut::new(
+                &format!("camera.bookmark_{digit}"),
+                &[format!("Ctrl+Digit{digit}").as_str()],
+            );
+            Shortcut::register(world, jump, move |world| {
+                Lnwindow::main_camera(world, |utils| {
+                    let db = world.single_fetch::<SaveDatabase>().unwrap();
+                    match Camera::bookmark(&db, &digit.to_string()) {
+                        Ok(Some(desc)) => utils.fly_to_view(
+                            world,
+                            desc.center,
+                            desc.zoom,
+                            desc.rotation,
+                            desc.flip,
+                        ),
+                        Ok(None) => {}
+                        Err(err) => log::error!("failed to load bookmark {digit}: {err}"),
+                    }
+                });
+            });
+
+            let save = Shortcut::new(
+                &format!("camera.save_bookmark_{digit}"),
+                &[format!("Ctrl+Shift+Digit{digit}").as_str()],
+            );
+            Shortcut::register(world, save, move |world| {
+                Lnwindow::main_camera(world, |_| {
+                    let db = world.single_fetch::<SaveDatabase>().unwrap();
+                    let camera = world.single_fetch::<Camera>().unwrap();
+                    if let Err(err) = camera.save_bookmark(&db, &digit.to_string()) {
+                        log::error!("failed to save bookmark {digit}: {err}");
+                    }
+                });
+            });
+        }
+
+        let mut dark = false;
+        let theme = Shortcut::new("theme.dark", &["Ctrl+Shift+KeyT"]);
+        Shortcut::register(world, theme, move |world| {
+            dark = !dark;
+            let luni = match dark {
+                true => Luni::dark(),
+                false => Luni::new(ColorScheme::user(world)),
+            };
+            let manager = world.single::<ThemeManager>().unwrap();
+            ThemeManager::switch(world, manager, luni);
+        });
+
+        let language = Shortcut::new("locale.next", &["Ctrl+Shift+KeyL"]);
+        Shortcut::register(world, language, |world| {
+            let mut locale = world.single_fetch_mut::<Locale>().unwrap();
+            let languages = Locale::languages().collect::<Vec<_>>();
+            let current = languages.iter().position(|&tag| tag == locale.language());
+            let next = languages[current.map_or(0, |idx| (idx + 1) % languages.len())];
+            locale.preferred = Some(next.into());
+        });
+
+        let mirror = Shortcut::new("locale.mirror", &["Ctrl+Shift+KeyM"]);
+        Shortcut::register(world, mirror, |world| {
+            let mut locale = world.single_fetch_mut::<Locale>().unwrap();
+            locale.direction = Some(locale.direction().flip());
+        });
+
+        let present = Shortcut::new("render.present", &["Ctrl+Shift+KeyP"]);
+        Shortcut::register(world, present, |world| {
+            let mut settings = world.single_fetch_mut::<RenderSettings>().unwrap();
+            settings.present = settings.present.next();
+            log::info!("presenting with {:?}", settings.present);
+        });
+
+        let msaa = Shortcut::new("render.msaa", &["Ctrl+Shift+KeyA"]);
+        Shortcut::register(world, msaa, |world| {
+            let mut settings = world.single_fetch_mut::<RenderSettings>().unwrap();
+            settings.msaa = settings.next_msaa();
+            log::info!("rendering with {} samples per pixel", settings.msaa);
+        });
+
+        let hdr = Shortcut::new("render.hdr", &["Ctrl+Shift+KeyR"]);
+        Shortcut::register(world, hdr, |world| {
+            let mut settings = world.single_fetch_mut::<RenderSettings>().unwrap();
+            settings.hdr = !settings.hdr;
+            log::info!("hdr surfaces preferred: {}", settings.hdr);
+        });
+    }
+
+    /// Lays the UI out in the writing direction of the [`Locale`], again once it changes.
+    fn follow_direction(world: &mut World) {
+        // the layouts are inserted first
+        world.flush();
+
+        let lnwindow = world.single::<Lnwindow>().unwrap();
+        let locale = world.single::<Locale>().unwrap();
+
+        let direction = world.fetch(locale).unwrap().direction();
+        world.broadcast(lnwindow, &WidgetDirection(direction));
+
+        let ob = world.observer(locale, move |LocaleChanged, world| {
+            let direction = world.fetch(locale).unwrap().direction();
+            world.broadcast(lnwindow, &WidgetDirection(direction));
+
+            let size = world.fetch(lnwindow).unwrap().window.surface_size();
+            let rect =
+                Rectangle::new_half(Position::ZERO, Size::new(size.width / 2, size.height / 2));
+            world.trigger(lnwindow, &WidgetRectangle(rect));
+        });
+        world.dependency(ob, lnwindow);
+    }
+
+    fn main_camera(world: &World, f: impl FnOnce(&mut CameraUtils)) {
+        let main = world.single_fetch::<MainCamera>().unwrap();
+        world.enter(main.0, || {
+            f(&mut world.single_fetch_mut::<CameraUtils>().unwrap());
+        });
     }
 
     pub fn cursor_to_screen(&self, position: PhysicalPosition<f64>) -> [f64; 2] {
@@ -513,8 +1298,168 @@ impl Lnwindow {
     }
 }
 
+/// The preset at `index` over `modifier`, keeping its color.
+fn brush_preset(index: usize, modifier: Modifier) -> Option<Modifier> {
+    let (min_size, max_size, min_flow, max_flow, softness) = match index {
+        // fineliner
+        0 => (0.5, 2.0, 0.9, 1.0, 0.1),
+        // marker
+        1 => (6.0, 10.0, 0.5, 0.6, 0.3),
+        // airbrush
+        2 => (10.0, 60.0, 0.02, 0.15, 1.0),
+        // wash
+        3 => (2.0, 40.0, 0.05, 0.8, 0.6),
+        _ => return None,
+    };
+
+    Some(Modifier {
+        min_size,
+        max_size,
+        size_force_exp: 1.0,
+        min_flow,
+        max_flow,
+        flow_force_exp: 1.5,
+        softness,
+        ..modifier
+    })
+}
+
 #[cfg(target_os = "android")]
 pub struct LnAndroid(pub AndroidApp);
 
 #[cfg(target_os = "android")]
 impl Element for LnAndroid {}
+
+#[cfg(test)]
+mod test {
+    use redb::{Database, backends::InMemoryBackend};
+
+    use super::*;
+    use crate::stroke::interpolate::Draw;
+
+    /// Like the slot of a window, with an offscreen [`Render`] on the software adapter if
+    /// there is no other.
+    fn offscreen_slot(world: &World) -> Handle {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        world.insert(SaveDatabase(Arc::new(db)));
+
+        let root = world.here();
+        let slot = world.insert(());
+        world.enter(slot, || {
+            world.option(ViewOptions { refs: vec![root] });
+            world.queue(|world| {
+                let db = world.single_fetch::<SaveDatabase>().unwrap();
+                let settings = RenderSettings::load(&db);
+                drop(db);
+
+                let size = PhysicalSize::new(64, 64);
+                world.insert(pollster::block_on(Render::new_offscreen(size, &settings)));
+                world.insert(settings);
+                world.flush();
+                Camera::init(world);
+            });
+        });
+        slot
+    }
+
+    #[test]
+    fn windows_share_the_stroke_layer() {
+        let mut world = World::default();
+        let slot = offscreen_slot(&world);
+        world.flush();
+
+        let cameras = world.enter(slot, || {
+            [(); 2].map(|_| Camera::build_unsaved(&world, PositionFract::ZERO, Fract::ZERO))
+        });
+        let [primary, secondary] = cameras;
+        world.flush();
+
+        Lnwindow::attach_stroke_layer(&world, primary, slot, None);
+        world.flush();
+        Lnwindow::attach_stroke_layer(&world, secondary, slot, Some(primary));
+        world.flush();
+
+        let stroke = world.enter(primary, || world.single::<StrokeLayer>().unwrap());
+        let seen = world.enter(secondary, || world.single::<StrokeLayer>().unwrap());
+        assert_eq!(seen, stroke);
+
+        world.enter(secondary, || {
+            world.single_fetch_mut::<StrokeLayer>().unwrap().shape = 2;
+        });
+        let shape = world.enter(primary, || {
+            world.single_fetch::<StrokeLayer>().unwrap().shape
+        });
+        assert_eq!(shape, 2);
+
+        // painted through one camera, seen through the other
+        let draw = |x| Draw {
+            position: PositionFract::new(Fract::from_f64(x), Fract::ZERO),
+            force: 1.0,
+        };
+        world.enter(secondary, || {
+            let mut layer = world.single_fetch_mut::<StrokeLayer>().unwrap();
+            let modifier = layer.modifier;
+            layer.stroke(vec![draw(-20.0), draw(20.0)], modifier, &world);
+            assert!(layer.wait_loaded(&world, Duration::from_secs(10)));
+        });
+        world.flush();
+
+        let picked = world.enter(primary, || {
+            let layer = world.single_fetch::<StrokeLayer>().unwrap();
+            (
+                layer.pick(&world, Position::ZERO),
+                layer.pick(&world, Position::new(0, 300)),
+            )
+        });
+        assert!(picked.0.is_some_and(|color| color.alpha > 0.0));
+        assert_eq!(picked.1.map(|color| color.alpha), Some(0.0));
+
+        // a secondary window closing leaves the layer to the primary one
+        world.remove(secondary).unwrap();
+        world.flush();
+        assert_eq!(world.fetch(stroke).map(|layer| layer.shape).ok(), Some(2));
+    }
+
+    #[test]
+    fn board_rendered() {
+        let mut lnwin = Lnwin::default();
+        let world = &mut lnwin.world;
+        let slot = offscreen_slot(world);
+        world.flush();
+
+        // a stroke saved by an earlier session
+        let camera = world.enter(slot, || {
+            Camera::build_unsaved(world, PositionFract::ZERO, Fract::ZERO)
+        });
+        world.flush();
+        Lnwindow::attach_stroke_layer(world, camera, slot, None);
+        world.flush();
+        world.enter(camera, || {
+            let mut layer = world.single_fetch_mut::<StrokeLayer>().unwrap();
+            let modifier = layer.modifier;
+            let draws = [-20.0, 20.0].map(|x| Draw {
+                position: PositionFract::new(Fract::from_f64(x), Fract::ZERO),
+                force: 1.0,
+            });
+            layer.stroke(draws.into(), modifier, world);
+            assert!(layer.wait_loaded(world, Duration::from_secs(10)));
+        });
+        world.flush();
+        Autosave::autosave_all(world);
+        world.remove(slot).unwrap();
+        world.flush();
+
+        let path = std::env::temp_dir().join(format!("ln_drawer_board_{}.png", std::process::id()));
+        lnwin
+            .render_board(&path, PhysicalSize::new(64, 48))
+            .unwrap();
+        let image = image::open(&path).unwrap().into_rgba8();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(image.dimensions(), (64, 48));
+        // the stroke over the board, and the board alone in a corner
+        assert_ne!(image.get_pixel(32, 24), image.get_pixel(1, 1));
+    }
+}
@@ -0,0 +1,573 @@
+use std::fmt;
+
+use cosmic_text::Metrics;
+use hashbrown::HashMap;
+use ln_world::{Element, Handle, World};
+use redb::ReadableDatabase;
+
+use crate::{
+    layout::WritingDirection,
+    measures::Rectangle,
+    render::{
+        RenderControl,
+        settings::TABLE_SETTINGS,
+        text::{Text, TextDescriptor},
+    },
+    save::SaveDatabase,
+    widgets::{WidgetEnabled, WidgetRectangle},
+};
+
+const SETTINGS_LOCALE: &str = "locale";
+
+/// Used for messages missing from the catalog of the language asked for.
+const FALLBACK: &str = "en-US";
+
+/// Primary languages written right to left.
+const RTL_LANGUAGES: &[&str] = &["ar", "fa", "he", "ur"];
+
+/// The catalogs shipped with the app, by language tag.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en-US", include_str!("../res/locales/en-US.toml")),
+    ("zh-CN", include_str!("../res/locales/zh-CN.toml")),
+];
+
+/// Messages of a language, by their dotted path in the TOML file.
+struct Catalog {
+    messages: HashMap<String, Message>,
+}
+
+enum Message {
+    Plain(String),
+    /// Forms by plural category like `one`, `other` always being there.
+    Plural(HashMap<String, String>),
+}
+
+/// Replaces `{name}` in a message. Numbers also pick the form of plural messages when
+/// named `count`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageArg {
+    Text(String),
+    Number(f64),
+}
+
+/// The language of the UI strings, shared by every window.
+///
+/// Detected from the system unless the user picked one, which is saved once modified.
+/// Triggers [`LocaleChanged`] on itself after switching.
+pub struct Locale {
+    /// A language tag like `zh-CN`, need not be shipped.
+    pub preferred: Option<String>,
+    /// Forces the direction of the layouts, which otherwise follows the language.
+    pub direction: Option<WritingDirection>,
+    language: &'static str,
+    resolved: WritingDirection,
+    catalogs: HashMap<&'static str, Catalog>,
+}
+
+/// The saved [`Locale::preferred`] and [`Locale::direction`].
+type LocalePreference = (Option<String>, Option<WritingDirection>);
+
+/// Sent to the [`Locale`] once its language or writing direction has changed.
+pub struct LocaleChanged;
+
+/// A [`Text`] showing a message, laid out again whenever the language or its rectangle
+/// changes. Send [`WidgetRectangle`] to move it and [`WidgetEnabled`] to hide it.
+pub struct LocalizedText {
+    pub key: String,
+    pub args: Vec<(String, MessageArg)>,
+    pub rect: Rectangle,
+    pub metrics: Metrics,
+    pub order: isize,
+    pub visible: bool,
+    pub clip: Option<Rectangle>,
+    text: Option<Handle<Text>>,
+}
+
+impl Catalog {
+    fn parse(source: &str) -> Result<Catalog, toml::de::Error> {
+        let table: toml::Table = toml::from_str(source)?;
+        let mut messages = HashMap::new();
+        Catalog::flatten(&mut messages, "", table);
+        Ok(Catalog { messages })
+    }
+
+    fn flatten(messages: &mut HashMap<String, Message>, prefix: &str, table: toml::Table) {
+        for (key, value) in table {
+            let path = match prefix.is_empty() {
+                true => key,
+                false => format!("{prefix}.{key}"),
+            };
+
+            match value {
+                toml::Value::String(text) => {
+                    messages.insert(path, Message::Plain(text));
+                }
+                toml::Value::Table(table) if table.get("other").is_some_and(|v| v.is_str()) => {
+                    let forms = (table.into_iter())
+                        .filter_map(|(form, text)| Some((form, text.as_str()?.to_owned())))
+                        .collect();
+                    messages.insert(path, Message::Plural(forms));
+                }
+                toml::Value::Table(table) => Catalog::flatten(messages, &path, table),
+                _ => log::warn!("message {path} is neither text nor a table"),
+            }
+        }
+    }
+}
+
+impl Message {
+    fn format<K: AsRef<str>>(&self, language: &str, args: &[(K, MessageArg)]) -> String {
+        let text = match self {
+            Message::Plain(text) => text,
+            Message::Plural(forms) => {
+                let count = args.iter().find_map(|(name, arg)| match arg {
+                    MessageArg::Number(count) if name.as_ref() == "count" => Some(*count),
+                    _ => None,
+                });
+                let category = count.map_or("other", |count| plural_category(language, count));
+                forms.get(category).unwrap_or(&forms["other"])
+            }
+        };
+
+        let mut formatted = String::with_capacity(text.len());
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find('{') {
+            formatted.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+
+            let name = &rest[1..end];
+            match args.iter().find(|(key, _)| key.as_ref() == name) {
+                Some((_, arg)) => formatted.push_str(&arg.to_string()),
+                None => formatted.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        formatted.push_str(rest);
+        formatted
+    }
+}
+
+/// The CLDR plural category of `count`, for the shipped languages only.
+fn plural_category(language: &str, count: f64) -> &'static str {
+    match language.split('-').next() {
+        Some("zh" | "ja" | "ko") => "other",
+        _ if count == 1.0 => "one",
+        _ => "other",
+    }
+}
+
+/// The language of the system like `zh-CN`. The POSIX variables come first, in their order
+/// of precedence, then what the platform reports.
+pub fn system_locale() -> Option<String> {
+    let posix = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty());
+
+    match posix {
+        Some(value) => posix_language(&value),
+        None => sys_locale::get_locale(),
+    }
+}
+
+/// The language of a POSIX locale like `zh_CN.UTF-8@latin`, none for `C` and `POSIX`.
+fn posix_language(locale: &str) -> Option<String> {
+    let language = locale.split(['.', '@']).next()?;
+    match language {
+        "" | "C" | "POSIX" => None,
+        language => Some(language.replace('_', "-")),
+    }
+}
+
+impl fmt::Display for MessageArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageArg::Text(text) => f.write_str(text),
+            MessageArg::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+                write!(f, "{}", *number as i64)
+            }
+            MessageArg::Number(number) => write!(f, "{number}"),
+        }
+    }
+}
+
+impl From<&str> for MessageArg {
+    fn from(value: &str) -> Self {
+        MessageArg::Text(value.into())
+    }
+}
+
+impl From<String> for MessageArg {
+    fn from(value: String) -> Self {
+        MessageArg::Text(value)
+    }
+}
+
+impl From<f64> for MessageArg {
+    fn from(value: f64) -> Self {
+        MessageArg::Number(value)
+    }
+}
+
+impl From<i32> for MessageArg {
+    fn from(value: i32) -> Self {
+        MessageArg::Number(value as f64)
+    }
+}
+
+impl From<usize> for MessageArg {
+    fn from(value: usize) -> Self {
+        MessageArg::Number(value as f64)
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        let catalogs = (CATALOGS.iter())
+            .map(|&(language, source)| (language, Catalog::parse(source).unwrap()))
+            .collect();
+
+        let mut locale = Locale {
+            preferred: None,
+            direction: None,
+            language: FALLBACK,
+            resolved: WritingDirection::LeftToRight,
+            catalogs,
+        };
+        locale.language = locale.resolve();
+        locale.resolved = locale.resolve_direction(locale.language);
+        locale
+    }
+}
+
+impl Locale {
+    /// The tags of the shipped languages.
+    pub fn languages() -> impl Iterator<Item = &'static str> {
+        CATALOGS.iter().map(|&(language, _)| language)
+    }
+
+    /// The shipped language in use.
+    pub fn language(&self) -> &'static str {
+        self.language
+    }
+
+    /// The direction layouts are written in.
+    pub fn direction(&self) -> WritingDirection {
+        self.resolved
+    }
+
+    /// The message in the current language, or in English if it is not translated.
+    pub fn message(&self, key: &str) -> String {
+        self.format::<&str>(key, &[])
+    }
+
+    /// Like [`Locale::message`], with the arguments replaced. Missing messages show their
+    /// key.
+    pub fn format<K: AsRef<str>>(&self, key: &str, args: &[(K, MessageArg)]) -> String {
+        self.try_format(key, args).unwrap_or_else(|| {
+            log::warn!("message {key} is missing");
+            key.into()
+        })
+    }
+
+    /// `None` if the message is missing from English too.
+    pub fn try_message(&self, key: &str) -> Option<String> {
+        self.try_format::<&str>(key, &[])
+    }
+
+    /// Like [`Locale::try_message`], with the arguments replaced.
+    pub fn try_format<K: AsRef<str>>(&self, key: &str, args: &[(K, MessageArg)]) -> Option<String> {
+        [self.language, FALLBACK].into_iter().find_map(|language| {
+            let message = self.catalogs[language].messages.get(key)?;
+            Some(message.format(language, args))
+        })
+    }
+
+    /// The preferred language if shipped, or one sharing its primary language, or else the
+    /// same for the language of the system.
+    fn resolve(&self) -> &'static str {
+        let wanted = [self.preferred.clone(), system_locale()];
+        for wanted in wanted.into_iter().flatten() {
+            if let Some(language) =
+                Locale::languages().find(|&tag| tag.eq_ignore_ascii_case(&wanted))
+            {
+                return language;
+            }
+
+            let primary = wanted.split('-').next().unwrap_or_default();
+            if let Some(language) =
+                Locale::languages().find(|tag| tag.split('-').next() == Some(primary))
+            {
+                return language;
+            }
+        }
+        FALLBACK
+    }
+
+    /// The forced direction, or the one `language` is written in.
+    fn resolve_direction(&self, language: &str) -> WritingDirection {
+        self.direction.unwrap_or_else(|| {
+            let primary = language.split('-').next().unwrap_or_default();
+            match RTL_LANGUAGES.contains(&primary) {
+                true => WritingDirection::RightToLeft,
+                false => WritingDirection::LeftToRight,
+            }
+        })
+    }
+
+    /// The preferred language and direction.
+    fn load(db: &SaveDatabase) -> Result<Option<LocalePreference>, redb::Error> {
+        let read = db.0.begin_read()?;
+        let table = match read.open_table(TABLE_SETTINGS) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let bytes = table.get(SETTINGS_LOCALE)?;
+        Ok(bytes.and_then(|bytes| postcard::from_bytes(bytes.value()).ok()))
+    }
+
+    fn save(&self, db: &SaveDatabase) -> Result<(), redb::Error> {
+        let write = db.0.begin_write()?;
+        {
+            let mut table = write.open_table(TABLE_SETTINGS)?;
+            let bytes = postcard::to_stdvec(&(&self.preferred, self.direction)).unwrap();
+            table.insert(SETTINGS_LOCALE, &bytes[..])?;
+        }
+        write.commit()?;
+        Ok(())
+    }
+}
+
+impl Element for Locale {
+    fn when_insert(&mut self, world: &World, _this: Handle<Self>) {
+        let db = world.single_fetch::<SaveDatabase>().unwrap();
+        match Locale::load(&db) {
+            Ok(Some((preferred, direction))) => {
+                self.preferred = preferred;
+                self.direction = direction;
+            }
+            Ok(None) => {}
+            Err(err) => log::error!("failed to load the locale: {err}"),
+        }
+
+        self.language = self.resolve();
+        self.resolved = self.resolve_direction(self.language);
+        log::info!("locale {}", self.language);
+    }
+
+    fn when_modify(&mut self, world: &World, this: Handle<Self>) {
+        if let Ok(db) = world.single_fetch::<SaveDatabase>()
+            && let Err(err) = self.save(&db)
+        {
+            log::error!("failed to save the locale: {err}");
+        }
+
+        let language = self.resolve();
+        let direction = self.resolve_direction(language);
+        if language != self.language || direction != self.resolved {
+            log::info!("locale switched to {language}, {direction:?}");
+            self.language = language;
+            self.resolved = direction;
+            world.queue_trigger(this, LocaleChanged);
+        }
+    }
+}
+
+impl LocalizedText {
+    pub fn new(key: &str, rect: Rectangle, metrics: Metrics, order: isize) -> Self {
+        LocalizedText {
+            key: key.into(),
+            args: Vec::new(),
+            rect,
+            metrics,
+            order,
+            visible: true,
+            clip: None,
+            text: None,
+        }
+    }
+
+    pub fn arg(mut self, name: &str, arg: impl Into<MessageArg>) -> Self {
+        self.args.push((name.into(), arg.into()));
+        self
+    }
+
+    /// Lays the message out again, call after modifying it.
+    pub fn relayout(world: &World, this: Handle<Self>) {
+        let locale = world.single::<Locale>().unwrap();
+        let mut localized = world.fetch_mut(this).unwrap();
+        let message = world
+            .fetch(locale)
+            .unwrap()
+            .format(&localized.key, &localized.args);
+
+        if let Some(text) = localized.text.take() {
+            let _ = world.remove(text);
+        }
+
+        let text = world.build(TextDescriptor {
+            text: &message,
+            spans: &[],
+            rect: localized.rect,
+            metrics: localized.metrics,
+            order: localized.order,
+            visible: localized.visible,
+            clip: localized.clip,
+        });
+        world.dependency(text, this);
+        localized.text = Some(text);
+
+        RenderControl::redraw(world);
+    }
+}
+
+impl Element for LocalizedText {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let locale = world.single::<Locale>().unwrap();
+        let ob = world.observer(locale, move |LocaleChanged, world| {
+            LocalizedText::relayout(world, this);
+        });
+        world.dependency(ob, this);
+
+        world.observer(this, move |&WidgetRectangle(rect), world| {
+            world.fetch_mut(this).unwrap().rect = rect;
+            LocalizedText::relayout(world, this);
+        });
+
+        world.observer(this, move |&WidgetEnabled(enabled), world| {
+            world.fetch_mut(this).unwrap().visible = enabled;
+            LocalizedText::relayout(world, this);
+        });
+
+        world.queue(move |world| {
+            if world.validate(this).is_ok() {
+                LocalizedText::relayout(world, this);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hashbrown::HashSet;
+
+    use crate::locale::{CATALOGS, Catalog, Locale, Message, MessageArg, posix_language};
+
+    fn catalogs() -> Vec<(&'static str, Catalog)> {
+        (CATALOGS.iter())
+            .map(|&(language, source)| (language, Catalog::parse(source).unwrap()))
+            .collect()
+    }
+
+    fn placeholders(message: &Message) -> HashSet<String> {
+        let texts: Vec<&String> = match message {
+            Message::Plain(text) => vec![text],
+            Message::Plural(forms) => forms.values().collect(),
+        };
+
+        (texts.into_iter())
+            .flat_map(|text| text.split('{').skip(1))
+            .filter_map(|part| Some(part.split_once('}')?.0.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn every_key_in_every_locale() {
+        let catalogs = catalogs();
+        let keys: HashSet<&String> = (catalogs.iter())
+            .flat_map(|(_, catalog)| catalog.messages.keys())
+            .collect();
+
+        for (language, catalog) in &catalogs {
+            for key in &keys {
+                assert!(
+                    catalog.messages.contains_key(*key),
+                    "{key} is missing from {language}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn same_placeholders() {
+        let catalogs = catalogs();
+        let (_, fallback) = &catalogs[0];
+
+        for (language, catalog) in &catalogs[1..] {
+            for (key, message) in &catalog.messages {
+                assert_eq!(
+                    placeholders(message),
+                    placeholders(&fallback.messages[key]),
+                    "{key} of {language} has other arguments"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fallback_first() {
+        assert_eq!(Locale::languages().next(), Some(super::FALLBACK));
+    }
+
+    #[test]
+    fn format() {
+        let catalog = Catalog::parse(
+            r#"
+            [note]
+            count = { one = "{count} note", other = "{count} notes" }
+            hello = "Hello {name}, {missing}"
+            "#,
+        )
+        .unwrap();
+
+        let count = &catalog.messages["note.count"];
+        assert_eq!(
+            count.format("en-US", &[("count", MessageArg::from(1))]),
+            "1 note"
+        );
+        assert_eq!(
+            count.format("en-US", &[("count", MessageArg::from(2))]),
+            "2 notes"
+        );
+        assert_eq!(
+            count.format("zh-CN", &[("count", MessageArg::from(1))]),
+            "1 notes"
+        );
+        assert_eq!(count.format::<&str>("en-US", &[]), "{count} notes");
+
+        let hello = &catalog.messages["note.hello"];
+        let args = [("name", MessageArg::from("Ln"))];
+        assert_eq!(hello.format("en-US", &args), "Hello Ln, {missing}");
+    }
+
+    #[test]
+    fn resolve() {
+        let mut locale = Locale::default();
+        locale.preferred = Some("zh-TW".into());
+        assert_eq!(locale.resolve(), "zh-CN");
+
+        locale.preferred = Some("en-gb".into());
+        assert_eq!(locale.resolve(), "en-US");
+
+        locale.preferred = Some("zh-cn".into());
+        assert_eq!(locale.resolve(), "zh-CN");
+        locale.language = locale.resolve();
+        assert_eq!(locale.message("text.cut"), "剪切");
+        assert_eq!(locale.message("missing.key"), "missing.key");
+    }
+
+    #[test]
+    fn posix_locales() {
+        assert_eq!(posix_language("zh_CN.UTF-8").as_deref(), Some("zh-CN"));
+        assert_eq!(posix_language("de_DE@euro").as_deref(), Some("de-DE"));
+        assert_eq!(posix_language("en").as_deref(), Some("en"));
+        assert_eq!(posix_language("C.UTF-8"), None);
+        assert_eq!(posix_language("POSIX"), None);
+    }
+}
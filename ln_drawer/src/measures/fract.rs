@@ -91,7 +91,7 @@ impl Fract {
 
     pub fn round(self) -> i32 {
         if self.nf >= u32::MAX / 2 {
-            self.n + 1
+            self.n.wrapping_add(1)
         } else {
             self.n
         }
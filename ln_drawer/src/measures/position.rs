@@ -99,10 +99,22 @@ impl Position {
         }
     }
 
+    /// The nearest point of `rect`, going either way around the world.
     pub fn clamp(self, rect: Rectangle) -> Position {
+        let clamp = |n: i32, origin: i32, extend: u32| {
+            let past_origin = n.wrapping_sub(origin).cast_unsigned();
+            if past_origin <= extend {
+                n
+            } else if past_origin - extend < past_origin.wrapping_neg() {
+                origin.wrapping_add_unsigned(extend)
+            } else {
+                origin
+            }
+        };
+
         Position {
-            x: self.x.clamp(rect.left(), rect.right()),
-            y: self.y.clamp(rect.down(), rect.up()),
+            x: clamp(self.x, rect.left(), rect.width()),
+            y: clamp(self.y, rect.down(), rect.height()),
         }
     }
 
@@ -66,7 +66,7 @@ impl Rectangle {
     pub fn new(left: i32, down: i32, right: i32, up: i32) -> Rectangle {
         Rectangle {
             origin: Position::new(left.min(right), down.min(up)),
-            extend: Size::new((right - left).unsigned_abs(), (up - down).unsigned_abs()),
+            extend: Size::new(right.abs_diff(left), up.abs_diff(down)),
         }
     }
 
@@ -209,25 +209,41 @@ impl Rectangle {
     }
 
     pub fn expand(self, val: i32) -> Rectangle {
-        Rectangle::new(
-            self.origin.x.wrapping_sub(val),
-            self.origin.y.wrapping_sub(val),
-            (self.origin.x)
-                .wrapping_add_unsigned(self.extend.w)
-                .wrapping_add(val),
-            (self.origin.y)
-                .wrapping_add_unsigned(self.extend.h)
-                .wrapping_add(val),
-        )
+        Rectangle {
+            origin: self.origin - Position::splat(val),
+            extend: Size::new(
+                self.extend.w.saturating_add_signed(val.saturating_mul(2)),
+                self.extend.h.saturating_add_signed(val.saturating_mul(2)),
+            ),
+        }
     }
 
+    /// The smallest rectangle covering both, going the shorter way around the world.
     pub fn grow(self, rhs: Rectangle) -> Rectangle {
-        Rectangle::new(
-            i32::min(self.left(), rhs.left()),
-            i32::min(self.down(), rhs.down()),
-            i32::max(self.right(), rhs.right()),
-            i32::max(self.up(), rhs.up()),
-        )
+        let (left, width) = span_grow(self.left(), self.width(), rhs.left(), rhs.width());
+        let (down, height) = span_grow(self.down(), self.height(), rhs.down(), rhs.height());
+        Rectangle {
+            origin: Position::new(left, down),
+            extend: Size::new(width, height),
+        }
+    }
+
+    /// Rectangles only touching at an edge do not intersect.
+    pub fn intersects(self, rhs: Rectangle) -> bool {
+        self.intersect(rhs).is_some()
+    }
+
+    /// The overlapping area, `None` if they do not intersect.
+    ///
+    /// Rectangles wider than half of the world may overlap twice around it, only the overlap
+    /// starting within `self` is returned then.
+    pub fn intersect(self, rhs: Rectangle) -> Option<Rectangle> {
+        let (left, width) = span_intersect(self.left(), self.width(), rhs.left(), rhs.width())?;
+        let (down, height) = span_intersect(self.down(), self.height(), rhs.down(), rhs.height())?;
+        Some(Rectangle {
+            origin: Position::new(left, down),
+            extend: Size::new(width, height),
+        })
     }
 
     /// will cause precise loss
@@ -250,3 +266,121 @@ impl Rectangle {
         }
     }
 }
+
+// The world wraps at the `i32` limits, so an axis is a circle and a span goes from its origin
+// counter-clockwise, possibly over the seam.
+
+fn span_grow(a: i32, a_len: u32, b: i32, b_len: u32) -> (i32, u32) {
+    let from_a = (b.wrapping_sub(a).cast_unsigned() as u64 + b_len as u64).max(a_len as u64);
+    let from_b = (a.wrapping_sub(b).cast_unsigned() as u64 + a_len as u64).max(b_len as u64);
+    match from_a <= from_b {
+        true => (a, from_a.min(u32::MAX as u64) as u32),
+        false => (b, from_b.min(u32::MAX as u64) as u32),
+    }
+}
+
+fn span_intersect(a: i32, a_len: u32, b: i32, b_len: u32) -> Option<(i32, u32)> {
+    let a_to_b = b.wrapping_sub(a).cast_unsigned();
+    let b_to_a = a.wrapping_sub(b).cast_unsigned();
+    if a_to_b < a_len && b_len > 0 {
+        Some((b, b_len.min(a_len - a_to_b)))
+    } else if b_to_a < b_len && a_len > 0 {
+        Some((a, a_len.min(b_len - b_to_a)))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::measures::{Position, Rectangle, Size};
+
+    /// Rectangles within a few thousand units of the wrap seam, from a fixed seed.
+    fn seam_rectangles() -> impl Iterator<Item = Rectangle> {
+        let mut state = 0x2545f4914f6cdd1du64;
+        std::iter::repeat_with(move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let [x, y, w, h, ..] = state.to_le_bytes().map(|n| n as i32 * 16);
+            Rectangle {
+                origin: Position::new(
+                    (i32::MAX - 2048).wrapping_add(x),
+                    (i32::MIN + 2048).wrapping_sub(y),
+                ),
+                extend: Size::new(w.cast_unsigned(), h.cast_unsigned()),
+            }
+        })
+    }
+
+    #[test]
+    fn seam_intersect() {
+        let mut rects = seam_rectangles();
+        for _ in 0..1000 {
+            let (a, b) = (rects.next().unwrap(), rects.next().unwrap());
+            let overlap = a.intersect(b);
+            assert_eq!(overlap.is_some(), b.intersects(a));
+
+            for point in [a.left_down(), a.right_up(), b.left_down(), b.right_up()] {
+                for offset in [-1, 0, 1] {
+                    let point = point + Position::splat(offset);
+                    assert_eq!(
+                        point.within(a) && point.within(b),
+                        overlap.is_some_and(|overlap| point.within(overlap)),
+                        "{point} in {a} and {b}",
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn seam_grow() {
+        let mut rects = seam_rectangles();
+        for _ in 0..1000 {
+            let (a, b) = (rects.next().unwrap(), rects.next().unwrap());
+            let grown = a.grow(b);
+            assert!(
+                grown.width() < 8192 && grown.height() < 8192,
+                "{a} grow {b}"
+            );
+
+            for corner in [a.left_down(), b.left_down()] {
+                assert!(corner.within(grown), "{corner} in {grown}");
+            }
+        }
+    }
+
+    #[test]
+    fn seam_translation() {
+        let mut rects = seam_rectangles();
+        for _ in 0..1000 {
+            let (a, b) = (rects.next().unwrap(), rects.next().unwrap());
+            let far = Position::new(i32::MIN / 2, i32::MAX / 3);
+            let (a_far, b_far) = (a - far, b - far);
+
+            assert_eq!(a.grow(b) - far, a_far.grow(b_far));
+            assert_eq!(a.intersect(b).map(|x| x - far), a_far.intersect(b_far));
+            let point = b.right_up();
+            assert_eq!(point.clamp(a) - far, (point - far).clamp(a_far));
+        }
+    }
+
+    #[test]
+    fn intersects() {
+        let a = Rectangle::new(0, 0, 10, 10);
+        assert!(a.intersects(Rectangle::new(5, 5, 15, 15)));
+        assert!(a.intersects(Rectangle::new(2, 2, 4, 4)));
+        assert!(Rectangle::new(2, 2, 4, 4).intersects(a));
+        assert!(!a.intersects(Rectangle::new(10, 0, 20, 10)));
+        assert!(!a.intersects(Rectangle::new(0, 11, 10, 20)));
+        assert!(!a.intersects(Rectangle::new(-20, -20, -5, -5)));
+
+        let seam = Rectangle {
+            origin: Position::new(i32::MAX - 5, 0),
+            extend: Size::new(11, 10),
+        };
+        assert!(seam.intersects(Rectangle::new(i32::MIN, 2, i32::MIN + 2, 4)));
+        assert!(!seam.intersects(Rectangle::new(0, 2, 10, 4)));
+    }
+}
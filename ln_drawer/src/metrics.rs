@@ -0,0 +1,549 @@
+//! Timings for tracking performance, see [`Metrics`].
+//!
+//! Durations are kept in [`Rolling`] windows of the latest samples. Pass `--metrics <file>`
+//! to dump them when a window closes, as JSON if the file ends with `.json` and CSV
+//! otherwise. Secondary windows dump next to it, see [`MetricsDump::path`].
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hashbrown::HashMap;
+use ln_world::{Element, Handle, World};
+use wgpu::{
+    Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoder, Device, Features,
+    MapMode, PollType, QUERY_SIZE, QuerySet, QuerySetDescriptor, QueryType,
+    RenderPassTimestampWrites,
+};
+use winit::event::WindowEvent;
+
+use crate::{
+    lnwin::Lnwindow,
+    render::{Render, RenderControl},
+};
+
+/// Frames kept for histograms and percentiles.
+pub const FRAME_HISTORY: usize = 600;
+/// Autosaves are rare, a short history is enough.
+pub const AUTOSAVE_HISTORY: usize = 32;
+
+/// Lives next to [`Render`] in the window view, read it with `single_fetch`.
+pub struct Metrics {
+    /// Between the starts of two redraws.
+    pub frames: Rolling,
+    /// Spent inside [`Render::redraw`].
+    pub cpu: Rolling,
+    /// Spent by the main render pass on GPU, empty if timestamps are not supported.
+    pub gpu: Rolling,
+    pub autosaves: Rolling,
+    /// Of the latest redraw, controls skipped by culling have no draw timing.
+    pub controls: HashMap<Handle<RenderControl>, ControlTiming>,
+    pub chunks: ChunkStats,
+    /// Whether any control asked for another frame.
+    pub refreshing: bool,
+
+    last_redraw: Option<Instant>,
+    timer: Option<GpuTimer>,
+}
+
+/// The root of the world holds it to enable dumping on close.
+pub struct MetricsDump(pub PathBuf);
+
+impl MetricsDump {
+    /// Where the metrics of a window go. The primary window, passed as `None`, uses the path
+    /// as it is, the others add their handle to the file name, like `metrics-window12.json`.
+    pub fn path(&self, secondary: Option<Handle>) -> PathBuf {
+        let Some(window) = secondary else {
+            return self.0.clone();
+        };
+
+        let id = window.to_string();
+        let stem = self.0.file_stem().unwrap_or_default().to_string_lossy();
+        let mut name = format!("{stem}-window{}", id.trim_start_matches('#'));
+        if let Some(ext) = self.0.extension() {
+            name = format!("{name}.{}", ext.to_string_lossy());
+        }
+        self.0.with_file_name(name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rolling {
+    samples: VecDeque<Duration>,
+    capacity: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Summary {
+    pub samples: usize,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ControlTiming {
+    pub prepare: Duration,
+    pub draw: Duration,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkStats {
+    /// Chunks known to the stroke layer, including empty ones.
+    pub loaded: usize,
+    pub capacity: usize,
+    /// Chunks around the camera still waiting for the loading thread.
+    pub queued: usize,
+}
+
+/// Timestamps at both ends of the main render pass, read back a few frames later.
+struct GpuTimer {
+    query_set: QuerySet,
+    resolve: Buffer,
+    readback: Buffer,
+    /// Nanoseconds per tick.
+    period: f32,
+    /// `readback` is being mapped, no more timestamps until it is read.
+    pending: bool,
+    /// Result of the latest `map_async`, taken when read.
+    mapped: Arc<Mutex<Option<Result<(), BufferAsyncError>>>>,
+}
+
+impl Metrics {
+    pub fn new(render: &Render) -> Metrics {
+        let timer = (render.device.features())
+            .contains(Features::TIMESTAMP_QUERY)
+            .then(|| GpuTimer::new(&render.device, render.queue.get_timestamp_period()));
+
+        Metrics {
+            frames: Rolling::new(FRAME_HISTORY),
+            cpu: Rolling::new(FRAME_HISTORY),
+            gpu: Rolling::new(FRAME_HISTORY),
+            autosaves: Rolling::new(AUTOSAVE_HISTORY),
+            controls: HashMap::new(),
+            chunks: ChunkStats::default(),
+            refreshing: false,
+            last_redraw: None,
+            timer,
+        }
+    }
+
+    /// Called at the start of every redraw.
+    pub fn begin_frame(&mut self, device: &Device, now: Instant) {
+        if let Some(last) = self.last_redraw {
+            self.frames.push(now - last);
+        }
+        self.last_redraw = Some(now);
+        self.controls.clear();
+
+        if let Some(timer) = &mut self.timer
+            && let Some(duration) = timer.read(device)
+        {
+            self.gpu.push(duration);
+        }
+    }
+
+    pub fn prepared(&mut self, control: Handle<RenderControl>, duration: Duration) {
+        self.controls.entry(control).or_default().prepare += duration;
+    }
+
+    pub fn drawn(&mut self, control: Handle<RenderControl>, duration: Duration) {
+        self.controls.entry(control).or_default().draw += duration;
+    }
+
+    /// `None` if the GPU is not supported or the previous timestamps are not read yet.
+    pub fn timestamp_writes(&self) -> Option<RenderPassTimestampWrites<'_>> {
+        let timer = self.timer.as_ref().filter(|timer| !timer.pending)?;
+        Some(RenderPassTimestampWrites {
+            query_set: &timer.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        })
+    }
+
+    /// Copy the timestamps written in this frame, must be paired with [`Metrics::submitted`].
+    pub fn resolve(&self, encoder: &mut CommandEncoder) {
+        if let Some(timer) = self.timer.as_ref().filter(|timer| !timer.pending) {
+            encoder.resolve_query_set(&timer.query_set, 0..2, &timer.resolve, 0);
+            encoder.copy_buffer_to_buffer(&timer.resolve, 0, &timer.readback, 0, None);
+        }
+    }
+
+    pub fn submitted(&mut self, cpu: Duration) {
+        self.cpu.push(cpu);
+
+        if let Some(timer) = self.timer.as_mut().filter(|timer| !timer.pending) {
+            timer.pending = true;
+            let mapped = timer.mapped.clone();
+            timer.readback.map_async(MapMode::Read, .., move |ret| {
+                *mapped.lock().unwrap() = Some(ret);
+            });
+        }
+    }
+
+    pub fn write_json(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{{")?;
+        for (name, rolling) in self.rollings() {
+            let summary = rolling.summary();
+            write!(writer, "  \"{name}\": {{\"samples\": {}", summary.samples)?;
+            for (field, duration) in summary.fields() {
+                write!(writer, ", \"{field}_ms\": {}", millis(duration))?;
+            }
+            let histogram = rolling.histogram(Duration::from_millis(4), 16);
+            writeln!(writer, ", \"histogram_4ms\": {histogram:?}}},")?;
+        }
+
+        let chunks = self.chunks;
+        writeln!(
+            writer,
+            "  \"chunks\": {{\"loaded\": {}, \"capacity\": {}, \"queued\": {}}},",
+            chunks.loaded, chunks.capacity, chunks.queued
+        )?;
+
+        writeln!(writer, "  \"controls\": [")?;
+        let controls = self.sorted_controls();
+        for (idx, (control, timing)) in controls.iter().enumerate() {
+            writeln!(
+                writer,
+                "    {{\"control\": \"{control}\", \"prepare_ms\": {}, \"draw_ms\": {}}}{}",
+                millis(timing.prepare),
+                millis(timing.draw),
+                if idx + 1 < controls.len() { "," } else { "" },
+            )?;
+        }
+        writeln!(writer, "  ]")?;
+        writeln!(writer, "}}")
+    }
+
+    /// One row per measured value, `metric,value` with durations in milliseconds.
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "metric,value")?;
+        for (name, rolling) in self.rollings() {
+            let summary = rolling.summary();
+            writeln!(writer, "{name}.samples,{}", summary.samples)?;
+            for (field, duration) in summary.fields() {
+                writeln!(writer, "{name}.{field}_ms,{}", millis(duration))?;
+            }
+        }
+
+        writeln!(writer, "chunks.loaded,{}", self.chunks.loaded)?;
+        writeln!(writer, "chunks.capacity,{}", self.chunks.capacity)?;
+        writeln!(writer, "chunks.queued,{}", self.chunks.queued)?;
+
+        for (control, timing) in self.sorted_controls() {
+            writeln!(
+                writer,
+                "control.{control}.prepare_ms,{}",
+                millis(timing.prepare)
+            )?;
+            writeln!(writer, "control.{control}.draw_ms,{}", millis(timing.draw))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn dump(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let writer = BufWriter::new(File::create(path)?);
+        match path.extension().is_some_and(|ext| ext == "json") {
+            true => self.write_json(writer),
+            false => self.write_csv(writer),
+        }
+    }
+
+    pub fn rollings(&self) -> [(&'static str, &Rolling); 4] {
+        [
+            ("frame", &self.frames),
+            ("cpu", &self.cpu),
+            ("gpu", &self.gpu),
+            ("autosave", &self.autosaves),
+        ]
+    }
+
+    /// The most expensive first.
+    pub fn sorted_controls(&self) -> Vec<(Handle<RenderControl>, ControlTiming)> {
+        let mut controls: Vec<_> = self.controls.iter().map(|(&k, &v)| (k, v)).collect();
+        controls.sort_by_key(|(_, timing)| std::cmp::Reverse(timing.prepare + timing.draw));
+        controls
+    }
+}
+
+impl Rolling {
+    pub fn new(capacity: usize) -> Rolling {
+        Rolling {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, sample: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn latest(&self) -> Option<Duration> {
+        self.samples.back().copied()
+    }
+
+    /// Oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.samples.iter().copied()
+    }
+
+    pub fn summary(&self) -> Summary {
+        if self.samples.is_empty() {
+            return Summary::default();
+        }
+
+        let mut sorted: Vec<_> = self.iter().collect();
+        sorted.sort();
+        let at = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+
+        Summary {
+            samples: sorted.len(),
+            mean: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+            p50: at(0.5),
+            p95: at(0.95),
+            p99: at(0.99),
+            max: *sorted.last().unwrap(),
+        }
+    }
+
+    /// Counts of samples in `[n * bucket, (n + 1) * bucket)`, the last one also takes
+    /// everything longer.
+    pub fn histogram(&self, bucket: Duration, buckets: usize) -> Vec<usize> {
+        let mut counts = vec![0; buckets];
+        if buckets == 0 || bucket.is_zero() {
+            return counts;
+        }
+
+        for sample in self.iter() {
+            let idx = (sample.as_nanos() / bucket.as_nanos()) as usize;
+            counts[idx.min(buckets - 1)] += 1;
+        }
+        counts
+    }
+}
+
+impl Summary {
+    pub fn fields(&self) -> [(&'static str, Duration); 5] {
+        [
+            ("mean", self.mean),
+            ("p50", self.p50),
+            ("p95", self.p95),
+            ("p99", self.p99),
+            ("max", self.max),
+        ]
+    }
+}
+
+impl GpuTimer {
+    fn new(device: &Device, period: f32) -> GpuTimer {
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("metrics_timestamp"),
+            ty: QueryType::Timestamp,
+            count: 2,
+        });
+
+        let resolve = device.create_buffer(&BufferDescriptor {
+            label: Some("metrics_resolve"),
+            size: 2 * QUERY_SIZE as u64,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: Some("metrics_readback"),
+            size: 2 * QUERY_SIZE as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        GpuTimer {
+            query_set,
+            resolve,
+            readback,
+            period,
+            pending: false,
+            mapped: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn read(&mut self, device: &Device) -> Option<Duration> {
+        if !self.pending {
+            return None;
+        }
+
+        let _ = device.poll(PollType::Poll);
+        let ret = self.mapped.lock().unwrap().take()?;
+        if let Err(err) = ret {
+            // nothing is mapped, try again with the next frame
+            log::warn!("failed to read the GPU timestamps: {err}");
+            self.pending = false;
+            return None;
+        }
+
+        let bytes = self.readback.get_mapped_range(..);
+        let begin = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let end = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+        drop(bytes);
+        self.readback.unmap();
+        self.pending = false;
+
+        let nanos = end.saturating_sub(begin) as f64 * self.period as f64;
+        Some(Duration::from_nanos(nanos as u64))
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl Element for Metrics {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
+
+        world.observer(lnwindow, move |event: &WindowEvent, world| {
+            if let WindowEvent::CloseRequested = event
+                && let Ok(dump) = world.single_fetch::<MetricsDump>()
+            {
+                let primary = world.fetch(lnwindow).unwrap().primary;
+                let path = dump.path(primary.map(|_| lnwindow.untyped()));
+                let metrics = world.fetch(this).unwrap();
+                match metrics.dump(&path) {
+                    Ok(()) => log::info!("metrics dumped into {}", path.display()),
+                    Err(err) => log::error!("failed to dump metrics: {err}"),
+                }
+            }
+        });
+    }
+}
+
+impl Element for MetricsDump {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rolling(millis: impl IntoIterator<Item = u64>) -> Rolling {
+        let mut rolling = Rolling::new(FRAME_HISTORY);
+        for ms in millis {
+            rolling.push(Duration::from_millis(ms));
+        }
+        rolling
+    }
+
+    fn metrics() -> Metrics {
+        Metrics {
+            frames: rolling([10, 20, 30]),
+            cpu: rolling([]),
+            gpu: rolling([]),
+            autosaves: rolling([]),
+            controls: HashMap::new(),
+            chunks: ChunkStats {
+                loaded: 4,
+                capacity: 16,
+                queued: 1,
+            },
+            refreshing: false,
+            last_redraw: None,
+            timer: None,
+        }
+    }
+
+    #[test]
+    fn rolling_capacity() {
+        let mut rolling = Rolling::new(3);
+        for ms in 1..=5 {
+            rolling.push(Duration::from_millis(ms));
+        }
+        let kept: Vec<_> = rolling.iter().map(|x| x.as_millis()).collect();
+        assert_eq!(kept, [3, 4, 5]);
+        assert_eq!(rolling.latest(), Some(Duration::from_millis(5)));
+    }
+
+    #[test]
+    fn summary() {
+        assert_eq!(rolling([]).summary().samples, 0);
+
+        let summary = rolling((1..=100).rev()).summary();
+        assert_eq!(summary.samples, 100);
+        assert_eq!(summary.mean, Duration::from_micros(50_500));
+        assert_eq!(summary.p50, Duration::from_millis(51));
+        assert_eq!(summary.p95, Duration::from_millis(95));
+        assert_eq!(summary.p99, Duration::from_millis(99));
+        assert_eq!(summary.max, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn histogram() {
+        let rolling = rolling([0, 3, 4, 9, 50]);
+        let bucket = Duration::from_millis(4);
+        assert_eq!(rolling.histogram(bucket, 3), [2, 1, 2]);
+        assert_eq!(rolling.histogram(bucket, 0), Vec::<usize>::new());
+        assert_eq!(rolling.histogram(Duration::ZERO, 2), [0, 0]);
+    }
+
+    #[test]
+    fn csv() {
+        let mut buf = Vec::new();
+        metrics().write_csv(&mut buf).unwrap();
+        let csv = String::from_utf8(buf).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+
+        assert_eq!(lines[0], "metric,value");
+        assert!(lines.contains(&"frame.samples,3"));
+        assert!(lines.contains(&"frame.mean_ms,20"));
+        assert!(lines.contains(&"frame.max_ms,30"));
+        assert!(lines.contains(&"gpu.samples,0"));
+        assert!(lines.contains(&"chunks.capacity,16"));
+        assert!(lines.iter().all(|line| line.split(',').count() == 2));
+    }
+
+    #[test]
+    fn json() {
+        let mut buf = Vec::new();
+        metrics().write_json(&mut buf).unwrap();
+        let json = String::from_utf8(buf).unwrap();
+
+        assert!(json.starts_with("{\n") && json.ends_with("}\n"));
+        assert!(json.contains(concat!(
+            "  \"frame\": {\"samples\": 3, \"mean_ms\": 20, \"p50_ms\": 20, ",
+            "\"p95_ms\": 30, \"p99_ms\": 30, \"max_ms\": 30, ",
+            "\"histogram_4ms\": [0, 0, 1, 0, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]},\n",
+        )));
+        assert!(json.contains("\"chunks\": {\"loaded\": 4, \"capacity\": 16, \"queued\": 1},"));
+        assert!(json.contains("\"controls\": [\n  ]"));
+    }
+
+    #[test]
+    fn dump_path() {
+        let world = World::default();
+        let window = world.insert(()).untyped();
+        let id = window.to_string().replace('#', "");
+
+        let dump = MetricsDump("out/metrics.json".into());
+        assert_eq!(dump.path(None), Path::new("out/metrics.json"));
+        assert_eq!(
+            dump.path(Some(window)),
+            Path::new(&format!("out/metrics-window{id}.json"))
+        );
+
+        let dump = MetricsDump("metrics".into());
+        assert_eq!(
+            dump.path(Some(window)),
+            Path::new(&format!("metrics-window{id}"))
+        );
+    }
+}
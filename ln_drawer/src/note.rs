@@ -0,0 +1,538 @@
+use cosmic_text::{Align, Color, Metrics};
+use hashbrown::HashMap;
+use ln_world::{Descriptor, Element, Handle, World};
+use palette::Srgba;
+use redb::{Database, ReadOnlyTable, ReadableDatabase, Table, TableDefinition};
+
+use crate::{
+    measures::{Position, Rectangle},
+    render::{
+        camera::{Camera, CameraPositionChanged},
+        rounded::{RoundedRect, RoundedRectDescriptor},
+        text::{TextEdit, TextEditDescriptor, TextEditStyle},
+    },
+    save::{Autosave, SaveDatabase},
+    stroke::StrokeLayer,
+    tools::focus::{Focus, FocusLeave, RequestFocus},
+    widgets::{WidgetRectangle, WidgetText, context_menu::ContextAction},
+};
+
+/// Notes keyed by their chunk, then by the left up corner they were placed at.
+const TABLE_NOTE: TableDefinition<NoteRow, &[u8]> = TableDefinition::new("note");
+
+/// Notes this far out of the view are loaded, and twice as far are unloaded.
+const NOTE_STREAM_MARGIN: i32 = 1024;
+/// Notes are stored by square chunks this large, so the ones in a view are found together.
+const NOTE_CHUNK: i32 = 1024;
+/// Views wider than this many chunks are searched at once, they cover most notes anyway.
+const NOTE_COLUMNS: i32 = 64;
+const NOTE_ORDER: isize = 10;
+const NOTE_PAD: i32 = 8;
+const NOTE_COLOR: Srgba = Srgba::new(1.0, 0.95, 0.65, 0.95);
+
+const MIN_FONT_SIZE: f32 = 8.0;
+const MIN_WRAP: u32 = 40;
+
+type NoteKey = (i32, i32);
+type NoteRow = ((i32, i32), NoteKey);
+
+/// What is saved of a [`Note`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NoteData {
+    pub text: String,
+    pub font_size: f32,
+    pub color: [u8; 4],
+    pub align: NoteAlign,
+    /// The width the text wraps at.
+    pub wrap: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum NoteAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// A sticky note on the board, edited in place. It grows downwards to fit its text and
+/// is removed once left empty.
+pub struct Note {
+    pub position: Position,
+    pub data: NoteData,
+    edit: Handle<TextEdit>,
+    background: Handle<RoundedRect>,
+    unsaved: bool,
+}
+
+pub struct NoteDescriptor {
+    /// The left up corner.
+    pub position: Position,
+    pub data: NoteData,
+}
+
+/// Loads the [`Note`]s around the camera it is inserted in and unloads the ones far from it.
+/// Every main camera has its own, so a note shown in two windows is edited twice.
+#[derive(Default)]
+pub struct NoteBoard {
+    notes: HashMap<NoteKey, Handle<Note>>,
+    removed: Vec<NoteKey>,
+}
+
+impl Default for NoteData {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            font_size: 20.0,
+            color: [0x20, 0x20, 0x20, 0xFF],
+            align: NoteAlign::Left,
+            wrap: 240,
+        }
+    }
+}
+
+impl NoteData {
+    fn style(&self) -> TextEditStyle {
+        let [r, g, b, a] = self.color;
+        TextEditStyle {
+            metrics: Metrics::new(self.font_size, (self.font_size * 1.25).ceil()),
+            color: Color::rgba(r, g, b, a),
+            align: Some(self.align.into()),
+        }
+    }
+}
+
+impl NoteAlign {
+    pub fn next(self) -> NoteAlign {
+        match self {
+            NoteAlign::Left => NoteAlign::Center,
+            NoteAlign::Center => NoteAlign::Right,
+            NoteAlign::Right => NoteAlign::Left,
+        }
+    }
+}
+
+impl From<NoteAlign> for Align {
+    fn from(align: NoteAlign) -> Self {
+        match align {
+            NoteAlign::Left => Align::Left,
+            NoteAlign::Center => Align::Center,
+            NoteAlign::Right => Align::Right,
+        }
+    }
+}
+
+impl Note {
+    /// Change how it looks, the text is kept.
+    pub fn restyle(world: &World, this: Handle<Self>, f: impl FnOnce(&mut NoteData)) {
+        let mut note = world.fetch_mut(this).unwrap();
+        f(&mut note.data);
+        note.data.font_size = note.data.font_size.max(MIN_FONT_SIZE);
+        note.data.wrap = note.data.wrap.max(MIN_WRAP);
+        note.unsaved = true;
+
+        let edit = note.edit;
+        let style = note.data.style();
+        let height = world.fetch(edit).unwrap().rect.height() as i32;
+        let rect = Note::edit_rect(note.position, &note.data, height);
+        drop(note);
+
+        world.trigger(edit, &style);
+        world.trigger(edit, &WidgetRectangle(rect));
+    }
+
+    fn key(&self) -> NoteKey {
+        (self.position.x, self.position.y)
+    }
+
+    /// Where the text goes, its height is fitted to the text afterwards.
+    fn edit_rect(position: Position, data: &NoteData, height: i32) -> Rectangle {
+        let (left, up) = (position.x + NOTE_PAD, position.y - NOTE_PAD);
+        Rectangle::new(left, up - height, left + data.wrap as i32, up)
+    }
+
+    fn attach_actions(world: &World, this: Handle<Self>, edit: Handle<TextEdit>) {
+        let collider = world.fetch(edit).unwrap().collider();
+        let restyle = |name: &str, f: fn(&mut NoteData)| {
+            ContextAction::register(world, collider, name, move |world, _| {
+                Note::restyle(world, this, f);
+            });
+        };
+
+        restyle("note.larger", |data| data.font_size += 4.0);
+        restyle("note.smaller", |data| data.font_size -= 4.0);
+        restyle("note.wider", |data| data.wrap += 40);
+        restyle("note.narrower", |data| {
+            data.wrap = data.wrap.saturating_sub(40)
+        });
+        restyle("note.align", |data| data.align = data.align.next());
+
+        ContextAction::register(world, collider, "note.color", move |world, _| {
+            let color = world.single_fetch::<StrokeLayer>().unwrap().modifier.color;
+            let color = color.into_format::<u8, u8>();
+            Note::restyle(world, this, |data| {
+                data.color = [color.red, color.green, color.blue, color.alpha];
+            });
+        });
+
+        ContextAction::register(world, collider, "note.remove", move |world, _| {
+            NoteBoard::remove(world, this);
+        });
+    }
+}
+
+impl Descriptor for NoteDescriptor {
+    type Target = Handle<Note>;
+
+    fn when_build(self, world: &World) -> Self::Target {
+        let style = self.data.style();
+        let height = style.metrics.line_height.ceil() as i32;
+        let rect = Note::edit_rect(self.position, &self.data, height);
+
+        let background = world.build(RoundedRectDescriptor {
+            rect: rect.expand(NOTE_PAD),
+            color: NOTE_COLOR,
+            shrink: 4.0,
+            value: 4.0,
+            shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.25),
+            order: NOTE_ORDER - 1,
+            ..Default::default()
+        });
+
+        let edit = world.build(TextEditDescriptor {
+            text: &self.data.text,
+            rect,
+            metrics: style.metrics,
+            color: style.color,
+            align: style.align,
+            fit: true,
+            order: NOTE_ORDER,
+            visible: true,
+        });
+
+        world.insert(Note {
+            position: self.position,
+            data: self.data,
+            edit,
+            background,
+            unsaved: false,
+        })
+    }
+}
+
+impl Element for Note {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let (edit, background) = (self.edit, self.background);
+        world.dependency(edit, this);
+        world.dependency(background, this);
+
+        world.observer(edit, move |WidgetText(text): &WidgetText, world| {
+            let mut note = world.fetch_mut(this).unwrap();
+            note.data.text.clone_from(text);
+            note.unsaved = true;
+        });
+
+        world.observer(edit, move |&WidgetRectangle(rect), world| {
+            world.trigger(background, &WidgetRectangle(rect.expand(NOTE_PAD)));
+        });
+
+        world.observer(edit, move |&FocusLeave, world| {
+            let note = world.fetch(this).unwrap();
+            if note.data.text.trim().is_empty() {
+                NoteBoard::remove(world, this);
+            }
+        });
+
+        Note::attach_actions(world, this, edit);
+    }
+}
+
+impl NoteBoard {
+    /// Put an empty note with its left up corner at `position` and focus it, below any
+    /// note already there.
+    pub fn place(world: &World, position: Position) {
+        let db = world.single_fetch::<SaveDatabase>().unwrap().clone();
+        let mut board = world.single_fetch_mut::<NoteBoard>().unwrap();
+
+        let read = db.0.begin_read().unwrap();
+        let table = read.open_table(TABLE_NOTE).unwrap();
+        let key = board.free_key(&table, position);
+
+        let note = world.build(NoteDescriptor {
+            position: Position::new(key.0, key.1),
+            data: NoteData::default(),
+        });
+        board.notes.insert(key, note);
+        drop(board);
+
+        world.queue(move |world| {
+            let Ok(edit) = world.fetch(note).map(|note| note.edit) else {
+                return;
+            };
+
+            let focus = world.single::<Focus>().unwrap();
+            world.trigger(focus, &RequestFocus(Some(edit.untyped())));
+        });
+    }
+
+    /// The note is removed from the database on the next autosave.
+    pub fn remove(world: &World, note: Handle<Note>) {
+        world.queue(move |world| {
+            let Ok(key) = world.fetch(note).map(|note| note.key()) else {
+                return;
+            };
+
+            world.single_fetch_mut::<NoteBoard>().unwrap().forget(key);
+            let _ = world.remove(note);
+        });
+    }
+
+    fn database_init(db: &Database) -> Result<(), redb::Error> {
+        let write = db.begin_write()?;
+        write.open_table(TABLE_NOTE)?;
+        write.commit()?;
+        Ok(())
+    }
+
+    fn attach_autosave(world: &World, this: Handle<Self>) {
+        let save = world.insert(Autosave(Box::new(move |world, write| {
+            let mut board = world.fetch_mut(this).unwrap();
+            let mut table = write.open_table(TABLE_NOTE).unwrap();
+            for key in board.removed.drain(..) {
+                table.remove(note_row(key)).unwrap();
+            }
+
+            for (&key, &note) in &board.notes {
+                NoteBoard::save(world, &mut table, key, note);
+            }
+        })));
+
+        world.dependency(save, this);
+    }
+
+    fn save(world: &World, table: &mut Table<NoteRow, &[u8]>, key: NoteKey, note: Handle<Note>) {
+        let mut note = world.fetch_mut(note).unwrap();
+        if note.unsaved {
+            let bytes = postcard::to_allocvec(&note.data).unwrap();
+            table.insert(note_row(key), &bytes[..]).unwrap();
+            note.unsaved = false;
+        }
+    }
+
+    /// Load the notes coming into the view and unload the ones far from it.
+    fn stream(&mut self, world: &World) {
+        let view = world.single_fetch::<Camera>().unwrap().world_view_rect();
+        let near = view.expand(NOTE_STREAM_MARGIN);
+        let far = view.expand(NOTE_STREAM_MARGIN * 2);
+
+        let db = world.single_fetch::<SaveDatabase>().unwrap().clone();
+        let gone = (self.notes.keys())
+            .filter(|&&(x, y)| !Position::new(x, y).within(far))
+            .copied()
+            .collect::<Vec<_>>();
+
+        if !gone.is_empty() {
+            let write = db.0.begin_write().unwrap();
+            let mut table = write.open_table(TABLE_NOTE).unwrap();
+            for key in gone {
+                let note = self.notes.remove(&key).unwrap();
+                NoteBoard::save(world, &mut table, key, note);
+                let _ = world.remove(note);
+            }
+            drop(table);
+            write.commit().unwrap();
+        }
+
+        let read = db.0.begin_read().unwrap();
+        let table = read.open_table(TABLE_NOTE).unwrap();
+        for (key, data) in self.unloaded(&table, near) {
+            let note = world.build(NoteDescriptor {
+                position: Position::new(key.0, key.1),
+                data,
+            });
+            self.notes.insert(key, note);
+        }
+    }
+
+    /// The first key at or below `position` taken by no note, loaded or saved.
+    fn free_key(
+        &self,
+        table: &ReadOnlyTable<NoteRow, &'static [u8]>,
+        position: Position,
+    ) -> NoteKey {
+        let mut key = (position.x, position.y);
+        while self.notes.contains_key(&key) || table.get(note_row(key)).unwrap().is_some() {
+            key.1 -= 1;
+        }
+        key
+    }
+
+    /// Unload the note at `key` for good, its row is deleted on the next autosave.
+    fn forget(&mut self, key: NoteKey) {
+        self.notes.remove(&key);
+        self.removed.push(key);
+    }
+
+    /// The saved notes within `near`, but neither loaded nor removed.
+    fn unloaded(
+        &self,
+        table: &ReadOnlyTable<NoteRow, &'static [u8]>,
+        near: Rectangle,
+    ) -> Vec<(NoteKey, NoteData)> {
+        let (left, down) = note_row((near.left(), near.down())).0;
+        let (right, up) = note_row((near.right(), near.up())).0;
+        let row = |x: i32, y: i32, key: i32| ((x, y), (key, key));
+
+        // rows are sorted by the column first, so each column is a range of its own
+        let ranges = match right - left < NOTE_COLUMNS {
+            true => (left..=right)
+                .map(|x| row(x, down, i32::MIN)..=row(x, up, i32::MAX))
+                .collect(),
+            false => vec![row(left, down, i32::MIN)..=row(right, up, i32::MAX)],
+        };
+
+        let mut notes = Vec::new();
+        for range in ranges {
+            for entry in table.range(range).unwrap() {
+                let (row, value) = entry.unwrap();
+                let (_, key) = row.value();
+                if !Position::new(key.0, key.1).within(near)
+                    || self.notes.contains_key(&key)
+                    || self.removed.contains(&key)
+                {
+                    continue;
+                }
+
+                match postcard::from_bytes::<NoteData>(value.value()) {
+                    Ok(data) => notes.push((key, data)),
+                    Err(err) => log::error!("failed to load the note at {key:?}: {err}"),
+                }
+            }
+        }
+        notes
+    }
+}
+
+/// Where the note at `key` is kept in [`TABLE_NOTE`].
+fn note_row(key: NoteKey) -> NoteRow {
+    let chunk = (key.0.div_euclid(NOTE_CHUNK), key.1.div_euclid(NOTE_CHUNK));
+    (chunk, key)
+}
+
+impl Element for NoteBoard {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let db = world.single_fetch::<SaveDatabase>().unwrap();
+        NoteBoard::database_init(&db.0).unwrap();
+        drop(db);
+
+        NoteBoard::attach_autosave(world, this);
+
+        let camera = world.single::<Camera>().unwrap();
+        let ob = world.observer(camera, move |_: &CameraPositionChanged, world| {
+            world.fetch_mut(this).unwrap().stream(world);
+        });
+        world.dependency(ob, this);
+
+        world.queue(move |world| {
+            if let Ok(mut board) = world.fetch_mut(this) {
+                board.stream(world);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use redb::backends::InMemoryBackend;
+
+    use super::*;
+
+    fn database() -> Database {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        NoteBoard::database_init(&db).unwrap();
+        db
+    }
+
+    /// Like the autosave does for a note edited to `text`.
+    fn save(db: &Database, key: NoteKey, text: &str) {
+        let data = NoteData {
+            text: text.into(),
+            ..Default::default()
+        };
+        let bytes = postcard::to_allocvec(&data).unwrap();
+
+        let write = db.begin_write().unwrap();
+        let mut table = write.open_table(TABLE_NOTE).unwrap();
+        table.insert(note_row(key), &bytes[..]).unwrap();
+        drop(table);
+        write.commit().unwrap();
+    }
+
+    fn free_key(board: &NoteBoard, db: &Database, position: Position) -> NoteKey {
+        let read = db.begin_read().unwrap();
+        let table = read.open_table(TABLE_NOTE).unwrap();
+        board.free_key(&table, position)
+    }
+
+    fn unloaded(board: &NoteBoard, db: &Database, near: Rectangle) -> Vec<(NoteKey, String)> {
+        let read = db.begin_read().unwrap();
+        let table = read.open_table(TABLE_NOTE).unwrap();
+        let mut notes = (board.unloaded(&table, near).into_iter())
+            .map(|(key, data)| (key, data.text))
+            .collect::<Vec<_>>();
+        notes.sort();
+        notes
+    }
+
+    #[test]
+    fn place_remove_stream() {
+        let db = database();
+        let mut board = NoteBoard::default();
+        let near = Rectangle::new(-100, -100, 100, 100);
+
+        let key = free_key(&board, &db, Position::new(10, 20));
+        assert_eq!(key, (10, 20));
+        save(&db, key, "hello");
+        assert_eq!(unloaded(&board, &db, near), [((10, 20), "hello".into())]);
+
+        // placed again at the same corner, it goes below
+        assert_eq!(free_key(&board, &db, Position::new(10, 20)), (10, 19));
+
+        // still saved until the next autosave, but never loaded again
+        board.forget(key);
+        assert!(unloaded(&board, &db, near).is_empty());
+    }
+
+    #[test]
+    fn stream_by_chunk() {
+        let db = database();
+        let board = NoteBoard::default();
+
+        let notes = [
+            ((-5, -5), "a"),
+            ((0, 0), "b"),
+            ((2000, 500), "c"),
+            // in a column of the view, but above it
+            ((0, NOTE_CHUNK * 3), "d"),
+            // in a row of the view, but right of it
+            ((NOTE_CHUNK * 3 + 5, 0), "e"),
+        ];
+        for (key, text) in notes {
+            save(&db, key, text);
+        }
+
+        let expected = [
+            ((-5, -5), "a".into()),
+            ((0, 0), "b".into()),
+            ((2000, 500), "c".into()),
+        ];
+        let near = Rectangle::new(-10, -10, NOTE_CHUNK * 2, 600);
+        assert_eq!(unloaded(&board, &db, near), expected);
+
+        // wider than the columns searched one by one
+        let wide = NOTE_CHUNK * NOTE_COLUMNS * 2;
+        let near = Rectangle::new(-wide, -10, NOTE_CHUNK * 2, 600);
+        assert_eq!(unloaded(&board, &db, near), expected);
+    }
+}
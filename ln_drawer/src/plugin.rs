@@ -0,0 +1,288 @@
+//! Statically registered extensions, see [`Plugin`] and [`Lnwin::with_plugin`].
+
+use ln_world::{Element, Handle, World};
+use palette::Srgba;
+use redb::{ReadableTable, TableDefinition, WriteTransaction};
+
+#[cfg(doc)]
+use crate::lnwin::Lnwin;
+use crate::{
+    layout::{luni::LuniChild, transform::TransformValue},
+    measures::{Position, Rectangle, Size},
+    save::SaveDatabase,
+    widgets::button::{Button, ButtonImage},
+};
+
+const TABLE_PLUGIN: TableDefinition<&str, &[u8]> = TableDefinition::new("plugin");
+
+/// Every hook runs once per window, in the order the plugins are registered.
+pub trait Plugin {
+    /// Stable and unique, the state of the plugin is saved under it.
+    fn name(&self) -> &'static str;
+
+    /// The format of the tables owned by this plugin. `0` means no tables.
+    fn version(&self) -> u32 {
+        0
+    }
+
+    /// Bring the tables from `from` up to [`Plugin::version`]. `from` is `0` on the first
+    /// launch with this plugin.
+    fn migrate(&self, _write: &WriteTransaction, _from: u32) -> Result<(), redb::Error> {
+        Ok(())
+    }
+
+    /// Runs in the window view once render resources are ready, the place to insert
+    /// pipelines and other singletons.
+    fn init(&self, _world: &World) {}
+
+    /// Runs in the main camera, after the [`StrokeLayer`](crate::stroke::StrokeLayer).
+    fn canvas(&self, _world: &World) {}
+
+    /// Runs in the UI camera while the side panel is built.
+    fn interface(&self, _world: &World, _toolbar: &mut Toolbar) {}
+}
+
+#[derive(Default)]
+pub struct PluginManager {
+    plugins: Vec<PluginEntry>,
+}
+
+struct PluginEntry {
+    plugin: Box<dyn Plugin>,
+    enabled: bool,
+    /// Set from the command line, saved on load.
+    requested: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+struct PluginState {
+    version: u32,
+    enabled: bool,
+}
+
+/// Entries of the side panel, from top to bottom.
+#[derive(Default)]
+pub struct Toolbar {
+    pub entries: Vec<(Handle, LuniChild)>,
+}
+
+impl PluginManager {
+    pub fn register(&mut self, plugin: impl Plugin + 'static) {
+        self.plugins.push(PluginEntry {
+            plugin: Box::new(plugin),
+            enabled: true,
+            requested: None,
+        });
+    }
+
+    /// Takes effect on the next launch, the elements of a running plugin are left alone.
+    pub fn request_enabled(&mut self, name: &str, enabled: bool) {
+        match self.plugins.iter_mut().find(|x| x.plugin.name() == name) {
+            Some(entry) => entry.requested = Some(enabled),
+            None => log::warn!("no plugin named {name}"),
+        }
+    }
+
+    /// Names and whether they are enabled, in the order of registration.
+    pub fn plugins(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        (self.plugins.iter()).map(|entry| (entry.plugin.name(), entry.enabled))
+    }
+
+    /// Read the saved states and migrate tables of enabled plugins.
+    pub fn load(&mut self, db: &SaveDatabase) -> Result<(), redb::Error> {
+        let write = db.0.begin_write()?;
+        {
+            let mut table = write.open_table(TABLE_PLUGIN)?;
+            for entry in &mut self.plugins {
+                let name = entry.plugin.name();
+                let mut state = PluginState::saved(&table, name)?;
+                state.enabled = entry.requested.take().unwrap_or(state.enabled);
+                entry.enabled = state.enabled;
+
+                // the request is still saved, for when the plugin catches up
+                let version = entry.plugin.version();
+                if state.version > version {
+                    log::error!("plugin {name} is older than its tables, disabled");
+                    entry.enabled = false;
+                } else if entry.enabled && state.version < version {
+                    log::info!("migrate plugin {name} from {} to {version}", state.version);
+                    entry.plugin.migrate(&write, state.version)?;
+                    state.version = version;
+                }
+
+                let bytes = postcard::to_stdvec(&state).unwrap();
+                table.insert(name, &bytes[..])?;
+            }
+        }
+
+        write.commit()?;
+        Ok(())
+    }
+
+    /// Saves at once whether the plugin is enabled from the next launch on, the elements
+    /// of a running plugin are left alone.
+    pub fn save_enabled(
+        &self,
+        db: &SaveDatabase,
+        name: &str,
+        enabled: bool,
+    ) -> Result<(), redb::Error> {
+        let write = db.0.begin_write()?;
+        {
+            let mut table = write.open_table(TABLE_PLUGIN)?;
+            let state = PluginState {
+                enabled,
+                ..PluginState::saved(&table, name)?
+            };
+
+            let bytes = postcard::to_stdvec(&state).unwrap();
+            table.insert(name, &bytes[..])?;
+        }
+
+        write.commit()?;
+        Ok(())
+    }
+
+    pub fn init(&self, world: &World) {
+        self.enabled().for_each(|plugin| plugin.init(world));
+    }
+
+    pub fn canvas(&self, world: &World) {
+        self.enabled().for_each(|plugin| plugin.canvas(world));
+    }
+
+    pub fn interface(&self, world: &World, toolbar: &mut Toolbar) {
+        self.enabled()
+            .for_each(|plugin| plugin.interface(world, toolbar));
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &dyn Plugin> {
+        (self.plugins.iter())
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.plugin.as_ref())
+    }
+}
+
+impl PluginState {
+    /// Enabled with no tables if never saved or unreadable.
+    fn saved(
+        table: &impl ReadableTable<&'static str, &'static [u8]>,
+        name: &str,
+    ) -> Result<Self, redb::Error> {
+        let saved = match table.get(name)? {
+            Some(bytes) => postcard::from_bytes::<PluginState>(bytes.value()).ok(),
+            None => None,
+        };
+
+        Ok(saved.unwrap_or(PluginState {
+            version: 0,
+            enabled: true,
+        }))
+    }
+}
+
+impl Toolbar {
+    pub fn push(&mut self, widget: impl Into<Handle>, child: LuniChild) {
+        self.entries.push((widget.into(), child));
+    }
+
+    /// A transparent square button in the style of the built-in tools.
+    pub fn button(&mut self, world: &World, image: Option<&'static [u8]>) -> Handle<Button> {
+        let button = world.insert(Button {
+            order: 10,
+            color: Srgba::new(0.5, 0.5, 0.5, 0.0),
+            active_color: Srgba::new(0.5, 0.5, 0.5, 0.2),
+            press_color: Srgba::new(0.5, 0.5, 0.5, 0.3),
+            shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
+            image: image.map(|bytes| ButtonImage {
+                transform: TransformValue::anchor(
+                    (0.5, 0.5),
+                    Rectangle::new_half(Position::ZERO, Size::splat(12)),
+                ),
+                bytes,
+            }),
+            ..Default::default()
+        });
+
+        self.push(
+            button,
+            LuniChild {
+                basis: Some(54),
+                shrink: Some(1.0),
+                ..Default::default()
+            },
+        );
+        button
+    }
+}
+
+impl Element for PluginManager {}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use redb::{Database, backends::InMemoryBackend};
+
+    use super::*;
+
+    struct Versioned(u32);
+
+    impl Plugin for Versioned {
+        fn name(&self) -> &'static str {
+            "versioned"
+        }
+
+        fn version(&self) -> u32 {
+            self.0
+        }
+    }
+
+    fn database() -> SaveDatabase {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        SaveDatabase(Arc::new(db))
+    }
+
+    fn launch(
+        db: &SaveDatabase,
+        version: u32,
+        requested: Option<bool>,
+    ) -> Vec<(&'static str, bool)> {
+        let mut plugins = PluginManager::default();
+        plugins.register(Versioned(version));
+        if let Some(enabled) = requested {
+            plugins.request_enabled("versioned", enabled);
+        }
+        plugins.load(db).unwrap();
+        plugins.plugins().collect()
+    }
+
+    #[test]
+    fn requested_state_persists() {
+        let db = database();
+        assert_eq!(launch(&db, 1, Some(false)), [("versioned", false)]);
+        assert_eq!(launch(&db, 1, None), [("versioned", false)]);
+    }
+
+    #[test]
+    fn newer_tables_keep_the_request() {
+        let db = database();
+        launch(&db, 2, None);
+
+        // an older build is disabled, but what it was asked for still counts later
+        assert_eq!(launch(&db, 1, Some(false)), [("versioned", false)]);
+        assert_eq!(launch(&db, 2, None), [("versioned", false)]);
+        assert_eq!(launch(&db, 1, Some(true)), [("versioned", false)]);
+        assert_eq!(launch(&db, 2, None), [("versioned", true)]);
+    }
+
+    #[test]
+    fn saved_at_once() {
+        let db = database();
+        let plugins = PluginManager::default();
+        plugins.save_enabled(&db, "versioned", false).unwrap();
+        assert_eq!(launch(&db, 0, None), [("versioned", false)]);
+    }
+}
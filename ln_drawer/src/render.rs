@@ -2,34 +2,40 @@ pub mod camera;
 pub mod canvas;
 pub mod rectangle;
 pub mod rounded;
+pub mod settings;
 pub mod text;
 pub mod vertex;
 
-use std::time::Instant;
+use std::{ops::Deref, time::Instant};
 
+use image::RgbaImage;
 use ln_world::{Element, Handle, World};
+use palette::{LinSrgba, Srgba};
 use wgpu::{
-    Adapter, Color, CommandEncoderDescriptor, CompositeAlphaMode, Device, DeviceDescriptor,
-    ExperimentalFeatures, Extent3d, Features, Instance, Limits, LoadOp, MemoryHints,
-    MultisampleState, Operations, PowerPreference, PresentMode, Queue, RenderPass,
-    RenderPassColorAttachment, RenderPassDescriptor, RequestAdapterOptions, StoreOp, Surface,
-    SurfaceConfiguration, Texture, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureUsages, TextureViewDescriptor, Trace,
+    Adapter, BufferDescriptor, BufferUsages, COPY_BYTES_PER_ROW_ALIGNMENT, Color,
+    CommandEncoderDescriptor, CompositeAlphaMode, Device, DeviceDescriptor, ExperimentalFeatures,
+    Extent3d, Features, Instance, Limits, LoadOp, MapMode, MemoryHints, MultisampleState,
+    Operations, Origin3d, PollType, PresentMode, Queue, RenderPass, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipeline, RequestAdapterOptions, StoreOp, Surface,
+    SurfaceConfiguration, SurfaceTexture, TexelCopyBufferInfoBase, TexelCopyBufferLayout,
+    TexelCopyTextureInfoBase, Texture, TextureAspect, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor, Trace,
 };
 use winit::{dpi::PhysicalSize, event::WindowEvent};
 
-use crate::{lnwin::Lnwindow, render::camera::Camera};
-
-pub const MSAA_SAMPLE_COUNT: u32 = 4;
-pub const MSAA_STATE: MultisampleState = MultisampleState {
-    count: MSAA_SAMPLE_COUNT,
-    mask: !0,
-    alpha_to_coverage_enabled: false,
+use crate::{
+    lnwin::Lnwindow,
+    measures::{Fract, PositionFract, Rectangle},
+    metrics::Metrics,
+    render::{
+        camera::{Camera, CameraDescriptor},
+        settings::RenderSettings,
+    },
 };
 
 pub struct Render {
     // wgpu surface
-    pub surface: Surface<'static>,
+    pub target: RenderTarget,
     pub config: SurfaceConfiguration,
 
     // wgpu interface
@@ -38,8 +44,10 @@ pub struct Render {
     pub device: Device,
     pub queue: Queue,
 
-    // msaa
-    msaa_texture: Texture,
+    // msaa, no texture if disabled
+    msaa: u32,
+    msaa_texture: Option<Texture>,
+    settings: RenderSettings,
 
     // render pass
     pub clear_color: Color,
@@ -49,9 +57,13 @@ pub struct Render {
     seq_dirty: Vec<(Handle<RenderControl>, Handle, isize)>,
     seq_remove: Vec<Handle<RenderControl>>,
     sequence: Vec<(Handle<RenderControl>, Handle, isize)>,
+}
 
-    // time tracing
-    last_redraw: Option<Instant>,
+/// Where frames are drawn to. The format and size are always in `Render::config`.
+pub enum RenderTarget {
+    Surface(Surface<'static>),
+    /// Without a window, read back by [`Render::capture`].
+    Offscreen(Texture),
 }
 
 type RenderPrepareCommand = Box<dyn FnMut(&World) -> Option<RenderInformation>>;
@@ -64,96 +76,362 @@ pub struct RenderControl {
 
     /// draw with given render pass
     pub draw: Option<RenderDrawCommand>,
+
+    /// world space area covered by the drawing, skipped when out of the camera view
+    pub bounding: Option<Rectangle>,
+
+    /// world space area the drawing is limited to
+    pub clip: Option<Rectangle>,
 }
 
 pub struct RenderInformation {
     pub keep_redrawing: bool,
 }
 
+/// Sent to [`Render`] when the surface format or MSAA changed, every [`MainPipeline`]
+/// has to be rebuilt.
+pub struct RenderRebuild;
+
+/// A pipeline drawing in the main render pass, following the format and MSAA of [`Render`].
+pub struct MainPipeline {
+    pipeline: RenderPipeline,
+    build: Box<dyn Fn(&Render) -> RenderPipeline>,
+}
+
 impl Render {
-    pub async fn new(lnwindow: &Lnwindow) -> Render {
+    pub async fn new(lnwindow: &Lnwindow, settings: &RenderSettings) -> Render {
         let instance = Instance::default();
 
         let surface = instance.create_surface(lnwindow.window.clone()).unwrap();
 
         let adapter = instance
             .request_adapter(&RequestAdapterOptions {
-                power_preference: PowerPreference::LowPower,
+                power_preference: settings.power.into(),
                 force_fallback_adapter: false,
                 compatible_surface: Some(&surface),
             })
             .await
             .unwrap();
 
+        let (device, queue) = Render::request_device(&adapter).await;
+
+        let size = lnwindow.window.surface_size();
+        let config = Render::configuration(&surface, &adapter, size, settings);
+        surface.configure(&device, &config);
+
+        Render::assemble(
+            RenderTarget::Surface(surface),
+            config,
+            *settings,
+            instance,
+            adapter,
+            device,
+            queue,
+        )
+    }
+
+    /// Render into another window on the device of `shared`, so that resources like the
+    /// chunks of the stroke layer can be drawn in both.
+    pub fn new_shared(lnwindow: &Lnwindow, settings: &RenderSettings, shared: &Render) -> Render {
+        let surface = (shared.instance)
+            .create_surface(lnwindow.window.clone())
+            .unwrap();
+
+        let size = lnwindow.window.surface_size();
+        let config = Render::configuration(&surface, &shared.adapter, size, settings);
+        surface.configure(&shared.device, &config);
+
+        Render::assemble(
+            RenderTarget::Surface(surface),
+            config,
+            *settings,
+            shared.instance.clone(),
+            shared.adapter.clone(),
+            shared.device.clone(),
+            shared.queue.clone(),
+        )
+    }
+
+    /// Render without a window, falling back to a software adapter if there is no GPU.
+    pub async fn new_offscreen(size: PhysicalSize<u32>, settings: &RenderSettings) -> Render {
+        let instance = Instance::default();
+
+        let mut options = RequestAdapterOptions {
+            power_preference: settings.power.into(),
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        };
+        let adapter = match instance.request_adapter(&options).await {
+            Ok(adapter) => adapter,
+            Err(err) => {
+                log::info!("no hardware adapter ({err}), falling back to software");
+                options.force_fallback_adapter = true;
+                instance.request_adapter(&options).await.unwrap()
+            }
+        };
+
+        let (device, queue) = Render::request_device(&adapter).await;
+
+        let config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format: TextureFormat::Rgba8UnormSrgb,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            desired_maximum_frame_latency: 2,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+        let texture = device.create_texture(&Render::offscreen_texel(&config));
+
+        Render::assemble(
+            RenderTarget::Offscreen(texture),
+            config,
+            *settings,
+            instance,
+            adapter,
+            device,
+            queue,
+        )
+    }
+
+    async fn request_device(adapter: &Adapter) -> (Device, Queue) {
         log::debug!("wgpu adapter: {:?}", adapter.get_info());
 
-        let (device, queue) = adapter
+        // timestamps are only for metrics, go without them if not supported
+        let timestamp = adapter.features() & Features::TIMESTAMP_QUERY;
+
+        adapter
             .request_device(&DeviceDescriptor {
                 label: None,
-                required_features: Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                required_features: Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES | timestamp,
                 required_limits: Limits::defaults(),
                 experimental_features: ExperimentalFeatures::disabled(),
                 memory_hints: MemoryHints::MemoryUsage,
                 trace: Trace::Off,
             })
             .await
-            .unwrap();
-
-        let size = lnwindow.window.surface_size();
-        let config = Render::configuration(&surface, &adapter, size);
-        surface.configure(&device, &config);
+            .unwrap()
+    }
 
-        let msaa_texture = device.create_texture(&Render::msaa_texel(size, &config));
+    fn assemble(
+        target: RenderTarget,
+        config: SurfaceConfiguration,
+        settings: RenderSettings,
+        instance: Instance,
+        adapter: Adapter,
+        device: Device,
+        queue: Queue,
+    ) -> Render {
+        let msaa = settings.sample_count(&adapter, config.format);
+        let msaa_texture = Render::msaa_texture(&device, &config, msaa);
 
         Render {
-            surface,
+            target,
             config,
             instance,
             adapter,
             device,
             queue,
+            msaa,
             msaa_texture,
+            settings,
             clear_color: Color::WHITE,
             preparing: false,
             seq_dirty: Vec::new(),
             seq_remove: Vec::new(),
             sequence: Vec::new(),
-            last_redraw: None,
         }
     }
 
     pub fn surface_recreate(&mut self, lnwindow: &Lnwindow) {
-        self.surface = self
-            .instance
+        let surface = (self.instance)
             .create_surface(lnwindow.window.clone())
             .unwrap();
         let size = lnwindow.window.surface_size();
-        self.config = Render::configuration(&self.surface, &self.adapter, size);
-        self.surface.configure(&self.device, &self.config);
+        self.config = Render::configuration(&surface, &self.adapter, size, &self.settings);
+        surface.configure(&self.device, &self.config);
+        self.target = RenderTarget::Surface(surface);
+
+        self.msaa_texture = Render::msaa_texture(&self.device, &self.config, self.msaa);
+    }
+
+    /// Reconfigure with new settings, `true` if [`RenderRebuild`] is needed. The adapter
+    /// is kept as every resource belongs to its device.
+    pub fn apply(&mut self, settings: &RenderSettings) -> bool {
+        let (format, msaa) = (self.config.format, self.msaa);
+        self.settings = *settings;
+
+        if let RenderTarget::Surface(surface) = &self.target {
+            let size = PhysicalSize::new(self.config.width, self.config.height);
+            self.config = Render::configuration(surface, &self.adapter, size, settings);
+            surface.configure(&self.device, &self.config);
+        }
+
+        self.msaa = settings.sample_count(&self.adapter, self.config.format);
+        self.msaa_texture = Render::msaa_texture(&self.device, &self.config, self.msaa);
 
-        let desc = Render::msaa_texel(size, &self.config);
-        self.msaa_texture = self.device.create_texture(&desc);
+        format != self.config.format || msaa != self.msaa
+    }
+
+    /// For pipelines drawing in the main render pass.
+    pub fn multisample(&self) -> MultisampleState {
+        MultisampleState {
+            count: self.msaa,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        }
     }
 
     pub fn surface_resize(&mut self, size: PhysicalSize<u32>) {
         self.config.width = size.width.max(1);
         self.config.height = size.height.max(1);
-        self.surface.configure(&self.device, &self.config);
+        match &mut self.target {
+            RenderTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+            RenderTarget::Offscreen(texture) => {
+                *texture = self
+                    .device
+                    .create_texture(&Render::offscreen_texel(&self.config));
+            }
+        }
 
-        let desc = Render::msaa_texel(size, &self.config);
-        self.msaa_texture = self.device.create_texture(&desc);
+        self.msaa_texture = Render::msaa_texture(&self.device, &self.config, self.msaa);
     }
 
-    fn msaa_texel(size: PhysicalSize<u32>, config: &SurfaceConfiguration) -> TextureDescriptor<'_> {
+    /// Draw a whole frame into a texture and read it back, whatever the target is.
+    ///
+    /// Supports 8-bit RGBA/BGRA and `Rgba16Float` formats.
+    pub fn capture(world: &mut World) -> Option<RgbaImage> {
+        let mut render = world.single_fetch_mut::<Render>().unwrap();
+        let texture = render
+            .device
+            .create_texture(&Render::offscreen_texel(&render.config));
+        let target = std::mem::replace(&mut render.target, RenderTarget::Offscreen(texture));
+        drop(render);
+
+        Render::redraw(world);
+
+        let mut render = world.single_fetch_mut::<Render>().unwrap();
+        let RenderTarget::Offscreen(texture) = std::mem::replace(&mut render.target, target) else {
+            unreachable!("target is replaced during capturing");
+        };
+
+        render.readback(&texture)
+    }
+
+    fn readback(&self, texture: &Texture) -> Option<RgbaImage> {
+        let format = self.config.format;
+        let texel = format.block_copy_size(None)?;
+        let (width, height) = (self.config.width, self.config.height);
+        let unpadded = width * texel;
+        let padded = unpadded.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let readback_buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("render_readback"),
+            size: (padded * height) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("render_readback"),
+            });
+
+        encoder.copy_texture_to_buffer(
+            TexelCopyTextureInfoBase {
+                texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            TexelCopyBufferInfoBase {
+                buffer: &readback_buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded),
+                    rows_per_image: Some(height),
+                },
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.queue.submit([encoder.finish()]);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let inner = readback_buffer.clone();
+        readback_buffer.map_async(MapMode::Read, .., move |ret| {
+            let bytes = ret.map(|_| inner.get_mapped_range(..).to_vec());
+            tx.send(bytes).unwrap();
+        });
+
+        self.device.poll(PollType::wait_indefinitely()).unwrap();
+        let bytes = rx.recv().unwrap().ok()?;
+
+        let mut image = RgbaImage::new(width, height);
+        for (y, row) in bytes.chunks(padded as usize).enumerate() {
+            for (x, texel) in row[..unpadded as usize].chunks(texel as usize).enumerate() {
+                let pixel = match format {
+                    TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => {
+                        [texel[0], texel[1], texel[2], texel[3]]
+                    }
+                    TextureFormat::Bgra8UnormSrgb | TextureFormat::Bgra8Unorm => {
+                        [texel[2], texel[1], texel[0], texel[3]]
+                    }
+                    TextureFormat::Rgba16Float => {
+                        let channel = |i: usize| f16_to_f32([texel[i * 2], texel[i * 2 + 1]]);
+                        let linear = LinSrgba::new(channel(0), channel(1), channel(2), channel(3));
+                        let color: Srgba<u8> = Srgba::from_linear(linear);
+                        color.into()
+                    }
+                    _ => {
+                        log::error!("cannot read back texture format {format:?}");
+                        return None;
+                    }
+                };
+                image.put_pixel(x as u32, y as u32, image::Rgba(pixel));
+            }
+        }
+
+        Some(image)
+    }
+
+    fn offscreen_texel(config: &SurfaceConfiguration) -> TextureDescriptor<'_> {
+        TextureDescriptor {
+            label: Some("render_offscreen"),
+            size: Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: config.format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        }
+    }
+
+    fn msaa_texture(device: &Device, config: &SurfaceConfiguration, msaa: u32) -> Option<Texture> {
+        (msaa > 1).then(|| device.create_texture(&Render::msaa_texel(config, msaa)))
+    }
+
+    fn msaa_texel(config: &SurfaceConfiguration, msaa: u32) -> TextureDescriptor<'_> {
         TextureDescriptor {
             label: Some("render_msaa"),
             size: Extent3d {
-                width: size.width,
-                height: size.height,
+                width: config.width,
+                height: config.height,
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
-            sample_count: MSAA_SAMPLE_COUNT,
+            sample_count: msaa,
             dimension: TextureDimension::D2,
             format: config.format,
             usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TRANSIENT,
@@ -165,35 +443,17 @@ impl Render {
         surface: &Surface,
         adapter: &Adapter,
         size: PhysicalSize<u32>,
+        settings: &RenderSettings,
     ) -> SurfaceConfiguration {
         let caps = surface.get_capabilities(&adapter);
-        let format = *caps
-            .formats
-            .iter()
-            .max_by_key(|&format| match format {
-                TextureFormat::Rgba16Float => 110,
-                TextureFormat::Rgba8UnormSrgb => 100,
-                TextureFormat::Bgra8UnormSrgb => 90,
-                _ if format.is_srgb() => 10,
-                _ => 0,
-            })
-            .unwrap();
+        let format = settings.format(&caps.formats);
         let config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
             format,
             width: size.width.max(1),
             height: size.height.max(1),
             desired_maximum_frame_latency: 2,
-            present_mode: {
-                let caps = &caps.present_modes;
-                if caps.contains(&PresentMode::FifoRelaxed) {
-                    PresentMode::FifoRelaxed
-                } else if caps.contains(&PresentMode::Fifo) {
-                    PresentMode::Fifo
-                } else {
-                    *caps.first().unwrap()
-                }
-            },
+            present_mode: settings.present_mode(&caps.present_modes),
             alpha_mode: {
                 let caps = &caps.alpha_modes;
                 if caps.contains(&CompositeAlphaMode::PreMultiplied) {
@@ -217,20 +477,28 @@ impl Render {
         config
     }
 
-    fn redraw(world: &mut World) {
+    /// Draw a frame into the target, mostly requested by the window.
+    pub fn redraw(world: &mut World) {
         // prepare controls
 
         let mut render = world.single_fetch_mut::<Render>().unwrap();
         render.preparing = true;
         drop(render);
 
+        let start = Instant::now();
         let mut refreshing = false;
+        let mut prepared = Vec::new();
         world.foreach_enter::<Camera>(|_| {
             world.foreach_fetch_mut::<RenderControl>(|mut control| {
-                if let Some(prepare) = &mut control.prepare
-                    && let Some(info) = prepare(world)
-                {
-                    refreshing |= info.keep_redrawing;
+                let handle = control.handle();
+                if let Some(prepare) = &mut control.prepare {
+                    let begin = Instant::now();
+                    let info = prepare(world);
+                    prepared.push((handle, begin.elapsed()));
+
+                    if let Some(info) = info {
+                        refreshing |= info.keep_redrawing;
+                    }
                 };
             });
         });
@@ -241,7 +509,15 @@ impl Render {
 
         let render = &mut *world.single_fetch_mut::<Render>().unwrap();
         render.preparing = false;
-        let now = Instant::now();
+
+        let mut metrics = world.single_fetch_mut::<Metrics>().ok();
+        if let Some(metrics) = &mut metrics {
+            metrics.begin_frame(&render.device, start);
+            metrics.refreshing = refreshing;
+            for (control, duration) in prepared {
+                metrics.prepared(control, duration);
+            }
+        }
 
         // order redraw sequence
 
@@ -265,13 +541,31 @@ impl Render {
 
         // setup render pass
 
-        let texture = render.surface.get_current_texture().unwrap();
-        let view = texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-        let msaa_view = render
-            .msaa_texture
-            .create_view(&TextureViewDescriptor::default());
+        let (frame, view) = render.target.acquire();
+        let msaa_view = (render.msaa_texture.as_ref())
+            .map(|texture| texture.create_view(&TextureViewDescriptor::default()));
+
+        // without msaa, draw into the target directly
+        let attachment = match &msaa_view {
+            Some(msaa_view) => RenderPassColorAttachment {
+                view: msaa_view,
+                resolve_target: Some(&view),
+                ops: Operations {
+                    load: LoadOp::Clear(render.clear_color),
+                    store: StoreOp::Discard,
+                },
+                depth_slice: None,
+            },
+            None => RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(render.clear_color),
+                    store: StoreOp::Store,
+                },
+                depth_slice: None,
+            },
+        };
 
         let mut encoder = render
             .device
@@ -281,56 +575,129 @@ impl Render {
 
         let mut rpass = encoder
             .begin_render_pass(&RenderPassDescriptor {
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &msaa_view,
-                    resolve_target: Some(&view),
-                    ops: Operations {
-                        load: LoadOp::Clear(render.clear_color),
-                        store: StoreOp::Discard,
-                    },
-                    depth_slice: None,
-                })],
+                color_attachments: &[Some(attachment)],
+                timestamp_writes: metrics.as_ref().and_then(|x| x.timestamp_writes()),
                 ..Default::default()
             })
             .forget_lifetime();
 
         // draw and submit
 
-        for &(control, view, _) in &render.sequence {
+        let (width, height) = (render.config.width, render.config.height);
+        for &(handle, view, _) in &render.sequence {
             world.enter(view, || {
-                let mut control = world.fetch_mut(control).unwrap();
-                if let Some(render) = &mut control.draw {
-                    render(world, &mut rpass);
+                let mut control = world.fetch_mut(handle).unwrap();
+                let mut scissor = None;
+                if let Ok(camera) = world.single_fetch::<Camera>() {
+                    if let Some(bounding) = control.bounding
+                        && !bounding.intersects(camera.world_view_rect())
+                    {
+                        return;
+                    }
+
+                    if let Some(clip) = control.clip {
+                        match scissor_rect(&camera.descriptor(), clip, width, height) {
+                            Some(rect) => scissor = Some(rect),
+                            None => return,
+                        }
+                    }
+                }
+
+                if let Some(draw) = &mut control.draw {
+                    if let Some([x, y, w, h]) = scissor {
+                        rpass.set_scissor_rect(x, y, w, h);
+                    }
+
+                    let begin = Instant::now();
+                    draw(world, &mut rpass);
+                    if let Some(metrics) = &mut metrics {
+                        metrics.drawn(handle, begin.elapsed());
+                    }
+
+                    if scissor.is_some() {
+                        rpass.set_scissor_rect(0, 0, width, height);
+                    }
                 }
             });
         }
 
         drop(rpass);
+        if let Some(metrics) = &metrics {
+            metrics.resolve(&mut encoder);
+        }
+
         render.queue.submit([encoder.finish()]);
-        texture.present();
+        if let Some(frame) = frame {
+            frame.present();
+        }
+
+        if let Some(metrics) = &mut metrics {
+            metrics.submitted(start.elapsed());
+        }
+
+        // without a window there is no one to redraw
+
+        let Ok(lnwindow) = world.single_fetch::<Lnwindow>() else {
+            return;
+        };
 
         // active refreshing
 
         if refreshing {
-            let lnwindow = world.single_fetch::<Lnwindow>().unwrap();
             lnwindow.window.request_redraw();
         }
+    }
+}
 
-        // time tracing
-
-        if let Some(last) = render.last_redraw {
-            let lnwindow = world.single_fetch::<Lnwindow>().unwrap();
-            lnwindow.window.set_title(&format!(
-                "frame time: {:.4} | {}",
-                (now - last).as_secs_f32(),
-                match refreshing {
-                    true => "ACTIVE",
-                    false => "INACTIVE",
-                },
-            ));
+impl MainPipeline {
+    pub fn new(render: &Render, build: impl Fn(&Render) -> RenderPipeline + 'static) -> Self {
+        MainPipeline {
+            pipeline: build(render),
+            build: Box::new(build),
         }
+    }
 
-        render.last_redraw = Some(now);
+    pub fn rebuild(&mut self, render: &Render) {
+        self.pipeline = (self.build)(render);
+    }
+
+    /// Rebuild the pipeline of `owner` on every [`RenderRebuild`].
+    pub fn follow<T: Element>(
+        world: &World,
+        owner: Handle<T>,
+        pipeline: impl Fn(&mut T) -> &mut MainPipeline + 'static,
+    ) {
+        let render = world.single::<Render>().unwrap();
+        let ob = world.observer(render, move |RenderRebuild, world| {
+            let render = world.single_fetch::<Render>().unwrap();
+            let mut owner = world.fetch_mut(owner).unwrap();
+            pipeline(&mut owner).rebuild(&render);
+        });
+        world.dependency(ob, owner);
+    }
+}
+
+impl Deref for MainPipeline {
+    type Target = RenderPipeline;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pipeline
+    }
+}
+
+impl RenderTarget {
+    /// The surface texture has to be presented after drawing.
+    fn acquire(&self) -> (Option<SurfaceTexture>, TextureView) {
+        match self {
+            RenderTarget::Surface(surface) => {
+                let frame = surface.get_current_texture().unwrap();
+                let view = (frame.texture).create_view(&TextureViewDescriptor::default());
+                (Some(frame), view)
+            }
+            RenderTarget::Offscreen(texture) => {
+                (None, texture.create_view(&TextureViewDescriptor::default()))
+            }
+        }
     }
 }
 
@@ -338,13 +705,22 @@ impl RenderControl {
     /// Safer functions to request redraw.
     pub fn redraw(world: &World) {
         let render = world.single_fetch::<Render>().unwrap();
-        let lnwindow = world.single_fetch::<Lnwindow>().unwrap();
+        let Ok(lnwindow) = world.single_fetch::<Lnwindow>() else {
+            return;
+        };
 
         if !render.preparing {
             lnwindow.window.request_redraw();
         }
     }
 
+    /// Keep [`RenderControl::bounding`] up to date when the owner moves.
+    pub fn bound(world: &World, handle: Handle<Self>, bounding: Option<Rectangle>) {
+        if let Ok(mut control) = world.fetch_mut(handle) {
+            control.bounding = bounding;
+        }
+    }
+
     pub fn reorder(order: Option<isize>, world: &World, handle: Handle<Self>) {
         let mut render = world.single_fetch_mut::<Render>().unwrap();
 
@@ -359,7 +735,10 @@ impl RenderControl {
 
 impl Element for Render {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
-        let lnwindow = world.single::<Lnwindow>().unwrap();
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
+
         world.observer(lnwindow, move |event: &WindowEvent, world| match event {
             WindowEvent::SurfaceResized(size) => {
                 let mut render = world.fetch_mut(this).unwrap();
@@ -387,3 +766,139 @@ impl Element for RenderControl {
         Self::reorder(None, world, this);
     }
 }
+
+/// Pixel area of a world space `clip` seen through `camera`, `None` if nothing is left.
+///
+/// A rotated camera turns the clip on screen, its bounding box is taken then.
+fn scissor_rect(
+    camera: &CameraDescriptor,
+    clip: Rectangle,
+    width: u32,
+    height: u32,
+) -> Option<[u32; 4]> {
+    let corner = |x: i32, y: i32| {
+        let point = PositionFract::new(Fract::new(x, 0), Fract::new(y, 0));
+        let [x, y] = camera.world_to_screen_absolute(point);
+        (
+            (x + 1.0) * 0.5 * width as f64,
+            (1.0 - y) * 0.5 * height as f64,
+        )
+    };
+
+    let corners = [
+        corner(clip.left(), clip.down()),
+        corner(clip.right(), clip.down()),
+        corner(clip.left(), clip.up()),
+        corner(clip.right(), clip.up()),
+    ];
+    let (mut x0, mut y0) = (f64::INFINITY, f64::INFINITY);
+    let (mut x1, mut y1) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for (x, y) in corners {
+        (x0, y0) = (x0.min(x), y0.min(y));
+        (x1, y1) = (x1.max(x), y1.max(y));
+    }
+
+    let (x0, x1) = (x0.clamp(0.0, width as f64), x1.clamp(0.0, width as f64));
+    let (y0, y1) = (y0.clamp(0.0, height as f64), y1.clamp(0.0, height as f64));
+    let (x, y) = (x0.floor() as u32, y0.floor() as u32);
+    let (w, h) = (x1.ceil() as u32 - x, y1.ceil() as u32 - y);
+
+    (w > 0 && h > 0).then_some([x, y, w, h])
+}
+
+/// IEEE 754 half precision, for reading back `Rgba16Float` targets.
+fn f16_to_f32(bytes: [u8; 2]) -> f32 {
+    let bits = u16::from_le_bytes(bytes) as u32;
+    let sign = (bits >> 15) << 31;
+    let exponent = (bits >> 10) & 0x1F;
+    let mantissa = bits & 0x3FF;
+
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        // subnormal
+        0 => {
+            let value = mantissa as f32 * (-24f32).exp2();
+            return if sign == 0 { value } else { -value };
+        }
+        0x1F => sign | 0x7F80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::FRAC_PI_4;
+
+    use super::*;
+    use crate::measures::Size;
+
+    fn half(bits: u16) -> f32 {
+        f16_to_f32(bits.to_le_bytes())
+    }
+
+    #[test]
+    fn f16_normal() {
+        assert_eq!(half(0x3C00), 1.0);
+        assert_eq!(half(0xC000), -2.0);
+        assert_eq!(half(0x3555), 0.333_251_95);
+        assert_eq!(half(0x7BFF), 65504.0);
+        assert_eq!(half(0x0400), 6.103_515_6e-5);
+    }
+
+    #[test]
+    fn f16_zero_and_subnormal() {
+        assert_eq!(half(0x0000).to_bits(), 0.0f32.to_bits());
+        assert_eq!(half(0x8000).to_bits(), (-0.0f32).to_bits());
+        assert_eq!(half(0x0001), (-24f32).exp2());
+        assert_eq!(half(0x03FF), 1023.0 * (-24f32).exp2());
+        assert_eq!(half(0x8001), -(-24f32).exp2());
+    }
+
+    #[test]
+    fn f16_infinity_and_nan() {
+        assert_eq!(half(0x7C00), f32::INFINITY);
+        assert_eq!(half(0xFC00), f32::NEG_INFINITY);
+        assert!(half(0x7E00).is_nan());
+        assert!(half(0xFE01).is_nan());
+    }
+
+    fn camera(rotation: f32) -> CameraDescriptor {
+        CameraDescriptor {
+            size: Size::new(200, 100),
+            rotation,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn scissor_unrotated() {
+        let clip = Rectangle::new(-50, -25, 50, 25);
+        assert_eq!(
+            scissor_rect(&camera(0.0), clip, 200, 100),
+            Some([50, 25, 100, 50])
+        );
+
+        let clip = Rectangle::new(-500, -20, 0, 20);
+        assert_eq!(
+            scissor_rect(&camera(0.0), clip, 200, 100),
+            Some([0, 30, 100, 40])
+        );
+    }
+
+    #[test]
+    fn scissor_rotated() {
+        let clip = Rectangle::new(-20, -20, 20, 20);
+        let [x, y, w, h] = scissor_rect(&camera(FRAC_PI_4), clip, 200, 100).unwrap();
+        // the diagonal of the square, about 56.6 pixels
+        assert!((56..=58).contains(&w) && (56..=58).contains(&h), "{w}x{h}");
+        assert_eq!((x + w / 2, y + h / 2), (100, 50));
+    }
+
+    #[test]
+    fn scissor_offscreen() {
+        let clip = Rectangle::new(300, 0, 400, 10);
+        assert_eq!(scissor_rect(&camera(0.0), clip, 200, 100), None);
+        assert_eq!(scissor_rect(&camera(FRAC_PI_4), clip, 200, 100), None);
+    }
+}
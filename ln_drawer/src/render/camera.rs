@@ -1,4 +1,4 @@
-use ln_world::{Descriptor, Element, Handle, World};
+use ln_world::{Descriptor, Element, Handle, RefMut, World};
 use redb::{ReadableDatabase, ReadableTable, TableDefinition};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
@@ -7,6 +7,7 @@ use wgpu::{
 use winit::event::WindowEvent;
 
 use crate::{
+    animation::{Animation, AnimationDescriptor, OnceAnimationDescriptor},
     lnwin::Lnwindow,
     measures::{Fract, PositionFract, Rectangle, Size},
     render::Render,
@@ -15,10 +16,17 @@ use crate::{
 
 const TABLE_CAMERA: TableDefinition<&str, &[u8]> = TableDefinition::new("camera");
 
+/// Bookmarks share the table with the saved cameras, under this prefix.
+const BOOKMARK_PREFIX: &str = "bookmark:";
+
 pub struct Camera {
     pub size: Size,
     pub center: PositionFract,
     pub zoom: Fract,
+    /// Counter-clockwise rotation of the board on screen, in radians.
+    pub rotation: f32,
+    /// Mirror the board horizontally, before rotating it.
+    pub flip: bool,
 
     pub bind: BindGroup,
     pub uniform: Buffer,
@@ -44,6 +52,8 @@ pub struct CameraDescriptor {
     pub size: Size,
     pub center: PositionFract,
     pub zoom: Fract,
+    pub rotation: f32,
+    pub flip: bool,
 }
 
 #[repr(C)]
@@ -54,6 +64,42 @@ struct CameraUniform {
     center_fract: [u32; 2],
     zoom: i32,
     zoom_fract: u32,
+    rotation: [f32; 2],
+    flip: f32,
+    _pad: u32,
+}
+
+impl CameraUniform {
+    fn new(desc: &CameraDescriptor) -> Self {
+        CameraUniform {
+            size: Size::new(desc.size.w.max(1), desc.size.h.max(1)).into_array(),
+            center: desc.center.into_array(),
+            center_fract: desc.center.into_arrayf(),
+            zoom: desc.zoom.n,
+            zoom_fract: desc.zoom.nf,
+            rotation: [desc.rotation.cos(), desc.rotation.sin()],
+            flip: if desc.flip { -1.0 } else { 1.0 },
+            _pad: 0,
+        }
+    }
+}
+
+impl CameraDescriptor {
+    pub fn world_to_screen_absolute(&self, point: PositionFract) -> [f64; 2] {
+        self.world_to_screen_relative(point - self.center)
+    }
+
+    pub fn world_to_screen_relative(&self, point: PositionFract) -> [f64; 2] {
+        let scale = (self.zoom.n as f64 + self.zoom.nf as f64 * (-32f64).exp2()).exp2();
+        let x = point.x.into_f64() * scale;
+        let y = point.y.into_f64() * scale;
+
+        let x = if self.flip { -x } else { x };
+        let (sin, cos) = (self.rotation as f64).sin_cos();
+        let (x, y) = (x * cos - y * sin, x * sin + y * cos);
+
+        [x * 2.0 / self.size.w as f64, y * 2.0 / self.size.h as f64]
+    }
 }
 
 impl Descriptor for CameraDescriptor {
@@ -66,13 +112,7 @@ impl Descriptor for CameraDescriptor {
 
         let uniform = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("camera_uniform"),
-            contents: bytemuck::bytes_of(&CameraUniform {
-                size: self.size.into_array(),
-                center: self.center.into_array(),
-                center_fract: self.center.into_arrayf(),
-                zoom: self.zoom.n,
-                zoom_fract: self.zoom.nf,
-            }),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&self)),
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
 
@@ -93,6 +133,8 @@ impl Descriptor for CameraDescriptor {
             size: self.size,
             center: self.center,
             zoom: self.zoom,
+            rotation: self.rotation,
+            flip: self.flip,
             uniform,
             bind,
             queue: render.queue.clone(),
@@ -102,7 +144,10 @@ impl Descriptor for CameraDescriptor {
 
 impl Element for Camera {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
-        let lnwindow = world.single::<Lnwindow>().unwrap();
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
+
         world.observer(lnwindow, move |event: &WindowEvent, world| {
             if let WindowEvent::SurfaceResized(size) = event {
                 let mut camera = world.fetch_mut(this).unwrap();
@@ -117,17 +162,12 @@ impl Element for Camera {
         self.queue.write_buffer(
             &self.uniform,
             0,
-            bytemuck::bytes_of(&CameraUniform {
-                size: Size::new(self.size.w.max(1), self.size.h.max(1)).into_array(),
-                center: self.center.into_array(),
-                center_fract: self.center.into_arrayf(),
-                zoom: self.zoom.n,
-                zoom_fract: self.zoom.nf,
-            }),
+            bytemuck::bytes_of(&CameraUniform::new(&self.descriptor())),
         );
 
-        let lnwindow = world.single_fetch::<Lnwindow>().unwrap();
-        lnwindow.window.request_redraw();
+        if let Ok(lnwindow) = world.single_fetch::<Lnwindow>() {
+            lnwindow.window.request_redraw();
+        }
     }
 }
 
@@ -141,36 +181,57 @@ impl Camera {
 
     pub fn screen_to_world_relative(&self, delta: [f64; 2]) -> PositionFract {
         let scale = (self.zoom.n as f64 + self.zoom.nf as f64 * (-32f64).exp2()).exp2();
-        let x = delta[0] / scale * self.size.w as f64 / 2.0;
-        let y = delta[1] / scale * self.size.h as f64 / 2.0;
-        PositionFract::new(Fract::from_f64(x), Fract::from_f64(y))
+        let x = delta[0] * self.size.w as f64 / 2.0;
+        let y = delta[1] * self.size.h as f64 / 2.0;
+
+        // undo the rotation, then the flip
+        let (sin, cos) = (self.rotation as f64).sin_cos();
+        let (x, y) = (x * cos + y * sin, y * cos - x * sin);
+        let x = if self.flip { -x } else { x };
+
+        PositionFract::new(Fract::from_f64(x / scale), Fract::from_f64(y / scale))
     }
 
     pub fn world_to_screen_absolute(&self, point: PositionFract) -> [f64; 2] {
-        self.world_to_screen_relative(point - self.center)
+        self.descriptor().world_to_screen_absolute(point)
     }
 
     pub fn world_to_screen_relative(&self, point: PositionFract) -> [f64; 2] {
-        let scale = (self.zoom.n as f64 + self.zoom.nf as f64 * (-32f64).exp2()).exp2();
-        let x = point.x.into_f64() * 2.0 / self.size.w as f64 * scale;
-        let y = point.y.into_f64() * 2.0 / self.size.h as f64 * scale;
-        [x, y]
+        self.descriptor().world_to_screen_relative(point)
     }
 
     pub fn world_view_rect(&self) -> Rectangle {
-        Self::manual_view_rect(self.zoom, self.size, self.center)
+        Self::manual_view_rect(self.zoom, self.size, self.center, self.rotation)
     }
 
-    pub fn manual_view_rect(zoom: Fract, size: Size, center: PositionFract) -> Rectangle {
+    /// The bounding box of the view, which is larger than the screen once rotated.
+    pub fn manual_view_rect(
+        zoom: Fract,
+        size: Size,
+        center: PositionFract,
+        rotation: f32,
+    ) -> Rectangle {
         let scale = (zoom.n as f64 + zoom.nf as f64 * (-32f64).exp2()).exp2();
-        let width = size.w as f64 / scale * 0.5;
-        let height = size.h as f64 / scale * 0.5;
+        let (sin, cos) = (rotation as f64).sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        let width = (size.w as f64 * cos + size.h as f64 * sin) / scale * 0.5;
+        let height = (size.w as f64 * sin + size.h as f64 * cos) / scale * 0.5;
         Rectangle::new_half(
             center.round(),
             Size::new(width.ceil() as u32, height.ceil() as u32),
         )
     }
 
+    pub fn descriptor(&self) -> CameraDescriptor {
+        CameraDescriptor {
+            size: self.size,
+            center: self.center,
+            zoom: self.zoom,
+            rotation: self.rotation,
+            flip: self.flip,
+        }
+    }
+
     pub fn init(world: &mut World) {
         let render = world.single_fetch::<Render>().unwrap();
         let device = &render.device;
@@ -205,10 +266,8 @@ impl Camera {
         let bytes = table.get(name)?.unwrap();
         let camera_desc = postcard::from_bytes::<CameraDescriptor>(bytes.value()).unwrap();
 
-        let lnwindow = world.single_fetch::<Lnwindow>().unwrap();
-        let size = lnwindow.window.surface_size();
         let camera = world.build(CameraDescriptor {
-            size: Size::new(size.width, size.height),
+            size: Camera::target_size(world),
             ..camera_desc
         });
 
@@ -217,6 +276,88 @@ impl Camera {
         Ok(camera)
     }
 
+    /// A camera fitting the window which is not saved, like the ones of secondary windows.
+    pub fn build_unsaved(world: &World, center: PositionFract, zoom: Fract) -> Handle<Camera> {
+        world.build(CameraDescriptor {
+            size: Camera::target_size(world),
+            center,
+            zoom,
+            ..Default::default()
+        })
+    }
+
+    /// The size of the window, or of the offscreen target without one.
+    fn target_size(world: &World) -> Size {
+        if let Ok(lnwindow) = world.single_fetch::<Lnwindow>() {
+            let size = lnwindow.window.surface_size();
+            return Size::new(size.width, size.height);
+        }
+
+        let render = world.single_fetch::<Render>().unwrap();
+        Size::new(render.config.width, render.config.height)
+    }
+
+    /// Save where this camera is looking at as a bookmark, replacing the one with the same name.
+    pub fn save_bookmark(&self, db: &SaveDatabase, name: &str) -> Result<(), redb::Error> {
+        Camera::save_bookmark_of(&self.descriptor(), db, name)
+    }
+
+    fn save_bookmark_of(
+        desc: &CameraDescriptor,
+        db: &SaveDatabase,
+        name: &str,
+    ) -> Result<(), redb::Error> {
+        let bytes = postcard::to_stdvec(desc).unwrap();
+
+        let write = db.0.begin_write()?;
+        {
+            let mut table = write.open_table(TABLE_CAMERA)?;
+            table.insert(&format!("{BOOKMARK_PREFIX}{name}")[..], &bytes[..])?;
+        }
+
+        write.commit()?;
+        Ok(())
+    }
+
+    pub fn bookmark(
+        db: &SaveDatabase,
+        name: &str,
+    ) -> Result<Option<CameraDescriptor>, redb::Error> {
+        let read = db.0.begin_read()?;
+        let table = read.open_table(TABLE_CAMERA)?;
+        let desc = (table.get(&format!("{BOOKMARK_PREFIX}{name}")[..])?)
+            .map(|bytes| postcard::from_bytes::<CameraDescriptor>(bytes.value()).unwrap());
+        Ok(desc)
+    }
+
+    /// Names of all bookmarks, in order.
+    pub fn bookmarks(db: &SaveDatabase) -> Result<Vec<String>, redb::Error> {
+        let read = db.0.begin_read()?;
+        let table = read.open_table(TABLE_CAMERA)?;
+
+        let mut names = Vec::new();
+        for result in table.range(BOOKMARK_PREFIX..)? {
+            let (name, _) = result?;
+            match name.value().strip_prefix(BOOKMARK_PREFIX) {
+                Some(name) => names.push(name.to_owned()),
+                None => break,
+            }
+        }
+
+        Ok(names)
+    }
+
+    pub fn remove_bookmark(db: &SaveDatabase, name: &str) -> Result<(), redb::Error> {
+        let write = db.0.begin_write()?;
+        {
+            let mut table = write.open_table(TABLE_CAMERA)?;
+            table.remove(&format!("{BOOKMARK_PREFIX}{name}")[..])?;
+        }
+
+        write.commit()?;
+        Ok(())
+    }
+
     fn build_default_if_empty(db: &SaveDatabase, name: &str) -> Result<(), redb::Error> {
         let write = db.0.begin_write()?;
         {
@@ -238,13 +379,7 @@ impl Camera {
         let name_owned = String::from(name);
         Autosave(Box::new(move |world, write| {
             let camera = world.fetch(camera).unwrap();
-
-            let bytes = postcard::to_stdvec(&CameraDescriptor {
-                size: camera.size,
-                center: camera.center,
-                zoom: camera.zoom,
-            })
-            .unwrap();
+            let bytes = postcard::to_stdvec(&camera.descriptor()).unwrap();
 
             let mut table = write.open_table(TABLE_CAMERA).unwrap();
             table.insert(&name_owned[..], &bytes[..]).unwrap();
@@ -262,19 +397,133 @@ pub struct CameraUtils {
     cursor_in_anchor: [f64; 2],
 
     locked: bool,
+    flight: Option<Handle<Animation<f32>>>,
 }
 
 impl CameraUtils {
     /// Adjust zoom value, zooming in/out the anchor.
     pub fn zoom_delta(&mut self, world: &World, delta: Fract) {
+        self.around_cursor(world, |camera| camera.zoom += delta);
+    }
+
+    /// Rotate the board counter-clockwise around the cursor, in radians.
+    pub fn rotate_delta(&mut self, world: &World, delta: f32) {
+        self.around_cursor(world, |camera| {
+            camera.rotation = (camera.rotation + delta).rem_euclid(std::f32::consts::TAU);
+        });
+    }
+
+    /// Mirror the board horizontally around the cursor.
+    pub fn flip(&mut self, world: &World) {
+        self.around_cursor(world, |camera| camera.flip = !camera.flip);
+    }
+
+    /// Animate the camera to `center` and `zoom`. When both ends can't be seen at once, it
+    /// zooms out on the way and back in, instead of sliding over a blurred board.
+    pub fn fly_to(&mut self, world: &World, center: PositionFract, zoom: Fract) {
+        let camera = world.single_fetch::<Camera>().unwrap();
+        let (rotation, flip) = (camera.rotation, camera.flip);
+        drop(camera);
+        self.fly_to_view(world, center, zoom, rotation, flip);
+    }
+
+    /// [`CameraUtils::fly_to`], turning the board to `rotation` the short way round on the
+    /// way. A mirror can't be eased, so the board is flipped halfway, where it's the smallest.
+    pub fn fly_to_view(
+        &mut self,
+        world: &World,
+        center: PositionFract,
+        zoom: Fract,
+        rotation: f32,
+        flip: bool,
+    ) {
+        self.land(world);
+
+        let camera = world.single_fetch::<Camera>().unwrap();
+        let (from, from_zoom) = (camera.center, camera.zoom);
+        let from_rotation = camera.rotation;
+        let bump = flight_bump(camera.size, (from, from_zoom), (center, zoom));
+
+        let flight = world.build(OnceAnimationDescriptor {
+            animation: AnimationDescriptor {
+                src: 0.0,
+                dst: 1.0,
+                factor: 8.0,
+            },
+            widget: camera.handle(),
+            action: move |mut camera: RefMut<Camera>, world: &World, t: f32| {
+                let (here, here_zoom) = flight_at((from, from_zoom), (center, zoom), bump, t);
+
+                world.queue_trigger(
+                    camera.handle(),
+                    CameraPositionChanged {
+                        from: camera.center,
+                        here,
+                    },
+                );
+                camera.center = here;
+                camera.zoom = here_zoom;
+                camera.rotation = flight_turn(from_rotation, rotation, t);
+                if t >= 0.5 {
+                    camera.flip = flip;
+                }
+            },
+        });
+
+        self.flight = Some(flight);
+    }
+
+    /// Animate the camera to frame `rect`, with a little margin.
+    pub fn fly_to_fit(&mut self, world: &World, rect: Rectangle) {
+        let camera = world.single_fetch::<Camera>().unwrap();
+        let (sin, cos) = (camera.rotation as f64).sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        let width = rect.width().max(1) as f64 * cos + rect.height().max(1) as f64 * sin;
+        let height = rect.width().max(1) as f64 * sin + rect.height().max(1) as f64 * cos;
+        let scale = f64::min(camera.size.w as f64 / width, camera.size.h as f64 / height) * 0.9;
+        drop(camera);
+
+        let center = PositionFract::new(
+            Fract::from_f64((rect.left() as f64 + rect.right() as f64) / 2.0),
+            Fract::from_f64((rect.down() as f64 + rect.up() as f64) / 2.0),
+        );
+        self.fly_to(world, center, Fract::from_f64(scale.log2()));
+    }
+
+    /// Stop the animation of [`CameraUtils::fly_to`], if any.
+    pub fn land(&mut self, world: &World) {
+        if let Some(flight) = self.flight.take() {
+            world.queue(move |world| {
+                let _ = world.remove(flight);
+            });
+        }
+    }
+
+    /// Jump the camera to `center` at once, like dragging the board.
+    pub fn move_to(&mut self, world: &World, center: PositionFract) {
+        self.land(world);
+        let mut camera = world.single_fetch_mut::<Camera>().unwrap();
+        world.queue_trigger(
+            camera.handle(),
+            CameraPositionChanged {
+                from: camera.center,
+                here: center,
+            },
+        );
+        camera.center = center;
+    }
+
+    /// Change the camera while keeping the point under the cursor.
+    fn around_cursor(&mut self, world: &World, f: impl FnOnce(&mut Camera)) {
+        self.land(world);
         let mut camera = world.single_fetch_mut::<Camera>().unwrap();
-        let zoom_center = camera.screen_to_world_absolute(self.cursor);
+        let center = camera.screen_to_world_absolute(self.cursor);
 
         let anchor_origin = self.anchor;
-        self.anchor = zoom_center;
+        self.anchor = center;
         self.cursor_in_anchor = [0.0, 0.0];
 
-        camera.zoom += delta;
+        f(&mut camera);
         drop(camera);
 
         self.update_locked(world);
@@ -316,6 +565,7 @@ impl CameraUtils {
 
     /// resolve `camera.center`
     fn update_locked(&mut self, world: &World) {
+        self.land(world);
         let mut camera = world.single_fetch_mut::<Camera>().unwrap();
         let delta = camera.screen_to_world_relative([
             self.cursor[0] - self.cursor_in_anchor[0],
@@ -342,6 +592,165 @@ impl CameraUtils {
     }
 }
 
+/// How far [`CameraUtils::fly_to`] zooms out halfway, for both ends to fit on a screen of
+/// `size` there.
+fn flight_bump(size: Size, from: (PositionFract, Fract), to: (PositionFract, Fract)) -> f64 {
+    let delta = to.0 - from.0;
+    let distance = delta.x.into_f64().hypot(delta.y.into_f64());
+    let screen = size.w.min(size.h).max(1) as f64;
+    let fit_zoom = (screen / distance).log2();
+    (from.1.into_f64().min(to.1.into_f64()) - fit_zoom).max(0.0)
+}
+
+/// Where [`CameraUtils::fly_to`] is at `t` between `0` and `1`, exactly `to` at the end.
+fn flight_at(
+    from: (PositionFract, Fract),
+    to: (PositionFract, Fract),
+    bump: f64,
+    t: f32,
+) -> (PositionFract, Fract) {
+    if t == 1.0 {
+        return to;
+    }
+
+    let t = t as f64;
+    let zoom = from.1.into_f64() * (1.0 - t) + to.1.into_f64() * t;
+    (
+        from.0 + (to.0 - from.0) * Fract::from_f64(t),
+        Fract::from_f64(zoom - bump * 4.0 * t * (1.0 - t)),
+    )
+}
+
+/// The rotation of [`CameraUtils::fly_to_view`] at `t` between `0` and `1`, turning the
+/// short way round and exactly `to` at the end.
+fn flight_turn(from: f32, to: f32, t: f32) -> f32 {
+    use std::f32::consts::{PI, TAU};
+
+    if t == 1.0 {
+        return to;
+    }
+
+    let delta = (to - from + PI).rem_euclid(TAU) - PI;
+    (from + delta * t).rem_euclid(TAU)
+}
+
 impl Element for MainCamera {}
 impl Element for UICamera {}
 impl Element for CameraUtils {}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use redb::{Database, backends::InMemoryBackend};
+
+    use super::*;
+
+    fn position(x: f64, y: f64) -> PositionFract {
+        PositionFract::new(Fract::from_f64(x), Fract::from_f64(y))
+    }
+
+    #[test]
+    fn bookmarks() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let db = SaveDatabase(Arc::new(db));
+        Camera::build_default_if_empty(&db, "main").unwrap();
+        assert!(Camera::bookmarks(&db).unwrap().is_empty());
+
+        let desc = |x| CameraDescriptor {
+            center: position(x, 2.0),
+            zoom: Fract::from_f64(-1.5),
+            rotation: 0.5,
+            flip: true,
+            ..Default::default()
+        };
+        Camera::save_bookmark_of(&desc(1.0), &db, "2").unwrap();
+        Camera::save_bookmark_of(&desc(1.0), &db, "1").unwrap();
+        // replaces the first one
+        Camera::save_bookmark_of(&desc(3.0), &db, "2").unwrap();
+        assert_eq!(Camera::bookmarks(&db).unwrap(), ["1", "2"]);
+
+        let bookmark = Camera::bookmark(&db, "2").unwrap().unwrap();
+        assert_eq!(bookmark.center, position(3.0, 2.0));
+        assert_eq!(bookmark.zoom, Fract::from_f64(-1.5));
+        assert_eq!((bookmark.rotation, bookmark.flip), (0.5, true));
+        assert!(Camera::bookmark(&db, "3").unwrap().is_none());
+        assert!(Camera::bookmark(&db, "main").unwrap().is_none());
+
+        Camera::remove_bookmark(&db, "1").unwrap();
+        Camera::remove_bookmark(&db, "3").unwrap();
+        assert_eq!(Camera::bookmarks(&db).unwrap(), ["2"]);
+    }
+
+    #[test]
+    fn view_rect_seam() {
+        let size = Size::new(800, 600);
+        let far = PositionFract::new(
+            Fract {
+                n: i32::MIN / 2,
+                nf: 0,
+            },
+            Fract { n: 12345, nf: 0 },
+        );
+
+        for (x, y) in [
+            (i32::MAX, i32::MIN),
+            (i32::MAX - 300, 7),
+            (i32::MIN + 5, i32::MAX - 200),
+        ] {
+            let center = PositionFract::new(Fract { n: x, nf: 1 << 31 }, Fract { n: y, nf: 0 });
+            for zoom in [-2.0, 0.0, 1.5] {
+                for rotation in [0.0, 0.3, std::f32::consts::FRAC_PI_2, 2.5] {
+                    let zoom = Fract::from_f64(zoom);
+                    let view = Camera::manual_view_rect(zoom, size, center, rotation);
+                    assert!(center.round().within(view), "{center:?} in {view:?}");
+                    assert!(view.width() >= size.w.min(size.h) >> 2);
+
+                    // the same view anywhere else in the world
+                    let moved = Camera::manual_view_rect(zoom, size, center - far, rotation);
+                    assert_eq!(view - far.round(), moved);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flight() {
+        let size = Size::new(800, 600);
+        let from = (PositionFract::ZERO, Fract::ZERO);
+
+        // both ends are on the screen already
+        let near = (position(100.0, 0.0), Fract::from_f64(1.0));
+        assert_eq!(flight_bump(size, from, near), 0.0);
+        let (_, zoom) = flight_at(from, near, 0.0, 0.5);
+        assert_eq!(zoom, Fract::from_f64(0.5));
+
+        // zooms out on the way, the most halfway, and lands exactly
+        let far = (position(600_000.0, 0.0), Fract::from_f64(1.0));
+        let bump = flight_bump(size, from, far);
+        assert!((bump - (600_000f64 / 600.0).log2()).abs() < 1e-6);
+
+        let zooms = [0.25, 0.5, 0.75].map(|t| flight_at(from, far, bump, t).1.into_f64());
+        assert!(zooms[1] < zooms[0] && zooms[1] < zooms[2]);
+        assert!((zooms[1] - (0.5 - bump)).abs() < 1e-6);
+
+        let (center, _) = flight_at(from, far, bump, 0.5);
+        assert!((center.x.into_f64() - 300_000.0).abs() < 1.0);
+        assert_eq!(flight_at(from, far, bump, 1.0), far);
+    }
+
+    #[test]
+    fn turn() {
+        use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+        let close = |a: f32, b: f32| ((a - b + PI).rem_euclid(TAU) - PI).abs() < 1e-5;
+        assert!(close(flight_turn(0.0, FRAC_PI_2, 0.5), FRAC_PI_2 / 2.0));
+        // over zero rather than all the way round
+        assert!(close(flight_turn(0.25, TAU - 0.25, 0.5), 0.0));
+        assert!(close(flight_turn(TAU - 0.25, 0.25, 0.25), TAU - 0.125));
+        assert!(close(flight_turn(PI, PI, 0.5), PI));
+        assert_eq!(flight_turn(0.3, 2.5, 1.0), 2.5);
+    }
+}
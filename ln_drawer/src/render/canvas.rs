@@ -4,7 +4,7 @@ use wgpu::{
     BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Buffer, BufferBinding,
     BufferBindingType, BufferUsages, ColorTargetState, ColorWrites, Extent3d, FragmentState,
-    Origin3d, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, Queue, RenderPipeline,
+    Origin3d, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, Queue,
     RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor,
     ShaderSource, ShaderStages, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture,
     TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
@@ -16,7 +16,7 @@ use wgpu::{
 use crate::{
     measures::Rectangle,
     render::{
-        MSAA_STATE, Render, RenderControl,
+        MainPipeline, Render, RenderControl,
         camera::{Camera, CameraBind},
         vertex::VertexUniform,
     },
@@ -48,7 +48,7 @@ pub struct CanvasDescriptor {
 }
 
 pub struct CanvasManager {
-    pipeline: RenderPipeline,
+    pipeline: MainPipeline,
     bind_layout: BindGroupLayout,
 }
 
@@ -113,36 +113,38 @@ impl Descriptor for CanvasManagerDescriptor {
                 immediate_size: 0,
             });
 
-        let pipeline = render
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("canvas_pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: VertexState {
-                    module: &shader_vs,
-                    entry_point: Some("vs_main"),
-                    compilation_options: Default::default(),
-                    buffers: &[],
-                },
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleStrip,
-                    ..Default::default()
-                },
-                fragment: Some(FragmentState {
-                    module: &shader_fs,
-                    entry_point: Some("fs_main"),
-                    compilation_options: Default::default(),
-                    targets: &[Some(ColorTargetState {
-                        format: render.config.format,
-                        blend: Some(BlendState::ALPHA_BLENDING),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                }),
-                depth_stencil: None,
-                multisample: MSAA_STATE,
-                multiview_mask: None,
-                cache: None,
-            });
+        let pipeline = MainPipeline::new(&render, move |render| {
+            render
+                .device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("canvas_pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: VertexState {
+                        module: &shader_vs,
+                        entry_point: Some("vs_main"),
+                        compilation_options: Default::default(),
+                        buffers: &[],
+                    },
+                    primitive: PrimitiveState {
+                        topology: PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    fragment: Some(FragmentState {
+                        module: &shader_fs,
+                        entry_point: Some("fs_main"),
+                        compilation_options: Default::default(),
+                        targets: &[Some(ColorTargetState {
+                            format: render.config.format,
+                            blend: Some(BlendState::ALPHA_BLENDING),
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    depth_stencil: None,
+                    multisample: render.multisample(),
+                    multiview_mask: None,
+                    cache: None,
+                })
+        });
 
         world.insert(CanvasManager {
             pipeline,
@@ -151,7 +153,11 @@ impl Descriptor for CanvasManagerDescriptor {
     }
 }
 
-impl Element for CanvasManager {}
+impl Element for CanvasManager {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        MainPipeline::follow(world, this, |manager| &mut manager.pipeline);
+    }
+}
 
 impl Descriptor for CanvasDescriptor {
     type Target = Handle<Canvas>;
@@ -247,6 +253,8 @@ impl Descriptor for CanvasDescriptor {
                 rpass.set_bind_group(1, &bind, &[]);
                 rpass.draw(0..4, 0..1);
             })),
+            bounding: Some(self.rect),
+            clip: None,
         });
 
         world.insert(Canvas {
@@ -275,6 +283,7 @@ impl Element for Canvas {
 
     fn when_modify(&mut self, world: &World, _this: Handle<Self>) {
         RenderControl::reorder(self.visible.then_some(self.order), world, self.control);
+        RenderControl::bound(world, self.control, Some(self.rect));
 
         let uniform = VertexUniform {
             origin: self.rect.origin.into_array(),
@@ -370,6 +379,10 @@ pub struct CanvasWriter<'painter> {
 }
 
 impl CanvasWriter<'_> {
+    pub fn clear(&mut self) {
+        self.canvas.data.fill(0);
+    }
+
     pub fn read(&self, x: i32, y: i32) -> Srgba {
         let x = x.rem_euclid(self.canvas.width as i32);
         let y = y.rem_euclid(self.canvas.height as i32);
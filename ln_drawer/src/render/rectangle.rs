@@ -9,7 +9,7 @@ use wgpu::{
 use crate::{
     measures::Rectangle,
     render::{
-        MSAA_STATE, Render, RenderControl,
+        MainPipeline, Render, RenderControl,
         camera::{Camera, CameraBind},
     },
 };
@@ -27,7 +27,7 @@ pub trait RectangleMeshMaterial: Clone + Copy + bytemuck::Pod + bytemuck::Zeroab
 }
 
 pub struct RectangleMeshPipeline<M: RectangleMeshMaterial> {
-    pipeline: RenderPipeline,
+    pipeline: MainPipeline,
     bind: BindGroupLayout,
     _marker: PhantomData<M>,
 }
@@ -96,47 +96,51 @@ impl<M: RectangleMeshMaterial> RectangleMesh<M> {
             ],
         });
 
-        let pipeline = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some(M::label()),
             bind_group_layouts: &[&camera.layout, &bind],
             immediate_size: 0,
         });
 
-        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some(M::label()),
-            layout: Some(&pipeline),
-            vertex: match M::vertex() {
-                Some(entry_point) => VertexState {
-                    module: &custom_shader,
-                    entry_point,
-                    compilation_options: Default::default(),
-                    buffers: &[],
-                },
-                None => VertexState {
-                    module: &rectangle_shader,
-                    entry_point: None,
-                    compilation_options: Default::default(),
-                    buffers: &[],
-                },
-            },
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleStrip,
-                ..Default::default()
-            },
-            fragment: Some(FragmentState {
-                module: &custom_shader,
-                entry_point: M::fragment(),
-                compilation_options: Default::default(),
-                targets: &[Some(ColorTargetState {
-                    format: render.config.format,
-                    blend: Some(BlendState::ALPHA_BLENDING),
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            depth_stencil: None,
-            multisample: MSAA_STATE,
-            multiview_mask: None,
-            cache: None,
+        let pipeline = MainPipeline::new(&render, move |render| {
+            render
+                .device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some(M::label()),
+                    layout: Some(&layout),
+                    vertex: match M::vertex() {
+                        Some(entry_point) => VertexState {
+                            module: &custom_shader,
+                            entry_point,
+                            compilation_options: Default::default(),
+                            buffers: &[],
+                        },
+                        None => VertexState {
+                            module: &rectangle_shader,
+                            entry_point: None,
+                            compilation_options: Default::default(),
+                            buffers: &[],
+                        },
+                    },
+                    primitive: PrimitiveState {
+                        topology: PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    fragment: Some(FragmentState {
+                        module: &custom_shader,
+                        entry_point: M::fragment(),
+                        compilation_options: Default::default(),
+                        targets: &[Some(ColorTargetState {
+                            format: render.config.format,
+                            blend: Some(BlendState::ALPHA_BLENDING),
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    depth_stencil: None,
+                    multisample: render.multisample(),
+                    multiview_mask: None,
+                    cache: None,
+                })
         });
 
         world.insert(RectangleMeshPipeline {
@@ -192,6 +196,8 @@ impl<M: RectangleMeshMaterial> RectangleMesh<M> {
                 rpass.set_bind_group(1, &bind, &[]);
                 rpass.draw(0..4, 0..1);
             })),
+            bounding: Some(desc.rect),
+            clip: None,
         });
 
         RectangleMesh {
@@ -232,7 +238,11 @@ impl<M: RectangleMeshMaterial> Descriptor for RectangleMeshDescriptor<M> {
     }
 }
 
-impl<M: RectangleMeshMaterial> Element for RectangleMeshPipeline<M> {}
+impl<M: RectangleMeshMaterial> Element for RectangleMeshPipeline<M> {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        MainPipeline::follow(world, this, |manager| &mut manager.pipeline);
+    }
+}
 
 impl<M: RectangleMeshMaterial> Element for RectangleMesh<M> {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
@@ -242,6 +252,7 @@ impl<M: RectangleMeshMaterial> Element for RectangleMesh<M> {
 
     fn when_modify(&mut self, world: &World, _this: Handle<Self>) {
         self.reorder(world);
+        RenderControl::bound(world, self.control, Some(self.desc.rect));
         self.update_buffer();
         RenderControl::redraw(world);
     }
@@ -5,7 +5,7 @@ use wgpu::*;
 use crate::{
     measures::Rectangle,
     render::{
-        MSAA_STATE, Render, RenderControl,
+        MainPipeline, Render, RenderControl,
         camera::{Camera, CameraBind},
     },
     widgets::{WidgetEnabled, WidgetRectangle},
@@ -26,7 +26,7 @@ pub struct RoundedRectDescriptor {
 }
 
 pub struct RoundedRectPipeline {
-    pipeline: RenderPipeline,
+    pipeline: MainPipeline,
     bind: BindGroupLayout,
 }
 
@@ -69,6 +69,13 @@ impl Default for RoundedRectDescriptor {
     }
 }
 
+impl RoundedRectDescriptor {
+    /// The quad is drawn `vertex_extend` beyond the rectangle to leave room for the shadow.
+    pub fn bounding(&self) -> Rectangle {
+        self.rect.expand(self.vertex_extend)
+    }
+}
+
 impl RoundedRect {
     pub fn init(world: &World) {
         let render = world.single_fetch::<Render>().unwrap();
@@ -94,39 +101,43 @@ impl RoundedRect {
             }],
         });
 
-        let pipeline = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("rounded"),
             bind_group_layouts: &[&camera.layout, &bind],
             immediate_size: 0,
         });
 
-        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("rounded"),
-            layout: Some(&pipeline),
-            vertex: VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                compilation_options: Default::default(),
-                buffers: &[],
-            },
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleStrip,
-                ..Default::default()
-            },
-            fragment: Some(FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                compilation_options: Default::default(),
-                targets: &[Some(ColorTargetState {
-                    format: render.config.format,
-                    blend: Some(BlendState::ALPHA_BLENDING),
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            depth_stencil: None,
-            multisample: MSAA_STATE,
-            multiview_mask: None,
-            cache: None,
+        let pipeline = MainPipeline::new(&render, move |render| {
+            render
+                .device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("rounded"),
+                    layout: Some(&layout),
+                    vertex: VertexState {
+                        module: &shader,
+                        entry_point: Some("vs_main"),
+                        compilation_options: Default::default(),
+                        buffers: &[],
+                    },
+                    primitive: PrimitiveState {
+                        topology: PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    fragment: Some(FragmentState {
+                        module: &shader,
+                        entry_point: Some("fs_main"),
+                        compilation_options: Default::default(),
+                        targets: &[Some(ColorTargetState {
+                            format: render.config.format,
+                            blend: Some(BlendState::ALPHA_BLENDING),
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    depth_stencil: None,
+                    multisample: render.multisample(),
+                    multiview_mask: None,
+                    cache: None,
+                })
         });
 
         world.insert(RoundedRectPipeline { pipeline, bind });
@@ -167,6 +178,8 @@ impl RoundedRect {
                 rpass.set_bind_group(1, &bind, &[]);
                 rpass.draw(0..4, 0..1);
             })),
+            bounding: Some(desc.bounding()),
+            clip: None,
         });
 
         Self::upload_uniform(&desc, &render.queue, &uniform);
@@ -214,7 +227,11 @@ impl Descriptor for RoundedRectDescriptor {
     }
 }
 
-impl Element for RoundedRectPipeline {}
+impl Element for RoundedRectPipeline {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        MainPipeline::follow(world, this, |manager| &mut manager.pipeline);
+    }
+}
 
 impl Element for RoundedRect {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
@@ -234,6 +251,7 @@ impl Element for RoundedRect {
 
     fn when_modify(&mut self, world: &World, _this: Handle<Self>) {
         self.reorder(world);
+        RenderControl::bound(world, self.control, Some(self.desc.bounding()));
         Self::upload_uniform(&self.desc, &self.queue, &self.uniform);
         RenderControl::redraw(world);
     }
@@ -4,13 +4,24 @@ struct Camera {
     center_fract: vec2u,
     zoom: i32,
     zoom_fract: u32,
+    rotation: vec2f,
+    flip: f32,
+}
+
+// flip, then rotate counter-clockwise
+fn camera_rotate(camera_space: vec2f) -> vec2f {
+    let flipped = vec2f(camera_space.x * camera.flip, camera_space.y);
+    return vec2f(
+        flipped.x * camera.rotation.x - flipped.y * camera.rotation.y,
+        flipped.x * camera.rotation.y + flipped.y * camera.rotation.x,
+    );
 }
 
 fn camera_convert(world_space: vec2i) -> vec2f {
     let camera_space = world_space - camera.center;
     let camera_scale = pow(2.0, f32(camera.zoom) + f32(camera.zoom_fract) * 0x1p-32);
-    let screen_space = (floor((vec2f(camera_space) - vec2f(camera.center_fract) * vec2f(0x1p-32))
-         * camera_scale * 2.0) + vec2f(camera.size % 2) / 2) / vec2f(camera.size);
+    let screen_space = (floor(camera_rotate((vec2f(camera_space) - vec2f(camera.center_fract) * vec2f(0x1p-32))
+         * camera_scale * 2.0)) + vec2f(camera.size % 2) / 2) / vec2f(camera.size);
 
     return screen_space;
 }
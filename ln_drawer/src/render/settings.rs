@@ -0,0 +1,268 @@
+use ln_world::{Element, Handle, World};
+use redb::{ReadableDatabase, TableDefinition};
+use wgpu::{Adapter, PowerPreference, PresentMode, TextureFormat, TextureFormatFeatureFlags};
+
+use crate::{
+    render::{Render, RenderControl, RenderRebuild},
+    save::SaveDatabase,
+};
+
+pub(crate) const TABLE_SETTINGS: TableDefinition<&str, &[u8]> = TableDefinition::new("settings");
+const SETTINGS_RENDER: &str = "render";
+
+/// Saved in the database and applied live once modified, except [`RenderSettings::power`]
+/// which picks the adapter and only takes effect on the next launch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RenderSettings {
+    pub present: PresentPreference,
+    /// Samples per pixel, lowered to what the adapter supports. `1` disables MSAA.
+    pub msaa: u32,
+    /// Prefer `Rgba16Float` surfaces when available.
+    pub hdr: bool,
+    pub power: AdapterPreference,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum PresentPreference {
+    /// Vsync, tearing only if a frame is late when supported.
+    Fifo,
+    /// Vsync without waiting, the latest frame wins.
+    Mailbox,
+    /// No vsync at all.
+    Immediate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum AdapterPreference {
+    LowPower,
+    HighPerformance,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            present: PresentPreference::Fifo,
+            msaa: 4,
+            hdr: true,
+            power: AdapterPreference::LowPower,
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Falls back to the default if nothing is saved or the saved one is broken.
+    pub fn load(db: &SaveDatabase) -> RenderSettings {
+        match RenderSettings::try_load(db) {
+            Ok(settings) => settings.unwrap_or_default(),
+            Err(err) => {
+                log::error!("failed to load render settings: {err}");
+                RenderSettings::default()
+            }
+        }
+    }
+
+    fn try_load(db: &SaveDatabase) -> Result<Option<RenderSettings>, redb::Error> {
+        let read = db.0.begin_read()?;
+        let table = match read.open_table(TABLE_SETTINGS) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let bytes = table.get(SETTINGS_RENDER)?;
+        Ok(bytes.and_then(|bytes| postcard::from_bytes(bytes.value()).ok()))
+    }
+
+    pub fn save(&self, db: &SaveDatabase) -> Result<(), redb::Error> {
+        let write = db.0.begin_write()?;
+        {
+            let mut table = write.open_table(TABLE_SETTINGS)?;
+            let bytes = postcard::to_stdvec(self).unwrap();
+            table.insert(SETTINGS_RENDER, &bytes[..])?;
+        }
+        write.commit()?;
+        Ok(())
+    }
+
+    /// The next [`RenderSettings::msaa`] of the cycle `1, 2, 4, 8`.
+    pub fn next_msaa(&self) -> u32 {
+        match self.msaa {
+            0 | 1 => 2,
+            2 | 3 => 4,
+            4..8 => 8,
+            _ => 1,
+        }
+    }
+
+    pub fn present_mode(&self, supported: &[PresentMode]) -> PresentMode {
+        let wanted: &[PresentMode] = match self.present {
+            PresentPreference::Fifo => &[PresentMode::FifoRelaxed, PresentMode::Fifo],
+            PresentPreference::Mailbox => &[PresentMode::Mailbox, PresentMode::Fifo],
+            PresentPreference::Immediate => &[PresentMode::Immediate, PresentMode::Fifo],
+        };
+
+        (wanted.iter())
+            .find(|mode| supported.contains(mode))
+            .copied()
+            .unwrap_or(supported[0])
+    }
+
+    pub fn format(&self, supported: &[TextureFormat]) -> TextureFormat {
+        *(supported.iter())
+            .max_by_key(|&format| match format {
+                TextureFormat::Rgba16Float if self.hdr => 110,
+                TextureFormat::Rgba8UnormSrgb => 100,
+                TextureFormat::Bgra8UnormSrgb => 90,
+                _ if format.is_srgb() => 10,
+                _ => 0,
+            })
+            .unwrap()
+    }
+
+    /// The highest supported count not above [`RenderSettings::msaa`].
+    pub fn sample_count(&self, adapter: &Adapter, format: TextureFormat) -> u32 {
+        let flags = adapter.get_texture_format_features(format).flags;
+        self.supported_samples(flags)
+    }
+
+    fn supported_samples(&self, flags: TextureFormatFeatureFlags) -> u32 {
+        [8, 4, 2]
+            .into_iter()
+            .find(|&count| count <= self.msaa && flags.sample_count_supported(count))
+            .unwrap_or(1)
+    }
+}
+
+impl PresentPreference {
+    /// The next one of the cycle `Fifo, Mailbox, Immediate`.
+    pub fn next(self) -> PresentPreference {
+        match self {
+            PresentPreference::Fifo => PresentPreference::Mailbox,
+            PresentPreference::Mailbox => PresentPreference::Immediate,
+            PresentPreference::Immediate => PresentPreference::Fifo,
+        }
+    }
+}
+
+impl From<AdapterPreference> for PowerPreference {
+    fn from(value: AdapterPreference) -> Self {
+        match value {
+            AdapterPreference::LowPower => PowerPreference::LowPower,
+            AdapterPreference::HighPerformance => PowerPreference::HighPerformance,
+        }
+    }
+}
+
+impl Element for RenderSettings {
+    fn when_modify(&mut self, world: &World, _this: Handle<Self>) {
+        let settings = *self;
+
+        if let Ok(db) = world.single_fetch::<SaveDatabase>()
+            && let Err(err) = settings.save(&db)
+        {
+            log::error!("failed to save render settings: {err}");
+        }
+
+        // pipelines may be in use by the current frame
+        world.queue(move |world| {
+            let render = world.single::<Render>().unwrap();
+            let rebuild = world.fetch_mut(render).unwrap().apply(&settings);
+            if rebuild {
+                world.trigger(render, &RenderRebuild);
+            }
+            RenderControl::redraw(world);
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn present_mode() {
+        let all = [
+            PresentMode::Fifo,
+            PresentMode::FifoRelaxed,
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+        ];
+        let mut settings = RenderSettings::default();
+
+        assert_eq!(settings.present_mode(&all), PresentMode::FifoRelaxed);
+        assert_eq!(
+            settings.present_mode(&[PresentMode::Fifo]),
+            PresentMode::Fifo
+        );
+
+        settings.present = PresentPreference::Mailbox;
+        assert_eq!(settings.present_mode(&all), PresentMode::Mailbox);
+        assert_eq!(
+            settings.present_mode(&[PresentMode::Immediate, PresentMode::Fifo]),
+            PresentMode::Fifo
+        );
+
+        settings.present = PresentPreference::Immediate;
+        assert_eq!(settings.present_mode(&all), PresentMode::Immediate);
+        // nothing wanted is supported, the first supported one is taken
+        assert_eq!(
+            settings.present_mode(&[PresentMode::Mailbox]),
+            PresentMode::Mailbox
+        );
+    }
+
+    #[test]
+    fn format() {
+        let all = [
+            TextureFormat::Bgra8Unorm,
+            TextureFormat::Bgra8UnormSrgb,
+            TextureFormat::Rgba8UnormSrgb,
+            TextureFormat::Rgba16Float,
+        ];
+        let mut settings = RenderSettings::default();
+        assert_eq!(settings.format(&all), TextureFormat::Rgba16Float);
+
+        settings.hdr = false;
+        assert_eq!(settings.format(&all), TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(settings.format(&all[..2]), TextureFormat::Bgra8UnormSrgb);
+
+        let other = [TextureFormat::Rgb10a2Unorm, TextureFormat::Rgba8UnormSrgb];
+        assert_eq!(settings.format(&other), TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(settings.format(&other[..1]), TextureFormat::Rgb10a2Unorm);
+    }
+
+    #[test]
+    fn sample_count() {
+        let flags =
+            TextureFormatFeatureFlags::MULTISAMPLE_X2 | TextureFormatFeatureFlags::MULTISAMPLE_X4;
+        let mut settings = RenderSettings::default();
+        assert_eq!(settings.supported_samples(flags), 4);
+
+        settings.msaa = 8;
+        assert_eq!(settings.supported_samples(flags), 4);
+
+        settings.msaa = 3;
+        assert_eq!(settings.supported_samples(flags), 2);
+
+        settings.msaa = 1;
+        assert_eq!(settings.supported_samples(flags), 1);
+
+        settings.msaa = 8;
+        let none = TextureFormatFeatureFlags::empty();
+        assert_eq!(settings.supported_samples(none), 1);
+    }
+
+    #[test]
+    fn cycles() {
+        let mut settings = RenderSettings::default();
+        let mut msaa = vec![];
+        for _ in 0..4 {
+            settings.msaa = settings.next_msaa();
+            msaa.push(settings.msaa);
+        }
+        assert_eq!(msaa, [8, 1, 2, 4]);
+
+        let present = PresentPreference::Fifo;
+        assert_eq!(present.next().next().next(), present);
+    }
+}
@@ -1,75 +1,148 @@
-use cosmic_text::{Attrs, Color, Family, FontSystem, Metrics, Shaping, SwashCache};
+mod atlas;
+pub mod fonts;
+
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use bytemuck::Zeroable;
+use cosmic_text::{
+    Action, Align, Attrs, AttrsList, BorrowedWithFontSystem, Change, Color, Cursor, Edit, Editor,
+    Family, FontSystem, Metrics, Motion, Selection, Shaping, Style, SwashCache, Weight,
+};
 use ln_world::{Descriptor, Element, Handle, World};
+use palette::Srgba;
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, BufferBinding,
-    BufferBindingType, BufferUsages, ColorTargetState, ColorWrites, Extent3d, FilterMode,
-    FragmentState, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, RenderPipeline,
-    RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor,
-    ShaderSource, ShaderStages, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureSampleType, TextureUsages, TextureViewDescriptor, TextureViewDimension, VertexState,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Buffer, BufferAddress,
+    BufferBinding, BufferBindingType, BufferUsages, ColorTargetState, ColorWrites, FilterMode,
+    FragmentState, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, Queue,
+    RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, TextureSampleType, TextureViewDimension,
+    VertexBufferLayout, VertexState, VertexStepMode,
     util::{BufferInitDescriptor, DeviceExt},
-    wgt::TextureDataOrder,
+    vertex_attr_array,
+};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{Ime, KeyEvent},
+    keyboard::{Key, KeyCode, NamedKey, PhysicalKey},
+    window::{ImeCapabilities, ImeEnableRequest, ImeRequest, ImeRequestData},
 };
 
 use crate::{
-    measures::Rectangle,
+    lnwin::Lnwindow,
+    measures::{Position, PositionFract, Rectangle},
     render::{
-        MSAA_STATE, Render, RenderControl,
+        MainPipeline, Render, RenderControl,
         camera::{Camera, CameraBind},
+        canvas::{Canvas, CanvasDescriptor},
+        text::{atlas::GlyphAtlas, fonts::FontConfig},
         vertex::VertexUniform,
     },
+    tools::{
+        clipboard::Clipboard,
+        collider::ToolCollider,
+        focus::{Focus, FocusEnter, FocusIme, FocusInput, FocusLeave, RequestFocus},
+        modifiers::ModifiersTool,
+        pointer::{PointerHit, PointerHitStatus},
+    },
+    widgets::{WidgetEnabled, WidgetRectangle, WidgetText, context_menu::ContextAction},
 };
 
+/// Glyphs are rasterized this many times larger than laid out, to stay sharp when zoomed in.
+const TEXT_SCALE: f32 = 4.0;
+
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+const CLICK_DISTANCE: f64 = 4.0;
+
+const TEXT_COLOR: Color = Color::rgb(0xFF, 0xFF, 0xFF);
+const SELECTION_COLOR: Color = Color::rgba(0x7F, 0x7F, 0xFF, 0x7F);
+const PREEDIT_COLOR: Color = Color::rgb(0xB0, 0xB0, 0xFF);
+
+/// A laid out text, drawn as quads of glyphs from the atlas of the [`TextManager`].
 pub struct Text {
     pub order: isize,
-    bind: BindGroup,
+    pub visible: bool,
+    rect: Rectangle,
+    clip: Option<Rectangle>,
+    glyphs: Buffer,
+    /// The glyphs on each page of the atlas, drawn with a bind group of that page.
+    pages: Vec<(BindGroup, Range<u32>)>,
 }
 
 #[derive(Debug)]
 pub struct TextDescriptor<'a> {
     pub text: &'a str,
+    /// Styles over the plain text, see [`TextSpan`].
+    pub spans: &'a [TextSpan],
     pub rect: Rectangle,
     pub metrics: Metrics,
     pub order: isize,
     pub visible: bool,
+    /// Glyphs are cut off outside of it, like lines too long for a panel.
+    pub clip: Option<Rectangle>,
 }
 
+/// Styles a byte range of the text, which must lie on character boundaries. Where spans
+/// overlap, each of them applies in order.
+#[derive(Debug, Clone, Default)]
+pub struct TextSpan {
+    pub range: Range<usize>,
+    pub bold: bool,
+    pub italic: bool,
+    pub color: Option<Srgba>,
+    /// The font size, lines are spaced in the same proportion as the text.
+    pub size: Option<f32>,
+}
+
+/// Fonts and the glyph atlas shared by the texts of a window.
 pub struct TextManager {
     font_system: FontSystem,
     swash_cache: SwashCache,
-    pipeline: RenderPipeline,
+    atlas: GlyphAtlas,
+    sampler: Sampler,
+    queue: Queue,
+    pipeline: MainPipeline,
     bind_layout: BindGroupLayout,
 }
 
 pub struct TextManagerDescriptor;
 
+/// A glyph quad, placed from the left up corner of its text with y down.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GlyphInstance {
+    offset: [f32; 2],
+    size: [f32; 2],
+    uv: [f32; 4],
+    color: [f32; 4],
+}
+
 impl Descriptor for TextManagerDescriptor {
     type Target = Handle<TextManager>;
 
     fn when_build(self, world: &World) -> Self::Target {
-        let mut font_system = FontSystem::new();
-        let database = font_system.db_mut();
-
-        let sans = include_bytes!("../../fonts/SourceHanSansCN-Regular.otf").to_vec();
-        let serif = include_bytes!("../../fonts/SourceHanSerifCN-Regular.otf").to_vec();
-
-        database.load_font_data(sans);
-        database.load_font_data(serif);
-
+        let font_system = FontConfig::user(world).font_system();
         let swash_cache = SwashCache::new();
 
         let render = world.single_fetch::<Render>().unwrap();
         let camera = world.single_fetch::<CameraBind>().unwrap();
 
-        let shader_vs = render.device.create_shader_module(ShaderModuleDescriptor {
-            label: Some("vertex_shader"),
-            source: ShaderSource::Wgsl(include_str!("vertex.wgsl").into()),
+        let atlas = GlyphAtlas::new(&render.device);
+        let sampler = render.device.create_sampler(&SamplerDescriptor {
+            label: Some("text_sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
         });
 
-        let shader_fs = render.device.create_shader_module(ShaderModuleDescriptor {
+        let shader = render.device.create_shader_module(ShaderModuleDescriptor {
             label: Some("text_shader"),
-            source: ShaderSource::Wgsl(include_str!("text.wgsl").into()),
+            source: ShaderSource::Wgsl(
+                concat!(include_str!("vertex.wgsl"), "\n", include_str!("text.wgsl")).into(),
+            ),
         });
 
         let bind_layout = render
@@ -114,52 +187,177 @@ impl Descriptor for TextManagerDescriptor {
                 immediate_size: 0,
             });
 
-        let pipeline = render
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("text_pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: VertexState {
-                    module: &shader_vs,
-                    entry_point: Some("vs_main"),
-                    compilation_options: Default::default(),
-                    buffers: &[],
-                },
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleStrip,
-                    ..Default::default()
-                },
-                fragment: Some(FragmentState {
-                    module: &shader_fs,
-                    entry_point: Some("fs_main"),
-                    compilation_options: Default::default(),
-                    targets: &[Some(ColorTargetState {
-                        format: render.config.format,
-                        blend: Some(BlendState::ALPHA_BLENDING),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                }),
-                depth_stencil: None,
-                multisample: MSAA_STATE,
-                multiview_mask: None,
-                cache: None,
-            });
+        let pipeline = MainPipeline::new(&render, move |render| {
+            render
+                .device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("text_pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: VertexState {
+                        module: &shader,
+                        entry_point: Some("vs_glyph"),
+                        compilation_options: Default::default(),
+                        buffers: &[VertexBufferLayout {
+                            array_stride: size_of::<GlyphInstance>() as BufferAddress,
+                            step_mode: VertexStepMode::Instance,
+                            attributes: &vertex_attr_array![
+                                0 => Float32x2,
+                                1 => Float32x2,
+                                2 => Float32x4,
+                                3 => Float32x4,
+                            ],
+                        }],
+                    },
+                    primitive: PrimitiveState {
+                        topology: PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    fragment: Some(FragmentState {
+                        module: &shader,
+                        entry_point: Some("fs_glyph"),
+                        compilation_options: Default::default(),
+                        targets: &[Some(ColorTargetState {
+                            format: render.config.format,
+                            blend: Some(BlendState::ALPHA_BLENDING),
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    depth_stencil: None,
+                    multisample: render.multisample(),
+                    multiview_mask: None,
+                    cache: None,
+                })
+        });
 
         world.insert(TextManager {
             font_system,
             swash_cache,
+            atlas,
+            sampler,
+            queue: render.queue.clone(),
             pipeline,
             bind_layout,
         })
     }
 }
 
-impl Element for TextManager {}
+impl Element for TextManager {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        MainPipeline::follow(world, this, |manager| &mut manager.pipeline);
+    }
+}
+
+impl TextManager {
+    /// Lay out `text` within `rect` and take the quads of its glyphs with their page of the
+    /// atlas, sorted by page. The ones not in the atlas yet are rasterized.
+    fn layout(
+        &mut self,
+        text: &str,
+        spans: &[TextSpan],
+        rect: Rectangle,
+        metrics: Metrics,
+    ) -> Vec<(usize, GlyphInstance)> {
+        let attrs = Attrs::new().family(Family::SansSerif);
+        let rich = TextSpan::split(text, spans, &attrs, metrics);
+
+        let mut buffer = cosmic_text::Buffer::new(&mut self.font_system, metrics);
+        let mut buffer_borrow = buffer.borrow_with(&mut self.font_system);
+        let (width, height) = (rect.width() as f32, rect.height() as f32);
+        buffer_borrow.set_size(Some(width), Some(height));
+        buffer_borrow.set_rich_text(rich, &attrs, Shaping::Advanced, None);
+        buffer_borrow.shape_until_scroll(true);
+
+        let atlas_size = GlyphAtlas::size() as f32;
+        let mut glyphs = Vec::new();
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs {
+                let physical = glyph.physical((0.0, run.line_y * TEXT_SCALE), TEXT_SCALE);
+                let Some(placed) = self.atlas.glyph(
+                    &mut self.font_system,
+                    &mut self.swash_cache,
+                    &self.queue,
+                    physical.cache_key,
+                ) else {
+                    continue;
+                };
+
+                let [x, y, w, h] = placed.uv.map(|x| x as f32);
+                let [r, g, b, a] = glyph.color_opt.unwrap_or(TEXT_COLOR).as_rgba();
+                let color = Srgba::new(r, g, b, a).into_format::<f32, f32>();
+                let instance = GlyphInstance {
+                    offset: [
+                        (physical.x + placed.left) as f32 / TEXT_SCALE,
+                        (physical.y - placed.top) as f32 / TEXT_SCALE,
+                    ],
+                    size: [w / TEXT_SCALE, h / TEXT_SCALE],
+                    uv: [x, y, w, h].map(|x| x / atlas_size),
+                    color: color.into_linear::<f32, f32>().into_components().into(),
+                };
+                glyphs.push((placed.page, instance));
+            }
+        }
+
+        glyphs.sort_by_key(|(page, _)| *page);
+        glyphs
+    }
+}
+
+impl TextSpan {
+    /// Cut the text where any span starts or ends, with the attributes of every span
+    /// covering each piece.
+    fn split<'a>(
+        text: &'a str,
+        spans: &[TextSpan],
+        attrs: &Attrs<'static>,
+        metrics: Metrics,
+    ) -> Vec<(&'a str, Attrs<'static>)> {
+        let mut cuts = vec![0, text.len()];
+        for span in spans {
+            cuts.push(span.range.start.min(text.len()));
+            cuts.push(span.range.end.min(text.len()));
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        (cuts.windows(2))
+            .map(|cut| {
+                let (start, end) = (cut[0], cut[1]);
+                let attrs = (spans.iter())
+                    .filter(|span| span.range.start <= start && end <= span.range.end)
+                    .fold(attrs.clone(), |attrs, span| span.apply(attrs, metrics));
+                (&text[start..end], attrs)
+            })
+            .collect()
+    }
+
+    fn apply(&self, mut attrs: Attrs<'static>, metrics: Metrics) -> Attrs<'static> {
+        if self.bold {
+            attrs = attrs.weight(Weight::BOLD);
+        }
+
+        if self.italic {
+            attrs = attrs.style(Style::Italic);
+        }
+
+        if let Some(color) = self.color {
+            let (r, g, b, a) = color.into_format::<u8, u8>().into_components();
+            attrs = attrs.color(Color::rgba(r, g, b, a));
+        }
+
+        if let Some(size) = self.size {
+            let line_height = size * metrics.line_height / metrics.font_size;
+            attrs = attrs.metrics(Metrics::new(size, line_height));
+        }
+
+        attrs
+    }
+}
 
 impl Default for TextDescriptor<'_> {
     fn default() -> Self {
         Self {
             text: Default::default(),
+            spans: &[],
             rect: Rectangle::new(0, 0, 200, 24),
             metrics: Metrics::new(24.0, 20.0),
             order: 100,
@@ -175,8 +373,6 @@ impl Descriptor for TextDescriptor<'_> {
         let render = world.single_fetch::<Render>().unwrap();
         let manager = &mut *world.single_fetch_mut::<TextManager>().unwrap();
 
-        // instance //
-
         let uniform = render.device.create_buffer_init(&BufferInitDescriptor {
             label: Some("text_uniform"),
             contents: bytemuck::bytes_of(&VertexUniform {
@@ -186,93 +382,68 @@ impl Descriptor for TextDescriptor<'_> {
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
 
-        let upscale_metrics = self.metrics.scale(4.0);
-        let upscale_width = self.rect.width() * 4;
-        let upscale_height = self.rect.height() * 4;
-
-        let mut data = vec![0; (upscale_width * upscale_height * 4) as usize];
-
-        let mut buffer = cosmic_text::Buffer::new(&mut manager.font_system, upscale_metrics);
-        let mut buffer_borrow = buffer.borrow_with(&mut manager.font_system);
-
-        let attrs = Attrs::new().family(Family::Name("Source Han Sans CN"));
-        buffer_borrow.set_size(Some(upscale_width as f32), Some(upscale_height as f32));
-        buffer_borrow.set_text(self.text, &attrs, Shaping::Advanced);
-        buffer_borrow.shape_until_scroll(true);
-        buffer_borrow.draw(
-            &mut manager.swash_cache,
-            Color::rgb(0xFF, 0xFF, 0xFF),
-            |x, y, _, _, color| {
-                let start = ((x + y * upscale_width as i32) * 4) as usize;
-                let rgba = color.as_rgba();
-                if start >= data.len() {
-                    return;
-                }
-                data[start] = rgba[0];
-                data[start + 1] = rgba[1];
-                data[start + 2] = rgba[2];
-                data[start + 3] = rgba[3];
-            },
-        );
-
-        let texture = render.device.create_texture_with_data(
-            &render.queue,
-            &TextureDescriptor {
-                label: Some("text_texture"),
-                size: Extent3d {
-                    width: upscale_width,
-                    height: upscale_height,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: TextureDimension::D2,
-                format: TextureFormat::Rgba8UnormSrgb,
-                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            },
-            TextureDataOrder::LayerMajor,
-            &data,
-        );
-
-        let view = texture.create_view(&TextureViewDescriptor {
-            label: Some("text_view"),
-            ..Default::default()
-        });
-
-        let sampler = render.device.create_sampler(&SamplerDescriptor {
-            label: Some("text_sampler"),
-            mag_filter: FilterMode::Linear,
-            min_filter: FilterMode::Linear,
-            ..Default::default()
+        let placed = manager.layout(self.text, self.spans, self.rect, self.metrics);
+        let mut ranges = Vec::<(usize, Range<u32>)>::new();
+        for (idx, &(page, _)) in placed.iter().enumerate() {
+            let idx = idx as u32;
+            match ranges.last_mut() {
+                Some((last, range)) if *last == page => range.end = idx + 1,
+                _ => ranges.push((page, idx..idx + 1)),
+            }
+        }
+        let glyphs = placed
+            .into_iter()
+            .map(|(_, glyph)| glyph)
+            .collect::<Vec<_>>();
+
+        // never empty, a buffer of no size can't be bound
+        let placeholder = [GlyphInstance::zeroed()];
+        let contents = match glyphs.is_empty() {
+            true => bytemuck::cast_slice(&placeholder),
+            false => bytemuck::cast_slice(&glyphs),
+        };
+
+        let glyphs = render.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("text_glyphs"),
+            contents,
+            usage: BufferUsages::VERTEX,
         });
 
-        let bind = render.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("text_bind"),
-            layout: &manager.bind_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::Buffer(BufferBinding {
-                        buffer: &uniform,
-                        offset: 0,
-                        size: None,
-                    }),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(&view),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: BindingResource::Sampler(&sampler),
-                },
-            ],
-        });
+        let pages = (ranges.into_iter())
+            .map(|(page, range)| {
+                let bind = render.device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("text_bind"),
+                    layout: &manager.bind_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::Buffer(BufferBinding {
+                                buffer: &uniform,
+                                offset: 0,
+                                size: None,
+                            }),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::TextureView(manager.atlas.view(page)),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: BindingResource::Sampler(&manager.sampler),
+                        },
+                    ],
+                });
+                (bind, range)
+            })
+            .collect();
 
         world.insert(Text {
             order: self.order,
-            bind,
+            visible: self.visible,
+            rect: self.rect,
+            clip: self.clip,
+            glyphs,
+            pages,
         })
     }
 }
@@ -285,317 +456,913 @@ impl Element for Text {
                 let manager = world.single_fetch::<TextManager>().unwrap();
                 let camera = world.single_fetch::<Camera>().unwrap();
                 let this = world.fetch(this).unwrap();
+                if this.pages.is_empty() {
+                    return;
+                }
 
                 rpass.set_pipeline(&manager.pipeline);
                 rpass.set_bind_group(0, &camera.bind, &[]);
-                rpass.set_bind_group(1, &this.bind, &[]);
-                rpass.draw(0..4, 0..1);
+                rpass.set_vertex_buffer(0, this.glyphs.slice(..));
+                for (bind, range) in &this.pages {
+                    rpass.set_bind_group(1, bind, &[]);
+                    rpass.draw(0..4, range.clone());
+                }
             })),
+            bounding: Some(self.rect),
+            clip: self.clip,
         });
 
+        RenderControl::reorder(self.visible.then_some(self.order), world, control);
         world.dependency(control, this);
     }
 }
 
-#[cfg(false)]
+/// An editable text, focused by clicking it. Dragging and double or triple clicks select,
+/// the usual shortcuts move, copy through the [`Clipboard`] and undo, and input methods
+/// compose in place.
+///
+/// Send [`WidgetText`] to it to replace the text, it sends one whenever its text changes.
+/// Send [`TextEditStyle`] to restyle it.
 pub struct TextEdit {
-    inner: Painter,
+    pub rect: Rectangle,
+    pub order: isize,
+    pub visible: bool,
+
+    state: EditState,
+    color: Color,
+    align: Option<Align>,
+    fit: bool,
+    canvas: Handle<Canvas>,
+    collider: Handle<ToolCollider>,
+    focused: bool,
+
+    /// When and where the pointer was last pressed, and how many times in a row.
+    clicks: Option<(Instant, PositionFract, u32)>,
+}
+
+/// The text, selection and history of a [`TextEdit`], apart from drawing it.
+struct EditState {
     editor: Editor<'static>,
-    font_system: Arc<Mutex<FontSystem>>,
-    swash_cache: Arc<Mutex<SwashCache>>,
+    attrs: Attrs<'static>,
+
+    undo: Vec<Change>,
+    redo: Vec<Change>,
 
-    redraw: bool,
+    /// The text being composed by the input method, kept out of the history.
+    preedit: Option<(Cursor, Cursor)>,
 }
 
-#[cfg(false)]
-impl Element for TextEdit {
-    fn when_inserted(&mut self, world: &World, this: Handle<Self>) {
-        let collider = world.insert(PointerCollider {
-            rect: self.inner.get_rect(),
-            z_order: self.inner.get_z_order(),
-        });
+pub struct TextEditDescriptor<'a> {
+    pub text: &'a str,
+    pub rect: Rectangle,
+    pub metrics: Metrics,
+    pub color: Color,
+    pub align: Option<Align>,
+    /// Grows or shrinks downwards to fit the text instead of scrolling it.
+    pub fit: bool,
+    pub order: isize,
+    pub visible: bool,
+}
 
-        world.dependency(collider, this);
+#[derive(Debug, Clone, Copy)]
+pub struct TextEditStyle {
+    pub metrics: Metrics,
+    pub color: Color,
+    pub align: Option<Align>,
+}
 
-        world.observer(collider, move |&PointerHit(event), world| match event {
-            PointerEvent::Pressed(position) => {
-                let fetched = &mut *world.fetch_mut(this).unwrap();
+impl Default for TextEditDescriptor<'_> {
+    fn default() -> Self {
+        Self {
+            text: Default::default(),
+            rect: Rectangle::new(0, 0, 200, 24),
+            metrics: Metrics::new(16.0, 20.0),
+            color: TEXT_COLOR,
+            align: None,
+            fit: false,
+            order: 100,
+            visible: true,
+        }
+    }
+}
 
-                let point = position - fetched.inner.get_rect().left_up();
-                let point = Position::new(point.x, -point.y);
+impl Descriptor for TextEditDescriptor<'_> {
+    type Target = Handle<TextEdit>;
 
-                let mut font_system = fetched.font_system.lock();
-                fetched.editor.action(
-                    &mut font_system,
-                    Action::Click {
-                        x: point.x,
-                        y: point.y,
-                    },
-                );
+    fn when_build(self, world: &World) -> Self::Target {
+        let mut manager = world.single_fetch_mut::<TextManager>().unwrap();
+        let font_system = &mut manager.font_system;
+
+        let attrs = Attrs::new().family(Family::SansSerif);
+        let mut buffer = cosmic_text::Buffer::new(font_system, self.metrics);
+        let width = self.rect.width() as f32;
+        let height = (!self.fit).then_some(self.rect.height() as f32);
+        buffer.set_size(font_system, Some(width), height);
+        buffer.set_text(font_system, self.text, &attrs, Shaping::Advanced);
+        drop(manager);
+
+        let canvas = TextEdit::canvas(world, self.rect, self.order, self.visible);
+        let collider = world.insert(ToolCollider {
+            rect: self.rect,
+            order: self.order,
+            enabled: self.visible,
+        });
 
-                drop(font_system);
+        world.insert(TextEdit {
+            rect: self.rect,
+            order: self.order,
+            visible: self.visible,
+            state: EditState {
+                editor: Editor::new(buffer),
+                attrs,
+                undo: Vec::new(),
+                redo: Vec::new(),
+                preedit: None,
+            },
+            color: self.color,
+            align: self.align,
+            fit: self.fit,
+            canvas,
+            collider,
+            focused: false,
+            clicks: None,
+        })
+    }
+}
 
-                let focus = world.single::<Focus>().unwrap();
-                world.trigger(focus, RequestFocus(Some(this.untyped())));
+impl Element for TextEdit {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let collider = self.collider;
+        world.dependency(self.canvas, this);
+        world.dependency(collider, this);
 
-                fetched.redraw = true;
+        world.observer(collider, move |event: &PointerHit, world| {
+            TextEdit::update(world, this, |edit, world| edit.pointer(world, this, event));
+        });
+
+        world.observer(this, move |FocusInput(event): &FocusInput, world| {
+            if !event.state.is_pressed() {
+                return;
             }
-            PointerEvent::Moved(position) => {
-                let fetched = &mut *world.fetch_mut(this).unwrap();
-
-                let point = position - fetched.inner.get_rect().left_up();
-                let point = Position::new(point.x, -point.y);
-
-                let mut font_system = fetched.font_system.lock();
-                fetched.editor.action(
-                    &mut font_system,
-                    Action::Drag {
-                        x: point.x,
-                        y: point.y,
-                    },
-                );
 
-                drop(font_system);
+            TextEdit::update(world, this, |edit, world| edit.key(world, event));
+        });
+
+        world.observer(this, move |FocusIme(ime): &FocusIme, world| {
+            TextEdit::update(world, this, |edit, world| match ime {
+                Ime::Preedit(text, cursor) => edit.state.set_preedit(text, *cursor),
+                Ime::Commit(text) => edit.edit(world, |editor| {
+                    editor.delete_selection();
+                    editor.insert_string(text, None);
+                }),
+                Ime::Disabled => edit.state.clear_preedit(),
+                _ => {}
+            });
+        });
 
-                fetched.redraw = true;
+        world.observer(this, move |&FocusEnter, world| {
+            let mut edit = world.fetch_mut(this).unwrap();
+            let data = edit.ime_data(world);
+            let capabilities = ImeCapabilities::new().with_cursor_area();
+            if let Some(request) = ImeEnableRequest::new(capabilities, data) {
+                TextEdit::ime_request(world, ImeRequest::Enable(request));
             }
-            _ => {}
+
+            edit.focused = true;
+            edit.redraw(world);
         });
 
-        world.observer(this, |FocusInput(event), world, this| {
-            if !event.state.is_pressed() {
+        world.observer(this, move |&FocusLeave, world| {
+            TextEdit::ime_request(world, ImeRequest::Disable);
+            TextEdit::update(world, this, |edit, _| {
+                edit.focused = false;
+                edit.state.clear_preedit();
+            });
+        });
+
+        world.observer(this, move |WidgetText(text): &WidgetText, world| {
+            TextEdit::update(world, this, |edit, world| {
+                if edit.text() != *text {
+                    edit.edit(world, |editor| {
+                        select_all(editor);
+                        editor.delete_selection();
+                        editor.insert_string(text, None);
+                    });
+                }
+            });
+        });
+
+        world.observer(this, move |&WidgetRectangle(rect), world| {
+            let mut edit = world.fetch_mut(this).unwrap();
+            let resized = (rect.width(), rect.height()) != (edit.rect.width(), edit.rect.height());
+            edit.rect = rect;
+            world.fetch_mut(collider).unwrap().rect = rect;
+
+            if !resized {
+                world.fetch_mut(edit.canvas).unwrap().rect = rect;
                 return;
             }
 
-            let fetched = &mut *world.fetch_mut(this).unwrap();
+            // the canvas is as large as the field
+            let _ = world.remove(edit.canvas);
+            edit.canvas = TextEdit::canvas(world, rect, edit.order, edit.visible);
+            world.dependency(edit.canvas, this);
+
+            let mut manager = world.single_fetch_mut::<TextManager>().unwrap();
+            let width = rect.width() as f32;
+            let height = (!edit.fit).then_some(rect.height() as f32);
+            (edit.state.editor).with_buffer_mut(|buffer| {
+                buffer.set_size(&mut manager.font_system, Some(width), height);
+            });
+            drop(manager);
+            drop(edit);
+
+            // the width may wrap the text differently
+            TextEdit::update(world, this, |_, _| {});
+        });
+
+        world.observer(this, move |style: &TextEditStyle, world| {
+            TextEdit::update(world, this, |edit, world| {
+                let mut manager = world.single_fetch_mut::<TextManager>().unwrap();
+                (edit.state.editor).with_buffer_mut(|buffer| {
+                    buffer.set_metrics(&mut manager.font_system, style.metrics);
+                });
+                edit.color = style.color;
+                edit.align = style.align;
+            });
+        });
+
+        world.observer(this, move |&WidgetEnabled(enabled), world| {
+            let mut edit = world.fetch_mut(this).unwrap();
+            edit.visible = enabled;
+            world.fetch_mut(edit.canvas).unwrap().visible = enabled;
+            world.fetch_mut(collider).unwrap().enabled = enabled;
+        });
+
+        // offered on right click and long press
+        let actions: [(&str, fn(&mut TextEdit, &World)); 4] = [
+            ("text.cut", TextEdit::cut),
+            ("text.copy", |edit, world| edit.copy(world)),
+            ("text.paste", TextEdit::paste),
+            ("text.select_all", |edit, _| {
+                select_all(&mut edit.state.editor)
+            }),
+        ];
+        for (name, action) in actions {
+            ContextAction::register(world, collider, name, move |world, _| {
+                TextEdit::update(world, this, action);
+            });
+        }
+
+        world.queue(move |world| {
+            if world.validate(this).is_ok() {
+                TextEdit::update(world, this, |_, _| {});
+            }
+        });
+    }
+}
+
+impl TextEdit {
+    pub fn text(&self) -> String {
+        self.state.text()
+    }
 
-            let mut font_system = fetched.font_system.lock();
-            let mut editor = fetched.editor.borrow_with(&mut font_system);
+    /// Context actions may be registered on it too.
+    pub fn collider(&self) -> Handle<ToolCollider> {
+        self.collider
+    }
 
-            let modifiers = world.single_fetch::<LnwinModifiers>().unwrap();
-            let ctrl_down = modifiers.0.state().control_key();
-            let shift_down = modifiers.0.state().shift_key();
+    pub fn selected(&self) -> Option<String> {
+        self.state.selected()
+    }
+
+    pub fn undo(&mut self) {
+        self.state.undo();
+    }
+
+    pub fn redo(&mut self) {
+        self.state.redo();
+    }
+
+    pub fn copy(&self, world: &World) {
+        let mut clipboard = world.single_fetch_mut::<Clipboard>().unwrap();
+        self.state.copy(&mut clipboard);
+    }
+
+    pub fn cut(&mut self, world: &World) {
+        let mut manager = world.single_fetch_mut::<TextManager>().unwrap();
+        let mut clipboard = world.single_fetch_mut::<Clipboard>().unwrap();
+        self.state.cut(&mut manager.font_system, &mut clipboard);
+    }
+
+    pub fn paste(&mut self, world: &World) {
+        let mut manager = world.single_fetch_mut::<TextManager>().unwrap();
+        let mut clipboard = world.single_fetch_mut::<Clipboard>().unwrap();
+        self.state.paste(&mut manager.font_system, &mut clipboard);
+    }
 
-            if shift_down && let Selection::None = editor.selection() {
-                let cursor = editor.cursor();
-                editor.set_selection(Selection::Normal(cursor));
+    /// Run `f` on the field, then redraw it and send [`WidgetText`] if the text changed. A
+    /// fitting field sends [`WidgetRectangle`] to itself if its height changed.
+    fn update(world: &World, this: Handle<Self>, f: impl FnOnce(&mut TextEdit, &World)) {
+        let mut edit = world.fetch_mut(this).unwrap();
+        let text = edit.text();
+        f(&mut edit, world);
+        edit.redraw(world);
+
+        let changed = edit.text();
+        if changed != text {
+            world.queue_trigger(this, WidgetText(changed));
+        }
+
+        if edit.fit {
+            let height = edit.state.editor.with_buffer(|buffer| {
+                let lines = buffer.layout_runs().count().max(1);
+                (lines as f32 * buffer.metrics().line_height).ceil() as i32
+            });
+
+            if height != edit.rect.height() as i32 {
+                let rect = edit.rect.with_down(edit.rect.up() - height);
+                world.queue_trigger(this, WidgetRectangle(rect));
             }
+        }
+    }
 
-            match &event.logical_key {
-                Key::Named(NamedKey::ArrowLeft) if ctrl_down => {
-                    if !shift_down {
-                        editor.set_selection(Selection::None);
-                    }
-                    editor.action(Action::Motion(Motion::LeftWord))
-                }
-                Key::Named(NamedKey::ArrowRight) if ctrl_down => {
-                    if !shift_down {
-                        editor.set_selection(Selection::None);
-                    }
-                    editor.action(Action::Motion(Motion::RightWord))
-                }
-                Key::Named(NamedKey::ArrowLeft) => {
-                    if !shift_down {
-                        editor.set_selection(Selection::None);
-                    }
-                    editor.action(Action::Motion(Motion::Left));
-                }
-                Key::Named(NamedKey::ArrowRight) => {
-                    if !shift_down {
-                        editor.set_selection(Selection::None);
+    /// One step of the history.
+    fn edit(
+        &mut self,
+        world: &World,
+        f: impl FnOnce(&mut BorrowedWithFontSystem<Editor<'static>>),
+    ) {
+        let mut manager = world.single_fetch_mut::<TextManager>().unwrap();
+        self.state.edit(&mut manager.font_system, f);
+    }
+
+    fn action(&mut self, world: &World, action: Action) {
+        let mut manager = world.single_fetch_mut::<TextManager>().unwrap();
+        self.state.editor.action(&mut manager.font_system, action);
+    }
+
+    fn pointer(&mut self, world: &World, this: Handle<Self>, event: &PointerHit) {
+        let x = (event.position.x.into_f64() - self.rect.left() as f64) as i32;
+        let y = (self.rect.up() as f64 - event.position.y.into_f64()) as i32;
+
+        match event.status {
+            PointerHitStatus::Press => {
+                let now = Instant::now();
+                let clicks = match self.clicks {
+                    Some((time, position, clicks))
+                        if now - time < DOUBLE_CLICK
+                            && position.distance(event.position).into_f64() < CLICK_DISTANCE =>
+                    {
+                        clicks % 3 + 1
                     }
-                    editor.action(Action::Motion(Motion::Right));
+                    _ => 1,
+                };
+                self.clicks = Some((now, event.position, clicks));
+
+                self.state.clear_preedit();
+                match clicks {
+                    1 => self.action(world, Action::Click { x, y }),
+                    2 => self.action(world, Action::DoubleClick { x, y }),
+                    _ => self.action(world, Action::TripleClick { x, y }),
                 }
-                Key::Named(NamedKey::ArrowUp) => {
-                    if !shift_down {
-                        editor.set_selection(Selection::None);
-                    }
-                    editor.action(Action::Motion(Motion::Up));
+
+                let focus = world.single::<Focus>().unwrap();
+                world.queue_trigger(focus, RequestFocus(Some(this.untyped())));
+            }
+            PointerHitStatus::Moving => self.action(world, Action::Drag { x, y }),
+            PointerHitStatus::Release => {}
+        }
+    }
+
+    fn key(&mut self, world: &World, event: &KeyEvent) {
+        let modifiers = world
+            .single_fetch::<ModifiersTool>()
+            .unwrap()
+            .modifiers
+            .state();
+        let (ctrl, shift) = (modifiers.control_key(), modifiers.shift_key());
+
+        // shortcuts of the field, by key position like the keymap
+        if ctrl && let PhysicalKey::Code(code) = event.physical_key {
+            match code {
+                KeyCode::KeyA => return select_all(&mut self.state.editor),
+                KeyCode::KeyC => return self.copy(world),
+                KeyCode::KeyX => return self.cut(world),
+                KeyCode::KeyV => return self.paste(world),
+                KeyCode::KeyZ if shift => return self.redo(),
+                KeyCode::KeyZ => return self.undo(),
+                KeyCode::KeyY => return self.redo(),
+                _ => {}
+            }
+        }
+
+        let motion = match &event.logical_key {
+            Key::Named(NamedKey::ArrowLeft) if ctrl => Some(Motion::LeftWord),
+            Key::Named(NamedKey::ArrowRight) if ctrl => Some(Motion::RightWord),
+            Key::Named(NamedKey::ArrowLeft) => Some(Motion::Left),
+            Key::Named(NamedKey::ArrowRight) => Some(Motion::Right),
+            Key::Named(NamedKey::ArrowUp) => Some(Motion::Up),
+            Key::Named(NamedKey::ArrowDown) => Some(Motion::Down),
+            Key::Named(NamedKey::Home) => Some(Motion::Home),
+            Key::Named(NamedKey::End) => Some(Motion::End),
+            Key::Named(NamedKey::PageUp) => Some(Motion::PageUp),
+            Key::Named(NamedKey::PageDown) => Some(Motion::PageDown),
+            _ => None,
+        };
+
+        if let Some(motion) = motion {
+            // shift extends the selection
+            match shift {
+                true if self.state.editor.selection() == Selection::None => {
+                    let cursor = self.state.editor.cursor();
+                    self.state.editor.set_selection(Selection::Normal(cursor));
                 }
-                Key::Named(NamedKey::ArrowDown) => {
-                    if !shift_down {
-                        editor.set_selection(Selection::None);
-                    }
-                    editor.action(Action::Motion(Motion::Down));
+                true => {}
+                false => self.state.editor.set_selection(Selection::None),
+            }
+            return self.action(world, Action::Motion(motion));
+        }
+
+        match &event.logical_key {
+            Key::Named(NamedKey::Escape) => {
+                let focus = world.single::<Focus>().unwrap();
+                world.queue_trigger(focus, RequestFocus(None));
+            }
+            Key::Named(NamedKey::Enter) => self.edit(world, |editor| {
+                editor.delete_selection();
+                editor.action(Action::Enter);
+            }),
+            Key::Named(NamedKey::Backspace) if ctrl => {
+                self.edit(world, |editor| delete_word(editor, Motion::PreviousWord))
+            }
+            Key::Named(NamedKey::Delete) if ctrl => {
+                self.edit(world, |editor| delete_word(editor, Motion::NextWord))
+            }
+            Key::Named(NamedKey::Backspace) => self.edit(world, |editor| {
+                if !editor.delete_selection() {
+                    editor.action(Action::Backspace);
                 }
-                Key::Named(NamedKey::Home) => editor.action(Action::Motion(Motion::Home)),
-                Key::Named(NamedKey::End) => editor.action(Action::Motion(Motion::End)),
-                Key::Named(NamedKey::PageUp) => editor.action(Action::Motion(Motion::PageUp)),
-                Key::Named(NamedKey::PageDown) => editor.action(Action::Motion(Motion::PageDown)),
-                Key::Named(NamedKey::Escape) => editor.action(Action::Escape),
-                Key::Named(NamedKey::Enter) => {
-                    editor.delete_selection();
-                    editor.action(Action::Enter);
+            }),
+            Key::Named(NamedKey::Delete) => self.edit(world, |editor| {
+                if !editor.delete_selection() {
+                    editor.action(Action::Delete);
                 }
-                Key::Named(NamedKey::Backspace) if ctrl_down => {
-                    if !editor.delete_selection() {
-                        let cursor = editor.cursor();
-                        editor.set_selection(Selection::Normal(cursor));
-                        editor.action(Action::Motion(Motion::PreviousWord));
-                        editor.delete_selection();
-                        editor.set_selection(Selection::None);
-                    }
+            }),
+            _ => {
+                let Some(text) = &event.text else {
+                    return;
+                };
+
+                if ctrl || text.chars().any(char::is_control) {
+                    return;
                 }
-                Key::Named(NamedKey::Delete) if ctrl_down => {
-                    if !editor.delete_selection() {
-                        let cursor = editor.cursor();
-                        editor.set_selection(Selection::Normal(cursor));
-                        editor.action(Action::Motion(Motion::NextWord));
-                        editor.delete_selection();
-                        editor.set_selection(Selection::None);
-                    }
-                    let cursor = editor.cursor();
-                    editor.set_selection(Selection::Normal(cursor));
-                    editor.action(Action::Motion(Motion::NextWord));
+
+                self.edit(world, |editor| {
                     editor.delete_selection();
-                    editor.set_selection(Selection::None);
-                }
-                Key::Named(NamedKey::Backspace) => {
-                    if !editor.delete_selection() {
-                        editor.action(Action::Backspace);
-                    }
-                }
-                Key::Named(NamedKey::Delete) => {
-                    if !editor.delete_selection() {
-                        editor.action(Action::Delete);
+                    editor.insert_string(text, None);
+                });
+            }
+        }
+    }
+
+    fn redraw(&mut self, world: &World) {
+        let mut manager = world.single_fetch_mut::<TextManager>().unwrap();
+        let manager = &mut *manager;
+        let mut canvas = world.fetch_mut(self.canvas).unwrap();
+        let (width, height) = (self.rect.width() as i32, self.rect.height() as i32);
+
+        let [r, g, b, _] = self.color.as_rgba();
+        let cursor_color = match self.focused {
+            true => Color::rgba(r, g, b, 0xC0),
+            false => Color::rgba(0, 0, 0, 0),
+        };
+
+        // lines split by editing don't keep the alignment
+        let align = self.align;
+        (self.state.editor).with_buffer_mut(|buffer| {
+            for line in &mut buffer.lines {
+                line.set_align(align);
+            }
+        });
+
+        let mut writer = canvas.open_writer();
+        writer.clear();
+        self.state
+            .editor
+            .shape_as_needed(&mut manager.font_system, true);
+        self.state.editor.draw(
+            &mut manager.font_system,
+            &mut manager.swash_cache,
+            self.color,
+            cursor_color,
+            SELECTION_COLOR,
+            self.color,
+            |x, y, w, h, color| {
+                let [r, g, b, a] = color.as_rgba();
+                let color = Srgba::new(r, g, b, a).into_format::<f32, f32>();
+                for y in y.max(0)..(y + h as i32).min(height) {
+                    for x in x.max(0)..(x + w as i32).min(width) {
+                        writer.draw(x, y, color);
                     }
                 }
-                Key::Named(key) => {
-                    if let Some(text) = key.to_text() {
-                        editor.delete_selection();
-                        for c in text.chars() {
-                            editor.action(Action::Insert(c));
-                        }
-                    }
+            },
+        );
+        drop(writer);
+        drop(canvas);
+
+        if self.focused {
+            let data = self.ime_data(world);
+            TextEdit::ime_request(world, ImeRequest::Update(data));
+        }
+
+        RenderControl::redraw(world);
+    }
+
+    /// The cursor in pixels of the window, for the candidates of the input method to follow.
+    fn ime_data(&self, world: &World) -> ImeRequestData {
+        let (x, y) = self.state.editor.cursor_position().unwrap_or_default();
+        let line_height = (self.state.editor).with_buffer(|buffer| buffer.metrics().line_height);
+
+        let camera = world.single_fetch::<Camera>().unwrap();
+        // as large as the window
+        let size = camera.size;
+        let to_window = |x: i32, y: i32| {
+            let position = Position::new(self.rect.left() + x, self.rect.up() - y);
+            let [x, y] = camera.world_to_screen_absolute(position.into_fract());
+            (
+                (x + 1.0) / 2.0 * size.w as f64,
+                (1.0 - y) / 2.0 * size.h as f64,
+            )
+        };
+
+        let (left, up) = to_window(x, y);
+        let (right, down) = to_window(x + 1, y + line_height.ceil() as i32);
+        ImeRequestData::default().with_cursor_area(
+            PhysicalPosition::new(left.min(right), up.min(down)).into(),
+            PhysicalSize::new((right - left).abs(), (down - up).abs()).into(),
+        )
+    }
+
+    fn ime_request(world: &World, request: ImeRequest) {
+        let Ok(lnwindow) = world.single_fetch::<Lnwindow>() else {
+            return;
+        };
+        if let Err(err) = lnwindow.window.request_ime_update(request) {
+            log::debug!("input method request failed: {err}");
+        }
+    }
+
+    fn canvas(world: &World, rect: Rectangle, order: isize, visible: bool) -> Handle<Canvas> {
+        world.build(CanvasDescriptor {
+            data: None,
+            width: rect.width(),
+            height: rect.height(),
+            rect,
+            order,
+            visible,
+        })
+    }
+}
+
+impl EditState {
+    fn text(&self) -> String {
+        self.editor.with_buffer(|buffer| {
+            let mut text = String::new();
+            for (idx, line) in buffer.lines.iter().enumerate() {
+                if idx > 0 {
+                    text.push('\n');
                 }
-                Key::Character(text) => {
-                    editor.delete_selection();
-                    for c in text.chars() {
-                        editor.action(Action::Insert(c));
+
+                match self.preedit {
+                    Some((start, end)) if start.line == idx && end.line == idx => {
+                        text.push_str(&line.text()[..start.index]);
+                        text.push_str(&line.text()[end.index..]);
                     }
+                    _ => text.push_str(line.text()),
                 }
-                _ => {}
             }
+            text
+        })
+    }
+
+    fn selected(&self) -> Option<String> {
+        self.editor.copy_selection()
+    }
+
+    fn undo(&mut self) {
+        self.clear_preedit();
+        if let Some(mut change) = self.undo.pop() {
+            change.reverse();
+            self.editor.apply_change(&change);
+            change.reverse();
+            self.redo.push(change);
+        }
+    }
 
-            drop(font_system);
+    fn redo(&mut self) {
+        self.clear_preedit();
+        if let Some(change) = self.redo.pop() {
+            self.editor.apply_change(&change);
+            self.undo.push(change);
+        }
+    }
+
+    fn copy(&self, clipboard: &mut Clipboard) {
+        if let Some(text) = self.selected() {
+            clipboard.write(text);
+        }
+    }
 
-            fetched.redraw = true;
+    fn cut(&mut self, font_system: &mut FontSystem, clipboard: &mut Clipboard) {
+        self.copy(clipboard);
+        self.edit(font_system, |editor| {
+            editor.delete_selection();
         });
+    }
 
-        world.observer(collider, move |&PointerMenu(position), world| {
-            world.insert(world.build(MenuDescriptor {
-                position,
-                entries: vec![MenuEntryDescriptor {
-                    label: "Remove".into(),
-                    action: Box::new(move |world| {
-                        world.remove(this);
-                    }),
-                }],
-                ..Default::default()
-            }));
+    fn paste(&mut self, font_system: &mut FontSystem, clipboard: &mut Clipboard) {
+        let Some(text) = clipboard.read() else {
+            return;
+        };
+
+        self.edit(font_system, |editor| {
+            editor.delete_selection();
+            editor.insert_string(&text, None);
         });
+    }
 
-        let interface = world.single::<Interface>().unwrap();
+    /// One step of the history.
+    fn edit(
+        &mut self,
+        font_system: &mut FontSystem,
+        f: impl FnOnce(&mut BorrowedWithFontSystem<Editor<'static>>),
+    ) {
+        self.clear_preedit();
+
+        self.editor.start_change();
+        f(&mut self.editor.borrow_with(font_system));
+
+        if let Some(change) = self.editor.finish_change()
+            && !change.items.is_empty()
+        {
+            self.undo.push(change);
+            self.redo.clear();
+        }
+    }
 
-        let tracker = world.observer(interface, move |Redraw, world| {
-            let mut this = world.fetch_mut(this).unwrap();
+    /// Put the text being composed at the cursor, instead of the one composed before.
+    fn set_preedit(&mut self, text: &str, cursor: Option<(usize, usize)>) {
+        self.clear_preedit();
+        if text.is_empty() {
+            return;
+        }
 
-            if this.redraw {
-                this.redraw();
-            }
-        });
+        self.editor.set_selection(Selection::None);
+        let start = self.editor.cursor();
+        let attrs = self.attrs.clone().color(PREEDIT_COLOR);
+        self.editor
+            .insert_string(text, Some(AttrsList::new(&attrs)));
+        let end = self.editor.cursor();
+        self.preedit = Some((start, end));
+
+        if let Some((index, _)) = cursor {
+            self.editor
+                .set_cursor(Cursor::new(start.line, start.index + index));
+        }
+    }
 
-        world.dependency(tracker, this);
+    fn clear_preedit(&mut self) {
+        if let Some((start, end)) = self.preedit.take() {
+            self.editor.set_selection(Selection::Normal(start));
+            self.editor.set_cursor(end);
+            self.editor.delete_selection();
+        }
     }
 }
 
-#[cfg(false)]
-impl TextEdit {
-    pub fn new(
-        rect: Rectangle,
-        text: String,
-        manager: &mut TextManager,
-        interface: &mut Interface,
-    ) -> TextEdit {
-        let mut font_system = manager.font_system.lock();
-        let mut swash_cache = manager.swash_cache.lock();
-
-        let metrics = Metrics::new(24.0, 20.0);
+fn select_all(editor: &mut Editor<'static>) {
+    let end = editor.with_buffer(|buffer| {
+        let line = buffer.lines.len().saturating_sub(1);
+        let index = buffer.lines.get(line).map_or(0, |line| line.text().len());
+        Cursor::new(line, index)
+    });
 
-        let mut buffer = Buffer::new(&mut font_system, metrics);
-        let mut buffer_borrow = buffer.borrow_with(&mut font_system);
+    editor.set_selection(Selection::Normal(Cursor::new(0, 0)));
+    editor.set_cursor(end);
+}
 
-        let attrs = Attrs::new().family(Family::Name("Source Han Sans CN"));
-        buffer_borrow.set_size(Some(rect.width() as f32), Some(rect.height() as f32));
-        buffer_borrow.set_text(&text, &attrs, Shaping::Advanced);
-        buffer_borrow.shape_until_scroll(true);
+/// Delete the selection, or up to the word boundary in the direction of `motion`.
+fn delete_word(editor: &mut BorrowedWithFontSystem<Editor<'static>>, motion: Motion) {
+    if !editor.delete_selection() {
+        let cursor = editor.cursor();
+        editor.set_selection(Selection::Normal(cursor));
+        editor.action(Action::Motion(motion));
+        editor.delete_selection();
+    }
+}
 
-        let mut data = vec![0; (rect.width() * rect.height() * 4) as usize];
-
-        buffer_borrow.draw(
-            &mut swash_cache,
-            Color::rgb(0xFF, 0xFF, 0xFF),
-            |x, y, _, _, color| {
-                let start = ((x + y * rect.width() as i32) * 4) as usize;
-                let rgba = color.as_rgba();
-                data[start] = rgba[0];
-                data[start + 1] = rgba[1];
-                data[start + 2] = rgba[2];
-                data[start + 3] = rgba[3];
-            },
-        );
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tools::clipboard::MemoryClipboard;
 
-        let inner = Painter::new_with(rect, data, interface);
+    fn state(text: &str) -> (EditState, FontSystem) {
+        // the bundled fonts only, the same everywhere
+        let mut font_system = FontConfig::default().font_system();
+        let attrs = Attrs::new().family(Family::SansSerif);
+        let mut buffer = cosmic_text::Buffer::new(&mut font_system, Metrics::new(16.0, 20.0));
+        buffer.set_text(&mut font_system, text, &attrs, Shaping::Advanced);
 
-        TextEdit {
-            inner,
+        let state = EditState {
             editor: Editor::new(buffer),
-            font_system: manager.font_system.clone(),
-            swash_cache: manager.swash_cache.clone(),
-            redraw: false,
-        }
+            attrs,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            preedit: None,
+        };
+        (state, font_system)
     }
 
-    pub fn clone_text(&self) -> String {
-        self.editor.with_buffer(|buffer| {
-            let mut selection = String::new();
+    fn select(state: &mut EditState, start: usize, end: usize) {
+        state
+            .editor
+            .set_selection(Selection::Normal(Cursor::new(0, start)));
+        state.editor.set_cursor(Cursor::new(0, end));
+    }
 
-            if let Some(line) = buffer.lines.first() {
-                selection.push_str(line.text());
-                for line_i in 1..buffer.lines.len() {
-                    selection.push('\n');
-                    selection.push_str(buffer.lines[line_i].text());
-                }
-            }
+    fn insert(state: &mut EditState, font_system: &mut FontSystem, text: &str) {
+        state.edit(font_system, |editor| {
+            editor.delete_selection();
+            editor.insert_string(text, None);
+        });
+    }
 
-            selection
-        })
+    #[test]
+    fn selection() {
+        let (mut state, _) = state("hello world\nagain");
+        assert_eq!(state.selected(), None);
+
+        select(&mut state, 6, 11);
+        assert_eq!(state.selected().as_deref(), Some("world"));
+
+        // backwards too
+        select(&mut state, 5, 0);
+        assert_eq!(state.selected().as_deref(), Some("hello"));
+
+        select_all(&mut state.editor);
+        assert_eq!(state.selected().as_deref(), Some("hello world\nagain"));
+    }
+
+    #[test]
+    fn undo_redo() {
+        let (mut state, mut font_system) = state("hello");
+        state.editor.set_cursor(Cursor::new(0, 5));
+        insert(&mut state, &mut font_system, " world");
+        assert_eq!(state.text(), "hello world");
+
+        state.undo();
+        assert_eq!(state.text(), "hello");
+        state.redo();
+        assert_eq!(state.text(), "hello world");
+
+        // a new edit forgets what was undone
+        state.undo();
+        select(&mut state, 0, 5);
+        insert(&mut state, &mut font_system, "bye");
+        assert_eq!(state.text(), "bye");
+        state.redo();
+        assert_eq!(state.text(), "bye");
+
+        state.undo();
+        assert_eq!(state.text(), "hello");
+        state.undo();
+        assert_eq!(state.text(), "hello");
     }
 
-    fn redraw(&mut self) {
-        self.redraw = false;
+    #[test]
+    fn clipboard() {
+        let (mut state, mut font_system) = state("hello world");
+        let mut clipboard = Clipboard::new(MemoryClipboard::default());
+
+        // nothing to paste yet, nor to undo after
+        state.paste(&mut font_system, &mut clipboard);
+        assert_eq!(state.text(), "hello world");
+        assert!(state.undo.is_empty());
+
+        select(&mut state, 0, 5);
+        state.copy(&mut clipboard);
+        assert_eq!(clipboard.read().as_deref(), Some("hello"));
+        assert_eq!(state.text(), "hello world");
+
+        select(&mut state, 5, 11);
+        state.cut(&mut font_system, &mut clipboard);
+        assert_eq!(clipboard.read().as_deref(), Some(" world"));
+        assert_eq!(state.text(), "hello");
+
+        // replaces the selection
+        select(&mut state, 0, 5);
+        state.paste(&mut font_system, &mut clipboard);
+        assert_eq!(state.text(), " world");
+
+        state.undo();
+        assert_eq!(state.text(), "hello");
+        state.undo();
+        assert_eq!(state.text(), "hello world");
+    }
 
-        let mut font_system = self.font_system.lock();
-        let mut swash_cache = self.swash_cache.lock();
+    #[test]
+    fn preedit_out_of_history() {
+        let (mut state, mut font_system) = state("ab");
+        state.editor.set_cursor(Cursor::new(0, 1));
+        state.set_preedit("xyz", Some((1, 1)));
+        assert_eq!(state.text(), "ab");
+        assert_eq!(state.editor.cursor(), Cursor::new(0, 2));
+
+        // committing replaces the composed text
+        insert(&mut state, &mut font_system, "x");
+        assert_eq!(state.text(), "axb");
+        assert_eq!(state.undo.len(), 1);
+
+        state.undo();
+        assert_eq!(state.text(), "ab");
+    }
 
-        let mut writer = self.inner.open_writer();
-        writer.clear([0; 4]);
-        self.editor.shape_as_needed(&mut font_system, true);
-        self.editor.draw(
-            &mut font_system,
-            &mut swash_cache,
-            Color::rgba(255, 255, 255, 255),
-            Color::rgba(255, 255, 255, 127),
-            Color::rgba(127, 127, 255, 127),
-            Color::rgba(255, 255, 255, 255),
-            |x, y, w, h, color| {
-                let rgba = color.as_rgba();
-                for x in x..(x + w as i32) {
-                    for y in y..(y + h as i32) {
-                        writer.draw(x, y, rgba);
-                    }
-                }
+    fn span(range: Range<usize>) -> TextSpan {
+        TextSpan {
+            range,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn spans_split() {
+        let attrs = Attrs::new().family(Family::SansSerif);
+        let metrics = Metrics::new(16.0, 20.0);
+        let spans = [
+            TextSpan {
+                bold: true,
+                ..span(0..5)
+            },
+            TextSpan {
+                italic: true,
+                ..span(3..8)
             },
+        ];
+
+        let pieces = TextSpan::split("hello world", &spans, &attrs, metrics);
+        let texts = pieces.iter().map(|(text, _)| *text).collect::<Vec<_>>();
+        assert_eq!(texts, ["hel", "lo", " wo", "rld"]);
+
+        let styles = (pieces.iter())
+            .map(|(_, attrs)| (attrs.weight, attrs.style))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            styles,
+            [
+                (Weight::BOLD, Style::Normal),
+                (Weight::BOLD, Style::Italic),
+                (Weight::NORMAL, Style::Italic),
+                (Weight::NORMAL, Style::Normal),
+            ]
+        );
+
+        // spans past the end are cut at it
+        let pieces = TextSpan::split("hello", &[span(3..100)], &attrs, metrics);
+        let texts = pieces.iter().map(|(text, _)| *text).collect::<Vec<_>>();
+        assert_eq!(texts, ["hel", "lo"]);
+
+        let pieces = TextSpan::split("hello", &[], &attrs, metrics);
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].1, attrs);
+    }
+
+    #[test]
+    fn spans_apply() {
+        let attrs = Attrs::new().family(Family::SansSerif);
+        let metrics = Metrics::new(16.0, 20.0);
+
+        let red = TextSpan {
+            color: Some(Srgba::new(1.0, 0.0, 0.0, 1.0)),
+            size: Some(32.0),
+            ..span(0..5)
+        };
+        let applied = red.apply(attrs.clone(), metrics);
+        assert_eq!(applied.color_opt, Some(Color::rgba(0xFF, 0, 0, 0xFF)));
+        // lines are spaced like the text they hold
+        assert_eq!(applied.metrics_opt, Some(Metrics::new(32.0, 40.0).into()));
+        assert_eq!(applied.weight, Weight::NORMAL);
+
+        // later spans win where they overlap
+        let blue = TextSpan {
+            color: Some(Srgba::new(0.0, 0.0, 1.0, 1.0)),
+            ..span(0..5)
+        };
+        let pieces = TextSpan::split("hello", &[red, blue], &attrs, metrics);
+        assert_eq!(pieces[0].1.color_opt, Some(Color::rgba(0, 0, 0xFF, 0xFF)));
+        assert_eq!(
+            pieces[0].1.metrics_opt,
+            Some(Metrics::new(32.0, 40.0).into())
         );
     }
 }
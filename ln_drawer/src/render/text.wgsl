@@ -1,22 +1,47 @@
-@group(1) @binding(1) var texture: texture_2d<f32>;
-@group(1) @binding(2) var texture_sampler: sampler;
+// Follows vertex.wgsl, for its camera and text rectangle.
 
-@fragment
-fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
-    let size = vec2f(textureDimensions(texture));
-    let point = size * uv;
-    
-    var value = vec4f();
-    value += textureSample(texture, texture_sampler, (point + vec2f(1.0, 1.0)) / size) * 0.7;
-    value += textureSample(texture, texture_sampler, (point + vec2f(-1.0, 1.0)) / size) * 0.7;
-    value += textureSample(texture, texture_sampler, (point + vec2f(-1.0, 1.0)) / size) * 0.7;
-    value += textureSample(texture, texture_sampler, (point + vec2f(-1.0, -1.0)) / size) * 0.7;
-    value += textureSample(texture, texture_sampler, (point + vec2f(1.0, 0.0)) / size) * 1.0;
-    value += textureSample(texture, texture_sampler, (point + vec2f(0.0, 1.0)) / size) * 1.0;
-    value += textureSample(texture, texture_sampler, (point + vec2f(-1.0, 0.0)) / size) * 1.0;
-    value += textureSample(texture, texture_sampler, (point + vec2f(0.0, -1.0)) / size) * 1.0;
-    value += textureSample(texture, texture_sampler, uv) * 16.0;
-    value /= 22.8;
+struct GlyphOutput {
+    @builtin(position) pos: vec4f,
+    @location(0) uv: vec2f,
+    @location(1) color: vec4f,
+}
+
+@group(1) @binding(1) var atlas: texture_2d<f32>;
+@group(1) @binding(2) var atlas_sampler: sampler;
+
+// like camera_convert, but with a fractional offset kept
+fn camera_convert_offset(world_space: vec2i, offset: vec2f) -> vec2f {
+    let camera_space = vec2f(world_space - camera.center)
+        - vec2f(camera.center_fract) * vec2f(0x1p-32) + offset;
+    let camera_scale = pow(2.0, f32(camera.zoom) + f32(camera.zoom_fract) * 0x1p-32);
+    let screen_space = (camera_rotate(camera_space * camera_scale * 2.0)
+        + vec2f(camera.size % 2) / 2) / vec2f(camera.size);
+
+    return screen_space;
+}
 
-    return smoothstep(vec4f(0.4), vec4f(0.6), value);
-}
\ No newline at end of file
+@vertex
+fn vs_glyph(
+    @builtin(vertex_index) index: u32,
+    @location(0) offset: vec2f,
+    @location(1) size: vec2f,
+    @location(2) uv: vec4f,
+    @location(3) color: vec4f,
+) -> GlyphOutput {
+    // glyphs are placed from the left up corner with y down
+    let corner = vec2f(f32(index / 2), f32(index % 2));
+    let left_up = vec2i(rectangle.origin.x, rectangle.origin.y + i32(rectangle.extend.y));
+    let point = offset + size * corner;
+
+    var ret: GlyphOutput;
+    ret.pos = vec4f(camera_convert_offset(left_up, vec2f(point.x, -point.y)), 0.0, 1.0);
+    ret.uv = uv.xy + uv.zw * corner;
+    ret.color = color;
+    return ret;
+}
+
+@fragment
+fn fs_glyph(in: GlyphOutput) -> @location(0) vec4f {
+    let coverage = textureSample(atlas, atlas_sampler, in.uv).r;
+    return vec4f(in.color.rgb, in.color.a * coverage);
+}
@@ -0,0 +1,182 @@
+use cosmic_text::{CacheKey, FontSystem, SwashCache, SwashContent};
+use hashbrown::HashMap;
+use wgpu::{
+    Device, Extent3d, Origin3d, Queue, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor,
+};
+
+/// The width and height of the atlas, a coverage byte per pixel.
+const ATLAS_SIZE: u32 = 4096;
+/// Left empty around every glyph, so sampling never bleeds into a neighbour.
+const ATLAS_PAD: u32 = 1;
+
+/// Coverage of every glyph drawn by the texts of a window, packed in rows into pages of
+/// one texture each. Glyphs are never evicted, once a page is full another one is added.
+pub struct GlyphAtlas {
+    device: Device,
+    pages: Vec<AtlasPage>,
+    glyphs: HashMap<CacheKey, Option<AtlasGlyph>>,
+    /// The left up corner of the free space in the current row of the last page, and the
+    /// height of the row.
+    cursor: (u32, u32),
+    row: u32,
+}
+
+struct AtlasPage {
+    texture: Texture,
+    view: TextureView,
+}
+
+/// Where a glyph is in the atlas, in pixels, and where it is placed from its origin.
+#[derive(Debug, Clone, Copy)]
+pub struct AtlasGlyph {
+    pub page: usize,
+    pub uv: [u32; 4],
+    pub left: i32,
+    pub top: i32,
+}
+
+impl GlyphAtlas {
+    pub fn new(device: &Device) -> GlyphAtlas {
+        GlyphAtlas {
+            device: device.clone(),
+            pages: vec![AtlasPage::new(device)],
+            glyphs: HashMap::new(),
+            cursor: (0, 0),
+            row: 0,
+        }
+    }
+
+    pub const fn size() -> u32 {
+        ATLAS_SIZE
+    }
+
+    pub fn view(&self, page: usize) -> &TextureView {
+        &self.pages[page].view
+    }
+
+    /// Rasterized and uploaded the first time it is asked for. `None` for glyphs covering
+    /// nothing, like spaces, and the ones larger than a page.
+    pub fn glyph(
+        &mut self,
+        font_system: &mut FontSystem,
+        swash_cache: &mut SwashCache,
+        queue: &Queue,
+        key: CacheKey,
+    ) -> Option<AtlasGlyph> {
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return *glyph;
+        }
+
+        let glyph = self.rasterize(font_system, swash_cache, queue, key);
+        self.glyphs.insert(key, glyph);
+        glyph
+    }
+
+    fn rasterize(
+        &mut self,
+        font_system: &mut FontSystem,
+        swash_cache: &mut SwashCache,
+        queue: &Queue,
+        key: CacheKey,
+    ) -> Option<AtlasGlyph> {
+        let image = swash_cache.get_image_uncached(font_system, key)?;
+        let (width, height) = (image.placement.width, image.placement.height);
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let coverage = coverage(image.content, image.data);
+        let (page, x, y) = self.allocate(width, height)?;
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: &self.pages[page].texture,
+                mip_level: 0,
+                origin: Origin3d { x, y, z: 0 },
+                aspect: TextureAspect::All,
+            },
+            &coverage,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Some(AtlasGlyph {
+            page,
+            uv: [x, y, width, height],
+            left: image.placement.left,
+            top: image.placement.top,
+        })
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(usize, u32, u32)> {
+        if width + ATLAS_PAD > ATLAS_SIZE || height + ATLAS_PAD > ATLAS_SIZE {
+            log::warn!("glyph of {width}x{height} is larger than the atlas, it is not drawn");
+            return None;
+        }
+
+        let (mut x, mut y) = self.cursor;
+        if x + width + ATLAS_PAD > ATLAS_SIZE {
+            x = 0;
+            y += self.row + ATLAS_PAD;
+            self.row = 0;
+        }
+
+        if y + height + ATLAS_PAD > ATLAS_SIZE {
+            self.pages.push(AtlasPage::new(&self.device));
+            log::debug!("glyph atlas is full, page {} is added", self.pages.len());
+            (x, y) = (0, 0);
+            self.row = 0;
+        }
+
+        self.cursor = (x + width + ATLAS_PAD, y);
+        self.row = self.row.max(height);
+        Some((self.pages.len() - 1, x, y))
+    }
+}
+
+impl AtlasPage {
+    fn new(device: &Device) -> AtlasPage {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("glyph_atlas"),
+            size: Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R8Unorm,
+            usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&TextureViewDescriptor {
+            label: Some("glyph_atlas_view"),
+            ..Default::default()
+        });
+
+        AtlasPage { texture, view }
+    }
+}
+
+/// A coverage byte per pixel. Colored glyphs like emoji are drawn in the color of the text,
+/// and subpixel masks are averaged over their channels.
+fn coverage(content: SwashContent, data: Vec<u8>) -> Vec<u8> {
+    match content {
+        SwashContent::Mask => data,
+        SwashContent::Color => data.chunks_exact(4).map(|pixel| pixel[3]).collect(),
+        SwashContent::SubpixelMask => (data.chunks_exact(4))
+            .map(|pixel| ((pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16) / 3) as u8)
+            .collect(),
+    }
+}
@@ -0,0 +1,190 @@
+use std::path::{Path, PathBuf};
+
+use cosmic_text::{Fallback, FontSystem, PlatformFallback, fontdb};
+use ln_world::World;
+use unicode_script::Script;
+
+use crate::{locale::system_locale, save::get_file_path};
+
+const FONT_FILE: &str = "fonts.toml";
+
+/// Fonts loaded besides the bundled Source Han Sans and Serif, read from `fonts.toml`
+/// next to the save. Missing entries are taken from the default.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FontConfig {
+    /// Discover the fonts installed on the system, slow on systems with many fonts.
+    pub system: bool,
+    /// Every font file found in these folders, searched recursively.
+    pub folders: Vec<PathBuf>,
+    pub sans_serif: String,
+    pub serif: String,
+    pub monospace: String,
+    /// Families tried in order for characters missing from the font asked for, before the
+    /// ones picked for the script by the platform.
+    pub fallback: Vec<String>,
+    /// Like `zh-CN`, picks between the fonts of scripts shared by languages. Taken from
+    /// `LANG` if empty.
+    pub locale: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FontConfigError {
+    #[error("failed to read the font config: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("malformed font config: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+/// The fallback of the platform, with the configured families in front.
+struct ConfigFallback {
+    common: Vec<&'static str>,
+    platform: PlatformFallback,
+}
+
+impl FontConfig {
+    pub fn load(path: &Path) -> Result<Self, FontConfigError> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// The config in `fonts.toml` next to the save, or the default one.
+    pub fn user(world: &World) -> Self {
+        let path = get_file_path(world, FONT_FILE);
+        if !path.exists() {
+            return FontConfig::default();
+        }
+
+        FontConfig::load(&path).unwrap_or_else(|err| {
+            log::error!("failed to load {}: {err}", path.display());
+            FontConfig::default()
+        })
+    }
+
+    pub fn font_system(&self) -> FontSystem {
+        let mut database = fontdb::Database::new();
+
+        let sans = include_bytes!("../../../fonts/SourceHanSansCN-Regular.otf").to_vec();
+        let serif = include_bytes!("../../../fonts/SourceHanSerifCN-Regular.otf").to_vec();
+        database.load_font_data(sans);
+        database.load_font_data(serif);
+
+        if self.system {
+            database.load_system_fonts();
+        }
+
+        for folder in &self.folders {
+            if !folder.is_dir() {
+                log::warn!("font folder {} is not found", folder.display());
+                continue;
+            }
+            database.load_fonts_dir(folder);
+        }
+
+        database.set_sans_serif_family(&self.sans_serif);
+        database.set_serif_family(&self.serif);
+        database.set_monospace_family(&self.monospace);
+        log::debug!("{} font faces loaded", database.len());
+
+        let platform = PlatformFallback;
+        // the fallback lives as long as the font system, which lives as long as the app
+        let common = (self.fallback.iter())
+            .map(|family| &*Box::leak(family.clone().into_boxed_str()))
+            .chain(platform.common_fallback().iter().copied())
+            .collect();
+
+        FontSystem::new_with_locale_and_db_and_fallback(
+            self.locale(),
+            database,
+            ConfigFallback { common, platform },
+        )
+    }
+
+    fn locale(&self) -> String {
+        if !self.locale.is_empty() {
+            return self.locale.clone();
+        }
+
+        system_locale().unwrap_or_else(|| "en-US".into())
+    }
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self {
+            system: false,
+            folders: Vec::new(),
+            sans_serif: "Source Han Sans CN".into(),
+            serif: "Source Han Serif CN".into(),
+            monospace: "Source Han Sans CN".into(),
+            fallback: Vec::new(),
+            locale: String::new(),
+        }
+    }
+}
+
+impl Fallback for ConfigFallback {
+    fn common_fallback(&self) -> &[&'static str] {
+        &self.common
+    }
+
+    fn forbidden_fallback(&self) -> &[&'static str] {
+        self.platform.forbidden_fallback()
+    }
+
+    fn script_fallback(&self, script: Script, locale: &str) -> &[&'static str] {
+        self.platform.script_fallback(script, locale)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn partial_config() {
+        let text = r#"
+            system = true
+            fallback = ["Noto Color Emoji", "Noto Sans Symbols"]
+            locale = "zh-CN"
+        "#;
+        let config = toml::from_str::<FontConfig>(text).unwrap();
+        assert!(config.system);
+        assert_eq!(config.fallback, ["Noto Color Emoji", "Noto Sans Symbols"]);
+        assert_eq!(config.locale(), "zh-CN");
+
+        // the rest is taken from the default
+        let default = FontConfig::default();
+        assert!(config.folders.is_empty());
+        assert_eq!(config.sans_serif, default.sans_serif);
+        assert_eq!(config.serif, default.serif);
+        assert_eq!(config.monospace, default.monospace);
+    }
+
+    #[test]
+    fn malformed_config() {
+        assert!(toml::from_str::<FontConfig>("system = \"yes\"").is_err());
+        assert!(toml::from_str::<FontConfig>("folders = [").is_err());
+    }
+
+    #[test]
+    fn load_errors() {
+        let dir = std::env::temp_dir().join(format!("ln_drawer_fonts_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let missing = FontConfig::load(&dir.join("missing.toml"));
+        assert!(matches!(missing, Err(FontConfigError::Io(_))));
+
+        let path = dir.join(FONT_FILE);
+        std::fs::write(&path, "sans_serif = 1").unwrap();
+        let malformed = FontConfig::load(&path);
+        assert!(matches!(malformed, Err(FontConfigError::Toml(_))));
+
+        std::fs::write(&path, "folders = [\"fonts\"]").unwrap();
+        let config = FontConfig::load(&path).unwrap();
+        assert_eq!(config.folders, [PathBuf::from("fonts")]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
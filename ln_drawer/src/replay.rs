@@ -0,0 +1,417 @@
+//! Recording winit's window events and feeding them back into a fresh [`Lnwin`].
+//!
+//! Only the events that can be built again by hand are kept: resizing, mouse pointer and
+//! wheel, and input methods. Keyboard, touch and tablet events carry platform data and are
+//! not recorded, so sessions relying on shortcuts, typing outside of an input method or a
+//! touch screen can't be replayed.
+//!
+//! Every event is kept with the [`Lnwindow::index`] of its window and replayed into the
+//! window opened in the same order. Events of windows that are not open are dropped.
+//!
+//! The replay runs on a board kept in memory, starting from an empty save every time.
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use winit::{
+    application::ApplicationHandler,
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{
+        ButtonSource, ElementState, Ime, MouseButton, MouseScrollDelta, PointerKind, PointerSource,
+        TouchPhase, WindowEvent,
+    },
+    event_loop::{ActiveEventLoop, ControlFlow},
+    window::WindowId,
+};
+
+use crate::{
+    lnwin::{Lnwin, Lnwindow},
+    save::SaveDatabase,
+};
+
+/// Longest event read back, a longer one is taken for a broken file.
+const MAX_EVENT_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum ReplayEvent {
+    SurfaceResized {
+        width: u32,
+        height: u32,
+    },
+    PointerEntered {
+        x: f64,
+        y: f64,
+    },
+    PointerLeft {
+        x: f64,
+        y: f64,
+    },
+    PointerMoved {
+        x: f64,
+        y: f64,
+    },
+    PointerButton {
+        x: f64,
+        y: f64,
+        button: ReplayButton,
+        pressed: bool,
+    },
+    LineWheel {
+        x: f32,
+        y: f32,
+    },
+    PixelWheel {
+        x: f64,
+        y: f64,
+    },
+    ImeEnabled,
+    ImePreedit {
+        text: String,
+        cursor: Option<(usize, usize)>,
+    },
+    ImeCommit(String),
+    ImeDisabled,
+    CloseRequested,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum ReplayButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// Appends every replayable window event into a file, see [`Replay`].
+pub struct ReplayRecorder {
+    writer: BufWriter<File>,
+    start: Instant,
+}
+
+/// Drives an [`Lnwin`] with recorded events instead of the user's input.
+pub struct Replay {
+    pub lnwin: Lnwin,
+    events: VecDeque<(Duration, usize, ReplayEvent)>,
+    start: Option<Instant>,
+}
+
+impl ReplayEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<ReplayEvent> {
+        Some(match event {
+            WindowEvent::SurfaceResized(size) => ReplayEvent::SurfaceResized {
+                width: size.width,
+                height: size.height,
+            },
+            WindowEvent::PointerEntered {
+                position,
+                kind: PointerKind::Mouse,
+                ..
+            } => ReplayEvent::PointerEntered {
+                x: position.x,
+                y: position.y,
+            },
+            WindowEvent::PointerLeft {
+                position: Some(position),
+                kind: PointerKind::Mouse,
+                ..
+            } => ReplayEvent::PointerLeft {
+                x: position.x,
+                y: position.y,
+            },
+            WindowEvent::PointerMoved {
+                position,
+                source: PointerSource::Mouse,
+                ..
+            } => ReplayEvent::PointerMoved {
+                x: position.x,
+                y: position.y,
+            },
+            WindowEvent::PointerButton {
+                position,
+                button: ButtonSource::Mouse(button),
+                state,
+                ..
+            } => ReplayEvent::PointerButton {
+                x: position.x,
+                y: position.y,
+                button: match button {
+                    MouseButton::Left => ReplayButton::Left,
+                    MouseButton::Right => ReplayButton::Right,
+                    MouseButton::Middle => ReplayButton::Middle,
+                    _ => return None,
+                },
+                pressed: state.is_pressed(),
+            },
+            WindowEvent::MouseWheel {
+                delta: MouseScrollDelta::LineDelta(x, y),
+                ..
+            } => ReplayEvent::LineWheel { x: *x, y: *y },
+            WindowEvent::MouseWheel {
+                delta: MouseScrollDelta::PixelDelta(delta),
+                ..
+            } => ReplayEvent::PixelWheel {
+                x: delta.x,
+                y: delta.y,
+            },
+            WindowEvent::Ime(Ime::Enabled) => ReplayEvent::ImeEnabled,
+            WindowEvent::Ime(Ime::Preedit(text, cursor)) => ReplayEvent::ImePreedit {
+                text: text.clone(),
+                cursor: *cursor,
+            },
+            WindowEvent::Ime(Ime::Commit(text)) => ReplayEvent::ImeCommit(text.clone()),
+            WindowEvent::Ime(Ime::Disabled) => ReplayEvent::ImeDisabled,
+            WindowEvent::CloseRequested => ReplayEvent::CloseRequested,
+            _ => return None,
+        })
+    }
+
+    pub fn into_window_event(self) -> WindowEvent {
+        match self {
+            ReplayEvent::SurfaceResized { width, height } => {
+                WindowEvent::SurfaceResized(PhysicalSize::new(width, height))
+            }
+            ReplayEvent::PointerEntered { x, y } => WindowEvent::PointerEntered {
+                device_id: None,
+                position: PhysicalPosition::new(x, y),
+                primary: true,
+                kind: PointerKind::Mouse,
+            },
+            ReplayEvent::PointerLeft { x, y } => WindowEvent::PointerLeft {
+                device_id: None,
+                position: Some(PhysicalPosition::new(x, y)),
+                primary: true,
+                kind: PointerKind::Mouse,
+            },
+            ReplayEvent::PointerMoved { x, y } => WindowEvent::PointerMoved {
+                device_id: None,
+                position: PhysicalPosition::new(x, y),
+                primary: true,
+                source: PointerSource::Mouse,
+            },
+            ReplayEvent::PointerButton {
+                x,
+                y,
+                button,
+                pressed,
+            } => WindowEvent::PointerButton {
+                device_id: None,
+                state: match pressed {
+                    true => ElementState::Pressed,
+                    false => ElementState::Released,
+                },
+                position: PhysicalPosition::new(x, y),
+                primary: true,
+                button: ButtonSource::Mouse(match button {
+                    ReplayButton::Left => MouseButton::Left,
+                    ReplayButton::Right => MouseButton::Right,
+                    ReplayButton::Middle => MouseButton::Middle,
+                }),
+            },
+            ReplayEvent::LineWheel { x, y } => WindowEvent::MouseWheel {
+                device_id: None,
+                delta: MouseScrollDelta::LineDelta(x, y),
+                phase: TouchPhase::Moved,
+            },
+            ReplayEvent::PixelWheel { x, y } => WindowEvent::MouseWheel {
+                device_id: None,
+                delta: MouseScrollDelta::PixelDelta(PhysicalPosition::new(x, y)),
+                phase: TouchPhase::Moved,
+            },
+            ReplayEvent::ImeEnabled => WindowEvent::Ime(Ime::Enabled),
+            ReplayEvent::ImePreedit { text, cursor } => {
+                WindowEvent::Ime(Ime::Preedit(text, cursor))
+            }
+            ReplayEvent::ImeCommit(text) => WindowEvent::Ime(Ime::Commit(text)),
+            ReplayEvent::ImeDisabled => WindowEvent::Ime(Ime::Disabled),
+            ReplayEvent::CloseRequested => WindowEvent::CloseRequested,
+        }
+    }
+}
+
+impl ReplayRecorder {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<ReplayRecorder> {
+        Ok(ReplayRecorder {
+            writer: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+        })
+    }
+
+    /// Keep `event` sent to the window numbered `window`, see [`Lnwindow::index`].
+    pub fn record(&mut self, window: usize, event: &WindowEvent) {
+        let Some(event) = ReplayEvent::from_window_event(event) else {
+            return;
+        };
+
+        let time = self.start.elapsed().as_micros() as u64;
+        let bytes = postcard::to_stdvec(&(time, window as u32, event)).unwrap();
+
+        let result = (self.writer.write_all(&(bytes.len() as u32).to_le_bytes()))
+            .and_then(|_| self.writer.write_all(&bytes));
+        if let Err(err) = result {
+            log::error!("failed to record window event: {err}");
+        }
+    }
+
+    pub fn finish(&mut self) {
+        if let Err(err) = self.writer.flush() {
+            log::error!("failed to finish recording: {err}");
+        }
+    }
+}
+
+impl Replay {
+    pub fn open(mut lnwin: Lnwin, path: impl AsRef<Path>) -> io::Result<Replay> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut events = VecDeque::new();
+
+        let mut len = [0; 4];
+        while reader.read_exact(&mut len).is_ok() {
+            let len = u32::from_le_bytes(len) as usize;
+            if len > MAX_EVENT_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("replay event of {len} bytes"),
+                ));
+            }
+
+            let mut bytes = vec![0; len];
+            reader.read_exact(&mut bytes)?;
+
+            let (time, window, event) = postcard::from_bytes::<(u64, u32, ReplayEvent)>(&bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            events.push_back((Duration::from_micros(time), window as usize, event));
+        }
+
+        log::info!("{} events loaded for replay", events.len());
+
+        // the same empty board every time, and the user's save is left alone
+        SaveDatabase::init_in_memory(&mut lnwin.world);
+
+        Ok(Replay {
+            lnwin,
+            events,
+            start: None,
+        })
+    }
+
+    fn pump(&mut self, event_loop: &dyn ActiveEventLoop, now: Duration) {
+        while let Some(&(time, ..)) = self.events.front()
+            && time <= now
+        {
+            let (_, window, event) = self.events.pop_front().unwrap();
+            let Some(window_id) = self.lnwin.window_of(window) else {
+                log::debug!("window {window} is not open to replay {event:?}");
+                continue;
+            };
+
+            // the real window has to follow, or the surface would mismatch
+            if let ReplayEvent::SurfaceResized { width, height } = event {
+                let world = &self.lnwin.world;
+                let view = self.lnwin.windows[&window_id];
+                world.enter(view, || {
+                    let lnwindow = world.single_fetch::<Lnwindow>().unwrap();
+                    let _ = (lnwindow.window)
+                        .request_surface_size(PhysicalSize::new(width, height).into());
+                });
+                continue;
+            }
+
+            (self.lnwin).window_event(event_loop, window_id, event.into_window_event());
+        }
+    }
+}
+
+impl ApplicationHandler for Replay {
+    fn can_create_surfaces(&mut self, event_loop: &dyn ActiveEventLoop) {
+        self.lnwin.can_create_surfaces(event_loop);
+        self.start.get_or_insert_with(Instant::now);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &dyn ActiveEventLoop,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        // the user's input is ignored during the replay
+        match event {
+            WindowEvent::PointerEntered { .. }
+            | WindowEvent::PointerLeft { .. }
+            | WindowEvent::PointerMoved { .. }
+            | WindowEvent::PointerButton { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::KeyboardInput { .. }
+            | WindowEvent::ModifiersChanged(_)
+            | WindowEvent::Ime(_) => {}
+            event => self.lnwin.window_event(event_loop, window_id, event),
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &dyn ActiveEventLoop) {
+        let Some(start) = self.start else {
+            return;
+        };
+
+        self.pump(event_loop, start.elapsed());
+
+        match self.events.front() {
+            Some(&(time, ..)) => event_loop.set_control_flow(ControlFlow::WaitUntil(start + time)),
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+    }
+
+    fn suspended(&mut self, event_loop: &dyn ActiveEventLoop) {
+        self.lnwin.suspended(event_loop);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ln_drawer_{name}_{}.replay", std::process::id()))
+    }
+
+    #[test]
+    fn windows_recorded() {
+        let path = temp_path("windows_recorded");
+        let mut recorder = ReplayRecorder::create(&path).unwrap();
+        recorder.record(0, &WindowEvent::SurfaceResized(PhysicalSize::new(4, 2)));
+        recorder.record(1, &WindowEvent::CloseRequested);
+        // not replayable
+        recorder.record(0, &WindowEvent::Focused(true));
+        recorder.finish();
+        drop(recorder);
+
+        let replay = Replay::open(Lnwin::default(), &path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let windows = (replay.events.iter())
+            .map(|(_, window, event)| (*window, format!("{event:?}")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            windows,
+            [
+                (0, "SurfaceResized { width: 4, height: 2 }".to_owned()),
+                (1, "CloseRequested".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_event() {
+        let path = temp_path("oversized_event");
+        fs::write(&path, u32::MAX.to_le_bytes()).unwrap();
+
+        let err = Replay::open(Lnwin::default(), &path).err().unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
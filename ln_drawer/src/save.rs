@@ -7,12 +7,13 @@ use std::{
 };
 
 use ln_world::{Element, Handle, World, WorldError};
-use redb::{Database, ReadableTable, TableDefinition, WriteTransaction};
+use redb::{Database, ReadableTable, TableDefinition, WriteTransaction, backends::InMemoryBackend};
 
 #[cfg(target_os = "android")]
 use crate::lnwin::LnAndroid;
 use crate::{
     lnwin::Lnwindow,
+    metrics::Metrics,
     render::camera::Camera,
     tools::timer::{Timer, TimerHit},
 };
@@ -23,7 +24,8 @@ use crate::{
 /// `version`: `format` (the last version that used it)
 /// - `v0.1.3-alpha.2`: 0
 /// - `v0.1.3-alpha.3`: 1
-const FORMAT_VERSION: u32 = 2;
+/// - `v0.1.3-dev`, before camera rotation: 2
+const FORMAT_VERSION: u32 = 3;
 
 /// The number of backup files.
 const BACKUP_SLOT: u32 = 6;
@@ -63,6 +65,19 @@ impl SaveDatabase {
         world.flush();
     }
 
+    /// A fresh database kept in memory, so nothing of the save is read or overwritten. Must
+    /// be inserted before [`SaveDatabase::init`] would be.
+    pub fn init_in_memory(world: &mut World) {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        SaveDatabase::fresh(&db).unwrap();
+        world.insert(SaveDatabase(Arc::new(db)));
+        log::debug!("in-memory database created");
+
+        world.flush();
+    }
+
     /// Format a fresh, empty database, this contains initializing minimum
     /// sets of data such as metadata and format version.
     fn fresh(db: &Database) -> Result<(), redb::Error> {
@@ -110,6 +125,7 @@ impl SaveDatabase {
             match migrate_format {
                 0 => legacy::migrate0(&write).unwrap(),
                 1 => legacy::migrate1(&write).unwrap(),
+                2 => legacy::migrate2(&write).unwrap(),
                 _ => unimplemented!("unsupported migration {migrate_format}"),
             }
 
@@ -188,6 +204,10 @@ impl Autosave {
 
         let duration = Instant::now().duration_since(start);
         log::debug!("autosave request finished in {duration:?}");
+
+        if let Ok(mut metrics) = world.single_fetch_mut::<Metrics>() {
+            metrics.autosaves.push(duration);
+        }
     }
 }
 
@@ -211,7 +231,9 @@ impl Element for Autosave {}
 
 impl Element for AutosaveScheduler {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
-        world.dependency(this, world.single::<Lnwindow>().unwrap());
+        if let Ok(lnwindow) = world.single::<Lnwindow>() {
+            world.dependency(this, lnwindow);
+        }
 
         let timer = world.insert(Timer::new(self.autosave_duration));
         world.observer(timer, move |TimerHit, world| {
@@ -4,6 +4,8 @@ use redb::{
 };
 use serde_bytes::ByteBuf;
 
+use crate::measures::{Fract, PositionFract, Size};
+
 /// Deprecate `SaveControl` and give custom tables to callers themselves to handle with.
 ///
 /// This migration will move StrokeLayer's chunks from main control table to their custom table.
@@ -107,3 +109,48 @@ pub fn migrate1(write: &WriteTransaction) -> Result<(), redb::Error> {
 
     Ok(())
 }
+
+/// Add rotation and flip to the saved cameras.
+///
+/// This migration will rewrite every camera in the camera table, with no rotation and no flip.
+pub fn migrate2(write: &WriteTransaction) -> Result<(), redb::Error> {
+    const TABLE_CAMERA: TableDefinition<&str, &[u8]> = TableDefinition::new("camera");
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct LegacyCameraDescriptor {
+        size: Size,
+        center: PositionFract,
+        zoom: Fract,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct CameraDescriptor {
+        size: Size,
+        center: PositionFract,
+        zoom: Fract,
+        rotation: f32,
+        flip: bool,
+    }
+
+    let mut table = write.open_table(TABLE_CAMERA)?;
+    let mut cameras = Vec::new();
+    for result in table.iter()? {
+        let (name, bytes) = result?;
+        let legacy = postcard::from_bytes::<LegacyCameraDescriptor>(bytes.value()).unwrap();
+        let bytes = postcard::to_stdvec(&CameraDescriptor {
+            size: legacy.size,
+            center: legacy.center,
+            zoom: legacy.zoom,
+            rotation: 0.0,
+            flip: false,
+        })
+        .unwrap();
+        cameras.push((name.value().to_owned(), bytes));
+    }
+
+    for (name, bytes) in cameras {
+        table.insert(&name[..], &bytes[..])?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,726 @@
+//! Rhai scripts loaded from the `scripts` folder next to the database.
+//!
+//! Every `*.rhai` file is run once when the UI is ready, in the order of their names.
+//! Scripts do not touch the world directly, they push [`ScriptCommand`]s which are applied
+//! after the script returns. Painted strokes are saved like any other stroke, so painting
+//! at the top level of a script will repeat on every launch, put it behind a button:
+//!
+//! ```rhai
+//! let grid = button(-300, -200, 100, 40);
+//! on_click(grid, || {
+//!     for i in 0..10 {
+//!         stroke([[i * 50, 0], [i * 50, 500]], #{ size: 2.0, color: [0.2, 0.2, 0.2] });
+//!     }
+//! });
+//! ```
+//!
+//! ### Functions
+//! - `stroke(points)`, `stroke(points, brush)`: paint a stroke in world coordinates, each
+//!   point is `[x, y]` or `[x, y, force]`. The brush is a map of `color` (`[r, g, b]` or
+//!   `[r, g, b, a]`), `size`, `flow` (a number or `[min, max]`) and `softness`, missing keys
+//!   fall back to the current brush. Chunks away from the cameras are loaded for it
+//!   first, so it may show up a moment later.
+//! - `camera(x, y)`, `zoom(z)`: move the main camera.
+//! - `button(x, y, w, h)`, `text(x, y, w, h, text)`: create a widget in UI pixels, where
+//!   `x, y` is the lower left corner and the screen center is zero. Returns an id.
+//!   Every element takes a map of properties in the place of the last argument too, like
+//!   `text(x, y, w, h, #{ text: "hello", size: 24 })`, see [`ScriptElement`].
+//! - `on_click(id, callback)`: call `callback` when the button is clicked, see
+//!   [`ScriptEvent`].
+//! - `remove(id)`: remove the widget.
+
+use std::{cell::RefCell, fs, path::PathBuf, rc::Rc};
+
+use cosmic_text::Metrics;
+use hashbrown::HashMap;
+use ln_world::{Element, Handle, World};
+use palette::Srgba;
+use rhai::{AST, Array, Dynamic, Engine, EvalAltResult, FnPtr, Map};
+
+use crate::{
+    measures::{Fract, PositionFract, Rectangle},
+    render::{
+        camera::{Camera, CameraPositionChanged, MainCamera},
+        text::TextDescriptor,
+    },
+    save::get_file_path,
+    stroke::{StrokeLayer, interpolate::Draw, modifier::Modifier},
+    theme::{Attach, ThemeManager},
+    widgets::{WidgetClick, button::Button},
+};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Scripts run on the UI thread, a run or callback going past these is stopped.
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+const MAX_EXPR_DEPTH: usize = 64;
+const MAX_STRING_SIZE: usize = 1 << 20;
+const MAX_COLLECTION_SIZE: usize = 1 << 16;
+
+/// Should be inserted into the UI camera of the primary window.
+pub struct ScriptHost {
+    /// Where the scripts are run from once inserted, the `scripts` folder next to the
+    /// database if `None`.
+    pub folder: Option<PathBuf>,
+    engine: Engine,
+    scripts: Vec<AST>,
+    commands: Rc<RefCell<ScriptCommands>>,
+    elements: HashMap<i64, Handle>,
+}
+
+pub enum ScriptCommand {
+    Stroke {
+        draws: Vec<Draw>,
+        brush: ScriptBrush,
+    },
+    Camera {
+        x: f64,
+        y: f64,
+    },
+    Zoom(f64),
+    Create {
+        id: i64,
+        build: Box<dyn FnOnce(&World) -> Handle>,
+    },
+    Observe {
+        id: i64,
+        callback: FnPtr,
+        observe: ObserveFn,
+    },
+    Remove(i64),
+}
+
+/// Calls the action whenever the event is sent to the target, returns the observer.
+pub type ObserveFn = fn(&World, Handle, Box<dyn FnMut(&World)>) -> Handle;
+
+/// An element scripts can create, by `NAME(x, y, w, h)` or `NAME(x, y, w, h, props)` with
+/// a map of properties. Registered by [`ScriptHost::register`].
+pub trait ScriptElement: Sized + 'static {
+    const NAME: &'static str;
+
+    /// Called as the script creates it, `rect` is in UI pixels.
+    fn parse(rect: Rectangle, props: Map) -> ScriptResult<Self>;
+
+    /// Called once the script has returned, in the view of the [`ScriptHost`].
+    fn build(self, world: &World) -> Handle;
+}
+
+/// An event scripts can react to by `NAME(id, callback)`. Registered by
+/// [`ScriptHost::listen`].
+pub trait ScriptEvent: 'static {
+    const NAME: &'static str;
+}
+
+/// A line of text, `text` and its font `size` are taken from the properties.
+pub struct ScriptText {
+    pub rect: Rectangle,
+    pub text: String,
+    pub size: f32,
+}
+
+/// Overrides over the current [`Modifier`] of the [`StrokeLayer`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScriptBrush {
+    pub color: Option<Srgba>,
+    pub size: Option<(f32, f32)>,
+    pub flow: Option<(f32, f32)>,
+    pub softness: Option<f32>,
+}
+
+#[derive(Default)]
+struct ScriptCommands {
+    buffer: Vec<ScriptCommand>,
+    next_id: i64,
+}
+
+impl ScriptCommands {
+    /// Ids are given out immediately, so scripts can use them before the widget exists.
+    fn create(&mut self, command: impl FnOnce(i64) -> ScriptCommand) -> i64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.buffer.push(command(id));
+        id
+    }
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        let commands = Rc::new(RefCell::new(ScriptCommands::default()));
+        let mut host = Self {
+            folder: None,
+            engine: script_engine(&commands),
+            scripts: Vec::new(),
+            commands,
+            elements: HashMap::new(),
+        };
+
+        host.register::<Button>();
+        host.register::<ScriptText>();
+        host.listen::<WidgetClick>();
+
+        // the text without other properties
+        let commands = host.commands.clone();
+        host.engine.register_fn(
+            "text",
+            move |x: i64, y: i64, w: i64, h: i64, text: &str| -> ScriptResult<i64> {
+                let mut props = Map::new();
+                props.insert("text".into(), Dynamic::from(text.to_owned()));
+                create::<ScriptText>(&commands, rectangle(x, y, w, h)?, props)
+            },
+        );
+
+        host
+    }
+}
+
+impl Element for ScriptHost {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let folder = (self.folder.clone()).unwrap_or_else(|| get_file_path(world, "scripts"));
+        let mut paths = match fs::read_dir(&folder) {
+            Ok(dir) => (dir.filter_map(|entry| entry.ok()))
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+                .collect::<Vec<_>>(),
+            Err(_) => {
+                log::debug!("no scripts in {}", folder.display());
+                return;
+            }
+        };
+        paths.sort();
+
+        for path in paths {
+            self.load(world, this, path);
+        }
+    }
+}
+
+impl ScriptHost {
+    /// Lets scripts create `T`, see [`ScriptElement`].
+    pub fn register<T: ScriptElement>(&mut self) {
+        let commands = self.commands.clone();
+        self.engine.register_fn(
+            T::NAME,
+            move |x: i64, y: i64, w: i64, h: i64| -> ScriptResult<i64> {
+                create::<T>(&commands, rectangle(x, y, w, h)?, Map::new())
+            },
+        );
+
+        let commands = self.commands.clone();
+        self.engine.register_fn(
+            T::NAME,
+            move |x: i64, y: i64, w: i64, h: i64, props: Map| -> ScriptResult<i64> {
+                create::<T>(&commands, rectangle(x, y, w, h)?, props)
+            },
+        );
+    }
+
+    /// Lets scripts react to `E` sent to the elements they created, see [`ScriptEvent`].
+    pub fn listen<E: ScriptEvent>(&mut self) {
+        let commands = self.commands.clone();
+        self.engine
+            .register_fn(E::NAME, move |id: i64, callback: FnPtr| {
+                commands.borrow_mut().buffer.push(ScriptCommand::Observe {
+                    id,
+                    callback,
+                    observe: observe::<E>,
+                });
+            });
+    }
+
+    pub fn load(&mut self, world: &World, this: Handle<Self>, path: PathBuf) {
+        let ast = match self.engine.compile_file(path.clone()) {
+            Ok(ast) => ast,
+            Err(err) => {
+                log::error!("failed to compile {}: {err}", path.display());
+                return;
+            }
+        };
+
+        log::info!("running script {}", path.display());
+        if let Err(err) = self.engine.run_ast(&ast) {
+            let failure = failure(&self.engine, &err);
+            log::error!("script {} failed: {failure}", path.display());
+        }
+
+        self.scripts.push(ast);
+        self.submit(world, this, self.scripts.len() - 1);
+    }
+
+    fn call(&mut self, world: &World, this: Handle<Self>, script: usize, callback: &FnPtr) {
+        if let Err(err) = callback.call::<()>(&self.engine, &self.scripts[script], ()) {
+            let failure = failure(&self.engine, &err);
+            log::error!("script callback {} failed: {failure}", callback.fn_name());
+        }
+
+        self.submit(world, this, script);
+    }
+
+    /// Commands may remove the widget whose observer is running, so they are always queued.
+    fn submit(&mut self, world: &World, this: Handle<Self>, script: usize) {
+        let commands = std::mem::take(&mut self.commands.borrow_mut().buffer);
+        if commands.is_empty() {
+            return;
+        }
+
+        world.queue(move |world| {
+            for command in commands {
+                ScriptHost::apply(world, this, script, command);
+                // flushed, so the next ones can remove what was just created
+                world.flush();
+            }
+        });
+    }
+
+    fn apply(world: &World, this: Handle<Self>, script: usize, command: ScriptCommand) {
+        match command {
+            ScriptCommand::Stroke { draws, brush } => {
+                let main_camera = world.single_fetch::<MainCamera>().unwrap().0;
+                world.enter(main_camera, || {
+                    let mut stroke = world.single_fetch_mut::<StrokeLayer>().unwrap();
+                    let modifier = brush.apply(&stroke.modifier);
+                    stroke.stroke(draws, modifier, world);
+                });
+            }
+            ScriptCommand::Camera { x, y } => {
+                let main_camera = world.single_fetch::<MainCamera>().unwrap().0;
+                let mut camera = world.enter_single_fetch_mut::<Camera>(main_camera).unwrap();
+                let here = PositionFract::new(Fract::from_f64(x), Fract::from_f64(y));
+                world.queue_trigger(
+                    main_camera,
+                    CameraPositionChanged {
+                        from: camera.center,
+                        here,
+                    },
+                );
+                camera.center = here;
+            }
+            ScriptCommand::Zoom(zoom) => {
+                let main_camera = world.single_fetch::<MainCamera>().unwrap().0;
+                let mut camera = world.enter_single_fetch_mut::<Camera>(main_camera).unwrap();
+                camera.zoom = Fract::from_f64(zoom);
+                world.queue_trigger(
+                    main_camera,
+                    CameraPositionChanged {
+                        from: camera.center,
+                        here: camera.center,
+                    },
+                );
+            }
+            ScriptCommand::Create { id, build } => {
+                let element = build(world);
+                world.dependency(element, this);
+                world.fetch_mut(this).unwrap().elements.insert(id, element);
+            }
+            ScriptCommand::Observe {
+                id,
+                callback,
+                observe,
+            } => {
+                let Some(&target) = world.fetch(this).unwrap().elements.get(&id) else {
+                    log::warn!("script element {id} does not exist");
+                    return;
+                };
+
+                observe(
+                    world,
+                    target,
+                    Box::new(move |world| {
+                        let mut host = world.fetch_mut(this).unwrap();
+                        host.call(world, this, script, &callback);
+                    }),
+                );
+            }
+            ScriptCommand::Remove(id) => {
+                let removed = world.fetch_mut(this).unwrap().elements.remove(&id);
+                if let Some(handle) = removed {
+                    let _ = world.remove(handle);
+                }
+            }
+        }
+    }
+}
+
+impl ScriptBrush {
+    pub fn apply(&self, base: &Modifier) -> Modifier {
+        let (min_size, max_size) = self.size.unwrap_or((base.min_size, base.max_size));
+        let (min_flow, max_flow) = self.flow.unwrap_or((base.min_flow, base.max_flow));
+        Modifier {
+            min_size,
+            max_size,
+            min_flow,
+            max_flow,
+            softness: self.softness.unwrap_or(base.softness),
+            color: self.color.unwrap_or(base.color),
+            ..*base
+        }
+    }
+
+    fn parse(map: Map) -> ScriptResult<ScriptBrush> {
+        let mut brush = ScriptBrush::default();
+        for (key, value) in map {
+            match key.as_str() {
+                "color" => {
+                    let color = value.into_array()?;
+                    let channel = |idx: usize| match color.get(idx) {
+                        Some(value) => number(value).map(|x| x as f32),
+                        None => Ok(1.0),
+                    };
+                    brush.color = Some(Srgba::new(
+                        channel(0)?,
+                        channel(1)?,
+                        channel(2)?,
+                        channel(3)?,
+                    ));
+                }
+                "size" => brush.size = Some(range(value)?),
+                "flow" => brush.flow = Some(range(value)?),
+                "softness" => brush.softness = Some(number(&value)? as f32),
+                key => return Err(format!("unknown brush key {key}").into()),
+            }
+        }
+        Ok(brush)
+    }
+}
+
+impl ScriptElement for Button {
+    const NAME: &'static str = "button";
+
+    fn parse(rect: Rectangle, props: Map) -> ScriptResult<Self> {
+        let mut button = Button {
+            rect,
+            ..Default::default()
+        };
+        for (key, value) in props {
+            match key.as_str() {
+                "order" => button.order = value.as_int()? as isize,
+                key => return Err(format!("unknown button property {key}").into()),
+            }
+        }
+        Ok(button)
+    }
+
+    fn build(self, world: &World) -> Handle {
+        let button = world.insert(self);
+        if let Ok(theme) = world.single::<ThemeManager>() {
+            world.queue_trigger(theme, Attach::here(world, button));
+        }
+        button.untyped()
+    }
+}
+
+impl ScriptElement for ScriptText {
+    const NAME: &'static str = "text";
+
+    fn parse(rect: Rectangle, props: Map) -> ScriptResult<Self> {
+        let mut text = ScriptText {
+            rect,
+            text: String::new(),
+            size: 16.0,
+        };
+        for (key, value) in props {
+            match key.as_str() {
+                "text" => text.text = value.into_string()?,
+                "size" => text.size = number(&value)? as f32,
+                key => return Err(format!("unknown text property {key}").into()),
+            }
+        }
+        Ok(text)
+    }
+
+    fn build(self, world: &World) -> Handle {
+        (world.build(TextDescriptor {
+            text: &self.text,
+            spans: &[],
+            rect: self.rect,
+            metrics: Metrics::new(self.size, self.size * 1.25),
+            order: 20,
+            visible: true,
+            clip: None,
+        }))
+        .untyped()
+    }
+}
+
+impl ScriptEvent for WidgetClick {
+    const NAME: &'static str = "on_click";
+}
+
+fn create<T: ScriptElement>(
+    commands: &RefCell<ScriptCommands>,
+    rect: Rectangle,
+    props: Map,
+) -> ScriptResult<i64> {
+    let element = T::parse(rect, props)?;
+    Ok(commands.borrow_mut().create(|id| ScriptCommand::Create {
+        id,
+        build: Box::new(move |world| element.build(world)),
+    }))
+}
+
+fn observe<E: ScriptEvent>(
+    world: &World,
+    target: Handle,
+    mut action: Box<dyn FnMut(&World)>,
+) -> Handle {
+    world.observer(target, move |_: &E, world| action(world))
+}
+
+fn script_engine(commands: &Rc<RefCell<ScriptCommands>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.on_print(|text| log::info!("script: {text}"));
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE);
+
+    let stroke = commands.clone();
+    engine.register_fn("stroke", move |points: Array| -> ScriptResult<()> {
+        stroke.borrow_mut().buffer.push(ScriptCommand::Stroke {
+            draws: draws(points)?,
+            brush: ScriptBrush::default(),
+        });
+        Ok(())
+    });
+
+    let stroke = commands.clone();
+    engine.register_fn(
+        "stroke",
+        move |points: Array, brush: Map| -> ScriptResult<()> {
+            stroke.borrow_mut().buffer.push(ScriptCommand::Stroke {
+                draws: draws(points)?,
+                brush: ScriptBrush::parse(brush)?,
+            });
+            Ok(())
+        },
+    );
+
+    let camera = commands.clone();
+    engine.register_fn(
+        "camera",
+        move |x: Dynamic, y: Dynamic| -> ScriptResult<()> {
+            camera.borrow_mut().buffer.push(ScriptCommand::Camera {
+                x: number(&x)?,
+                y: number(&y)?,
+            });
+            Ok(())
+        },
+    );
+
+    let zoom = commands.clone();
+    engine.register_fn("zoom", move |z: Dynamic| -> ScriptResult<()> {
+        zoom.borrow_mut()
+            .buffer
+            .push(ScriptCommand::Zoom(number(&z)?));
+        Ok(())
+    });
+
+    let remove = commands.clone();
+    engine.register_fn("remove", move |id: i64| {
+        remove.borrow_mut().buffer.push(ScriptCommand::Remove(id));
+    });
+
+    engine
+}
+
+/// What went wrong, naming the limit if the script was stopped by one.
+fn failure(engine: &Engine, err: &EvalAltResult) -> String {
+    match err {
+        EvalAltResult::ErrorInFunctionCall(_, _, err, _) => failure(engine, err),
+        EvalAltResult::ErrorTooManyOperations(pos) => {
+            let max = engine.max_operations();
+            format!("stopped after {max} operations at {pos}")
+        }
+        EvalAltResult::ErrorStackOverflow(pos) => {
+            let max = engine.max_call_levels();
+            format!("stopped over {max} nested calls at {pos}")
+        }
+        EvalAltResult::ErrorDataTooLarge(what, pos) => {
+            format!("stopped as {what} grew over the limit at {pos}")
+        }
+        err => err.to_string(),
+    }
+}
+
+fn draws(points: Array) -> ScriptResult<Vec<Draw>> {
+    (points.into_iter())
+        .map(|point| {
+            let point = point.into_array()?;
+            let (Some(x), Some(y)) = (point.first(), point.get(1)) else {
+                return Err("a point needs at least x and y".into());
+            };
+
+            Ok(Draw {
+                position: PositionFract::new(
+                    Fract::from_f64(number(x)?),
+                    Fract::from_f64(number(y)?),
+                ),
+                force: match point.get(2) {
+                    Some(force) => number(force)? as f32,
+                    None => 1.0,
+                },
+            })
+        })
+        .collect()
+}
+
+fn rectangle(x: i64, y: i64, w: i64, h: i64) -> ScriptResult<Rectangle> {
+    let int = |value: i64| -> ScriptResult<i32> {
+        i32::try_from(value).map_err(|_| format!("{value} is out of the screen").into())
+    };
+    let (x, y, w, h) = (int(x)?, int(y)?, int(w)?, int(h)?);
+
+    let (Some(right), Some(up)) = (x.checked_add(w), y.checked_add(h)) else {
+        return Err(format!("the rectangle at {x}, {y} is out of the screen").into());
+    };
+    Ok(Rectangle::new(x, y, right, up))
+}
+
+/// Either a number used for both ends or `[min, max]`.
+fn range(value: Dynamic) -> ScriptResult<(f32, f32)> {
+    if value.is_array() {
+        let range = value.into_array()?;
+        let [min, max] = &range[..] else {
+            return Err("a range needs exactly two numbers".into());
+        };
+        Ok((number(min)? as f32, number(max)? as f32))
+    } else {
+        let value = number(&value)? as f32;
+        Ok((value, value))
+    }
+}
+
+fn number(value: &Dynamic) -> ScriptResult<f64> {
+    (value.as_float())
+        .or_else(|_| value.as_int().map(|x| x as f64))
+        .map_err(|_| format!("expect a number, got {}", value.type_name()).into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Probe(Rectangle);
+    impl Element for Probe {}
+
+    impl ScriptElement for Probe {
+        const NAME: &'static str = "probe";
+
+        fn parse(rect: Rectangle, props: Map) -> ScriptResult<Self> {
+            match props.is_empty() {
+                true => Ok(Probe(rect)),
+                false => Err("a probe has no properties".into()),
+            }
+        }
+
+        fn build(self, world: &World) -> Handle {
+            world.insert(self).untyped()
+        }
+    }
+
+    /// Runs `source` as the only script of a headless world.
+    fn run(name: &str, source: &str) -> World {
+        let folder = std::env::temp_dir().join(format!("ln_drawer_{name}_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("test.rhai"), source).unwrap();
+
+        let mut host = ScriptHost {
+            folder: Some(folder.clone()),
+            ..Default::default()
+        };
+        host.register::<Probe>();
+
+        let mut world = World::default();
+        world.insert(host);
+        world.flush();
+
+        fs::remove_dir_all(folder).unwrap();
+        world
+    }
+
+    fn probes(world: &World) -> Vec<Rectangle> {
+        let mut probes = Vec::new();
+        world.foreach_fetch::<Probe>(|probe| probes.push(probe.0));
+        probes
+    }
+
+    #[test]
+    fn create_and_remove() {
+        let world = run(
+            "create_and_remove",
+            "let a = probe(0, 0, 10, 20); let b = probe(5, 5, 1, 1); remove(b);",
+        );
+
+        assert_eq!(probes(&world), [Rectangle::new(0, 0, 10, 20)]);
+    }
+
+    #[test]
+    fn callback() {
+        let mut world = run(
+            "callback",
+            "let a = probe(0, 0, 10, 10); on_click(a, || { probe(1, 1, 2, 2); });",
+        );
+
+        let mut clicked = None;
+        world.foreach::<Probe>(|probe| clicked = Some(probe));
+        world.trigger(clicked.unwrap(), &WidgetClick);
+        world.flush();
+
+        let mut probes = probes(&world);
+        probes.sort_by_key(|rect| rect.left());
+        assert_eq!(
+            probes,
+            [Rectangle::new(0, 0, 10, 10), Rectangle::new(1, 1, 3, 3)]
+        );
+    }
+
+    #[test]
+    fn out_of_range() {
+        let world = run("out_of_range", "probe(2147483647, 0, 10, 10);");
+        assert!(probes(&world).is_empty());
+
+        let world = run("out_of_range_width", "probe(0, 0, 4294967296, 10);");
+        assert!(probes(&world).is_empty());
+
+        assert!(rectangle(-10, -10, 20, 20).is_ok());
+        assert!(rectangle(i64::MAX, 0, 0, 0).is_err());
+    }
+
+    #[test]
+    fn properties() {
+        let world = run("properties", "probe(0, 0, 1, 1, #{ size: 2 });");
+        assert!(probes(&world).is_empty());
+
+        let text = ScriptText::parse(Rectangle::default(), {
+            let mut props = Map::new();
+            props.insert("text".into(), Dynamic::from("hello".to_owned()));
+            props.insert("size".into(), Dynamic::from(24_i64));
+            props
+        })
+        .unwrap();
+        assert_eq!((&text.text[..], text.size), ("hello", 24.0));
+    }
+
+    #[test]
+    fn limits() {
+        let commands = Rc::new(RefCell::new(ScriptCommands::default()));
+        let mut engine = script_engine(&commands);
+        engine.set_max_operations(10_000).set_max_array_size(100);
+        let stopped = |source| failure(&engine, &engine.run(source).unwrap_err());
+
+        assert_eq!(
+            stopped("loop {}"),
+            "stopped after 10000 operations at line 1, position 6"
+        );
+        assert!(stopped("fn deeper(n) { deeper(n + 1) } deeper(0);").contains("64 nested calls"));
+        assert!(stopped("let a = []; loop { a.push(1); }").contains("over the limit"));
+        assert!(stopped("undefined()").contains("undefined"));
+
+        // the commands pushed before stopping are still applied
+        let _ = engine.run("remove(1); loop {}");
+        assert!(!commands.borrow().buffer.is_empty());
+    }
+}
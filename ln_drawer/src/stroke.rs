@@ -5,8 +5,11 @@ pub mod shape;
 mod stream;
 
 use std::{
+    collections::VecDeque,
+    f64::consts::{PI, TAU},
     sync::mpsc::{Receiver, Sender, channel},
     thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
 use bytemuck::{bytes_of, cast_slice};
@@ -22,10 +25,10 @@ use wgpu::{
     ColorWrites, CommandEncoderDescriptor, ComputePass, ComputePassDescriptor, ComputePipeline,
     ComputePipelineDescriptor, Device, Extent3d, FilterMode, FragmentState,
     PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, Queue,
-    RenderPipeline, RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor,
-    ShaderModuleDescriptor, ShaderSource, ShaderStages, StorageTextureAccess, Texture,
-    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
-    TextureViewDescriptor, TextureViewDimension, VertexState,
+    RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages, StorageTextureAccess, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureViewDescriptor, TextureViewDimension,
+    VertexState,
     util::{BufferInitDescriptor, DeviceExt},
 };
 use winit::event::PointerKind;
@@ -33,8 +36,10 @@ use winit::event::PointerKind;
 use crate::{
     lnwin::Lnwindow,
     measures::{Fract, Position, PositionFract, Rectangle, Size},
+    metrics::{ChunkStats, Metrics},
+    note::NoteBoard,
     render::{
-        MSAA_STATE, Render, RenderControl, RenderInformation,
+        MainPipeline, Render, RenderControl, RenderInformation,
         camera::{Camera, CameraPositionChanged, CameraUtils, UICamera},
         rounded::{RoundedRect, RoundedRectDescriptor},
         vertex::VertexUniform,
@@ -50,7 +55,7 @@ use crate::{
         pointer::{PointerHover, PointerHoverStatus},
         touch::{MultiTouchGroup, MultiTouchStatus},
     },
-    widgets::{WidgetEnabled, WidgetRectangle},
+    widgets::{WidgetEnabled, WidgetRectangle, context_menu::ContextAction},
 };
 
 const CHUNK_SIZE: u32 = 512;
@@ -58,6 +63,9 @@ const CHUNK_CAPS: usize = 512;
 const CHUNK_BATCH: usize = 8;
 const CHUNK_MIPMAP: u8 = 8;
 const MAX_STROKE: u64 = 200;
+/// Chunks loaded at once for a stroke painted away from the cameras, longer ones are
+/// painted in parts.
+const CHUNK_PENDING: usize = CHUNK_CAPS / 4;
 
 const CHUNK_META0_FORMAT: u32 = 1;
 
@@ -92,14 +100,13 @@ type ChunkKey = (i32, i32, u8);
 
 pub struct StrokeLayer {
     chunks: HashMap<ChunkKey, Option<Chunk>>,
+    chunks_queued: usize,
     meta_unsaved: HashSet<ChunkKey>,
+    /// Bounds of the chunks painted in this session, grown by the saved ones once the loading
+    /// thread has looked through them.
+    content: Option<Rectangle>,
 
     pub render_debugging: bool,
-    render_pipeline: RenderPipeline,
-    render_debug_pipeline: RenderPipeline,
-
-    render_group_unfiltered: BindGroup,
-    render_group_filtered: BindGroup,
 
     mipmap_pipeline: ComputePipeline,
     gamma_fixing_pipeline: ComputePipeline,
@@ -119,13 +126,46 @@ pub struct StrokeLayer {
     thread_rx: Receiver<ThreadOutput>,
     thread: Option<JoinHandle<()>>,
 
-    brush_preview: Handle<RoundedRect>,
-
     pub interpolation: Interpolation,
     pub modifier: Modifier,
     pub dirty: Dirty,
     pub shape: u32,
     prev: Option<Draw>,
+    pending: VecDeque<PendingStroke>,
+    requested: Vec<ChunkKey>,
+}
+
+/// Shows the [`StrokeLayer`] through the camera it is inserted in and paints on it. The
+/// layer is shared, so every window may have its own view on the same board.
+pub struct StrokeView {
+    camera: Handle<Camera>,
+
+    render_pipeline: MainPipeline,
+    render_debug_pipeline: MainPipeline,
+
+    render_group_unfiltered: BindGroup,
+    render_group_filtered: BindGroup,
+
+    /// Pixels of the surface drawn into as `[x, y, width, height]` from the top left corner,
+    /// the whole surface if `None`.
+    pub viewport: Option<[f32; 4]>,
+    order: isize,
+
+    /// Only the views painting on the layer have one.
+    brush_preview: Option<Handle<RoundedRect>>,
+}
+
+/// Sent to [`StrokeLayer`] when its chunks have changed.
+pub struct StrokeChanged;
+
+/// Sent to [`StrokeLayer`] when the brush takes a color picked from the board.
+pub struct StrokeColorPicked(pub Srgba);
+
+/// A stroke waiting for its chunks to be loaded.
+struct PendingStroke {
+    draws: Vec<Draw>,
+    modifier: Modifier,
+    prev: Option<Draw>,
 }
 
 struct Chunk {
@@ -156,8 +196,11 @@ struct ChunkBind {
 }
 
 enum ThreadInput {
-    SetStreamCamera(Fract, Size, PositionFract),
+    SetStreamCamera(Handle<Camera>, Fract, Size, PositionFract, f32),
+    RemoveStreamCamera(Handle<Camera>),
     MarkUnsaved(ChunkKey),
+    /// Chunks to load and keep for a pending stroke, in place of the last ones.
+    Request(Vec<ChunkKey>),
     Create(ChunkKey, Texture),
     Autosave,
     Finish,
@@ -166,6 +209,10 @@ enum ThreadInput {
 enum ThreadOutput {
     Insert(ChunkKey, Option<Texture>),
     Remove(ChunkKey),
+    /// Chunks waiting to be loaded, sent when changed.
+    Queued(usize),
+    /// Bounds of the saved chunks, sent once at start.
+    Content(Option<Rectangle>),
 }
 
 #[repr(C)]
@@ -185,7 +232,6 @@ struct ChunkUniform {
 impl StrokeLayer {
     pub fn new(world: &World) -> Self {
         let render = world.single_fetch::<Render>().unwrap();
-        let camera = world.single_fetch::<Camera>().unwrap();
         let device = &render.device;
 
         let chunk_draw_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -330,48 +376,6 @@ impl StrokeLayer {
             ],
         });
 
-        let render_sampler_unfiltered = device.create_sampler(&SamplerDescriptor {
-            label: Some("stroke_sampler"),
-            address_mode_u: AddressMode::ClampToEdge,
-            address_mode_v: AddressMode::ClampToEdge,
-            address_mode_w: AddressMode::ClampToEdge,
-            mag_filter: FilterMode::Nearest,
-            min_filter: FilterMode::Nearest,
-            ..Default::default()
-        });
-
-        let render_sampler_filtered = device.create_sampler(&SamplerDescriptor {
-            label: Some("stroke_sampler"),
-            address_mode_u: AddressMode::ClampToEdge,
-            address_mode_v: AddressMode::ClampToEdge,
-            address_mode_w: AddressMode::ClampToEdge,
-            mag_filter: FilterMode::Linear,
-            min_filter: FilterMode::Linear,
-            ..Default::default()
-        });
-
-        let render_camera_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("chunk_render"),
-            entries: &[
-                BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::VERTEX_FRAGMENT,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStages::FRAGMENT,
-                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
-        });
-
         let chunk_render_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("chunk_render"),
             entries: &[
@@ -398,113 +402,6 @@ impl StrokeLayer {
             ],
         });
 
-        let render_group_unfiltered = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("stroke_render"),
-            layout: &render_camera_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::Buffer(BufferBinding {
-                        buffer: &camera.uniform,
-                        offset: 0,
-                        size: None,
-                    }),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(&render_sampler_unfiltered),
-                },
-            ],
-        });
-
-        let render_group_filtered = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("stroke_render"),
-            layout: &render_camera_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::Buffer(BufferBinding {
-                        buffer: &camera.uniform,
-                        offset: 0,
-                        size: None,
-                    }),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(&render_sampler_filtered),
-                },
-            ],
-        });
-
-        let render_shader = device.create_shader_module(ShaderModuleDescriptor {
-            label: Some("stroke_chunk"),
-            source: ShaderSource::Wgsl(include_str!("stroke/chunk.wgsl").into()),
-        });
-
-        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: Some("stroke_chunk"),
-            bind_group_layouts: &[&render_camera_layout, &chunk_render_layout],
-            immediate_size: 0,
-        });
-
-        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("stroke_chunk"),
-            layout: Some(&render_pipeline_layout),
-            vertex: VertexState {
-                module: &render_shader,
-                entry_point: Some("vs_main"),
-                compilation_options: Default::default(),
-                buffers: &[],
-            },
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleStrip,
-                ..Default::default()
-            },
-            fragment: Some(FragmentState {
-                module: &render_shader,
-                entry_point: Some("fs_main"),
-                compilation_options: Default::default(),
-                targets: &[Some(ColorTargetState {
-                    format: render.config.format,
-                    blend: Some(BlendState::ALPHA_BLENDING),
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            depth_stencil: None,
-            multisample: MSAA_STATE,
-            multiview_mask: None,
-            cache: None,
-        });
-
-        let render_debug_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("stroke_chunk_debug"),
-            layout: Some(&render_pipeline_layout),
-            vertex: VertexState {
-                module: &render_shader,
-                entry_point: Some("vs_main"),
-                compilation_options: Default::default(),
-                buffers: &[],
-            },
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleStrip,
-                ..Default::default()
-            },
-            fragment: Some(FragmentState {
-                module: &render_shader,
-                entry_point: Some("fs_main_debug"),
-                compilation_options: Default::default(),
-                targets: &[Some(ColorTargetState {
-                    format: render.config.format,
-                    blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            depth_stencil: None,
-            multisample: MSAA_STATE,
-            multiview_mask: None,
-            cache: None,
-        });
-
         let mipmap_pipeline = mipmap_pipeline(device, &chunk_draw_layout, &dispatch_group_layout);
         let gamma_fixing_pipeline =
             gamma_fixing_pipeline(device, &chunk_draw_layout, &dispatch_group_layout);
@@ -515,47 +412,21 @@ impl StrokeLayer {
         let (thread_output_tx, thread_output_rx) = channel();
 
         let database = world.single_fetch::<SaveDatabase>().unwrap().clone();
-        let camera = world.single_fetch::<Camera>().unwrap();
         let render = world.single_fetch::<Render>().unwrap();
         let device = render.device.clone();
         let queue = render.queue.clone();
 
-        thread_input_tx
-            .send(ThreadInput::SetStreamCamera(
-                camera.zoom,
-                camera.size,
-                camera.center,
-            ))
-            .unwrap();
-
         let thread = std::thread::spawn(|| {
             stream::loading_thread(database, device, queue, thread_input_rx, thread_output_tx)
                 .unwrap();
         });
 
-        let ui_camera = world.single_fetch::<UICamera>().unwrap();
-        let brush_preview = world.enter(ui_camera.0, || {
-            world.build(RoundedRectDescriptor {
-                rect: Rectangle::new_half(Position::new(0, 0), Size::new(5, 5)),
-                color: Srgba::new(0.5, 0.5, 0.5, 0.4),
-                shrink: 8.0,
-                value: 8.0,
-                shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.2),
-                shadow_offset: Vec2::ZERO,
-                shadow_blur: 30.0,
-                visible: false,
-                vertex_extend: 80,
-                order: -10,
-                ..Default::default()
-            })
-        });
-
         StrokeLayer {
             chunks: HashMap::new(),
+            chunks_queued: 0,
             meta_unsaved: HashSet::new(),
+            content: None,
             render_debugging: false,
-            render_pipeline,
-            render_debug_pipeline,
             mipmap_pipeline,
             gamma_fixing_pipeline,
             brush_round_pipeline,
@@ -563,20 +434,19 @@ impl StrokeLayer {
             chunk_draw_layout,
             dispatch,
             draws_length,
-            render_group_filtered,
-            render_group_unfiltered,
             draws_array,
             dispatch_group,
             dispatch_group_draw,
             thread_tx: thread_input_tx,
             thread_rx: thread_output_rx,
             thread: Some(thread),
-            brush_preview,
             interpolation: DEFAULT_INTERPOLATION,
             modifier: DEFAULT_MODIFIER,
             dirty: DEFAULT_DIRTY,
             shape: 0,
             prev: None,
+            pending: VecDeque::new(),
+            requested: Vec::new(),
         }
     }
 
@@ -682,6 +552,19 @@ impl StrokeLayer {
         }
     }
 
+    /// The bounding box of every painted chunk, whether saved or not. The saved ones are
+    /// only counted once the loading thread has looked through them, shortly after start.
+    pub fn content_bounds(&self) -> Option<Rectangle> {
+        self.content
+    }
+
+    fn grow_content(&mut self, bounds: Option<Rectangle>) {
+        self.content = match (self.content, bounds) {
+            (Some(content), Some(bounds)) => Some(content.grow(bounds)),
+            (content, bounds) => content.or(bounds),
+        };
+    }
+
     fn database_init(&mut self, db: &Database) -> Result<(), redb::Error> {
         let write = db.begin_write()?;
         write.open_table(TABLE_STROKE_CHUNK)?;
@@ -704,65 +587,44 @@ impl StrokeLayer {
         })));
 
         world.dependency(save, this);
-
-        let camera = world.single::<Camera>().unwrap();
-        world.observer(camera, move |_: &CameraPositionChanged, world| {
-            let this = world.fetch(this).unwrap();
-            let camera = world.fetch(camera).unwrap();
-
-            this.thread_tx
-                .send(ThreadInput::SetStreamCamera(
-                    camera.zoom,
-                    camera.size,
-                    camera.center,
-                ))
-                .unwrap();
-        });
     }
 
-    fn attach_render(&mut self, world: &World, this: Handle<Self>) {
-        let control = world.insert(RenderControl {
-            prepare: Some(Box::new(move |world| {
-                let this = &mut *world.fetch_mut(this).unwrap();
-                while let Ok(output) = this.thread_rx.try_recv() {
-                    this.process_thread_output(world, output);
-                }
+    /// Take in what the loading thread has done, any [`StrokeView`] can do it before drawing.
+    fn receive(&mut self, world: &World) {
+        let mut received = false;
+        while let Ok(output) = self.thread_rx.try_recv() {
+            self.process_thread_output(world, output);
+            received = true;
+        }
 
-                Some(RenderInformation {
-                    keep_redrawing: false,
-                })
-            })),
-            draw: Some(Box::new(|world, rpass| {
-                let stroke = world.single_fetch::<StrokeLayer>().unwrap();
-                let camera = world.single_fetch::<Camera>().unwrap();
+        if received {
+            self.paint_pending(world);
+            StrokeLayer::changed(world);
+        }
+    }
 
-                let view_rect = camera.world_view_rect();
-                let mipmap = lower_mipmap_of(camera.zoom);
-                let (chunk_src, chunk_dst) = chunks_within(view_rect, mipmap);
+    /// Take in what the loading thread does until nothing is queued and the pending strokes
+    /// are painted. Returns `false` if `timeout` passes first.
+    pub fn wait_loaded(&mut self, world: &World, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.chunks_queued > 0 || !self.pending.is_empty() {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let Ok(output) = self.thread_rx.recv_timeout(left) else {
+                return false;
+            };
 
-                match stroke.render_debugging {
-                    false => rpass.set_pipeline(&stroke.render_pipeline),
-                    true => rpass.set_pipeline(&stroke.render_debug_pipeline),
-                }
+            self.process_thread_output(world, output);
+            self.paint_pending(world);
+            StrokeLayer::changed(world);
+        }
 
-                if camera.zoom.into_f32().exp2() > 6.0 {
-                    rpass.set_bind_group(0, &stroke.render_group_unfiltered, &[]);
-                } else {
-                    rpass.set_bind_group(0, &stroke.render_group_filtered, &[]);
-                }
+        true
+    }
 
-                for chunk_x in chunk_src.0..chunk_dst.0 {
-                    for chunk_y in chunk_src.1..chunk_dst.1 {
-                        if let Some(Some(chunk)) = stroke.chunks.get(&(chunk_x, chunk_y, mipmap)) {
-                            rpass.set_bind_group(1, &chunk.bind.render, &[]);
-                            rpass.draw(0..4, 0..1);
-                        }
-                    }
-                }
-            })),
-        });
-        RenderControl::reorder(Some(-100), world, control);
-        world.dependency(control, this);
+    /// Have every [`StrokeView`] redrawn, wherever its window is.
+    fn changed(world: &World) {
+        let stroke = world.single::<StrokeLayer>().unwrap();
+        world.queue_trigger(stroke, StrokeChanged);
     }
 
     fn process_thread_output(&mut self, world: &World, output: ThreadOutput) {
@@ -864,106 +726,123 @@ impl StrokeLayer {
 
                 self.chunks.remove(&key);
             }
+            ThreadOutput::Queued(queued) => {
+                self.chunks_queued = queued;
+            }
+            ThreadOutput::Content(bounds) => {
+                self.grow_content(bounds);
+            }
         }
     }
 
-    fn attach_touch(&mut self, world: &World, this: Handle<Self>) {
-        let collider = world.insert(ToolCollider::fullscreen(-100));
-        world.dependency(collider, this);
+    pub fn chunk_stats(&self) -> ChunkStats {
+        ChunkStats {
+            loaded: self.chunks.len(),
+            capacity: CHUNK_CAPS,
+            queued: self.chunks_queued,
+        }
+    }
 
-        world.observer(collider, move |event: &PointerHover, world| {
-            if let PointerKind::Touch(_) = event.pointer.kind {
-                return;
-            }
+    /// The color painted at `position`, transparent where nothing is. `None` until its chunk
+    /// is loaded.
+    pub fn pick(&self, world: &World, position: Position) -> Option<Srgba> {
+        let size = CHUNK_SIZE as i32;
+        let key = chunk_wrap((position.x.div_euclid(size), position.y.div_euclid(size), 0));
+        let Some(chunk) = self.chunks.get(&key)? else {
+            return Some(Srgba::new(0.0, 0.0, 0.0, 0.0));
+        };
 
-            let this = world.fetch(this).unwrap();
-            let ui_camera = world.single_fetch::<UICamera>().unwrap();
-            world.enter(ui_camera.0, || {
-                let camera = world.single_fetch::<Camera>().unwrap();
-                let mut brush_preview = world.fetch_mut(this.brush_preview).unwrap();
-                brush_preview.desc.shadow_offset = event.pointer.tilt * 48.0;
-                world.queue_trigger(
-                    this.brush_preview,
-                    WidgetRectangle(Rectangle::new_half(
-                        camera
-                            .screen_to_world_absolute(event.pointer.screen)
-                            .round(),
-                        Size::new(5, 5),
-                    )),
-                );
+        let render = world.single_fetch::<Render>().unwrap();
+        let at = [
+            position.x.rem_euclid(size) as u32,
+            position.y.rem_euclid(size) as u32,
+        ];
+        let texture = &chunk.bind.texture;
+        let [r, g, b, a] = stream::pixel_readback(texture, &render.device, &render.queue, at);
+        Some(Srgba::new(r, g, b, a).into_format())
+    }
 
-                match event.status {
-                    PointerHoverStatus::Enter => {
-                        world.queue_trigger(this.brush_preview, WidgetEnabled(true));
-                    }
-                    PointerHoverStatus::Moving => {}
-                    PointerHoverStatus::Leave => {
-                        world.queue_trigger(this.brush_preview, WidgetEnabled(false));
-                    }
-                }
-            });
+    /// Take the color at `position` for the brush, if something is painted there.
+    fn eyedropper(world: &World, position: Position) {
+        let mut layer = world.single_fetch_mut::<StrokeLayer>().unwrap();
+        let Some(color) = layer.pick(world, position) else {
+            return;
+        };
+
+        if color.alpha == 0.0 {
+            return;
+        }
+
+        let color = Srgba::new(color.red, color.green, color.blue, 1.0);
+        layer.modifier.color = color;
+        let this = layer.handle();
+        drop(layer);
+        world.trigger(this, &StrokeColorPicked(color));
+    }
+
+    /// Paint a whole stroke with `modifier`, leaving the current brush untouched. The
+    /// chunks away from the cameras are loaded for it first, so it may be painted later.
+    pub fn stroke(&mut self, draws: Vec<Draw>, modifier: Modifier, world: &World) {
+        self.pending.push_back(PendingStroke {
+            draws,
+            modifier,
+            prev: None,
         });
+        self.paint_pending(world);
+    }
 
-        let mut pinch_distance = None;
-        world.observer(collider, move |event: &MultiTouchGroup, world| {
-            let primary = event.members.first().unwrap();
+    /// Paint the pending strokes in order, as far as their chunks are loaded.
+    fn paint_pending(&mut self, world: &World) {
+        while let Some(mut pending) = self.pending.pop_front() {
+            let (keys, len) = pending_chunks(&self.interpolation, &self.dirty, &pending);
+            if keys.len() > CHUNK_PENDING {
+                log::warn!(
+                    "skipped a stroke too large to load, over {} chunks",
+                    keys.len()
+                );
+                continue;
+            }
 
-            if matches!(event.active.pointer, PointerKind::Touch(_)) || event.members.len() != 1 {
-                let mut sum = [0f64; 2];
-                for member in &event.members {
-                    sum[0] += member.screen[0];
-                    sum[1] += member.screen[1];
+            if keys.iter().any(|key| !self.chunks.contains_key(key)) {
+                let mut keys = keys.into_iter().collect::<Vec<_>>();
+                keys.sort();
+                if keys != self.requested {
+                    self.thread_tx
+                        .send(ThreadInput::Request(keys.clone()))
+                        .unwrap();
+                    self.requested = keys;
                 }
 
-                let cnt = event.members.len() as f64;
-                let center = [sum[0] / cnt, sum[1] / cnt];
+                self.pending.push_front(pending);
+                return;
+            }
 
-                let mut camera_utils = world.single_fetch_mut::<CameraUtils>().unwrap();
+            let rest = pending.draws.split_off(len);
+            let modifier = std::mem::replace(&mut self.modifier, pending.modifier);
+            let prev = std::mem::replace(&mut self.prev, pending.prev);
 
-                match event.active.status {
-                    MultiTouchStatus::Press => {
-                        camera_utils.locked(false);
-                        camera_utils.cursor(world, center);
-                        camera_utils.anchor_on_screen(world, center);
-                        camera_utils.locked(true);
-                    }
-                    MultiTouchStatus::Holding => {
-                        camera_utils.cursor(world, center);
-                        camera_utils.locked(true);
-                    }
-                    MultiTouchStatus::Release => {
-                        camera_utils.cursor(world, center);
-                        camera_utils.locked(false);
-                    }
-                }
-
-                if event.members.len() == 2 {
-                    let first = event.members.first().unwrap().screen;
-                    let last = event.members.last().unwrap().screen;
-
-                    let (x, y) = (first[0] - last[0], first[1] - last[1]);
-                    let cur = (x * x + y * y).sqrt();
-                    let prev = pinch_distance.get_or_insert(cur);
-                    camera_utils.zoom_delta(world, Fract::from_f64((cur - *prev) * 2.0));
-                    *prev = cur;
-                } else {
-                    pinch_distance = None;
-                }
-            } else if let MultiTouchStatus::Holding | MultiTouchStatus::Press = primary.status {
-                let mut this = world.fetch_mut(this).unwrap();
-                let target = Draw {
-                    position: primary.position,
-                    force: primary.data.force.unwrap_or(1.0),
-                };
+            for draw in pending.draws {
+                self.paint(draw, world);
+            }
 
-                this.paint(target, world);
-            } else {
-                world.queue(move |world| {
-                    let mut this = world.fetch_mut(this).unwrap();
-                    this.prev = None;
+            // the rest goes on from where this part ends
+            let prev = std::mem::replace(&mut self.prev, prev);
+            let modifier = std::mem::replace(&mut self.modifier, modifier);
+            if !rest.is_empty() {
+                self.pending.push_front(PendingStroke {
+                    draws: rest,
+                    modifier,
+                    prev,
                 });
             }
-        });
+        }
+
+        if !self.requested.is_empty() {
+            self.requested.clear();
+            self.thread_tx
+                .send(ThreadInput::Request(Vec::new()))
+                .unwrap();
+        }
     }
 
     fn paint(&mut self, next: Draw, world: &World) {
@@ -1032,8 +911,7 @@ impl StrokeLayer {
         drop(cpass);
         queue.submit([encoder.finish()]);
 
-        let lnwindow = world.single_fetch::<Lnwindow>().unwrap();
-        lnwindow.window.request_redraw();
+        StrokeLayer::changed(world);
     }
 
     fn fix_unmipmapped(&mut self, lower: (i32, i32, u8), render: &Render) {
@@ -1120,20 +998,7 @@ impl StrokeLayer {
     }
 
     fn validate_chunks(&mut self, dirty: Rectangle) -> bool {
-        for mipmap in 0..CHUNK_MIPMAP {
-            let (chunk_src, chunk_dst) = chunks_within(dirty, mipmap);
-            for chunk_x in chunk_src.0..chunk_dst.0 {
-                for chunk_y in chunk_src.1..chunk_dst.1 {
-                    let chunk_id = (chunk_x, chunk_y, mipmap);
-
-                    if let None = self.chunks.get(&chunk_id) {
-                        return false;
-                    }
-                }
-            }
-        }
-
-        true
+        dirty_chunks(dirty).all(|key| self.chunks.contains_key(&key))
     }
 
     /// Assume `validate_chunks` results true.
@@ -1148,7 +1013,7 @@ impl StrokeLayer {
             let (chunk_src, chunk_dst) = chunks_within(dirty, mipmap);
             for chunk_x in chunk_src.0..chunk_dst.0 {
                 for chunk_y in chunk_src.1..chunk_dst.1 {
-                    let key = (chunk_x, chunk_y, mipmap);
+                    let key = chunk_wrap((chunk_x, chunk_y, mipmap));
 
                     if let Some(chunk) = self.chunks.get(&key) {
                         if chunk.is_none() {
@@ -1158,6 +1023,10 @@ impl StrokeLayer {
                                 .send(ThreadInput::Create(key, bind.texture.clone()))
                                 .unwrap();
 
+                            if key.2 == 0 {
+                                self.grow_content(Some(chunk_rect(key)));
+                            }
+
                             let chunk = self.chunks.get_mut(&key).unwrap();
                             self.meta_unsaved.insert(key);
                             *chunk = Some(Chunk {
@@ -1183,6 +1052,417 @@ impl StrokeLayer {
     }
 }
 
+impl StrokeView {
+    /// Needs a [`StrokeLayer`] visible from here, either in the same view or referred to.
+    pub fn new(world: &World) -> Self {
+        let ui_camera = world.single_fetch::<UICamera>().unwrap();
+        let brush_preview = world.enter(ui_camera.0, || {
+            world.build(RoundedRectDescriptor {
+                rect: Rectangle::new_half(Position::new(0, 0), Size::new(5, 5)),
+                color: Srgba::new(0.5, 0.5, 0.5, 0.4),
+                shrink: 8.0,
+                value: 8.0,
+                shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.2),
+                shadow_offset: Vec2::ZERO,
+                shadow_blur: 30.0,
+                visible: false,
+                vertex_extend: 80,
+                order: -10,
+                ..Default::default()
+            })
+        });
+
+        StrokeView {
+            brush_preview: Some(brush_preview),
+            ..StrokeView::overview(world, -100)
+        }
+    }
+
+    /// Only showing the layer, drawn in the given order, like the one in a minimap.
+    pub fn overview(world: &World, order: isize) -> Self {
+        let render = world.single_fetch::<Render>().unwrap();
+        let camera = world.single_fetch::<Camera>().unwrap();
+        let stroke = world.single_fetch::<StrokeLayer>().unwrap();
+        let device = &render.device;
+
+        let render_sampler_unfiltered = device.create_sampler(&SamplerDescriptor {
+            label: Some("stroke_sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let render_sampler_filtered = device.create_sampler(&SamplerDescriptor {
+            label: Some("stroke_sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let render_camera_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("chunk_render"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX_FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let render_group_unfiltered = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("stroke_render"),
+            layout: &render_camera_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: &camera.uniform,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&render_sampler_unfiltered),
+                },
+            ],
+        });
+
+        let render_group_filtered = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("stroke_render"),
+            layout: &render_camera_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: &camera.uniform,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&render_sampler_filtered),
+                },
+            ],
+        });
+
+        let render_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("stroke_chunk"),
+            source: ShaderSource::Wgsl(include_str!("stroke/chunk.wgsl").into()),
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("stroke_chunk"),
+            bind_group_layouts: &[&render_camera_layout, &stroke.chunk_render_layout],
+            immediate_size: 0,
+        });
+
+        let layout = render_pipeline_layout.clone();
+        let shader = render_shader.clone();
+        let render_pipeline = MainPipeline::new(&render, move |render| {
+            render
+                .device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("stroke_chunk"),
+                    layout: Some(&layout),
+                    vertex: VertexState {
+                        module: &shader,
+                        entry_point: Some("vs_main"),
+                        compilation_options: Default::default(),
+                        buffers: &[],
+                    },
+                    primitive: PrimitiveState {
+                        topology: PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    fragment: Some(FragmentState {
+                        module: &shader,
+                        entry_point: Some("fs_main"),
+                        compilation_options: Default::default(),
+                        targets: &[Some(ColorTargetState {
+                            format: render.config.format,
+                            blend: Some(BlendState::ALPHA_BLENDING),
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    depth_stencil: None,
+                    multisample: render.multisample(),
+                    multiview_mask: None,
+                    cache: None,
+                })
+        });
+
+        let render_debug_pipeline = MainPipeline::new(&render, move |render| {
+            render
+                .device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("stroke_chunk_debug"),
+                    layout: Some(&render_pipeline_layout),
+                    vertex: VertexState {
+                        module: &render_shader,
+                        entry_point: Some("vs_main"),
+                        compilation_options: Default::default(),
+                        buffers: &[],
+                    },
+                    primitive: PrimitiveState {
+                        topology: PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    fragment: Some(FragmentState {
+                        module: &render_shader,
+                        entry_point: Some("fs_main_debug"),
+                        compilation_options: Default::default(),
+                        targets: &[Some(ColorTargetState {
+                            format: render.config.format,
+                            blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    depth_stencil: None,
+                    multisample: render.multisample(),
+                    multiview_mask: None,
+                    cache: None,
+                })
+        });
+
+        StrokeView {
+            camera: camera.handle(),
+            render_pipeline,
+            render_debug_pipeline,
+            render_group_unfiltered,
+            render_group_filtered,
+            viewport: None,
+            order,
+            brush_preview: None,
+        }
+    }
+
+    fn attach_stream(&mut self, world: &World, this: Handle<Self>) {
+        let camera = self.camera;
+        let stream = move |world: &World| {
+            let stroke = world.single_fetch::<StrokeLayer>().unwrap();
+            let camera_ref = world.fetch(camera).unwrap();
+
+            stroke
+                .thread_tx
+                .send(ThreadInput::SetStreamCamera(
+                    camera,
+                    camera_ref.zoom,
+                    camera_ref.size,
+                    camera_ref.center,
+                    camera_ref.rotation,
+                ))
+                .unwrap();
+        };
+
+        stream(world);
+        let ob = world.observer(camera, move |_: &CameraPositionChanged, world| {
+            stream(world);
+        });
+        world.dependency(ob, this);
+    }
+
+    fn attach_render(&mut self, world: &World, this: Handle<Self>) {
+        let control = world.insert(RenderControl {
+            prepare: Some(Box::new(|world| {
+                let mut stroke = world.single_fetch_mut::<StrokeLayer>().unwrap();
+                stroke.receive(world);
+
+                if let Ok(mut metrics) = world.single_fetch_mut::<Metrics>() {
+                    metrics.chunks = stroke.chunk_stats();
+                }
+
+                Some(RenderInformation {
+                    keep_redrawing: false,
+                })
+            })),
+            draw: Some(Box::new(move |world, rpass| {
+                let stroke = world.single_fetch::<StrokeLayer>().unwrap();
+                let view = world.fetch(this).unwrap();
+                let camera = world.single_fetch::<Camera>().unwrap();
+
+                let view_rect = camera.world_view_rect();
+                let mipmap = lower_mipmap_of(camera.zoom);
+                let (chunk_src, chunk_dst) = chunks_within(view_rect, mipmap);
+
+                match stroke.render_debugging {
+                    false => rpass.set_pipeline(&view.render_pipeline),
+                    true => rpass.set_pipeline(&view.render_debug_pipeline),
+                }
+
+                if camera.zoom.into_f32().exp2() > 6.0 {
+                    rpass.set_bind_group(0, &view.render_group_unfiltered, &[]);
+                } else {
+                    rpass.set_bind_group(0, &view.render_group_filtered, &[]);
+                }
+
+                if let Some([x, y, w, h]) = view.viewport {
+                    rpass.set_viewport(x, y, w, h, 0.0, 1.0);
+                }
+
+                for chunk_x in chunk_src.0..chunk_dst.0 {
+                    for chunk_y in chunk_src.1..chunk_dst.1 {
+                        let key = chunk_wrap((chunk_x, chunk_y, mipmap));
+                        if let Some(Some(chunk)) = stroke.chunks.get(&key) {
+                            rpass.set_bind_group(1, &chunk.bind.render, &[]);
+                            rpass.draw(0..4, 0..1);
+                        }
+                    }
+                }
+
+                if view.viewport.is_some()
+                    && let Ok(lnwindow) = world.single_fetch::<Lnwindow>()
+                {
+                    let size = lnwindow.window.surface_size();
+                    rpass.set_viewport(0.0, 0.0, size.width as f32, size.height as f32, 0.0, 1.0);
+                }
+            })),
+            bounding: None,
+            clip: None,
+        });
+        RenderControl::reorder(Some(self.order), world, control);
+        world.dependency(control, this);
+    }
+
+    fn attach_touch(&mut self, world: &World, this: Handle<Self>, preview: Handle<RoundedRect>) {
+        let collider = world.insert(ToolCollider::fullscreen(-100));
+        world.dependency(collider, this);
+
+        let eyedropper = "stroke.eyedropper";
+        ContextAction::register(world, collider, eyedropper, StrokeLayer::eyedropper);
+
+        ContextAction::register(world, collider, "camera.center", |world, position| {
+            let zoom = world.single_fetch::<Camera>().unwrap().zoom;
+            let mut camera_utils = world.single_fetch_mut::<CameraUtils>().unwrap();
+            camera_utils.fly_to(world, position.into_fract(), zoom);
+        });
+
+        ContextAction::register(world, collider, "note.add", NoteBoard::place);
+
+        world.observer(collider, move |event: &PointerHover, world| {
+            if let PointerKind::Touch(_) = event.pointer.kind {
+                return;
+            }
+
+            let ui_camera = world.single_fetch::<UICamera>().unwrap();
+            world.enter(ui_camera.0, || {
+                let camera = world.single_fetch::<Camera>().unwrap();
+                let mut brush_preview = world.fetch_mut(preview).unwrap();
+                brush_preview.desc.shadow_offset = event.pointer.tilt * 48.0;
+                world.queue_trigger(
+                    preview,
+                    WidgetRectangle(Rectangle::new_half(
+                        camera
+                            .screen_to_world_absolute(event.pointer.screen)
+                            .round(),
+                        Size::new(5, 5),
+                    )),
+                );
+
+                match event.status {
+                    PointerHoverStatus::Enter => {
+                        world.queue_trigger(preview, WidgetEnabled(true));
+                    }
+                    PointerHoverStatus::Moving => {}
+                    PointerHoverStatus::Leave => {
+                        world.queue_trigger(preview, WidgetEnabled(false));
+                    }
+                }
+            });
+        });
+
+        let mut pinch_distance = None;
+        let mut pinch_angle = None;
+        world.observer(collider, move |event: &MultiTouchGroup, world| {
+            let primary = event.members.first().unwrap();
+
+            if matches!(event.active.pointer, PointerKind::Touch(_)) || event.members.len() != 1 {
+                let mut sum = [0f64; 2];
+                for member in &event.members {
+                    sum[0] += member.screen[0];
+                    sum[1] += member.screen[1];
+                }
+
+                let cnt = event.members.len() as f64;
+                let center = [sum[0] / cnt, sum[1] / cnt];
+
+                let mut camera_utils = world.single_fetch_mut::<CameraUtils>().unwrap();
+
+                match event.active.status {
+                    MultiTouchStatus::Press => {
+                        camera_utils.locked(false);
+                        camera_utils.cursor(world, center);
+                        camera_utils.anchor_on_screen(world, center);
+                        camera_utils.locked(true);
+                    }
+                    MultiTouchStatus::Holding => {
+                        camera_utils.cursor(world, center);
+                        camera_utils.locked(true);
+                    }
+                    MultiTouchStatus::Release => {
+                        camera_utils.cursor(world, center);
+                        camera_utils.locked(false);
+                    }
+                }
+
+                if event.members.len() == 2 {
+                    let first = event.members.first().unwrap().screen;
+                    let last = event.members.last().unwrap().screen;
+
+                    let (x, y) = (first[0] - last[0], first[1] - last[1]);
+                    let cur = (x * x + y * y).sqrt();
+                    let prev = pinch_distance.get_or_insert(cur);
+                    camera_utils.zoom_delta(world, Fract::from_f64((cur - *prev) * 2.0));
+                    *prev = cur;
+
+                    // the angle is only meaningful in pixels, not in the stretched screen space
+                    let size = world.single_fetch::<Camera>().unwrap().size;
+                    let cur = (y * size.h as f64).atan2(x * size.w as f64);
+                    let prev = pinch_angle.get_or_insert(cur);
+                    let delta = (cur - *prev + PI).rem_euclid(TAU) - PI;
+                    camera_utils.rotate_delta(world, delta as f32);
+                    *prev = cur;
+                } else {
+                    pinch_distance = None;
+                    pinch_angle = None;
+                }
+            } else if let MultiTouchStatus::Holding | MultiTouchStatus::Press = primary.status {
+                let mut stroke = world.single_fetch_mut::<StrokeLayer>().unwrap();
+                let target = Draw {
+                    position: primary.position,
+                    force: primary.data.force.unwrap_or(1.0),
+                };
+
+                stroke.paint(target, world);
+            } else {
+                world.queue(|world| {
+                    let mut stroke = world.single_fetch_mut::<StrokeLayer>().unwrap();
+                    stroke.prev = None;
+                });
+            }
+        });
+    }
+}
+
 fn mipmap_pipeline(
     device: &Device,
     chunk_draw_layout: &BindGroupLayout,
@@ -1298,22 +1578,33 @@ fn chunk_rect(key: (i32, i32, u8)) -> Rectangle {
     }
 }
 
+/// The smallest rectangle around all `chunks` of mipmap 0, possibly over the wrap seam.
+fn chunks_bounds(chunks: impl IntoIterator<Item = ChunkKey>) -> Option<Rectangle> {
+    (chunks.into_iter().map(chunk_rect)).reduce(Rectangle::grow)
+}
+
 /// Guaranteed assumption: Upper layer is always loaded first
 fn chunk_distance(x: i32, y: i32, z: u8, cx: i32, cy: i32, cz: u8) -> u32 {
+    // the shorter way around the world, measured in chunks of mipmap 0
+    let shift = CHUNK_SIZE.trailing_zeros();
     let dx = (x * chunk_size_scale(z) + chunk_size_scale(z.saturating_sub(1)))
         - (cx * chunk_size_scale(cz) + chunk_size_scale(cz.saturating_sub(1)));
     let dy = (y * chunk_size_scale(z) + chunk_size_scale(z.saturating_sub(1)))
         - (cy * chunk_size_scale(cz) + chunk_size_scale(cz.saturating_sub(1)));
+    let (dx, dy) = (
+        dx.wrapping_shl(shift) >> shift,
+        dy.wrapping_shl(shift) >> shift,
+    );
     let dz = (CHUNK_MIPMAP - z) as i32 * 0x8000;
     dx.unsigned_abs() + dy.unsigned_abs() + dz.unsigned_abs()
 }
 
 fn mipmap_of(zoom: Fract) -> u8 {
-    (-zoom.round()).max(0) as u8
+    (-zoom.round()).clamp(0, CHUNK_MIPMAP as i32 - 1) as u8
 }
 
 fn lower_mipmap_of(zoom: Fract) -> u8 {
-    (-(zoom.floor() + 1)).max(0) as u8
+    (-(zoom.floor() + 1)).clamp(0, CHUNK_MIPMAP as i32 - 1) as u8
 }
 
 fn chunk_size(mipmap: u8) -> i32 {
@@ -1324,19 +1615,78 @@ fn chunk_size_scale(mipmap: u8) -> i32 {
     2i32.pow(mipmap as u32)
 }
 
+/// The range may run past the wrap seam of the world, pass the keys through [`chunk_wrap`].
 fn chunks_within(view_rect: Rectangle, mipmap: u8) -> ((i32, i32), (i32, i32)) {
     let size = chunk_size(mipmap);
+    let count = |origin: i32, extend: u32| {
+        let count = (origin.rem_euclid(size) as u64 + extend as u64).div_ceil(size as u64);
+        // never twice the same chunk, even for views larger than the world
+        count.min(1 << (32 - size.trailing_zeros())) as i32
+    };
+
     let chunk_src = (
         view_rect.left().div_euclid(size),
         view_rect.down().div_euclid(size),
     );
     let chunk_dst = (
-        (view_rect.right() - 1).div_euclid(size) + 1,
-        (view_rect.up() - 1).div_euclid(size) + 1,
+        chunk_src.0 + count(view_rect.left(), view_rect.width()),
+        chunk_src.1 + count(view_rect.down(), view_rect.height()),
     );
     (chunk_src, chunk_dst)
 }
 
+/// Every chunk painted on in `dirty`, through all mipmaps.
+fn dirty_chunks(dirty: Rectangle) -> impl Iterator<Item = ChunkKey> {
+    (0..CHUNK_MIPMAP).flat_map(move |mipmap| {
+        let (chunk_src, chunk_dst) = chunks_within(dirty, mipmap);
+        (chunk_src.0..chunk_dst.0).flat_map(move |chunk_x| {
+            (chunk_src.1..chunk_dst.1).map(move |chunk_y| chunk_wrap((chunk_x, chunk_y, mipmap)))
+        })
+    })
+}
+
+/// The chunks the first draws of `pending` are painted on and how many draws they are,
+/// at least one and as many as fit in [`CHUNK_PENDING`] chunks.
+fn pending_chunks(
+    interpolation: &Interpolation,
+    dirty: &Dirty,
+    pending: &PendingStroke,
+) -> (HashSet<ChunkKey>, usize) {
+    let mut keys = HashSet::new();
+    let mut prev = pending.prev;
+    let mut draw_buf = Vec::new();
+
+    for (idx, &next) in pending.draws.iter().enumerate() {
+        let curr = interpolation.interpolate(prev, next, &pending.modifier, &mut draw_buf);
+        prev = Some(curr);
+
+        let dirty = dirty.compute(curr.position.round(), &draw_buf);
+        if dirty.extend.w == 0 || dirty.extend.h == 0 {
+            continue;
+        }
+
+        let more = dirty_chunks(dirty)
+            .filter(|key| !keys.contains(key))
+            .collect::<Vec<_>>();
+        if idx > 0 && keys.len() + more.len() > CHUNK_PENDING {
+            return (keys, idx);
+        }
+        keys.extend(more);
+    }
+
+    (keys, pending.draws.len())
+}
+
+/// The same chunk on the other side of the wrap seam, if it is past it.
+fn chunk_wrap((x, y, mipmap): ChunkKey) -> ChunkKey {
+    let shift = chunk_size(mipmap).trailing_zeros();
+    (
+        x.wrapping_shl(shift) >> shift,
+        y.wrapping_shl(shift) >> shift,
+        mipmap,
+    )
+}
+
 fn upper_chunk_of(chunk: ChunkKey) -> ChunkKey {
     (chunk.0.div_euclid(2), chunk.1.div_euclid(2), chunk.2 + 1)
 }
@@ -1357,9 +1707,35 @@ impl Element for StrokeLayer {
         let db = world.single_fetch::<SaveDatabase>().unwrap();
         self.database_init(&db.0).unwrap();
 
-        self.attach_touch(world, this);
         self.attach_autosave(world, this);
+    }
+}
+
+impl Element for StrokeView {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        if let Some(preview) = self.brush_preview {
+            world.dependency(preview, this);
+            self.attach_touch(world, this, preview);
+        }
         self.attach_render(world, this);
+        self.attach_stream(world, this);
+
+        let stroke = world.single::<StrokeLayer>().unwrap();
+        let ob = world.observer(stroke, |StrokeChanged, world| {
+            RenderControl::redraw(world);
+        });
+        world.dependency(ob, this);
+
+        MainPipeline::follow(world, this, |view| &mut view.render_pipeline);
+        MainPipeline::follow(world, this, |view| &mut view.render_debug_pipeline);
+    }
+
+    fn when_remove(&mut self, world: &World, _this: Handle<Self>) {
+        let Ok(stroke) = world.single_fetch::<StrokeLayer>() else {
+            return;
+        };
+
+        let _ = (stroke.thread_tx).send(ThreadInput::RemoveStreamCamera(self.camera));
     }
 }
 
@@ -1370,3 +1746,144 @@ impl Drop for StrokeLayer {
         thread.join().unwrap();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn draw(x: f64, y: f64) -> Draw {
+        Draw {
+            position: PositionFract::new(Fract::from_f64(x), Fract::from_f64(y)),
+            force: 1.0,
+        }
+    }
+
+    /// Rectangles of up to a few chunks within a few thousand units of the wrap seam, from a
+    /// fixed seed.
+    fn seam_rectangles() -> impl Iterator<Item = Rectangle> {
+        let mut state = 0x2545f4914f6cdd1du64;
+        std::iter::repeat_with(move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let [x, y, w, h, ..] = state.to_le_bytes().map(|n| n as i32 * 16);
+            Rectangle {
+                origin: Position::new(
+                    (i32::MAX - 2048).wrapping_add(x),
+                    (i32::MIN + 2048).wrapping_sub(y),
+                ),
+                extend: Size::new(w.cast_unsigned() * 4 + 1, h.cast_unsigned() * 4 + 1),
+            }
+        })
+    }
+
+    #[test]
+    fn chunks_within_seam() {
+        let mut rects = seam_rectangles();
+        for _ in 0..200 {
+            let rect = rects.next().unwrap();
+            for mipmap in 0..CHUNK_MIPMAP {
+                let size = chunk_size(mipmap);
+                let (chunk_src, chunk_dst) = chunks_within(rect, mipmap);
+                let keys = (chunk_src.0..chunk_dst.0)
+                    .flat_map(|x| (chunk_src.1..chunk_dst.1).map(move |y| (x, y, mipmap)))
+                    .map(chunk_wrap)
+                    .collect::<Vec<_>>();
+
+                let unique = keys.iter().copied().collect::<HashSet<_>>();
+                assert_eq!(unique.len(), keys.len(), "{rect} at {mipmap}");
+                for &key in &keys {
+                    assert!(chunk_rect(key).intersects(rect), "{key:?} in {rect}");
+                }
+
+                // every point of the rectangle is in one of the chunks
+                let step = |extend: u32| {
+                    let steps = (0..extend).step_by(size as usize / 2);
+                    steps.chain([extend - 1]).map(|step| step as i32)
+                };
+                for dx in step(rect.width()) {
+                    for dy in step(rect.height()) {
+                        let point = rect.origin + Position::new(dx, dy);
+                        let key = (point.x.div_euclid(size), point.y.div_euclid(size), mipmap);
+                        assert!(unique.contains(&key), "{point} of {rect} at {mipmap}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn chunk_distance_seam() {
+        let distance = |a: ChunkKey, b: ChunkKey| chunk_distance(a.0, a.1, a.2, b.0, b.1, b.2);
+        for mipmap in 0..CHUNK_MIPMAP {
+            let last = i32::MAX / chunk_size(mipmap);
+            assert_eq!(chunk_wrap((last + 1, 0, mipmap)), (-last - 1, 0, mipmap));
+
+            for (dx, dy) in [(1, 0), (3, 2), (16, -5)] {
+                let near = distance((0, 0, mipmap), (dx, dy, mipmap));
+                for from in [(last, -last - 1), (last - 2, last), (-last - 1, 0)] {
+                    let from = (from.0, from.1, mipmap);
+                    let to = chunk_wrap((from.0 + dx, from.1 + dy, mipmap));
+                    assert_eq!(distance(from, to), near, "{from:?} to {to:?}");
+                    assert_eq!(distance(to, from), near, "{to:?} to {from:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bounds_at_seam() {
+        let size = CHUNK_SIZE as i32;
+        let last = i32::MAX / size;
+
+        let bounds = chunks_bounds([(last, 0, 0)]).unwrap();
+        let origin = Position::new(i32::MAX - size + 1, 0);
+        assert_eq!(
+            bounds,
+            Rectangle {
+                origin,
+                extend: Size::splat(CHUNK_SIZE)
+            }
+        );
+
+        // the two chunks next to each other over the seam
+        let bounds = chunks_bounds([(last, 0, 0), (-last - 1, 1, 0)]).unwrap();
+        assert_eq!(bounds.left(), i32::MAX - size + 1);
+        assert_eq!(bounds.extend, Size::new(CHUNK_SIZE * 2, CHUNK_SIZE * 2));
+
+        assert_eq!(chunks_bounds([]), None);
+    }
+
+    #[test]
+    fn pending_in_parts() {
+        // one chunk and everything above it
+        let keys = dirty_chunks(Rectangle::new(10, 10, 20, 20)).collect::<Vec<_>>();
+        assert_eq!(keys.len(), CHUNK_MIPMAP as usize);
+
+        // long enough to pass many more chunks than loaded at once
+        let draws = (0..1000).map(|i| draw(i as f64 * 100.0, 0.0)).collect();
+        let mut pending = PendingStroke {
+            draws,
+            modifier: DEFAULT_MODIFIER,
+            prev: None,
+        };
+
+        let mut parts = 0;
+        let mut painted = 0;
+        loop {
+            let (keys, len) = pending_chunks(&DEFAULT_INTERPOLATION, &DEFAULT_DIRTY, &pending);
+            assert!(len > 0 && keys.len() <= CHUNK_PENDING);
+            parts += 1;
+            painted += len;
+
+            if len == pending.draws.len() {
+                break;
+            }
+            pending.prev = Some(pending.draws[len - 1]);
+            pending.draws = pending.draws.split_off(len);
+        }
+
+        assert!(parts > 1);
+        assert_eq!(painted, 1000);
+    }
+}
@@ -3,9 +3,10 @@ use std::{
     sync::mpsc::{Receiver, RecvError, Sender, TryRecvError},
 };
 
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
 use indexmap::{IndexMap, IndexSet};
-use redb::ReadableDatabase;
+use ln_world::Handle;
+use redb::{ReadableDatabase, ReadableTable};
 use wgpu::{
     BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, Extent3d, MapMode, Origin3d,
     PollType, Queue, TexelCopyBufferInfoBase, TexelCopyBufferLayout, TexelCopyTextureInfoBase,
@@ -13,16 +14,23 @@ use wgpu::{
 };
 
 use crate::{
-    measures::{Position, Rectangle, Size},
+    measures::Rectangle,
     render::camera::Camera,
     save::SaveDatabase,
     stroke::{
         CHUNK_BATCH, CHUNK_CAPS, CHUNK_MIPMAP, CHUNK_SIZE, ChunkKey, TABLE_STROKE_CHUNK,
-        ThreadInput, ThreadOutput, chunk_distance, chunk_of, chunks_within,
-        chunk_texture_desc,
+        ThreadInput, ThreadOutput, chunk_distance, chunk_of, chunk_texture_desc, chunk_wrap,
+        chunks_bounds, chunks_within,
     },
 };
 
+/// Chunks around one of the cameras showing the layer.
+struct Stream {
+    center: ChunkKey,
+    rect: Rectangle,
+    range: ((i32, i32), (i32, i32)),
+}
+
 pub fn loading_thread(
     database: SaveDatabase,
     device: Device,
@@ -34,13 +42,16 @@ pub fn loading_thread(
     let mut texel_staging = IndexSet::<ChunkKey>::new();
     let mut texel_unsaved = HashSet::new();
 
-    let mut stream_center = (0, 0, 0);
-    let mut stream_rect = Rectangle::new_half(Position::ZERO, Size::splat(50));
-    let mut stream_range = chunks_within(stream_rect, 0);
+    let mut streams = HashMap::<Handle<Camera>, Stream>::new();
     let mut stream_outdated = false;
+    let mut requested = Vec::new();
 
     let mut stream_front = 0;
     let mut stream_queue = IndexSet::with_capacity(400);
+    let mut stream_reported = 0;
+
+    // looked through here, the main thread can't wait for every chunk to be listed
+    output_tx.send(ThreadOutput::Content(saved_content(&database)?))?;
 
     loop {
         let input = if stream_front < stream_queue.len() || stream_outdated {
@@ -57,21 +68,40 @@ pub fn loading_thread(
         };
 
         match input {
-            Some(ThreadInput::SetStreamCamera(zoom, size, center)) => {
-                stream_rect = Camera::manual_view_rect(zoom, size, center);
-                let stream_center_new = chunk_of(center.round(), zoom);
-                let stream_range_new = chunks_within(stream_rect, stream_center.2);
-                if stream_range_new != stream_range || stream_center_new != stream_center {
-                    stream_range = stream_range_new;
-                    stream_center = stream_center_new;
-                    stream_outdated = true;
+            Some(ThreadInput::SetStreamCamera(camera, zoom, size, center, rotation)) => {
+                let rect = Camera::manual_view_rect(zoom, size, center, rotation);
+                let center = chunk_of(center.round(), zoom);
+                match streams.get_mut(&camera) {
+                    Some(stream) => {
+                        stream.rect = rect;
+                        let range = chunks_within(rect, stream.center.2);
+                        if range != stream.range || center != stream.center {
+                            stream.range = range;
+                            stream.center = center;
+                            stream_outdated = true;
+                        }
+                    }
+                    None => {
+                        let range = chunks_within(rect, center.2);
+                        streams.insert(camera, Stream { center, rect, range });
+                        stream_outdated = true;
+                    }
                 }
                 continue;
             }
+            Some(ThreadInput::RemoveStreamCamera(camera)) => {
+                stream_outdated |= streams.remove(&camera).is_some();
+                continue;
+            }
             Some(ThreadInput::MarkUnsaved(chunk)) => {
                 texel_unsaved.insert(chunk);
                 continue;
             }
+            Some(ThreadInput::Request(chunks)) => {
+                stream_outdated |= chunks != requested;
+                requested = chunks;
+                continue;
+            }
             Some(ThreadInput::Create(chunk_id, texture)) => {
                 // this happen when main thread doesn't receive Remove signal when
                 // our thread already unload the chunk. Ignoring it is okay, though
@@ -109,20 +139,27 @@ pub fn loading_thread(
             stream_front = 0;
             stream_queue.clear();
 
-            for z in stream_center.2.saturating_sub(1)..CHUNK_MIPMAP {
-                let (range_src, range_dst) = chunks_within(stream_rect, z);
-                for x in range_src.0..range_dst.0 {
-                    for y in range_src.1..range_dst.1 {
-                        stream_queue.insert((x, y, z));
+            for stream in streams.values() {
+                for z in stream.center.2.saturating_sub(1)..CHUNK_MIPMAP {
+                    let (range_src, range_dst) = chunks_within(stream.rect, z);
+                    for x in range_src.0..range_dst.0 {
+                        for y in range_src.1..range_dst.1 {
+                            stream_queue.insert(chunk_wrap((x, y, z)));
+                        }
                     }
                 }
             }
 
-            debug_assert!(stream_queue.len() < CHUNK_CAPS - 1);
+            stream_queue.sort_by_key(|&key| stream_distance(&streams, key));
+
+            // several cameras may ask for more than we can hold, leaving out the farthest
+            // and some room for the staging ones
+            stream_queue.truncate(CHUNK_CAPS - CHUNK_BATCH - requested.len());
 
-            stream_queue.sort_by_key(|&(x, y, z)| {
-                chunk_distance(x, y, z, stream_center.0, stream_center.1, stream_center.2)
-            });
+            // a pending stroke goes first, and keeps its chunks until it is painted
+            let around = std::mem::take(&mut stream_queue);
+            stream_queue.extend(requested.iter().copied());
+            stream_queue.extend(around);
         }
 
         // Assign loading
@@ -140,15 +177,20 @@ pub fn loading_thread(
             batch_cnt += 1;
         }
 
+        // Reporting queue depth
+        let queued = stream_queue.len() - stream_front + texel_staging.len();
+        if queued != stream_reported {
+            stream_reported = queued;
+            output_tx.send(ThreadOutput::Queued(queued))?;
+        }
+
         // Early exiting
         if texel_staging.is_empty() {
             continue;
         }
 
         // Unloading
-        texel.sort_by_key(|&(x, y, z), _| {
-            chunk_distance(x, y, z, stream_center.0, stream_center.1, stream_center.2)
-        });
+        texel.sort_by_key(|&key, _| stream_distance(&streams, key));
         let write = database.0.begin_write()?;
         let mut table_chunk = write.open_table(TABLE_STROKE_CHUNK)?;
         let mut frnt = texel.len();
@@ -208,9 +250,49 @@ pub fn loading_thread(
                 output_tx.send(ThreadOutput::Insert(chunk_id, None))?;
             }
         }
+
+        // Reporting once the last batch is in
+        let queued = stream_queue.len() - stream_front;
+        if queued != stream_reported {
+            stream_reported = queued;
+            output_tx.send(ThreadOutput::Queued(queued))?;
+        }
     }
 }
 
+/// The bounds of the saved chunks of mipmap 0, see [`StrokeLayer::content_bounds`].
+///
+/// [`StrokeLayer::content_bounds`]: crate::stroke::StrokeLayer::content_bounds
+fn saved_content(database: &SaveDatabase) -> Result<Option<Rectangle>, redb::Error> {
+    let read = database.0.begin_read()?;
+    let table_chunk = match read.open_table(TABLE_STROKE_CHUNK) {
+        Ok(table) => table,
+        Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut chunks = Vec::new();
+    for result in table_chunk.iter()? {
+        let (key, _) = result?;
+        if let (0, chunk @ (_, _, 0)) = key.value() {
+            chunks.push(chunk);
+        }
+    }
+
+    Ok(chunks_bounds(chunks))
+}
+
+/// Distance to the nearest stream.
+fn stream_distance(streams: &HashMap<Handle<Camera>, Stream>, (x, y, z): ChunkKey) -> u32 {
+    (streams.values())
+        .map(|stream| {
+            let (cx, cy, cz) = stream.center;
+            chunk_distance(x, y, z, cx, cy, cz)
+        })
+        .min()
+        .unwrap_or(u32::MAX)
+}
+
 fn chunk_readback(texture: &Texture, device: &Device, queue: &Queue) -> Vec<u8> {
     let (tx, rx) = std::sync::mpsc::channel();
 
@@ -262,3 +344,58 @@ fn chunk_readback(texture: &Texture, device: &Device, queue: &Queue) -> Vec<u8>
     device.poll(PollType::wait_indefinitely()).unwrap();
     rx.recv().unwrap()
 }
+
+/// The sRGB encoded `[r, g, b, a]` of the texel at `[x, y]`, waiting for the GPU.
+pub fn pixel_readback(texture: &Texture, device: &Device, queue: &Queue, at: [u32; 2]) -> [u8; 4] {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    let readback_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("pixel_readback"),
+        size: 4,
+        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("pixel_readback"),
+    });
+
+    encoder.copy_texture_to_buffer(
+        TexelCopyTextureInfoBase {
+            texture,
+            mip_level: 0,
+            origin: Origin3d {
+                x: at[0],
+                y: at[1],
+                z: 0,
+            },
+            aspect: TextureAspect::All,
+        },
+        TexelCopyBufferInfoBase {
+            buffer: &readback_buffer,
+            layout: TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: None,
+                rows_per_image: None,
+            },
+        },
+        Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    queue.submit([encoder.finish()]);
+
+    let inner = readback_buffer.clone();
+    readback_buffer.map_async(MapMode::Read, .., move |ret| {
+        ret.unwrap();
+
+        let view = inner.get_mapped_range(..);
+        tx.send([view[0], view[1], view[2], view[3]]).unwrap();
+    });
+
+    device.poll(PollType::wait_indefinitely()).unwrap();
+    rx.recv().unwrap()
+}
@@ -1,9 +1,37 @@
-use ln_world::Element;
+use std::{path::Path, rc::Rc};
+
+use ln_world::{Element, Handle, World};
 use palette::Srgba;
 
+use crate::{
+    save::get_file_path,
+    widgets::{
+        button::Button,
+        headless::{
+            check_button::CheckButton,
+            number_scroll::NumberScroll,
+            slider::{Slider, WideSlider},
+        },
+    },
+};
+
+pub mod luni;
+
+const THEME_FILE: &str = "theme.toml";
+
+/// Written by hand, parsed when asked for.
+const DARK_SCHEME: &str = include_str!("../res/themes/dark.toml");
+
+/// Colors and sizes of a theme, loaded from TOML files with colors written like
+/// `"#dcdcdcff"`. Missing entries are taken from the default light scheme.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct ColorScheme {
+    #[serde(with = "hex")]
     pub color: Srgba,
+    #[serde(with = "hex")]
     pub active_color: Srgba,
+    #[serde(with = "hex")]
     pub press_color: Srgba,
     pub roundness: f32,
     pub press_roundness: f32,
@@ -12,6 +40,79 @@ pub struct ColorScheme {
     pub pad: i32,
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum ThemeError {
+    #[error("failed to read the theme: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("malformed theme: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+/// Builds the visuals of headless widgets. Everything built for a widget, observers
+/// included, must depend on `look`, which is removed when the theme is switched.
+///
+/// The visuals are built in the view of the widget. A [`Button`] draws itself, the theme
+/// only restyles it.
+pub trait Theme {
+    fn button(&self, world: &World, look: Handle, widget: Handle<Button>);
+    fn check_button(&self, world: &World, look: Handle, widget: Handle<CheckButton>);
+    fn slider(&self, world: &World, look: Handle, widget: Handle<Slider>);
+    fn wide_slider(&self, world: &World, look: Handle, widget: Handle<WideSlider>);
+    fn number_scroll(&self, world: &World, look: Handle, widget: Handle<NumberScroll>);
+}
+
+/// Trigger it on the [`ThemeManager`] to give a headless widget the look of the current
+/// theme, which is removed along with the widget.
+pub struct Attach<T> {
+    pub widget: Handle<T>,
+    pub view: Handle,
+}
+
+/// Holds the current [`Theme`] and the looks it built, to build them again when switched.
+pub struct ThemeManager {
+    theme: Rc<dyn Theme>,
+    looks: Vec<Look>,
+}
+
+struct Look {
+    handle: Handle<LookRoot>,
+    widget: Handle,
+    view: Handle,
+    build: Box<dyn Fn(&dyn Theme, &World, Handle)>,
+}
+
+/// Holds the parts of a look, and has the [`ThemeManager`] forget it once removed along
+/// with its widget.
+struct LookRoot(Handle<ThemeManager>);
+
+/// Sent to the [`ThemeManager`] for each removed look.
+struct LookRemoved(Handle<LookRoot>);
+
+impl ColorScheme {
+    pub fn dark() -> Self {
+        toml::from_str(DARK_SCHEME).unwrap()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ThemeError> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// The scheme in `theme.toml` next to the save, or the default one.
+    pub fn user(world: &World) -> Self {
+        let path = get_file_path(world, THEME_FILE);
+        if !path.exists() {
+            return ColorScheme::default();
+        }
+
+        ColorScheme::load(&path).unwrap_or_else(|err| {
+            log::error!("failed to load {}: {err}", path.display());
+            ColorScheme::default()
+        })
+    }
+}
+
 impl Default for ColorScheme {
     fn default() -> Self {
         Self {
@@ -27,4 +128,201 @@ impl Default for ColorScheme {
     }
 }
 
-impl Element for ColorScheme {}
+impl<T> Attach<T> {
+    /// For a widget inserted into the current view.
+    pub fn here(world: &World, widget: Handle<T>) -> Self {
+        Attach {
+            widget,
+            view: world.here(),
+        }
+    }
+}
+
+impl ThemeManager {
+    pub fn new(theme: impl Theme + 'static) -> Self {
+        ThemeManager {
+            theme: Rc::new(theme),
+            looks: Vec::new(),
+        }
+    }
+
+    /// Remove every look built by the current theme and build them again with `theme`.
+    pub fn switch(world: &World, this: Handle<Self>, theme: impl Theme + 'static) {
+        let mut manager = world.fetch_mut(this).unwrap();
+        manager.theme = Rc::new(theme);
+
+        let manager = &mut *manager;
+        for look in &mut manager.looks {
+            let _ = world.enter(look.view, || world.remove(look.handle));
+            look.handle = ThemeManager::build(
+                world,
+                this,
+                &*manager.theme,
+                look.view,
+                look.widget,
+                &*look.build,
+            );
+        }
+    }
+
+    fn attach<T: Element>(
+        &mut self,
+        world: &World,
+        this: Handle<Self>,
+        event: &Attach<T>,
+        build: impl Fn(&dyn Theme, &World, Handle, Handle<T>) + 'static,
+    ) {
+        let (widget, view) = (event.widget, event.view);
+        let build: Box<dyn Fn(&dyn Theme, &World, Handle)> =
+            Box::new(move |theme, world, look| build(theme, world, look, widget));
+
+        let handle =
+            ThemeManager::build(world, this, &*self.theme, view, widget.untyped(), &*build);
+        self.looks.push(Look {
+            handle,
+            widget: widget.untyped(),
+            view,
+            build,
+        });
+    }
+
+    /// A new look of `widget`, in its view.
+    fn build(
+        world: &World,
+        this: Handle<Self>,
+        theme: &dyn Theme,
+        view: Handle,
+        widget: Handle,
+        build: &dyn Fn(&dyn Theme, &World, Handle),
+    ) -> Handle<LookRoot> {
+        world.enter(view, || {
+            let look = world.insert(LookRoot(this));
+            world.dependency(look, widget);
+            build(theme, world, look.untyped());
+            look
+        })
+    }
+}
+
+impl Element for ThemeManager {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        world.observer(this, move |&LookRemoved(look), world| {
+            let mut manager = world.fetch_mut(this).unwrap();
+            manager.looks.retain(|other| other.handle != look);
+        });
+
+        world.observer(this, move |event: &Attach<Button>, world| {
+            let mut manager = world.fetch_mut(this).unwrap();
+            manager.attach(world, this, event, |theme, world, look, widget| {
+                theme.button(world, look, widget)
+            });
+        });
+
+        world.observer(this, move |event: &Attach<CheckButton>, world| {
+            let mut manager = world.fetch_mut(this).unwrap();
+            manager.attach(world, this, event, |theme, world, look, widget| {
+                theme.check_button(world, look, widget)
+            });
+        });
+
+        world.observer(this, move |event: &Attach<Slider>, world| {
+            let mut manager = world.fetch_mut(this).unwrap();
+            manager.attach(world, this, event, |theme, world, look, widget| {
+                theme.slider(world, look, widget)
+            });
+        });
+
+        world.observer(this, move |event: &Attach<WideSlider>, world| {
+            let mut manager = world.fetch_mut(this).unwrap();
+            manager.attach(world, this, event, |theme, world, look, widget| {
+                theme.wide_slider(world, look, widget)
+            });
+        });
+
+        world.observer(this, move |event: &Attach<NumberScroll>, world| {
+            let mut manager = world.fetch_mut(this).unwrap();
+            manager.attach(world, this, event, |theme, world, look, widget| {
+                theme.number_scroll(world, look, widget)
+            });
+        });
+    }
+}
+
+impl Element for LookRoot {
+    fn when_remove(&mut self, world: &World, this: Handle<Self>) {
+        // the manager may be switching looks right now
+        world.queue_trigger(self.0, LookRemoved(this));
+    }
+}
+
+mod hex {
+    use palette::Srgba;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(color: &Srgba, serializer: S) -> Result<S::Ok, S::Error> {
+        let (r, g, b, a) = color.into_format::<u8, u8>().into_components();
+        serializer.serialize_str(&format!("#{r:02x}{g:02x}{b:02x}{a:02x}"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Srgba, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let digits = text.strip_prefix('#').unwrap_or(&text);
+        let invalid = || D::Error::custom(format!("{text:?} is not a hex color"));
+
+        let value = match digits.len() {
+            6 => u32::from_str_radix(digits, 16).map(|x| (x << 8) | 0xff),
+            8 => u32::from_str_radix(digits, 16),
+            _ => return Err(invalid()),
+        };
+        let value = value.map_err(|_| invalid())?;
+
+        let [r, g, b, a] = value.to_be_bytes();
+        Ok(Srgba::new(r, g, b, a).into_format())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hex_round_trip() {
+        let text = toml::to_string(&ColorScheme::default()).unwrap();
+        assert!(text.contains("color = \"#dcdcdcff\""), "{text}");
+
+        let scheme: ColorScheme = toml::from_str(&text).unwrap();
+        assert_eq!(toml::to_string(&scheme).unwrap(), text);
+    }
+
+    #[test]
+    fn hex_digits() {
+        let scheme: ColorScheme =
+            toml::from_str("color = \"#102030\"\nactive_color = \"40506080\"").unwrap();
+        let bytes = |color: Srgba| color.into_format::<u8, u8>();
+        assert_eq!(bytes(scheme.color), Srgba::new(0x10, 0x20, 0x30, 0xff));
+        assert_eq!(
+            bytes(scheme.active_color),
+            Srgba::new(0x40, 0x50, 0x60, 0x80)
+        );
+
+        for bad in ["#12345", "#gg0000", "#1020304050", ""] {
+            let text = format!("color = \"{bad}\"");
+            assert!(toml::from_str::<ColorScheme>(&text).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn missing_fields() {
+        let scheme: ColorScheme = toml::from_str("pad = 8\npress_color = \"#000000\"").unwrap();
+        let default = ColorScheme::default();
+        assert_eq!(scheme.pad, 8);
+        assert_eq!(scheme.press_color, Srgba::new(0.0, 0.0, 0.0, 1.0));
+        assert_eq!(scheme.color, default.color);
+        assert_eq!(scheme.active_color, default.active_color);
+        assert_eq!(scheme.roundness, default.roundness);
+        assert_eq!(scheme.anim_factor_menu, default.anim_factor_menu);
+
+        let dark = ColorScheme::dark();
+        assert_ne!(dark.color, default.color);
+    }
+}
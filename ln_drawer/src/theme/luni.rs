@@ -0,0 +1,321 @@
+use cosmic_text::Metrics;
+use ln_world::{Handle, World};
+use palette::Srgba;
+
+use crate::{
+    animation::{AnimationDescriptor, AnimationValue},
+    measures::Rectangle,
+    render::{
+        rounded::{RoundedRect, RoundedRectDescriptor},
+        text::{Text, TextDescriptor},
+    },
+    theme::{ColorScheme, Theme},
+    widgets::{
+        WidgetButton, WidgetHover, WidgetPropertyChange,
+        button::{Button, ButtonRestyle},
+        headless::{
+            check_button::CheckButton,
+            number_scroll::NumberScroll,
+            slider::{Slider, WideSlider},
+        },
+    },
+};
+
+/// Height of the track of a [`Slider`].
+const TRACK_HEIGHT: i32 = 4;
+
+/// The default theme, flat rounded rectangles colored by a [`ColorScheme`].
+pub struct Luni {
+    pub scheme: ColorScheme,
+}
+
+impl Luni {
+    pub fn new(scheme: ColorScheme) -> Self {
+        Luni { scheme }
+    }
+
+    pub fn dark() -> Self {
+        Luni::new(ColorScheme::dark())
+    }
+}
+
+impl Default for Luni {
+    fn default() -> Self {
+        Luni::new(ColorScheme::default())
+    }
+}
+
+impl Theme for Luni {
+    fn button(&self, world: &World, _look: Handle, widget: Handle<Button>) {
+        let scheme = &self.scheme;
+        let mut button = world.fetch_mut(widget).unwrap();
+
+        // buttons that do not react to the pointer keep not reacting
+        let flat = button.color == button.active_color && button.color == button.press_color;
+        button.color = scheme.color;
+        (button.active_color, button.press_color) = match flat {
+            true => (scheme.color, scheme.color),
+            false => (scheme.active_color, scheme.press_color),
+        };
+        button.roundness = scheme.roundness;
+        drop(button);
+
+        world.trigger(widget, &ButtonRestyle);
+    }
+
+    fn check_button(&self, world: &World, look: Handle, widget: Handle<CheckButton>) {
+        let scheme = &self.scheme;
+        let button = world.fetch(widget).unwrap();
+
+        let frame = frame(
+            world,
+            scheme,
+            look,
+            widget.untyped(),
+            button.rect,
+            button.order,
+            button.enabled,
+        );
+        let mark = world.build(RoundedRectDescriptor {
+            rect: mark_rect(button.rect, scheme.pad),
+            color: scheme.press_color,
+            shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
+            shrink: scheme.roundness,
+            value: scheme.roundness,
+            visible: button.enabled && button.checked,
+            order: button.order + 1,
+            ..Default::default()
+        });
+        world.dependency(mark, look);
+
+        let pad = scheme.pad;
+        let ob = world.observer(widget, move |WidgetPropertyChange, world| {
+            let button = world.fetch(widget).unwrap();
+            follow(world, frame, button.rect, button.enabled);
+            follow(
+                world,
+                mark,
+                mark_rect(button.rect, pad),
+                button.enabled && button.checked,
+            );
+        });
+        world.dependency(ob, look);
+    }
+
+    fn slider(&self, world: &World, look: Handle, widget: Handle<Slider>) {
+        let scheme = &self.scheme;
+        let slider = world.fetch(widget).unwrap();
+
+        let track = world.build(RoundedRectDescriptor {
+            rect: track_rect(slider.rect),
+            color: scheme.active_color,
+            shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
+            shrink: TRACK_HEIGHT as f32 / 2.0,
+            value: TRACK_HEIGHT as f32 / 2.0,
+            visible: slider.enabled,
+            order: slider.order,
+            ..Default::default()
+        });
+        world.dependency(track, look);
+
+        let knob = frame(
+            world,
+            scheme,
+            look,
+            widget.untyped(),
+            slider.knob_rect(),
+            slider.order + 1,
+            slider.enabled,
+        );
+
+        let ob = world.observer(widget, move |WidgetPropertyChange, world| {
+            let slider = world.fetch(widget).unwrap();
+            follow(world, track, track_rect(slider.rect), slider.enabled);
+            follow(world, knob, slider.knob_rect(), slider.enabled);
+        });
+        world.dependency(ob, look);
+    }
+
+    fn wide_slider(&self, world: &World, look: Handle, widget: Handle<WideSlider>) {
+        let scheme = &self.scheme;
+        let slider = world.fetch(widget).unwrap();
+
+        let frame = frame(
+            world,
+            scheme,
+            look,
+            widget.untyped(),
+            slider.rect,
+            slider.order,
+            slider.enabled,
+        );
+        let fill = world.build(RoundedRectDescriptor {
+            rect: fill_rect(slider.rect, slider.fraction()),
+            color: scheme.press_color,
+            shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
+            shrink: scheme.roundness,
+            value: scheme.roundness,
+            visible: slider.enabled,
+            order: slider.order + 1,
+            ..Default::default()
+        });
+        world.dependency(fill, look);
+
+        let ob = world.observer(widget, move |WidgetPropertyChange, world| {
+            let slider = world.fetch(widget).unwrap();
+            follow(world, frame, slider.rect, slider.enabled);
+            follow(
+                world,
+                fill,
+                fill_rect(slider.rect, slider.fraction()),
+                slider.enabled,
+            );
+        });
+        world.dependency(ob, look);
+    }
+
+    fn number_scroll(&self, world: &World, look: Handle, widget: Handle<NumberScroll>) {
+        let scheme = &self.scheme;
+        let scroll = world.fetch(widget).unwrap();
+
+        let frame = frame(
+            world,
+            scheme,
+            look,
+            widget.untyped(),
+            scroll.rect,
+            scroll.order,
+            scroll.enabled,
+        );
+
+        // text is drawn once, so it is built again whenever the shown number changes
+        let mut shown = (scroll_label(&scroll), scroll.rect, scroll.enabled);
+        let mut text = scroll_text(world, &scroll);
+        world.dependency(text, look);
+
+        let ob = world.observer(widget, move |WidgetPropertyChange, world| {
+            let scroll = world.fetch(widget).unwrap();
+            follow(world, frame, scroll.rect, scroll.enabled);
+
+            let now = (scroll_label(&scroll), scroll.rect, scroll.enabled);
+            if now != shown {
+                shown = now;
+                let _ = world.remove(text);
+                text = scroll_text(world, &scroll);
+                world.dependency(text, look);
+            }
+        });
+        world.dependency(ob, look);
+    }
+}
+
+/// A rounded rectangle whose color follows the hover and press of `widget`.
+fn frame(
+    world: &World,
+    scheme: &ColorScheme,
+    look: Handle,
+    widget: Handle,
+    rect: Rectangle,
+    order: isize,
+    visible: bool,
+) -> Handle<RoundedRect> {
+    let frame = world.build(RoundedRectDescriptor {
+        rect,
+        color: scheme.color,
+        shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.2),
+        shadow_blur: 6.0,
+        shrink: scheme.roundness,
+        value: scheme.roundness,
+        visible,
+        order,
+        ..Default::default()
+    });
+    world.dependency(frame, look);
+
+    let color = world.build(AnimationDescriptor::new(scheme.color, scheme.anim_factor));
+    world.dependency(color, look);
+
+    world.observer(color, move |&AnimationValue(value), world| {
+        world.fetch_mut(frame).unwrap().desc.color = value;
+    });
+
+    let colors = [scheme.color, scheme.active_color, scheme.press_color];
+
+    let ob = world.observer(widget, move |event: &WidgetHover, world| {
+        let mut color = world.fetch_mut(color).unwrap();
+        match event {
+            WidgetHover::HoverEnter => color.dst = colors[1],
+            WidgetHover::HoverLeave => color.dst = colors[0],
+        }
+    });
+    world.dependency(ob, look);
+
+    let ob = world.observer(widget, move |event: &WidgetButton, world| {
+        let mut color = world.fetch_mut(color).unwrap();
+        match event {
+            WidgetButton::ButtonPress => color.dst = colors[2],
+            WidgetButton::ButtonRelease => color.dst = colors[1],
+        }
+    });
+    world.dependency(ob, look);
+
+    frame
+}
+
+fn follow(world: &World, rect: Handle<RoundedRect>, to: Rectangle, visible: bool) {
+    let mut rect = world.fetch_mut(rect).unwrap();
+    if rect.desc.rect != to || rect.desc.visible != visible {
+        rect.desc.rect = to;
+        rect.desc.visible = visible;
+    }
+}
+
+fn mark_rect(rect: Rectangle, pad: i32) -> Rectangle {
+    let pad = pad
+        .min(rect.width() as i32 / 2)
+        .min(rect.height() as i32 / 2);
+    Rectangle::new(
+        rect.left() + pad,
+        rect.down() + pad,
+        rect.right() - pad,
+        rect.up() - pad,
+    )
+}
+
+fn track_rect(rect: Rectangle) -> Rectangle {
+    let middle = (rect.down() + rect.up()) / 2;
+    Rectangle::new(
+        rect.left(),
+        middle - TRACK_HEIGHT / 2,
+        rect.right(),
+        middle + TRACK_HEIGHT / 2,
+    )
+}
+
+fn fill_rect(rect: Rectangle, fraction: f32) -> Rectangle {
+    let right = rect.left() + (rect.width() as f32 * fraction).round() as i32;
+    Rectangle::new(rect.left(), rect.down(), right, rect.up())
+}
+
+fn scroll_label(scroll: &NumberScroll) -> String {
+    // as many decimals as the step needs
+    let fract = scroll.step.fract().abs();
+    let decimals = match fract > 0.0 {
+        true => (-fract.log10()).ceil() as usize,
+        false => 0,
+    };
+    format!("{:.decimals$}", scroll.value)
+}
+
+fn scroll_text(world: &World, scroll: &NumberScroll) -> Handle<Text> {
+    let height = scroll.rect.height() as f32;
+    world.build(TextDescriptor {
+        text: &scroll_label(scroll),
+        spans: &[],
+        rect: scroll.rect,
+        metrics: Metrics::new(height * 0.6, height),
+        order: scroll.order + 1,
+        visible: scroll.enabled,
+        clip: None,
+    })
+}
@@ -1,8 +1,10 @@
 //! `Tools` are a series of elements, which help with parsing winit's raw window event into
 //! useful events, and fitting them into the world environment of LnDrawer.
 
+pub mod clipboard;
 pub mod collider;
 pub mod focus;
+pub mod keymap;
 pub mod modifiers;
 pub mod mouse;
 pub mod pointer;
@@ -0,0 +1,94 @@
+use ln_world::Element;
+
+/// Where copied text goes. The one of the system is used with the `system-clipboard`
+/// feature, plugins may provide others.
+pub trait ClipboardBackend {
+    fn read(&mut self) -> Option<String>;
+    fn write(&mut self, text: String);
+}
+
+/// Keeps the text in memory, shared by the windows of the app only.
+#[derive(Default)]
+pub struct MemoryClipboard(Option<String>);
+
+/// The clipboard of the system, shared with other apps.
+#[cfg(all(feature = "system-clipboard", not(target_os = "android")))]
+pub struct SystemClipboard(arboard::Clipboard);
+
+/// Copied text shared by every window, held by a [`ClipboardBackend`].
+pub struct Clipboard {
+    backend: Box<dyn ClipboardBackend>,
+}
+
+impl ClipboardBackend for MemoryClipboard {
+    fn read(&mut self) -> Option<String> {
+        self.0.clone()
+    }
+
+    fn write(&mut self, text: String) {
+        self.0 = Some(text);
+    }
+}
+
+#[cfg(all(feature = "system-clipboard", not(target_os = "android")))]
+impl SystemClipboard {
+    pub fn new() -> Result<Self, arboard::Error> {
+        arboard::Clipboard::new().map(SystemClipboard)
+    }
+}
+
+#[cfg(all(feature = "system-clipboard", not(target_os = "android")))]
+impl ClipboardBackend for SystemClipboard {
+    fn read(&mut self) -> Option<String> {
+        match self.0.get_text() {
+            Ok(text) => Some(text),
+            Err(arboard::Error::ContentNotAvailable) => None,
+            Err(err) => {
+                log::error!("failed to read the clipboard: {err}");
+                None
+            }
+        }
+    }
+
+    fn write(&mut self, text: String) {
+        if let Err(err) = self.0.set_text(text) {
+            log::error!("failed to write the clipboard: {err}");
+        }
+    }
+}
+
+impl Clipboard {
+    pub fn new(backend: impl ClipboardBackend + 'static) -> Self {
+        Clipboard {
+            backend: Box::new(backend),
+        }
+    }
+
+    /// Text copied before is lost with the previous backend.
+    pub fn set_backend(&mut self, backend: impl ClipboardBackend + 'static) {
+        self.backend = Box::new(backend);
+    }
+
+    pub fn read(&mut self) -> Option<String> {
+        self.backend.read()
+    }
+
+    pub fn write(&mut self, text: String) {
+        self.backend.write(text);
+    }
+}
+
+/// The clipboard of the system if there is one to reach, or else in memory.
+impl Default for Clipboard {
+    fn default() -> Self {
+        #[cfg(all(feature = "system-clipboard", not(target_os = "android")))]
+        match SystemClipboard::new() {
+            Ok(system) => return Clipboard::new(system),
+            Err(err) => log::warn!("copying within the app only, no system clipboard: {err}"),
+        }
+
+        Clipboard::new(MemoryClipboard::default())
+    }
+}
+
+impl Element for Clipboard {}
@@ -1,5 +1,5 @@
 use ln_world::{Element, Handle, World};
-use winit::event::{KeyEvent, WindowEvent};
+use winit::event::{Ime, KeyEvent, WindowEvent};
 
 use crate::lnwin::Lnwindow;
 
@@ -10,17 +10,24 @@ pub struct Focus {
 
 impl Element for Focus {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
-        let lnwindow = world.single::<Lnwindow>().unwrap();
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
 
         world.observer(lnwindow, move |event: &WindowEvent, world| {
-            let WindowEvent::KeyboardInput { event, .. } = event else {
+            let fetched = world.fetch(this).unwrap();
+            let Some(focus_on) = fetched.focus else {
                 return;
             };
 
-            let fetched = world.fetch(this).unwrap();
-
-            if let Some(focus_on) = fetched.focus {
-                world.trigger(focus_on, &FocusInput(event.clone()));
+            match event {
+                WindowEvent::KeyboardInput { event, .. } => {
+                    world.trigger(focus_on, &FocusInput(event.clone()));
+                }
+                WindowEvent::Ime(ime) => {
+                    world.trigger(focus_on, &FocusIme(ime.clone()));
+                }
+                _ => {}
             }
         });
 
@@ -43,6 +50,12 @@ impl Element for Focus {
     }
 }
 
+impl Focus {
+    pub fn focused(&self) -> Option<Handle> {
+        self.focus
+    }
+}
+
 pub struct RequestFocus(pub Option<Handle>);
 
 pub struct FocusEnter;
@@ -50,3 +63,6 @@ pub struct FocusEnter;
 pub struct FocusLeave;
 
 pub struct FocusInput(pub KeyEvent);
+
+/// Input method events, only sent while the window has asked for one.
+pub struct FocusIme(pub Ime);
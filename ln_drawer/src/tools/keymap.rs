@@ -0,0 +1,448 @@
+use std::{any::type_name, fmt, str::FromStr};
+
+use indexmap::IndexMap;
+use ln_world::{Element, Handle, World};
+use redb::{ReadableDatabase, TableDefinition};
+use winit::{
+    event::{KeyEvent, WindowEvent},
+    keyboard::{ModifiersState, PhysicalKey},
+};
+
+use crate::{
+    lnwin::Lnwindow,
+    render::text::TextEdit,
+    save::SaveDatabase,
+    tools::{focus::Focus, modifiers::ModifiersTool},
+};
+
+/// Rebound shortcuts only, by name. Missing ones use their defaults.
+const TABLE_KEYMAP: TableDefinition<&str, &[u8]> = TableDefinition::new("keymap");
+
+/// A physical key with the modifiers held down, written like `Ctrl+Shift+Digit1`.
+///
+/// The key is named after winit's `KeyCode`, so bindings follow the position of the keys
+/// whatever the keyboard layout.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub meta: bool,
+    pub key: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChordError {
+    #[error("{0:?} has no key")]
+    NoKey(String),
+
+    #[error("{0:?} is not a modifier")]
+    UnknownModifier(String),
+}
+
+impl Chord {
+    pub fn from_event(modifiers: ModifiersState, event: &KeyEvent) -> Option<Chord> {
+        let PhysicalKey::Code(code) = event.physical_key else {
+            return None;
+        };
+
+        Some(Chord {
+            ctrl: modifiers.control_key(),
+            shift: modifiers.shift_key(),
+            alt: modifiers.alt_key(),
+            meta: modifiers.meta_key(),
+            key: format!("{code:?}"),
+        })
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.ctrl, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+            (self.meta, "Meta+"),
+        ];
+        for (held, name) in modifiers {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        f.write_str(&self.key)
+    }
+}
+
+impl FromStr for Chord {
+    type Err = ChordError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = s.rsplit_once('+').unwrap_or(("", s));
+        if key.is_empty() {
+            return Err(ChordError::NoKey(s.into()));
+        }
+
+        let mut chord = Chord {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            meta: false,
+            key: key.into(),
+        };
+
+        for modifier in modifiers.split('+').filter(|x| !x.is_empty()) {
+            match modifier {
+                "Ctrl" => chord.ctrl = true,
+                "Shift" => chord.shift = true,
+                "Alt" => chord.alt = true,
+                "Meta" => chord.meta = true,
+                _ => return Err(ChordError::UnknownModifier(modifier.into())),
+            }
+        }
+
+        Ok(chord)
+    }
+}
+
+/// A named action of the keymap, observe [`ShortcutHit`] on it to do the work.
+///
+/// Modifying [`Shortcut::chords`] rebinds it and saves the binding in the database.
+pub struct Shortcut {
+    pub name: String,
+    pub defaults: Vec<Chord>,
+    pub chords: Vec<Chord>,
+    /// Hit again while the key is held down.
+    pub repeat: bool,
+    /// The chords as saved, modifying anything else is not a rebinding.
+    bound: Vec<Chord>,
+}
+
+pub struct ShortcutHit;
+
+/// Triggered on the [`Keymap`] once a `shortcut` is bound to a chord of some `other` one,
+/// when inserted or rebound. Only one of them is hit by the chord.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortcutConflict {
+    pub chord: Chord,
+    pub shortcut: Handle<Shortcut>,
+    pub other: Handle<Shortcut>,
+}
+
+impl Shortcut {
+    /// Panics on malformed default chords, they are written by hand.
+    pub fn new(name: &str, defaults: &[&str]) -> Shortcut {
+        let defaults = (defaults.iter())
+            .map(|chord| chord.parse().unwrap())
+            .collect::<Vec<Chord>>();
+
+        Shortcut {
+            name: name.into(),
+            chords: defaults.clone(),
+            bound: defaults.clone(),
+            defaults,
+            repeat: false,
+        }
+    }
+
+    pub fn repeat(self) -> Shortcut {
+        Shortcut {
+            repeat: true,
+            ..self
+        }
+    }
+
+    /// Insert it into the window, where the [`Keymap`] looks for it, while `action` runs in
+    /// the current view. Chords that are already taken are reported as [`ShortcutConflict`].
+    pub fn register(
+        world: &World,
+        shortcut: Shortcut,
+        mut action: impl FnMut(&World) + 'static,
+    ) -> Handle<Shortcut> {
+        // never hit without a window
+        let this = match world.single::<Lnwindow>() {
+            Ok(lnwindow) => world.enter(lnwindow, || world.insert(shortcut)),
+            Err(_) => world.insert(shortcut),
+        };
+        world.observer(this, move |ShortcutHit, world| action(world));
+        this
+    }
+
+    fn load(db: &SaveDatabase, name: &str) -> Result<Option<Vec<Chord>>, redb::Error> {
+        let read = db.0.begin_read()?;
+        let table = match read.open_table(TABLE_KEYMAP) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let bytes = table.get(name)?;
+        Ok(bytes.and_then(|bytes| postcard::from_bytes(bytes.value()).ok()))
+    }
+
+    fn save(&self, db: &SaveDatabase) -> Result<(), redb::Error> {
+        let write = db.0.begin_write()?;
+        {
+            let mut table = write.open_table(TABLE_KEYMAP)?;
+            if self.chords == self.defaults {
+                table.remove(&self.name[..])?;
+            } else {
+                let bytes = postcard::to_stdvec(&self.chords).unwrap();
+                table.insert(&self.name[..], &bytes[..])?;
+            }
+        }
+        write.commit()?;
+        Ok(())
+    }
+
+    /// Chords of this one also bound to other shortcuts.
+    pub fn conflicts(&self, world: &World, this: Handle<Self>) -> Vec<ShortcutConflict> {
+        let mut conflicts = Vec::new();
+        // this one may be borrowed already
+        world.foreach::<Shortcut>(|other| {
+            if other == this {
+                return;
+            }
+
+            let fetched = world.fetch(other).unwrap();
+            for chord in self.chords.iter().filter(|x| fetched.chords.contains(x)) {
+                log::warn!(
+                    "{chord} is bound to both {} and {}, only one of them is hit",
+                    fetched.name,
+                    self.name,
+                );
+                conflicts.push(ShortcutConflict {
+                    chord: chord.clone(),
+                    shortcut: this,
+                    other,
+                });
+            }
+        });
+        conflicts
+    }
+
+    fn report_conflicts(&self, world: &World, this: Handle<Self>) {
+        let conflicts = self.conflicts(world, this);
+        if let Ok(keymap) = world.single::<Keymap>() {
+            for conflict in conflicts {
+                world.queue_trigger(keymap, conflict);
+            }
+        }
+    }
+}
+
+impl Element for Shortcut {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let db = world.single_fetch::<SaveDatabase>().unwrap();
+        match Shortcut::load(&db, &self.name) {
+            Ok(Some(chords)) => self.chords = chords,
+            Ok(None) => {}
+            Err(err) => log::error!("failed to load the keymap of {}: {err}", self.name),
+        }
+        self.bound = self.chords.clone();
+
+        self.report_conflicts(world, this);
+    }
+
+    fn when_modify(&mut self, world: &World, this: Handle<Self>) {
+        if self.chords == self.bound {
+            return;
+        }
+        self.bound = self.chords.clone();
+
+        if let Ok(db) = world.single_fetch::<SaveDatabase>()
+            && let Err(err) = self.save(&db)
+        {
+            log::error!("failed to save the keymap of {}: {err}", self.name);
+        }
+
+        self.report_conflicts(world, this);
+    }
+}
+
+/// Turns key presses into [`ShortcutHit`], reading the modifiers from [`ModifiersTool`].
+/// Stays quiet while a [`TextEdit`] has the keyboard [`Focus`]. Observe [`ShortcutConflict`]
+/// on it to learn about chords bound twice.
+#[derive(Default)]
+pub struct Keymap;
+
+impl Element for Keymap {
+    fn when_insert(&mut self, world: &World, _this: Handle<Self>) {
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
+
+        world.observer(lnwindow, move |event: &WindowEvent, world| {
+            let WindowEvent::KeyboardInput { event, .. } = event else {
+                return;
+            };
+
+            if !event.state.is_pressed() || Keymap::typing(world) {
+                return;
+            }
+
+            let modifiers = world.single_fetch::<ModifiersTool>().unwrap();
+            let Some(chord) = Chord::from_event(modifiers.modifiers.state(), event) else {
+                return;
+            };
+            drop(modifiers);
+
+            let mut hit = None;
+            world.foreach_fetch::<Shortcut>(|shortcut| {
+                if hit.is_none()
+                    && shortcut.chords.contains(&chord)
+                    && (shortcut.repeat || !event.repeat)
+                {
+                    hit = Some(shortcut.handle());
+                }
+            });
+
+            if let Some(hit) = hit {
+                world.trigger(hit, &ShortcutHit);
+            }
+        });
+    }
+}
+
+impl Keymap {
+    /// Whether a [`TextEdit`] has the focus, its keys are not shortcuts then.
+    fn typing(world: &World) -> bool {
+        let Ok(focus) = world.single_fetch::<Focus>() else {
+            return false;
+        };
+        let focused = focus.focused().and_then(|x| world.type_name_of(x));
+        focused == Some(type_name::<TextEdit>())
+    }
+
+    /// Chords bound to more than one shortcut, with the names of those.
+    pub fn conflicts(world: &World) -> Vec<(Chord, Vec<String>)> {
+        let mut bound = IndexMap::<Chord, Vec<String>>::new();
+        world.foreach_fetch::<Shortcut>(|shortcut| {
+            for chord in &shortcut.chords {
+                let names = bound.entry(chord.clone()).or_default();
+                names.push(shortcut.name.clone());
+            }
+        });
+
+        bound.retain(|_, names| names.len() > 1);
+        bound.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+    use redb::{Database, backends::InMemoryBackend};
+
+    use super::*;
+
+    fn world() -> World {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let mut world = World::default();
+        world.insert(SaveDatabase(Arc::new(db)));
+        world.flush();
+        world
+    }
+
+    #[test]
+    fn chord_round_trip() {
+        for text in [
+            "KeyA",
+            "Ctrl+KeyN",
+            "Ctrl+Shift+Digit1",
+            "Ctrl+Shift+Alt+Meta+F5",
+        ] {
+            let chord: Chord = text.parse().unwrap();
+            assert_eq!(chord.to_string(), text);
+        }
+
+        let chord: Chord = "Shift+Ctrl+KeyZ".parse().unwrap();
+        assert!(chord.ctrl && chord.shift && !chord.alt && !chord.meta);
+        assert_eq!(chord.key, "KeyZ");
+        assert_eq!(chord.to_string(), "Ctrl+Shift+KeyZ");
+    }
+
+    #[test]
+    fn chord_errors() {
+        assert!(matches!("".parse::<Chord>(), Err(ChordError::NoKey(_))));
+        assert!(matches!(
+            "Ctrl+".parse::<Chord>(),
+            Err(ChordError::NoKey(_))
+        ));
+        assert!(matches!(
+            "Hyper+KeyA".parse::<Chord>(),
+            Err(ChordError::UnknownModifier(x)) if x == "Hyper"
+        ));
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut world = world();
+        let keymap = world.insert(Keymap);
+        let reported = Rc::new(RefCell::new(Vec::new()));
+        let sink = reported.clone();
+        world.observer(keymap, move |conflict: &ShortcutConflict, _| {
+            sink.borrow_mut().push(conflict.clone());
+        });
+
+        let copy = Shortcut::register(&world, Shortcut::new("copy", &["Ctrl+KeyC"]), |_| {});
+        world.flush();
+        assert!(reported.borrow().is_empty());
+
+        let paste = Shortcut::new("paste", &["Ctrl+KeyV", "Ctrl+KeyC"]);
+        let paste = Shortcut::register(&world, paste, |_| {});
+        world.flush();
+
+        let chord: Chord = "Ctrl+KeyC".parse().unwrap();
+        let conflict = ShortcutConflict {
+            chord: chord.clone(),
+            shortcut: paste,
+            other: copy,
+        };
+        assert_eq!(*reported.borrow(), [conflict]);
+
+        let conflicts = Keymap::conflicts(&world);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].0, chord);
+        assert_eq!(conflicts[0].1, ["copy", "paste"]);
+    }
+
+    #[test]
+    fn rebinding_reports_and_saves() {
+        let mut world = world();
+        let keymap = world.insert(Keymap);
+        let reported = Rc::new(RefCell::new(0));
+        let sink = reported.clone();
+        world.observer(keymap, move |_: &ShortcutConflict, _| {
+            *sink.borrow_mut() += 1
+        });
+
+        Shortcut::register(&world, Shortcut::new("copy", &["Ctrl+KeyC"]), |_| {});
+        let cut = Shortcut::register(&world, Shortcut::new("cut", &["Ctrl+KeyX"]), |_| {});
+        world.flush();
+
+        world.fetch_mut(cut).unwrap().repeat = true;
+        world.flush();
+        assert_eq!(*reported.borrow(), 0);
+
+        world.fetch_mut(cut).unwrap().chords = vec!["Ctrl+KeyC".parse().unwrap()];
+        world.flush();
+        assert_eq!(*reported.borrow(), 1);
+
+        let db = world.single_fetch::<SaveDatabase>().unwrap();
+        let saved = Shortcut::load(&db, "cut").unwrap();
+        assert_eq!(saved, Some(vec!["Ctrl+KeyC".parse().unwrap()]));
+    }
+
+    #[test]
+    fn no_conflicts() {
+        let mut world = world();
+        Shortcut::register(&world, Shortcut::new("copy", &["Ctrl+KeyC"]), |_| {});
+        Shortcut::register(&world, Shortcut::new("cut", &["Ctrl+Shift+KeyC"]), |_| {});
+        world.flush();
+
+        assert!(Keymap::conflicts(&world).is_empty());
+    }
+}
@@ -10,7 +10,9 @@ pub struct ModifiersTool {
 
 impl Element for ModifiersTool {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
-        let lnwindow = world.single::<Lnwindow>().unwrap();
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
 
         world.observer(lnwindow, move |event: &WindowEvent, world| {
             if let WindowEvent::ModifiersChanged(modifiers) = event {
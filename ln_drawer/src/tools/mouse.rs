@@ -14,13 +14,15 @@ use crate::{
 #[derive(Default)]
 pub struct MouseTool;
 
-/// Right-click events.
+/// Right-click or long press events, sent to the top collider with the position in its view.
 #[derive(Clone, Copy)]
 pub struct MouseMenu(pub Position);
 
 impl Element for MouseTool {
     fn when_insert(&mut self, world: &World, _this: Handle<Self>) {
-        let lnwindow = world.single::<Lnwindow>().unwrap();
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
 
         world.observer(lnwindow, |event: &WindowEvent, world| match event {
             // right-click //
@@ -126,7 +126,9 @@ impl PointerTool {
 
 impl Element for PointerTool {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
-        let lnwindow = world.single::<Lnwindow>().unwrap();
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
         world.observer(lnwindow, move |event: &WindowEvent, world| {
             let mut this = world.fetch_mut(this).unwrap();
             let lnwindow = world.single_fetch::<Lnwindow>().unwrap();
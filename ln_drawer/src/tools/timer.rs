@@ -38,7 +38,9 @@ struct TimerLastTick(Instant);
 
 impl Element for TimerLastTick {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
-        let lnwindow = world.single::<Lnwindow>().unwrap();
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
         world.observer(lnwindow, move |_: &WindowEvent, world| {
             let mut this = world.fetch_mut(this).unwrap();
             this.tick(world);
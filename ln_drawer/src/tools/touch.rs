@@ -1,3 +1,5 @@
+use std::time::{Duration, Instant};
+
 use hashbrown::HashMap;
 use ln_world::{Element, Handle, World};
 use winit::event::{
@@ -5,14 +7,36 @@ use winit::event::{
 };
 
 use crate::{
-    lnwin::Lnwindow, measures::PositionFract, render::camera::Camera, tools::collider::ToolCollider,
+    lnwin::Lnwindow,
+    measures::{Position, PositionFract},
+    render::{RenderControl, RenderInformation, camera::Camera},
+    tools::{collider::ToolCollider, mouse::MouseMenu},
 };
 
+const LONG_PRESS: Duration = Duration::from_millis(500);
+
+/// Pixels a finger may drift and still be held in place.
+const LONG_PRESS_DISTANCE: f64 = 10.0;
+
 /// Multi touch actions that allow inputs with more points than [`PointerTool`] but no hovering
+///
+/// A single finger held in place sends [`MouseMenu`] like a right click.
 #[derive(Default)]
 pub struct MultiTouchTool {
     touches: HashMap<PointerKind, Handle<ToolCollider>>,
     groups: HashMap<Handle<ToolCollider>, Vec<MultiTouch>>,
+    long_press: Option<LongPress>,
+}
+
+/// A finger held down, timed by a render control in the camera it was pressed in.
+struct LongPress {
+    pointer: PointerKind,
+    since: Instant,
+    screen: [f64; 2],
+    target: Handle<ToolCollider>,
+    position: Position,
+    view: Handle<Camera>,
+    control: Handle<RenderControl>,
 }
 
 impl Element for MultiTouchTool {
@@ -51,7 +75,9 @@ pub struct MultiTouchData {
 
 impl MultiTouchTool {
     fn listening_window_event(&mut self, world: &World, _this: Handle<Self>) {
-        let lnwindow = world.single::<Lnwindow>().unwrap();
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
         world.observer(lnwindow, |event, world| match event {
             WindowEvent::PointerButton {
                 state: ElementState::Pressed,
@@ -86,6 +112,16 @@ impl MultiTouchTool {
                 };
 
                 let tool = &mut *world.single_fetch_mut::<MultiTouchTool>().unwrap();
+
+                // another finger makes it a gesture
+                tool.cancel_long_press(world);
+                if let PointerKind::Touch(_) = kind
+                    && tool.touches.is_empty()
+                {
+                    let press = LongPress::start(world, kind, screen, target, view, position);
+                    tool.long_press = Some(press);
+                }
+
                 let replaced = tool.touches.insert(kind, target);
                 if let Some(replaced_target) = replaced {
                     // Edge-cases: duplicated TouchId is pressed
@@ -142,13 +178,24 @@ impl MultiTouchTool {
                     return;
                 };
 
-                let list = tool.groups.get_mut(&target).unwrap();
-                let touch = list.iter_mut().find(|x| x.pointer == kind).unwrap();
-
                 let lnwindow = world.single_fetch::<Lnwindow>().unwrap();
                 let screen = lnwindow.cursor_to_screen(*position);
+
+                if let Some(press) = &tool.long_press
+                    && press.pointer == kind
+                {
+                    let size = lnwindow.window.surface_size();
+                    let x = (screen[0] - press.screen[0]) * size.width as f64 / 2.0;
+                    let y = (screen[1] - press.screen[1]) * size.height as f64 / 2.0;
+                    if x.hypot(y) > LONG_PRESS_DISTANCE {
+                        tool.cancel_long_press(world);
+                    }
+                }
                 drop(lnwindow);
 
+                let list = tool.groups.get_mut(&target).unwrap();
+                let touch = list.iter_mut().find(|x| x.pointer == kind).unwrap();
+
                 let position = world.enter(touch.view, || {
                     let camera = world.single_fetch::<Camera>().unwrap();
                     camera.screen_to_world_absolute(screen)
@@ -189,6 +236,10 @@ impl MultiTouchTool {
                     return;
                 };
 
+                if tool.long_press.as_ref().is_some_and(|x| x.pointer == kind) {
+                    tool.cancel_long_press(world);
+                }
+
                 let list = tool.groups.get_mut(&target).unwrap();
                 let (idx, touch) = list
                     .iter_mut()
@@ -232,6 +283,12 @@ impl MultiTouchTool {
         });
     }
 
+    fn cancel_long_press(&mut self, world: &World) {
+        if let Some(press) = self.long_press.take() {
+            press.remove(world);
+        }
+    }
+
     fn button_to_kind(button: &ButtonSource) -> Option<PointerKind> {
         match button {
             ButtonSource::Mouse(MouseButton::Left) => Some(PointerKind::Mouse),
@@ -268,3 +325,61 @@ impl MultiTouchTool {
         }
     }
 }
+
+impl LongPress {
+    fn start(
+        world: &World,
+        pointer: PointerKind,
+        screen: [f64; 2],
+        target: Handle<ToolCollider>,
+        view: Handle<Camera>,
+        position: PositionFract,
+    ) -> LongPress {
+        let since = Instant::now();
+
+        // redrawing until it is held long enough, or no longer held
+        let control = world.enter(view, || {
+            world.insert(RenderControl {
+                prepare: Some(Box::new(move |world| {
+                    let mut tool = world.single_fetch_mut::<MultiTouchTool>().unwrap();
+                    let press = tool.long_press.as_ref()?;
+                    if press.since != since {
+                        return None;
+                    }
+
+                    if press.since.elapsed() < LONG_PRESS {
+                        return Some(RenderInformation {
+                            keep_redrawing: true,
+                        });
+                    }
+
+                    let press = tool.long_press.take().unwrap();
+                    world.queue_trigger(press.target, MouseMenu(press.position));
+                    press.remove(world);
+                    None
+                })),
+                draw: None,
+                bounding: None,
+                clip: None,
+            })
+        });
+        RenderControl::redraw(world);
+
+        LongPress {
+            pointer,
+            since,
+            screen,
+            target,
+            position: position.floor(),
+            view,
+            control,
+        }
+    }
+
+    fn remove(self, world: &World) {
+        let (view, control) = (self.view, self.control);
+        world.queue(move |world| {
+            let _ = world.enter(view, || world.remove(control));
+        });
+    }
+}
@@ -1,8 +1,14 @@
 use ::palette::Hsla;
 
-use crate::measures::Rectangle;
+use crate::{layout::WritingDirection, measures::Rectangle};
 
 pub mod button;
+pub mod context_menu;
+pub mod headless;
+pub mod inspector;
+pub mod menu;
+pub mod metrics;
+pub mod minimap;
 pub mod palette;
 pub mod renderer;
 
@@ -30,6 +36,9 @@ pub struct WidgetRectangle(pub Rectangle);
 /// Send when widget's rectangle data is changed which needs to be animated.
 pub struct WidgetAnimatedRectangle(pub Rectangle);
 
+/// Send when widget's text is changed.
+pub struct WidgetText(pub String);
+
 /// Send when widget's checked data is changed.
 pub struct WidgetChecked(pub bool);
 
@@ -39,8 +48,15 @@ pub struct WidgetHsla(pub Hsla);
 /// Send when widget is folded or expanded.
 pub struct WidgetExpanded(pub bool);
 
+/// Send when the writing direction of widget's subtree is changed. Broadcast, so everything
+/// depending on the widget is told as well.
+pub struct WidgetDirection(pub WritingDirection);
+
 /// Send when widget is enabled or disabled.
 pub struct WidgetEnabled(pub bool);
 
+/// Send when headless widget's properties are modified.
+pub struct WidgetPropertyChange;
+
 /// Send when widget is about to be removed.
 pub struct WidgetDestroyed;
@@ -49,6 +49,8 @@ pub struct ButtonDrag {
 
 pub struct ButtonChecked(pub bool);
 pub struct ButtonColor(pub Srgba);
+/// Send it once the colors or the roundness are changed, like by a theme, to show them.
+pub struct ButtonRestyle;
 pub struct ButtonAnim {
     pub src: Rectangle,
     pub dst: Rectangle,
@@ -139,6 +141,22 @@ impl Button {
             }
         });
 
+        world.observer(this, move |ButtonRestyle, world| {
+            let this = world.fetch(this).unwrap();
+            let color = match this.checked {
+                true => this.press_color,
+                false => this.color,
+            };
+
+            let mut frame_anim_color = world.fetch_mut(frame_anim_color).unwrap();
+            frame_anim_color.src = color;
+            frame_anim_color.dst = color;
+
+            let mut frame = world.fetch_mut(frame).unwrap();
+            frame.desc.shrink = this.roundness;
+            frame.desc.value = this.roundness;
+        });
+
         world.observer(this, move |&ButtonColor(color), world| {
             let mut frame_anim_color = world.fetch_mut(frame_anim_color).unwrap();
             frame_anim_color.src = color;
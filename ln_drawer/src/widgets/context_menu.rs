@@ -0,0 +1,326 @@
+use cosmic_text::Metrics;
+use glam::Vec2;
+use ln_world::{Element, Handle, World};
+use palette::Srgba;
+use winit::keyboard::{Key, NamedKey};
+
+use crate::{
+    locale::LocalizedText,
+    measures::{Position, Rectangle, Size},
+    render::{
+        RenderControl,
+        camera::{Camera, UICamera},
+        rounded::RoundedRectDescriptor,
+    },
+    tools::{
+        collider::ToolCollider,
+        focus::{Focus, FocusInput, FocusLeave, RequestFocus},
+        mouse::MouseMenu,
+        pointer::{PointerHit, PointerHitStatus},
+    },
+    widgets::{WidgetClick, button::Button},
+};
+
+const MENU_ORDER: isize = 900;
+const MENU_PAD: i32 = 4;
+const ROW_WIDTH: i32 = 180;
+const ROW_HEIGHT: i32 = 28;
+const LABEL_HEIGHT: i32 = 18;
+
+/// An action offered by the [`ContextMenu`] when a menu is asked for over `collider`, with
+/// a right click or a long press. Observe [`ContextActionHit`] on it to do the work.
+///
+/// Shown as the message of the [`Locale`](crate::locale::Locale) named like the action.
+pub struct ContextAction {
+    pub name: String,
+    pub collider: Handle<ToolCollider>,
+}
+
+/// Sent with the position the menu was asked for at, in the view of the collider.
+pub struct ContextActionHit(pub Position);
+
+/// Lists the [`ContextAction`]s of the collider hit at the pointer, should be inserted into
+/// the UI camera. Closed by clicking outside of it, picking an action or `Escape`.
+#[derive(Default)]
+pub struct ContextMenu {
+    offers: Vec<Offer>,
+    screen: [f64; 2],
+    page: Option<Handle<()>>,
+}
+
+/// An action gathered for the menu about to be opened.
+struct Offer {
+    action: Handle<ContextAction>,
+    view: Handle,
+    position: Position,
+}
+
+impl ContextAction {
+    /// Offer it over `collider`, which must be in the current view. `action` runs in that
+    /// view too.
+    pub fn register(
+        world: &World,
+        collider: Handle<ToolCollider>,
+        name: &str,
+        mut action: impl FnMut(&World, Position) + 'static,
+    ) -> Handle<ContextAction> {
+        let this = world.insert(ContextAction {
+            name: name.into(),
+            collider,
+        });
+        world.observer(this, move |&ContextActionHit(position), world| {
+            action(world, position)
+        });
+        this
+    }
+}
+
+impl Element for ContextAction {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        world.dependency(this, self.collider);
+
+        let ob = world.observer(self.collider, move |&MouseMenu(position), world| {
+            let camera = world.single_fetch::<Camera>().unwrap();
+            let screen = camera.world_to_screen_absolute(position.into_fract());
+            drop(camera);
+
+            ContextMenu::offer(world, this, screen, position);
+        });
+        world.dependency(ob, this);
+    }
+}
+
+impl ContextMenu {
+    /// Every action of the collider hit offers itself, the menu opens once they are all in.
+    fn offer(world: &World, action: Handle<ContextAction>, screen: [f64; 2], position: Position) {
+        let view = world.here();
+        let ui_camera = world.single_fetch::<UICamera>().unwrap().0;
+
+        world.enter(ui_camera, || {
+            let Ok(mut menu) = world.single_fetch_mut::<ContextMenu>() else {
+                return;
+            };
+
+            if menu.offers.is_empty() {
+                let this = menu.handle();
+                world.queue(move |world| {
+                    if let Ok(mut menu) = world.fetch_mut(this) {
+                        menu.open(world, this);
+                    }
+                });
+            }
+
+            menu.screen = screen;
+            menu.offers.push(Offer {
+                action,
+                view,
+                position,
+            });
+        });
+    }
+
+    /// Removing the page removes buttons whose observers may be running, so it is queued.
+    pub fn close(world: &World, this: Handle<Self>) {
+        world.queue(move |world| {
+            let Ok(mut menu) = world.fetch_mut(this) else {
+                return;
+            };
+
+            let Some(page) = menu.page.take() else {
+                return;
+            };
+            drop(menu);
+            let _ = world.remove(page);
+
+            let focus = world.single::<Focus>().unwrap();
+            if world.fetch(focus).unwrap().focused() == Some(this.untyped()) {
+                world.trigger(focus, &RequestFocus(None));
+            }
+
+            RenderControl::redraw(world);
+        });
+    }
+
+    fn open(&mut self, world: &World, this: Handle<Self>) {
+        if let Some(page) = self.page.take() {
+            let _ = world.remove(page);
+        }
+
+        let offers = std::mem::take(&mut self.offers);
+        let offers = (offers.into_iter())
+            .filter_map(|offer| {
+                let action = world.enter(offer.view, || {
+                    world.fetch(offer.action).map(|action| action.name.clone())
+                });
+                Some((action.ok()?, offer))
+            })
+            .collect::<Vec<_>>();
+
+        if offers.is_empty() {
+            return;
+        }
+
+        let page = world.insert(());
+        world.dependency(page, this);
+        self.page = Some(page);
+
+        let camera = world.single_fetch::<Camera>().unwrap();
+        let screen = Rectangle::new_half(
+            Position::ZERO,
+            Size::new(camera.size.w / 2, camera.size.h / 2),
+        );
+        let at = camera.screen_to_world_absolute(self.screen).floor();
+        drop(camera);
+
+        let menu = menu_rect(at, screen, offers.len());
+        let background = world.build(RoundedRectDescriptor {
+            rect: menu,
+            color: Srgba::new(0.1, 0.1, 0.12, 0.95),
+            order: MENU_ORDER,
+            ..Default::default()
+        });
+        world.dependency(background, page);
+
+        // clicking anywhere else closes it
+        let catcher = world.insert(ToolCollider::fullscreen(MENU_ORDER));
+        world.dependency(catcher, page);
+
+        world.observer(catcher, move |event: &PointerHit, world| {
+            if event.status == PointerHitStatus::Press {
+                ContextMenu::close(world, this);
+            }
+        });
+
+        world.observer(catcher, move |&MouseMenu(_), world| {
+            ContextMenu::close(world, this);
+        });
+
+        for (idx, (name, offer)) in offers.into_iter().enumerate() {
+            let rect = row_rect(menu, idx);
+
+            let row = world.insert(Button {
+                rect,
+                rect_transition: false,
+                order: MENU_ORDER + 1,
+                color: Srgba::new(0.1, 0.1, 0.12, 0.0),
+                active_color: Srgba::new(0.24, 0.24, 0.28, 1.0),
+                press_color: Srgba::new(0.3, 0.3, 0.35, 1.0),
+                roundness: 4.0,
+                shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
+                shadow_offset: Vec2::ZERO,
+                ..Default::default()
+            });
+            world.dependency(row, page);
+
+            // follows the language, if it is switched while open
+            let margin = (ROW_HEIGHT - LABEL_HEIGHT) / 2;
+            let label = LocalizedText {
+                clip: Some(rect),
+                ..LocalizedText::new(
+                    &name,
+                    Rectangle::new(
+                        rect.left() + margin * 2,
+                        rect.down() + margin,
+                        rect.right() - margin,
+                        rect.up() - margin,
+                    ),
+                    Metrics::new(14.0, LABEL_HEIGHT as f32),
+                    MENU_ORDER + 2,
+                )
+            };
+            let label = world.insert(label);
+            world.dependency(label, page);
+
+            let Offer {
+                action,
+                view,
+                position,
+            } = offer;
+            world.observer(row, move |&WidgetClick, world| {
+                ContextMenu::close(world, this);
+                world.enter(view, || {
+                    world.trigger(action, &ContextActionHit(position));
+                });
+            });
+        }
+
+        let focus = world.single::<Focus>().unwrap();
+        world.trigger(focus, &RequestFocus(Some(this.untyped())));
+
+        RenderControl::redraw(world);
+    }
+}
+
+impl Element for ContextMenu {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        world.observer(this, move |FocusInput(event): &FocusInput, world| {
+            if event.state.is_pressed() && event.logical_key == Key::Named(NamedKey::Escape) {
+                ContextMenu::close(world, this);
+            }
+        });
+
+        world.observer(this, move |FocusLeave, world| {
+            ContextMenu::close(world, this);
+        });
+    }
+}
+
+/// Opened down and to the right of `at`, but moved to stay within `screen`.
+fn menu_rect(at: Position, screen: Rectangle, rows: usize) -> Rectangle {
+    let width = ROW_WIDTH + MENU_PAD * 2;
+    let height = ROW_HEIGHT * rows as i32 + MENU_PAD * 2;
+    let left = at.x.min(screen.right() - width);
+    let up = at.y.max(screen.down() + height);
+    Rectangle::new(left, up - height, left + width, up)
+}
+
+/// Rows go down from the top of the menu.
+fn row_rect(menu: Rectangle, idx: usize) -> Rectangle {
+    let left = menu.left() + MENU_PAD;
+    let up = menu.up() - MENU_PAD - ROW_HEIGHT * idx as i32;
+    Rectangle::new(left, up - ROW_HEIGHT, left + ROW_WIDTH, up)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn screen() -> Rectangle {
+        Rectangle::new(-400, -300, 400, 300)
+    }
+
+    #[test]
+    fn opens_down_right() {
+        let menu = menu_rect(Position::new(10, 20), screen(), 2);
+        assert_eq!(menu, Rectangle::new(10, -44, 198, 20));
+    }
+
+    #[test]
+    fn kept_within_screen() {
+        // near the right edge, it opens to the left instead
+        let menu = menu_rect(Position::new(350, 0), screen(), 1);
+        assert_eq!(menu.right(), 400);
+        assert_eq!(menu.up(), 0);
+
+        // near the bottom, it goes up
+        let menu = menu_rect(Position::new(0, -290), screen(), 3);
+        assert_eq!(menu.down(), -300);
+        assert_eq!(menu.left(), 0);
+
+        // in the corner, both
+        let menu = menu_rect(Position::new(399, -299), screen(), 3);
+        assert_eq!((menu.right(), menu.down()), (400, -300));
+    }
+
+    #[test]
+    fn rows_within_menu() {
+        let menu = menu_rect(Position::new(-100, 100), screen(), 3);
+        let rows = [0, 1, 2].map(|idx| row_rect(menu, idx));
+
+        assert_eq!(rows[0], Rectangle::new(-96, 68, 84, 96));
+        assert_eq!(rows[0].down(), rows[1].up());
+        assert_eq!(rows[1].down(), rows[2].up());
+        assert_eq!(rows[2].down(), menu.down() + MENU_PAD);
+        assert!(rows.iter().all(|row| menu.intersect(*row) == Some(*row)));
+    }
+}
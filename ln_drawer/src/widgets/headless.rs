@@ -0,0 +1,96 @@
+//! Headless widgets own their properties and turn pointer input into their own events, but
+//! draw nothing. The look is bound apart, by triggering [`Attach`] on the [`ThemeManager`].
+//!
+//! Any modification of a headless widget sends [`WidgetPropertyChange`] to it, which is all
+//! a look needs to follow it.
+//!
+//! [`Attach`]: crate::theme::Attach
+//! [`ThemeManager`]: crate::theme::ThemeManager
+//! [`WidgetPropertyChange`]: crate::widgets::WidgetPropertyChange
+
+use ln_world::{Handle, World};
+
+use crate::{
+    measures::Rectangle,
+    tools::{
+        collider::ToolCollider,
+        pointer::{PointerHover, PointerHoverStatus},
+    },
+    widgets::{WidgetEnabled, WidgetHover, WidgetRectangle},
+};
+
+pub mod check_button;
+pub mod number_scroll;
+pub mod slider;
+
+/// A collider following the rectangle and the enabled status of `widget`, hovering it sends
+/// [`WidgetHover`] to `widget`.
+fn attach_collider(
+    world: &World,
+    widget: Handle,
+    rect: Rectangle,
+    order: isize,
+    enabled: bool,
+) -> Handle<ToolCollider> {
+    let collider = world.insert(ToolCollider {
+        rect,
+        order,
+        enabled,
+    });
+    world.dependency(collider, widget);
+
+    world.observer(widget, move |&WidgetRectangle(rect), world| {
+        world.fetch_mut(collider).unwrap().rect = rect;
+    });
+
+    world.observer(widget, move |&WidgetEnabled(enabled), world| {
+        world.fetch_mut(collider).unwrap().enabled = enabled;
+    });
+
+    world.observer(collider, move |event: &PointerHover, world| {
+        match event.status {
+            PointerHoverStatus::Enter => {
+                world.trigger(widget, &WidgetHover::HoverEnter);
+            }
+            PointerHoverStatus::Leave => {
+                world.trigger(widget, &WidgetHover::HoverLeave);
+            }
+            PointerHoverStatus::Moving => {}
+        }
+    });
+
+    collider
+}
+
+/// `value` snapped to `step` from `min` if `step` is positive, and kept in `min..=max`.
+fn snap(value: f32, min: f32, max: f32, step: f32) -> f32 {
+    let value = match step > 0.0 {
+        true => min + ((value - min) / step).round() * step,
+        false => value,
+    };
+    value.clamp(min, max)
+}
+
+#[cfg(test)]
+mod test {
+    use super::snap;
+
+    #[test]
+    fn snap_to_step() {
+        assert_eq!(snap(0.26, 0.0, 1.0, 0.25), 0.25);
+        assert_eq!(snap(0.4, 0.0, 1.0, 0.25), 0.5);
+        // counted from min, not from zero
+        assert_eq!(snap(6.2, 1.0, 10.0, 2.0), 7.0);
+        assert_eq!(snap(8.0, 1.0, 10.0, 2.0), 9.0);
+    }
+
+    #[test]
+    fn snap_within_range() {
+        assert_eq!(snap(0.3, 0.0, 1.0, 0.0), 0.3);
+        assert_eq!(snap(12.0, 0.0, 10.0, 0.0), 10.0);
+        assert_eq!(snap(-1.0, 0.0, 10.0, 0.0), 0.0);
+        // the last step may be beyond max
+        assert_eq!(snap(9.9, 0.0, 10.0, 4.0), 8.0);
+        assert_eq!(snap(10.0, 0.0, 10.0, 4.0), 10.0);
+    }
+}
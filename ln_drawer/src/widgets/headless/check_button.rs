@@ -0,0 +1,75 @@
+use ln_world::{Element, Handle, World};
+
+use crate::{
+    measures::Rectangle,
+    tools::pointer::{PointerHit, PointerHitStatus},
+    widgets::{
+        WidgetButton, WidgetClick, WidgetEnabled, WidgetPropertyChange, WidgetRectangle,
+        headless::attach_collider,
+    },
+};
+
+/// A button holding a checked state. Releasing the pointer over it sends [`WidgetClick`],
+/// then switches the state and sends [`Switch`].
+pub struct CheckButton {
+    pub rect: Rectangle,
+    pub order: isize,
+    pub enabled: bool,
+    pub checked: bool,
+}
+
+/// Sent with the state just switched to.
+pub struct Switch(pub bool);
+
+impl Default for CheckButton {
+    fn default() -> Self {
+        Self {
+            rect: Rectangle::new(0, 0, 24, 24),
+            order: 10,
+            enabled: true,
+            checked: false,
+        }
+    }
+}
+
+impl Element for CheckButton {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let collider = attach_collider(world, this.untyped(), self.rect, self.order, self.enabled);
+
+        world.observer(collider, move |event: &PointerHit, world| {
+            match event.status {
+                PointerHitStatus::Press => {
+                    world.trigger(this, &WidgetButton::ButtonPress);
+                }
+                PointerHitStatus::Moving => {}
+                PointerHitStatus::Release => {
+                    world.trigger(this, &WidgetButton::ButtonRelease);
+
+                    let rect = world.fetch(this).unwrap().rect;
+                    if !event.position.floor().within(rect) {
+                        return;
+                    }
+
+                    world.trigger(this, &WidgetClick);
+                    let mut button = world.fetch_mut(this).unwrap();
+                    button.checked = !button.checked;
+                    let checked = button.checked;
+                    drop(button);
+                    world.trigger(this, &Switch(checked));
+                }
+            }
+        });
+
+        world.observer(this, move |&WidgetRectangle(rect), world| {
+            world.fetch_mut(this).unwrap().rect = rect;
+        });
+
+        world.observer(this, move |&WidgetEnabled(enabled), world| {
+            world.fetch_mut(this).unwrap().enabled = enabled;
+        });
+    }
+
+    fn when_modify(&mut self, world: &World, this: Handle<Self>) {
+        world.queue_trigger(this, WidgetPropertyChange);
+    }
+}
@@ -0,0 +1,150 @@
+use ln_world::{Element, Handle, World};
+
+use crate::{
+    measures::Rectangle,
+    tools::pointer::{PointerHit, PointerHitStatus},
+    widgets::{
+        WidgetButton, WidgetEnabled, WidgetPropertyChange, WidgetRectangle,
+        headless::{attach_collider, snap},
+    },
+};
+
+/// Pixels of vertical drag for one step.
+const SCROLL_DISTANCE: f64 = 8.0;
+
+/// A number stepped by clicking its upper or lower half, or by dragging up and down. Sends
+/// [`Scroll`] with the steps, then takes them.
+pub struct NumberScroll {
+    pub rect: Rectangle,
+    pub order: isize,
+    pub enabled: bool,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+}
+
+/// Sent with the number of steps scrolled, positive upwards.
+pub struct Scroll(pub i32);
+
+impl NumberScroll {
+    /// The value `steps` away from the current one, kept in range.
+    pub fn scrolled(&self, steps: i32) -> f32 {
+        let value = self.value + steps as f32 * self.step;
+        snap(value, self.min, self.max, self.step)
+    }
+}
+
+impl Default for NumberScroll {
+    fn default() -> Self {
+        Self {
+            rect: Rectangle::new(0, 0, 64, 24),
+            order: 10,
+            enabled: true,
+            value: 0.0,
+            min: 0.0,
+            max: 100.0,
+            step: 1.0,
+        }
+    }
+}
+
+impl Element for NumberScroll {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let collider = attach_collider(world, this.untyped(), self.rect, self.order, self.enabled);
+
+        // the pointer when pressed, the steps sent since and whether it was dragged
+        let mut start = None;
+        world.observer(collider, move |event: &PointerHit, world| {
+            let y = event.position.y.into_f64();
+
+            let steps = match event.status {
+                PointerHitStatus::Press => {
+                    world.trigger(this, &WidgetButton::ButtonPress);
+                    start = Some((y, 0, false));
+                    return;
+                }
+                PointerHitStatus::Moving => {
+                    let Some((from, sent, dragged)) = &mut start else {
+                        return;
+                    };
+
+                    let steps = ((y - *from) / SCROLL_DISTANCE).trunc() as i32;
+                    let steps = steps - std::mem::replace(sent, steps);
+                    if steps == 0 {
+                        return;
+                    }
+                    *dragged = true;
+                    steps
+                }
+                PointerHitStatus::Release => {
+                    world.trigger(this, &WidgetButton::ButtonRelease);
+
+                    // a click without dragging steps once
+                    let Some((_, _, false)) = start.take() else {
+                        return;
+                    };
+
+                    let rect = world.fetch(this).unwrap().rect;
+                    if !event.position.floor().within(rect) {
+                        return;
+                    }
+
+                    let middle = (rect.down() as f64 + rect.up() as f64) / 2.0;
+                    match y >= middle {
+                        true => 1,
+                        false => -1,
+                    }
+                }
+            };
+
+            let mut scroll = world.fetch_mut(this).unwrap();
+            scroll.value = scroll.scrolled(steps);
+            drop(scroll);
+            world.trigger(this, &Scroll(steps));
+        });
+
+        world.observer(this, move |&WidgetRectangle(rect), world| {
+            world.fetch_mut(this).unwrap().rect = rect;
+        });
+
+        world.observer(this, move |&WidgetEnabled(enabled), world| {
+            world.fetch_mut(this).unwrap().enabled = enabled;
+        });
+    }
+
+    fn when_modify(&mut self, world: &World, this: Handle<Self>) {
+        world.queue_trigger(this, WidgetPropertyChange);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scrolled() {
+        let scroll = NumberScroll {
+            value: 4.0,
+            min: 0.0,
+            max: 10.0,
+            step: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(scroll.scrolled(1), 6.0);
+        assert_eq!(scroll.scrolled(-2), 0.0);
+        assert_eq!(scroll.scrolled(-5), 0.0);
+        assert_eq!(scroll.scrolled(9), 10.0);
+    }
+
+    #[test]
+    fn scrolled_back_on_step() {
+        let scroll = NumberScroll {
+            value: 0.3,
+            step: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(scroll.scrolled(1), 1.0);
+        assert_eq!(scroll.scrolled(0), 0.5);
+    }
+}
@@ -0,0 +1,285 @@
+use ln_world::{Element, Handle, World};
+
+use crate::{
+    measures::Rectangle,
+    tools::pointer::{PointerHit, PointerHitStatus},
+    widgets::{
+        WidgetButton, WidgetEnabled, WidgetPropertyChange, WidgetRectangle,
+        headless::{attach_collider, snap},
+    },
+};
+
+/// A knob sliding along a horizontal track. Dragging the knob sends [`Slide`], pressing the
+/// track elsewhere sends [`Leap`] and keeps sliding from there. It takes the values sent.
+pub struct Slider {
+    pub rect: Rectangle,
+    pub order: isize,
+    pub enabled: bool,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    /// Values are snapped to it from [`Slider::min`], `0.0` to not snap.
+    pub step: f32,
+    /// Width of the knob, which stays within the rectangle.
+    pub knob: u32,
+}
+
+/// A slider without knob, the whole of it is dragged. Sends [`Slide`] only, moving the
+/// value by the part of its width the pointer moved.
+pub struct WideSlider {
+    pub rect: Rectangle,
+    pub order: isize,
+    pub enabled: bool,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    /// Values are snapped to it from [`WideSlider::min`], `0.0` to not snap.
+    pub step: f32,
+}
+
+/// Sent with the value slid to.
+pub struct Slide(pub f32);
+
+/// Sent with the value jumped to, when the track is pressed away from the knob.
+pub struct Leap(pub f32);
+
+impl Slider {
+    /// The knob, in the same space as [`Slider::rect`].
+    pub fn knob_rect(&self) -> Rectangle {
+        let knob = self.knob.min(self.rect.width());
+        let travel = (self.rect.width() - knob) as f32;
+        let left = self.rect.left() + (travel * self.fraction()).round() as i32;
+        Rectangle::new(left, self.rect.down(), left + knob as i32, self.rect.up())
+    }
+
+    /// Where the value is between [`Slider::min`] and [`Slider::max`], from `0.0` to `1.0`.
+    pub fn fraction(&self) -> f32 {
+        fraction(self.value, self.min, self.max)
+    }
+
+    /// The value whose knob is centered at `x`.
+    fn value_at(&self, x: f64) -> f32 {
+        let knob = self.knob.min(self.rect.width());
+        let travel = (self.rect.width() - knob).max(1) as f64;
+        let start = self.rect.left() as f64 + knob as f64 / 2.0;
+        let fraction = ((x - start) / travel) as f32;
+        let value = self.min + (self.max - self.min) * fraction;
+        snap(value, self.min, self.max, self.step)
+    }
+}
+
+impl WideSlider {
+    /// Where the value is between [`WideSlider::min`] and [`WideSlider::max`], from `0.0`
+    /// to `1.0`.
+    pub fn fraction(&self) -> f32 {
+        fraction(self.value, self.min, self.max)
+    }
+}
+
+impl Default for Slider {
+    fn default() -> Self {
+        Self {
+            rect: Rectangle::new(0, 0, 160, 24),
+            order: 10,
+            enabled: true,
+            value: 0.0,
+            min: 0.0,
+            max: 1.0,
+            step: 0.0,
+            knob: 12,
+        }
+    }
+}
+
+impl Default for WideSlider {
+    fn default() -> Self {
+        Self {
+            rect: Rectangle::new(0, 0, 160, 24),
+            order: 10,
+            enabled: true,
+            value: 0.0,
+            min: 0.0,
+            max: 1.0,
+            step: 0.0,
+        }
+    }
+}
+
+impl Element for Slider {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let collider = attach_collider(world, this.untyped(), self.rect, self.order, self.enabled);
+
+        // from the pointer to the center of the knob, while sliding
+        let mut grab = None;
+        world.observer(collider, move |event: &PointerHit, world| {
+            let x = event.position.x.into_f64();
+
+            match event.status {
+                PointerHitStatus::Press => {
+                    world.trigger(this, &WidgetButton::ButtonPress);
+
+                    let mut slider = world.fetch_mut(this).unwrap();
+                    let knob = slider.knob_rect();
+                    if event.position.floor().within(knob) {
+                        grab = Some((knob.left() as f64 + knob.right() as f64) / 2.0 - x);
+                        return;
+                    }
+
+                    grab = Some(0.0);
+                    slider.value = slider.value_at(x);
+                    let value = slider.value;
+                    drop(slider);
+                    world.trigger(this, &Leap(value));
+                }
+                PointerHitStatus::Moving => {
+                    let Some(grab) = grab else {
+                        return;
+                    };
+
+                    let mut slider = world.fetch_mut(this).unwrap();
+                    let value = slider.value_at(x + grab);
+                    if value != slider.value {
+                        slider.value = value;
+                        drop(slider);
+                        world.trigger(this, &Slide(value));
+                    }
+                }
+                PointerHitStatus::Release => {
+                    grab = None;
+                    world.trigger(this, &WidgetButton::ButtonRelease);
+                }
+            }
+        });
+
+        world.observer(this, move |&WidgetRectangle(rect), world| {
+            world.fetch_mut(this).unwrap().rect = rect;
+        });
+
+        world.observer(this, move |&WidgetEnabled(enabled), world| {
+            world.fetch_mut(this).unwrap().enabled = enabled;
+        });
+    }
+
+    fn when_modify(&mut self, world: &World, this: Handle<Self>) {
+        world.queue_trigger(this, WidgetPropertyChange);
+    }
+}
+
+impl Element for WideSlider {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let collider = attach_collider(world, this.untyped(), self.rect, self.order, self.enabled);
+
+        // the pointer and the value when pressed
+        let mut start = None;
+        world.observer(collider, move |event: &PointerHit, world| {
+            let x = event.position.x.into_f64();
+
+            match event.status {
+                PointerHitStatus::Press => {
+                    world.trigger(this, &WidgetButton::ButtonPress);
+                    start = Some((x, world.fetch(this).unwrap().value));
+                }
+                PointerHitStatus::Moving => {
+                    let Some((from, value)) = start else {
+                        return;
+                    };
+
+                    let mut slider = world.fetch_mut(this).unwrap();
+                    let moved = ((x - from) / slider.rect.width().max(1) as f64) as f32;
+                    let value = value + (slider.max - slider.min) * moved;
+                    let value = snap(value, slider.min, slider.max, slider.step);
+                    if value != slider.value {
+                        slider.value = value;
+                        drop(slider);
+                        world.trigger(this, &Slide(value));
+                    }
+                }
+                PointerHitStatus::Release => {
+                    start = None;
+                    world.trigger(this, &WidgetButton::ButtonRelease);
+                }
+            }
+        });
+
+        world.observer(this, move |&WidgetRectangle(rect), world| {
+            world.fetch_mut(this).unwrap().rect = rect;
+        });
+
+        world.observer(this, move |&WidgetEnabled(enabled), world| {
+            world.fetch_mut(this).unwrap().enabled = enabled;
+        });
+    }
+
+    fn when_modify(&mut self, world: &World, this: Handle<Self>) {
+        world.queue_trigger(this, WidgetPropertyChange);
+    }
+}
+
+fn fraction(value: f32, min: f32, max: f32) -> f32 {
+    match max > min {
+        true => ((value - min) / (max - min)).clamp(0.0, 1.0),
+        false => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fractions() {
+        let slider = |value| Slider {
+            value,
+            min: -1.0,
+            max: 3.0,
+            ..Default::default()
+        };
+        assert_eq!(slider(1.0).fraction(), 0.5);
+        assert_eq!(slider(-4.0).fraction(), 0.0);
+        assert_eq!(slider(5.0).fraction(), 1.0);
+
+        let empty = WideSlider {
+            value: 2.0,
+            min: 2.0,
+            max: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(empty.fraction(), 0.0);
+    }
+
+    #[test]
+    fn value_at() {
+        // the knob center travels from 6 to 154
+        let slider = Slider::default();
+        assert_eq!(slider.value_at(6.0), 0.0);
+        assert_eq!(slider.value_at(80.0), 0.5);
+        assert_eq!(slider.value_at(154.0), 1.0);
+        assert_eq!(slider.value_at(-100.0), 0.0);
+        assert_eq!(slider.value_at(500.0), 1.0);
+
+        let stepped = Slider {
+            step: 0.25,
+            ..Default::default()
+        };
+        assert_eq!(stepped.value_at(50.0), 0.25);
+        assert_eq!(stepped.value_at(110.0), 0.75);
+    }
+
+    #[test]
+    fn knob_follows_value() {
+        let slider = Slider {
+            value: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(slider.knob_rect(), Rectangle::new(74, 0, 86, 24));
+        assert_eq!(slider.value_at(80.0), slider.value);
+
+        // a knob wider than the slider fills it and does not move
+        let wide = Slider {
+            value: 0.5,
+            knob: 400,
+            ..Default::default()
+        };
+        assert_eq!(wide.knob_rect(), wide.rect);
+    }
+}
@@ -0,0 +1,437 @@
+use std::{any::type_name, fmt::Write, ops::Range, time::Duration};
+
+use cosmic_text::Metrics;
+use hashbrown::HashSet;
+use ln_world::{BorrowState, Element, Handle, InspectElement, Inspection, World};
+use palette::Srgba;
+use winit::{
+    event::WindowEvent,
+    keyboard::{Key, NamedKey},
+};
+
+use crate::{
+    lnwin::Lnwindow,
+    measures::{Position, Rectangle, Size},
+    render::{RenderControl, rounded::RoundedRectDescriptor, text::TextDescriptor},
+    tools::{
+        collider::ToolCollider,
+        pointer::{PointerHit, PointerHitStatus},
+        timer::{Timer, TimerHit},
+    },
+    widgets::WidgetRectangle,
+};
+
+const INSPECTOR_ORDER: isize = 1000;
+const INSPECTOR_WIDTH: i32 = 440;
+const INSPECTOR_MARGIN: i32 = 20;
+const LINE_HEIGHT: i32 = 16;
+
+/// Debug overlay listing the live element graph of the world, toggled by `F12` and
+/// scrolled by `PageUp`, `PageDown`, `Home` and `End`.
+///
+/// Should be inserted into the UI camera. Clicking a collider line highlights the
+/// rectangle of that [`ToolCollider`] in its own camera.
+pub struct Inspector {
+    pub enabled: bool,
+    pub refresh_period: Duration,
+
+    screen: Rectangle,
+    selected: Option<Handle>,
+    page: Option<Handle<()>>,
+    /// Lines scrolled past below the header, and how many fit in the panel.
+    scroll: usize,
+    capacity: usize,
+}
+
+/// A line of text, clickable if it is about a collider.
+type InspectorLine = (String, Option<Handle>);
+
+impl Default for Inspector {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_period: Duration::from_secs(1),
+            screen: Rectangle::default(),
+            selected: None,
+            page: None,
+            scroll: 0,
+            capacity: 0,
+        }
+    }
+}
+
+impl Element for Inspector {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
+
+        let size = world.fetch(lnwindow).unwrap().window.surface_size();
+        self.screen =
+            Rectangle::new_half(Position::ZERO, Size::new(size.width / 2, size.height / 2));
+
+        world.observer(lnwindow, move |event: &WindowEvent, world| {
+            let WindowEvent::KeyboardInput { event, .. } = event else {
+                return;
+            };
+
+            if !event.state.is_pressed() {
+                return;
+            }
+
+            let mut inspector = world.fetch_mut(this).unwrap();
+            let page = inspector.capacity.saturating_sub(1).max(1);
+            match &event.logical_key {
+                Key::Named(NamedKey::F12) if !event.repeat => {
+                    inspector.enabled = !inspector.enabled;
+                }
+                Key::Named(NamedKey::PageDown) if inspector.enabled => inspector.scroll += page,
+                Key::Named(NamedKey::PageUp) if inspector.enabled => {
+                    inspector.scroll = inspector.scroll.saturating_sub(page);
+                }
+                Key::Named(NamedKey::Home) if inspector.enabled => inspector.scroll = 0,
+                // clamped on refresh
+                Key::Named(NamedKey::End) if inspector.enabled => inspector.scroll = usize::MAX,
+                _ => return,
+            }
+            Inspector::request_refresh(world, this);
+        });
+
+        world.observer(lnwindow, move |&WidgetRectangle(rect), world| {
+            let mut inspector = world.fetch_mut(this).unwrap();
+            inspector.screen = rect;
+            if inspector.enabled {
+                Inspector::request_refresh(world, this);
+            }
+        });
+
+        let timer = world.insert(Timer::new(self.refresh_period));
+        world.observer(timer, move |TimerHit, world| {
+            if world.fetch(this).unwrap().enabled {
+                Inspector::request_refresh(world, this);
+            }
+        });
+
+        world.dependency(timer, this);
+    }
+}
+
+impl Inspector {
+    /// Rebuilding removes colliders whose observers may be running, so it is always queued.
+    pub fn request_refresh(world: &World, this: Handle<Self>) {
+        world.queue(move |world| {
+            let Ok(mut inspector) = world.fetch_mut(this) else {
+                return;
+            };
+
+            let page = inspector.page.take();
+            drop(inspector);
+            if let Some(page) = page {
+                let _ = world.remove(page);
+            }
+
+            // not borrowed while inspected, or the inspector would always list itself
+            let inspection = world.inspect();
+            if let Ok(mut inspector) = world.fetch_mut(this) {
+                inspector.refresh(world, this, &inspection);
+            }
+        });
+    }
+
+    fn refresh(&mut self, world: &World, this: Handle<Self>, inspection: &Inspection) {
+        if !self.enabled {
+            RenderControl::redraw(world);
+            return;
+        }
+
+        let page = world.insert(());
+        world.dependency(page, this);
+        self.page = Some(page);
+
+        let panel = Rectangle::new(
+            self.screen.right() - INSPECTOR_WIDTH - INSPECTOR_MARGIN,
+            self.screen.down() + INSPECTOR_MARGIN,
+            self.screen.right() - INSPECTOR_MARGIN,
+            self.screen.up() - INSPECTOR_MARGIN,
+        );
+
+        let background = world.build(RoundedRectDescriptor {
+            rect: panel,
+            color: Srgba::new(0.1, 0.1, 0.12, 0.85),
+            order: INSPECTOR_ORDER,
+            ..Default::default()
+        });
+        world.dependency(background, page);
+
+        let capacity = (panel.height() as i32 - 2 * LINE_HEIGHT) / LINE_HEIGHT;
+        self.capacity = capacity.max(0) as usize;
+
+        let mut lines = self.lines(inspection);
+        let shown = scrolled(lines.len(), self.capacity, self.scroll);
+        self.scroll = shown.start - 1;
+        if shown.len() + 1 < lines.len() {
+            let _ = write!(
+                lines[0].0,
+                " {}-{} of {}",
+                shown.start,
+                shown.end - 1,
+                lines.len() - 1
+            );
+        }
+        let line_rect = Rectangle::new(
+            panel.left() + LINE_HEIGHT / 2,
+            panel.up() - LINE_HEIGHT * 2,
+            panel.right() - LINE_HEIGHT / 2,
+            panel.up() - LINE_HEIGHT,
+        );
+
+        // the header stays on top
+        let header = lines.iter().take(self.capacity.min(1));
+        for (idx, (text, collider)) in header.chain(&lines[shown]).enumerate() {
+            let rect = line_rect.pad_down(0, idx);
+            let text = world.build(TextDescriptor {
+                text,
+                spans: &[],
+                rect,
+                metrics: Metrics::new(13.0, LINE_HEIGHT as f32),
+                order: INSPECTOR_ORDER + 1,
+                visible: true,
+                clip: Some(panel),
+            });
+            world.dependency(text, page);
+
+            if let Some(selected) = *collider {
+                let hit = world.insert(ToolCollider {
+                    rect,
+                    order: INSPECTOR_ORDER,
+                    enabled: true,
+                });
+                world.dependency(hit, page);
+
+                world.observer(hit, move |event: &PointerHit, world| {
+                    if event.status != PointerHitStatus::Release {
+                        return;
+                    }
+
+                    let mut inspector = world.fetch_mut(this).unwrap();
+                    inspector.selected = match inspector.selected == Some(selected) {
+                        true => None,
+                        false => Some(selected),
+                    };
+                    Inspector::request_refresh(world, this);
+                });
+            }
+        }
+
+        if let Some(selected) = self.selected.and_then(|x| inspection.element(x)) {
+            let view = selected.view;
+            let mut rect = None;
+            world.enter(view, || {
+                world.foreach_fetch::<ToolCollider>(|collider| {
+                    if collider.handle().untyped() == selected.handle {
+                        rect = Some(collider.rect);
+                    }
+                });
+            });
+
+            if let Some(rect) = rect {
+                let highlight = world.enter(view, || {
+                    world.build(RoundedRectDescriptor {
+                        rect,
+                        color: Srgba::new(1.0, 0.3, 0.2, 0.35),
+                        shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
+                        shrink: 0.0,
+                        value: 0.0,
+                        order: INSPECTOR_ORDER + 2,
+                        ..Default::default()
+                    })
+                });
+                world.dependency(highlight, page);
+            }
+        } else {
+            self.selected = None;
+        }
+
+        RenderControl::redraw(world);
+    }
+
+    fn lines(&self, inspection: &Inspection) -> Vec<InspectorLine> {
+        let mut lines = Vec::new();
+        let mut push = |line: String| lines.push((line, None));
+
+        push(format!(
+            "{} elements, {} types, {} views [F12]",
+            inspection.elements.len(),
+            inspection.types.len(),
+            inspection.views.len(),
+        ));
+
+        push("# views".into());
+        for view in &inspection.views {
+            let mut line = format!(
+                "{} {} ({})",
+                view.handle,
+                short_name(view.type_name),
+                view.elements
+            );
+            for refs in &view.refs {
+                let _ = write!(line, " -> {refs}");
+            }
+            push(line);
+        }
+
+        if let Some(selected) = self.selected.and_then(|x| inspection.element(x)) {
+            push(format!("# selected {}", selected.handle));
+            for &parent in &selected.parents {
+                push(format!("  parent {}", describe(inspection, parent)));
+            }
+            for observers in inspection.observers.iter() {
+                if observers.target == selected.handle {
+                    push(format!(
+                        "  on {} x{}",
+                        short_name(observers.event),
+                        observers.count
+                    ));
+                }
+            }
+        }
+
+        push("# borrowed".into());
+        for borrow in &inspection.borrowed {
+            push(match borrow.state {
+                BorrowState::Shared(cnt) => {
+                    format!("{} &x{cnt}", describe(inspection, borrow.handle))
+                }
+                BorrowState::Mutable => format!("{} &mut", describe(inspection, borrow.handle)),
+            });
+        }
+
+        push("# colliders".into());
+        let colliders = (inspection.elements.iter())
+            .filter(|elem| elem.type_name == type_name::<ToolCollider>());
+        for collider in colliders {
+            lines.push((
+                format!(
+                    "{} {} in {} of {}",
+                    match self.selected == Some(collider.handle) {
+                        true => '>',
+                        false => ' ',
+                    },
+                    collider.handle,
+                    collider.view,
+                    owners(inspection, collider),
+                ),
+                Some(collider.handle),
+            ));
+        }
+
+        lines.push(("# observers".into(), None));
+        for observers in &inspection.observers {
+            lines.push((
+                format!(
+                    "{} on {} x{}",
+                    describe(inspection, observers.target),
+                    short_name(observers.event),
+                    observers.count
+                ),
+                None,
+            ));
+        }
+
+        lines.push(("# dependencies".into(), None));
+        let mut visited = HashSet::new();
+        for root in inspection.roots().filter(|x| !x.children.is_empty()) {
+            tree(inspection, root, 0, &mut visited, &mut lines);
+        }
+
+        lines.push(("# types".into(), None));
+        let mut types = inspection.types.clone();
+        types.sort_by(|a, b| b.1.cmp(&a.1));
+        for (name, cnt) in types {
+            lines.push((format!("{} x{cnt}", short_name(name)), None));
+        }
+
+        lines
+    }
+}
+
+/// The lines shown below the header of `len` lines, scrolled down by `scroll` but never
+/// past the last page.
+fn scrolled(len: usize, capacity: usize, scroll: usize) -> Range<usize> {
+    let shown = capacity.saturating_sub(1);
+    let body = len.saturating_sub(1);
+    let start = 1 + scroll.min(body.saturating_sub(shown));
+    start..(start + shown).min(len).max(start)
+}
+
+/// The element and everything depending on it, indented by depth. Colliders are clickable.
+fn tree(
+    inspection: &Inspection,
+    elem: &InspectElement,
+    depth: usize,
+    visited: &mut HashSet<Handle>,
+    lines: &mut Vec<InspectorLine>,
+) {
+    let indent = depth * 2;
+    if !visited.insert(elem.handle) {
+        lines.push((format!("{:indent$}{} (loop)", "", elem.handle), None));
+        return;
+    }
+
+    let collider = elem.type_name == type_name::<ToolCollider>();
+    lines.push((
+        format!("{:indent$}{}", "", describe(inspection, elem.handle)),
+        collider.then_some(elem.handle),
+    ));
+    for &child in &elem.children {
+        if let Some(child) = inspection.element(child) {
+            tree(inspection, child, depth + 1, visited, lines);
+        }
+    }
+}
+
+fn describe(inspection: &Inspection, handle: Handle) -> String {
+    match inspection.element(handle) {
+        Some(elem) => format!("{} {}", handle, short_name(elem.type_name)),
+        None => format!("{handle} ?"),
+    }
+}
+
+fn owners(inspection: &Inspection, elem: &InspectElement) -> String {
+    let mut buf = String::new();
+    for &parent in &elem.parents {
+        if !buf.is_empty() {
+            buf.push_str(", ");
+        }
+        buf.push_str(&describe(inspection, parent));
+    }
+    buf
+}
+
+/// Strip the module path, but keep generic parameters as they are.
+fn short_name(name: &str) -> &str {
+    let end = name.find('<').unwrap_or(name.len());
+    let start = name[..end].rfind("::").map_or(0, |x| x + 2);
+    &name[start..]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scrolling() {
+        // everything fits
+        assert_eq!(scrolled(5, 10, 0), 1..5);
+        assert_eq!(scrolled(5, 10, 3), 1..5);
+
+        // a page of 3 lines below the header
+        assert_eq!(scrolled(10, 4, 0), 1..4);
+        assert_eq!(scrolled(10, 4, 3), 4..7);
+        assert_eq!(scrolled(10, 4, usize::MAX), 7..10);
+
+        // not even the header fits
+        assert!(scrolled(10, 0, 2).is_empty());
+        assert_eq!(scrolled(0, 4, 0), 1..1);
+    }
+}
@@ -0,0 +1,405 @@
+use ln_world::{Descriptor, Element, Handle, World};
+
+use crate::{
+    animation::{Animation, AnimationDescriptor, AnimationValue},
+    layout::WritingDirection,
+    measures::{Rectangle, Size},
+    tools::{
+        collider::ToolCollider,
+        pointer::{PointerHit, PointerHitStatus, PointerHover, PointerHoverStatus},
+    },
+    widgets::{
+        WidgetAnimatedRectangle, WidgetButton, WidgetClick, WidgetDirection, WidgetEnabled,
+        WidgetExpanded, WidgetHover, WidgetRectangle, WidgetSelect,
+    },
+};
+
+const MENU_ANIM_FACTOR: f32 = 50.0;
+
+/// How the entries of a [`Menu`] are tiled once expanded, rows run from the start to the
+/// end of the writing direction.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MenuLayout {
+    /// In a row after the end of the menu.
+    Row,
+    /// In a column below the menu.
+    Column,
+    /// In rows of `columns` below the menu.
+    Grid { columns: u32 },
+}
+
+/// Entries spread across the canvas around the menu rectangle instead of scrolling in a
+/// list. Send [`WidgetExpanded`] to it to expand or collapse, the entries fly between its
+/// rectangle and their tiles.
+///
+/// Clicking an entry sends [`WidgetClick`] to the entry, then [`WidgetSelect`] with its
+/// index to the menu. Entries draw nothing, follow them with a [`Transform`] to show
+/// something. Send [`WidgetDirection`] to it to tile the other way.
+///
+/// [`Transform`]: crate::layout::transform::Transform
+pub struct Menu {
+    pub rect: Rectangle,
+    pub layout: MenuLayout,
+    pub tile: Size,
+    pub gap: i32,
+    pub order: isize,
+    pub collapse_on_select: bool,
+    pub expanded: bool,
+    direction: WritingDirection,
+    entries: Vec<Handle<MenuEntry>>,
+}
+
+pub struct MenuDescriptor {
+    pub rect: Rectangle,
+    pub layout: MenuLayout,
+    pub tile: Size,
+    pub gap: i32,
+    pub order: isize,
+    pub collapse_on_select: bool,
+}
+
+/// A tile of a [`Menu`], which is registered when built and removed along with the menu,
+/// so no entry is left out of a menu.
+pub struct MenuEntry {
+    pub menu: Handle<Menu>,
+    pub rect: Rectangle,
+    pub order: isize,
+    expanded: bool,
+    target: Rectangle,
+    anim: Handle<Animation<[f32; 4]>>,
+}
+
+pub struct MenuEntryDescriptor {
+    pub menu: Handle<Menu>,
+    pub order: isize,
+}
+
+impl Menu {
+    /// The tile of the entry at `index` when expanded.
+    pub fn tile_rect(&self, index: usize) -> Rectangle {
+        let index = index as i32;
+        let (column, row) = match self.layout {
+            MenuLayout::Row => (index, 0),
+            MenuLayout::Column => (0, index),
+            MenuLayout::Grid { columns } => {
+                let columns = columns.max(1) as i32;
+                (index % columns, index / columns)
+            }
+        };
+
+        let (w, h) = (self.tile.w as i32, self.tile.h as i32);
+        let (left, up) = match self.layout {
+            MenuLayout::Row => (self.rect.right() + self.gap, self.rect.up()),
+            MenuLayout::Column | MenuLayout::Grid { .. } => {
+                (self.rect.left(), self.rect.down() - self.gap)
+            }
+        };
+
+        let left = left + column * (w + self.gap);
+        let up = up - row * (h + self.gap);
+        let right = left + w;
+
+        // mirrored around the menu
+        let (left, right) = match self.direction {
+            WritingDirection::LeftToRight => (left, right),
+            WritingDirection::RightToLeft => {
+                let mirror = self.rect.left() + self.rect.right();
+                (mirror - right, mirror - left)
+            }
+        };
+        Rectangle::new(left, up - h, right, up)
+    }
+
+    pub fn entries(&self) -> &[Handle<MenuEntry>] {
+        &self.entries
+    }
+
+    /// Where the entry at `index` goes now.
+    fn target(&self, index: usize) -> Rectangle {
+        match self.expanded {
+            true => self.tile_rect(index),
+            false => self.rect,
+        }
+    }
+
+    fn layout(&mut self, world: &World) {
+        self.entries.retain(|entry| world.validate(*entry).is_ok());
+        for (index, &entry) in self.entries.iter().enumerate() {
+            world.trigger(entry, &WidgetAnimatedRectangle(self.target(index)));
+        }
+    }
+
+    fn request_layout(world: &World, this: Handle<Self>) {
+        world.queue(move |world| {
+            if let Ok(mut menu) = world.fetch_mut(this) {
+                menu.layout(world);
+            }
+        });
+    }
+}
+
+impl Default for MenuDescriptor {
+    fn default() -> Self {
+        Self {
+            rect: Rectangle::new(0, 0, 50, 50),
+            layout: MenuLayout::Row,
+            tile: Size::splat(50),
+            gap: 8,
+            order: 20,
+            collapse_on_select: true,
+        }
+    }
+}
+
+impl Descriptor for MenuDescriptor {
+    type Target = Handle<Menu>;
+
+    fn when_build(self, world: &World) -> Self::Target {
+        world.insert(Menu {
+            rect: self.rect,
+            layout: self.layout,
+            tile: self.tile,
+            gap: self.gap,
+            order: self.order,
+            collapse_on_select: self.collapse_on_select,
+            expanded: false,
+            direction: WritingDirection::default(),
+            entries: Vec::new(),
+        })
+    }
+}
+
+impl Element for Menu {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        world.observer(this, move |&WidgetExpanded(expanded), world| {
+            let mut menu = world.fetch_mut(this).unwrap();
+            if menu.expanded == expanded {
+                return;
+            }
+
+            menu.expanded = expanded;
+            for &entry in &menu.entries {
+                world.trigger(entry, &WidgetExpanded(expanded));
+            }
+            menu.layout(world);
+        });
+
+        world.observer(this, move |&WidgetRectangle(rect), world| {
+            world.fetch_mut(this).unwrap().rect = rect;
+            Menu::request_layout(world, this);
+        });
+
+        world.observer(this, move |&WidgetDirection(direction), world| {
+            world.fetch_mut(this).unwrap().direction = direction;
+            Menu::request_layout(world, this);
+        });
+    }
+}
+
+impl Descriptor for MenuEntryDescriptor {
+    type Target = Handle<MenuEntry>;
+
+    fn when_build(self, world: &World) -> Self::Target {
+        let menu = self.menu;
+        let anim = world.build(AnimationDescriptor::new([0.0; 4], MENU_ANIM_FACTOR));
+
+        let entry = world.insert(MenuEntry {
+            menu,
+            rect: Rectangle::default(),
+            order: self.order,
+            expanded: false,
+            target: Rectangle::default(),
+            anim,
+        });
+        world.dependency(entry, menu);
+
+        // registered once both are in the world, flying out of the menu
+        world.queue(move |world| {
+            let Ok(mut menu) = world.fetch_mut(menu) else {
+                return;
+            };
+
+            menu.entries.push(entry);
+            world.fetch_mut(anim).unwrap().src = rect_array(menu.rect);
+            world.trigger(entry, &WidgetExpanded(menu.expanded));
+            menu.layout(world);
+        });
+
+        entry
+    }
+}
+
+impl Element for MenuEntry {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        world.dependency(self.anim, this);
+
+        let collider = world.insert(ToolCollider {
+            rect: self.rect,
+            order: self.order,
+            enabled: false,
+        });
+        world.dependency(collider, this);
+
+        let anim = self.anim;
+        world.observer(anim, move |&AnimationValue(value), world| {
+            let mut entry = world.fetch_mut(this).unwrap();
+            let rect = Rectangle::new(
+                value[0].round() as i32,
+                value[1].round() as i32,
+                value[2].round() as i32,
+                value[3].round() as i32,
+            );
+            entry.rect = rect;
+            world.fetch_mut(collider).unwrap().rect = rect;
+
+            // hidden once back into the menu
+            let hidden = !entry.expanded && rect == entry.target;
+            drop(entry);
+
+            world.trigger(this, &WidgetRectangle(rect));
+            if hidden {
+                world.trigger(this, &WidgetEnabled(false));
+            }
+        });
+
+        world.observer(this, move |&WidgetAnimatedRectangle(target), world| {
+            world.fetch_mut(this).unwrap().target = target;
+            world.fetch_mut(anim).unwrap().dst = rect_array(target);
+        });
+
+        world.observer(this, move |&WidgetExpanded(expanded), world| {
+            world.fetch_mut(this).unwrap().expanded = expanded;
+            world.fetch_mut(collider).unwrap().enabled = expanded;
+            if expanded {
+                world.trigger(this, &WidgetEnabled(true));
+            }
+        });
+
+        world.observer(collider, move |event: &PointerHover, world| {
+            match event.status {
+                PointerHoverStatus::Enter => {
+                    world.trigger(this, &WidgetHover::HoverEnter);
+                }
+                PointerHoverStatus::Leave => {
+                    world.trigger(this, &WidgetHover::HoverLeave);
+                }
+                PointerHoverStatus::Moving => {}
+            }
+        });
+
+        world.observer(collider, move |event: &PointerHit, world| {
+            match event.status {
+                PointerHitStatus::Press => {
+                    world.trigger(this, &WidgetButton::ButtonPress);
+                }
+                PointerHitStatus::Moving => {}
+                PointerHitStatus::Release => {
+                    world.trigger(this, &WidgetButton::ButtonRelease);
+
+                    let entry = world.fetch(this).unwrap();
+                    if !event.position.floor().within(entry.rect) {
+                        return;
+                    }
+                    let menu_handle = entry.menu;
+                    drop(entry);
+
+                    let menu = world.fetch(menu_handle).unwrap();
+                    let index = menu.entries.iter().position(|x| *x == this);
+                    let collapse = menu.collapse_on_select;
+                    drop(menu);
+
+                    world.trigger(this, &WidgetClick);
+                    world.trigger(menu_handle, &WidgetSelect(index.map(|x| x as i32)));
+                    if collapse {
+                        world.trigger(menu_handle, &WidgetExpanded(false));
+                    }
+                }
+            }
+        });
+    }
+
+    fn when_remove(&mut self, world: &World, _this: Handle<Self>) {
+        Menu::request_layout(world, self.menu);
+    }
+}
+
+fn rect_array(rect: Rectangle) -> [f32; 4] {
+    [
+        rect.left() as f32,
+        rect.down() as f32,
+        rect.right() as f32,
+        rect.up() as f32,
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn menu(layout: MenuLayout) -> Menu {
+        Menu {
+            rect: Rectangle::new(0, 0, 50, 50),
+            layout,
+            tile: Size::new(40, 30),
+            gap: 10,
+            order: 0,
+            collapse_on_select: true,
+            expanded: true,
+            direction: WritingDirection::LeftToRight,
+            entries: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn row_tiles() {
+        let menu = menu(MenuLayout::Row);
+        assert_eq!(menu.tile_rect(0), Rectangle::new(60, 20, 100, 50));
+        assert_eq!(menu.tile_rect(2), Rectangle::new(160, 20, 200, 50));
+    }
+
+    #[test]
+    fn column_tiles() {
+        let menu = menu(MenuLayout::Column);
+        assert_eq!(menu.tile_rect(0), Rectangle::new(0, -40, 40, -10));
+        assert_eq!(menu.tile_rect(2), Rectangle::new(0, -120, 40, -90));
+    }
+
+    #[test]
+    fn grid_tiles() {
+        let grid = menu(MenuLayout::Grid { columns: 3 });
+        assert_eq!(grid.tile_rect(0), Rectangle::new(0, -40, 40, -10));
+        assert_eq!(grid.tile_rect(2), Rectangle::new(100, -40, 140, -10));
+        assert_eq!(grid.tile_rect(4), Rectangle::new(50, -80, 90, -50));
+
+        // no columns is one column
+        let single = menu(MenuLayout::Grid { columns: 0 });
+        assert_eq!(single.tile_rect(2), Rectangle::new(0, -120, 40, -90));
+    }
+
+    #[test]
+    fn collapsed_target() {
+        let mut menu = menu(MenuLayout::Row);
+        menu.expanded = false;
+        assert_eq!(menu.target(3), menu.rect);
+    }
+
+    #[test]
+    fn mirrored_tiles() {
+        let rtl = |layout| Menu {
+            direction: WritingDirection::RightToLeft,
+            ..menu(layout)
+        };
+
+        let row = rtl(MenuLayout::Row);
+        assert_eq!(row.tile_rect(0), Rectangle::new(-50, 20, -10, 50));
+        assert_eq!(row.tile_rect(2), Rectangle::new(-150, 20, -110, 50));
+
+        // right aligned with the menu
+        let column = rtl(MenuLayout::Column);
+        assert_eq!(column.tile_rect(0), Rectangle::new(10, -40, 50, -10));
+
+        let grid = rtl(MenuLayout::Grid { columns: 3 });
+        assert_eq!(grid.tile_rect(2), Rectangle::new(-90, -40, -50, -10));
+        assert_eq!(grid.tile_rect(4), Rectangle::new(-40, -80, 0, -50));
+    }
+}
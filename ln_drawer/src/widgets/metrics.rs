@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use cosmic_text::Metrics as TextMetrics;
+use ln_world::{Element, Handle, World};
+use palette::Srgba;
+use winit::{
+    event::WindowEvent,
+    keyboard::{Key, NamedKey},
+};
+
+use crate::{
+    lnwin::Lnwindow,
+    measures::{Position, Rectangle, Size},
+    metrics::{Metrics, Rolling},
+    render::{RenderControl, rounded::RoundedRectDescriptor, text::TextDescriptor},
+    tools::timer::{Timer, TimerHit},
+    widgets::WidgetRectangle,
+};
+
+const OVERLAY_ORDER: isize = 1000;
+const OVERLAY_WIDTH: i32 = 360;
+const OVERLAY_MARGIN: i32 = 20;
+const LINE_HEIGHT: i32 = 16;
+const HISTOGRAM_BUCKET: Duration = Duration::from_millis(4);
+const HISTOGRAM_BUCKETS: usize = 8;
+const HISTOGRAM_WIDTH: usize = 24;
+const CONTROL_LINES: usize = 8;
+
+/// Frame timings and other [`Metrics`] in the top left corner, toggled by `F11`.
+///
+/// Should be inserted into the UI camera.
+pub struct MetricsOverlay {
+    pub enabled: bool,
+    pub refresh_period: Duration,
+
+    screen: Rectangle,
+    page: Option<Handle<()>>,
+}
+
+impl Default for MetricsOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_period: Duration::from_millis(500),
+            screen: Rectangle::default(),
+            page: None,
+        }
+    }
+}
+
+impl Element for MetricsOverlay {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
+
+        let size = world.fetch(lnwindow).unwrap().window.surface_size();
+        self.screen =
+            Rectangle::new_half(Position::ZERO, Size::new(size.width / 2, size.height / 2));
+
+        world.observer(lnwindow, move |event: &WindowEvent, world| {
+            let WindowEvent::KeyboardInput { event, .. } = event else {
+                return;
+            };
+
+            if event.state.is_pressed()
+                && !event.repeat
+                && event.logical_key == Key::Named(NamedKey::F11)
+            {
+                let mut overlay = world.fetch_mut(this).unwrap();
+                overlay.enabled = !overlay.enabled;
+                MetricsOverlay::request_refresh(world, this);
+            }
+        });
+
+        world.observer(lnwindow, move |&WidgetRectangle(rect), world| {
+            let mut overlay = world.fetch_mut(this).unwrap();
+            overlay.screen = rect;
+            if overlay.enabled {
+                MetricsOverlay::request_refresh(world, this);
+            }
+        });
+
+        let timer = world.insert(Timer::new(self.refresh_period));
+        world.observer(timer, move |TimerHit, world| {
+            if world.fetch(this).unwrap().enabled {
+                MetricsOverlay::request_refresh(world, this);
+            }
+        });
+
+        world.dependency(timer, this);
+    }
+}
+
+impl MetricsOverlay {
+    pub fn request_refresh(world: &World, this: Handle<Self>) {
+        world.queue(move |world| {
+            if let Ok(mut overlay) = world.fetch_mut(this) {
+                overlay.refresh(world, this);
+            }
+        });
+    }
+
+    fn refresh(&mut self, world: &World, this: Handle<Self>) {
+        if let Some(page) = self.page.take() {
+            let _ = world.remove(page);
+        }
+
+        let lines = match world.single_fetch::<Metrics>() {
+            Ok(metrics) if self.enabled => overlay_lines(&metrics),
+            _ => {
+                RenderControl::redraw(world);
+                return;
+            }
+        };
+
+        let page = world.insert(());
+        world.dependency(page, this);
+        self.page = Some(page);
+
+        let height = LINE_HEIGHT * (lines.len() as i32 + 1);
+        let panel = Rectangle::new(
+            self.screen.left() + OVERLAY_MARGIN,
+            self.screen.up() - OVERLAY_MARGIN - height,
+            self.screen.left() + OVERLAY_MARGIN + OVERLAY_WIDTH,
+            self.screen.up() - OVERLAY_MARGIN,
+        );
+
+        let background = world.build(RoundedRectDescriptor {
+            rect: panel,
+            color: Srgba::new(0.1, 0.1, 0.12, 0.85),
+            order: OVERLAY_ORDER,
+            ..Default::default()
+        });
+        world.dependency(background, page);
+
+        let line_rect = Rectangle::new(
+            panel.left() + LINE_HEIGHT / 2,
+            panel.up() - LINE_HEIGHT * 3 / 2,
+            panel.right() - LINE_HEIGHT / 2,
+            panel.up() - LINE_HEIGHT / 2,
+        );
+
+        for (idx, text) in lines.iter().enumerate() {
+            let text = world.build(TextDescriptor {
+                text,
+                spans: &[],
+                rect: line_rect.pad_down(0, idx),
+                metrics: TextMetrics::new(13.0, LINE_HEIGHT as f32),
+                order: OVERLAY_ORDER + 1,
+                visible: true,
+                clip: None,
+            });
+            world.dependency(text, page);
+        }
+
+        RenderControl::redraw(world);
+    }
+}
+
+fn overlay_lines(metrics: &Metrics) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let latest = metrics.frames.latest().unwrap_or_default();
+    lines.push(format!(
+        "frame {} ({}) [F11]",
+        ms(latest),
+        match metrics.refreshing {
+            true => "active",
+            false => "idle",
+        },
+    ));
+
+    for (name, rolling) in metrics.rollings() {
+        lines.push(summarize(name, rolling));
+    }
+
+    lines.push("# frame histogram".into());
+    let histogram = metrics
+        .frames
+        .histogram(HISTOGRAM_BUCKET, HISTOGRAM_BUCKETS);
+    let most = histogram.iter().copied().max().unwrap_or_default().max(1);
+    for (idx, &count) in histogram.iter().enumerate() {
+        let bar = "#".repeat(count * HISTOGRAM_WIDTH / most);
+        let bound = HISTOGRAM_BUCKET * (idx as u32 + 1);
+        let label = match idx + 1 == HISTOGRAM_BUCKETS {
+            true => format!(">{}", bound.as_millis() - HISTOGRAM_BUCKET.as_millis()),
+            false => format!("<{}", bound.as_millis()),
+        };
+        lines.push(format!("{label:>4}ms {bar} {count}"));
+    }
+
+    let chunks = metrics.chunks;
+    lines.push(format!(
+        "chunks {}/{}, {} queued",
+        chunks.loaded, chunks.capacity, chunks.queued
+    ));
+
+    lines.push("# controls".into());
+    for (control, timing) in metrics.sorted_controls().into_iter().take(CONTROL_LINES) {
+        lines.push(format!(
+            "{control} prepare {} draw {}",
+            ms(timing.prepare),
+            ms(timing.draw)
+        ));
+    }
+
+    lines
+}
+
+fn summarize(name: &str, rolling: &Rolling) -> String {
+    let summary = rolling.summary();
+    if summary.samples == 0 {
+        return format!("{name} -");
+    }
+
+    format!(
+        "{name} mean {} p99 {} max {}",
+        ms(summary.mean),
+        ms(summary.p99),
+        ms(summary.max)
+    )
+}
+
+fn ms(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
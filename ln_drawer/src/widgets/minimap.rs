@@ -0,0 +1,362 @@
+use std::time::Duration;
+
+use ln_world::{Element, Handle, ViewOptions, World};
+use palette::Srgba;
+
+use crate::{
+    lnwin::Lnwindow,
+    measures::{Fract, Position, PositionFract, Rectangle, Size},
+    render::{
+        camera::{Camera, CameraDescriptor, CameraPositionChanged, CameraUtils, MainCamera},
+        rounded::{RoundedRect, RoundedRectDescriptor},
+    },
+    stroke::{StrokeChanged, StrokeLayer, StrokeView},
+    tools::{
+        collider::ToolCollider,
+        pointer::{PointerHit, PointerHitStatus},
+        timer::{Timer, TimerHit},
+    },
+    widgets::WidgetRectangle,
+};
+
+const MINIMAP_ORDER: isize = 900;
+const MINIMAP_SIZE: Size = Size::new(240, 160);
+const MINIMAP_MARGIN: i32 = 20;
+
+/// An overview of the painted area in the bottom right corner, with the view of the main
+/// camera as a rectangle to drag around. Clicking elsewhere flies the main camera there.
+///
+/// The overview streams the upper mipmaps only, so it never loads full resolution chunks.
+/// Should be inserted into the UI camera.
+pub struct Minimap {
+    screen: Rectangle,
+
+    /// Painted area, reloaded now and then while painting.
+    content: Option<Rectangle>,
+    content_outdated: bool,
+
+    /// World space area shown in the minimap, kept while dragging.
+    frame: Rectangle,
+    /// Minimap pixels per world unit.
+    scale: f64,
+    /// The view of the main camera, in the UI camera.
+    indicator_rect: Rectangle,
+    /// Offset from the pointer to the center of the main camera while dragging.
+    dragging: Option<PositionFract>,
+
+    camera: Handle<Camera>,
+    overview: Option<Handle<StrokeView>>,
+    background: Handle<RoundedRect>,
+    indicator: Handle<RoundedRect>,
+    collider: Handle<ToolCollider>,
+}
+
+impl Minimap {
+    /// Needs the [`StrokeLayer`] visible from here.
+    pub fn new(world: &World) -> Self {
+        let size = world.single_fetch::<Camera>().unwrap().size;
+        let screen = Rectangle::new_half(Position::ZERO, Size::new(size.w / 2, size.h / 2));
+
+        // a camera of its own, next to the others of the window
+        let window = (world.single::<Lnwindow>()).map_or(world.here(), |x| x.untyped());
+        let camera = world.enter(window, || {
+            world.build(CameraDescriptor {
+                size: MINIMAP_SIZE,
+                ..Default::default()
+            })
+        });
+
+        let background = world.build(RoundedRectDescriptor {
+            color: Srgba::new(0.96, 0.96, 0.96, 0.9),
+            shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.2),
+            shadow_blur: 20.0,
+            vertex_extend: 40,
+            order: MINIMAP_ORDER,
+            ..Default::default()
+        });
+
+        let indicator = world.build(RoundedRectDescriptor {
+            color: Srgba::new(0.3, 0.5, 0.9, 0.25),
+            shadow_color: Srgba::new(0.0, 0.0, 0.0, 0.0),
+            order: MINIMAP_ORDER + 2,
+            ..Default::default()
+        });
+
+        let collider = world.insert(ToolCollider {
+            rect: Rectangle::default(),
+            order: MINIMAP_ORDER,
+            enabled: true,
+        });
+
+        Minimap {
+            screen,
+            content: Minimap::load_content(world),
+            content_outdated: false,
+            frame: Rectangle::default(),
+            scale: 1.0,
+            indicator_rect: Rectangle::default(),
+            dragging: None,
+            camera,
+            overview: None,
+            background,
+            indicator,
+            collider,
+        }
+    }
+
+    fn load_content(world: &World) -> Option<Rectangle> {
+        let stroke = world.single_fetch::<StrokeLayer>().unwrap();
+        stroke.content_bounds()
+    }
+
+    fn request_layout(world: &World, this: Handle<Self>) {
+        world.queue(move |world| {
+            if let Ok(mut minimap) = world.fetch_mut(this) {
+                minimap.layout(world);
+            }
+        });
+    }
+
+    fn layout(&mut self, world: &World) {
+        let main = world.single_fetch::<MainCamera>().unwrap();
+        let view = world.fetch(main.0).unwrap().world_view_rect();
+
+        let rect = self.rect();
+        let (width, height) = (MINIMAP_SIZE.w as f64, MINIMAP_SIZE.h as f64);
+
+        // the frame would move under the pointer otherwise
+        if self.dragging.is_none() {
+            let frame = self.content.map_or(view, |content| content.grow(view));
+            self.scale = f64::min(
+                width / frame.width().max(1) as f64,
+                height / frame.height().max(1) as f64,
+            ) * 0.9;
+            self.frame = frame;
+
+            let center = rect_center(frame);
+            let mut camera = world.fetch_mut(self.camera).unwrap();
+            world.queue_trigger(
+                self.camera,
+                CameraPositionChanged {
+                    from: camera.center,
+                    here: center,
+                },
+            );
+            camera.center = center;
+            camera.zoom = Fract::from_f64(self.scale.log2());
+            // the camera follows the window by itself
+            camera.size = MINIMAP_SIZE;
+        }
+
+        if let Some(overview) = self.overview
+            && let Ok(mut overview) = world.fetch_mut(overview)
+        {
+            overview.viewport = Some([
+                (rect.left() - self.screen.left()) as f32,
+                (self.screen.up() - rect.up()) as f32,
+                width as f32,
+                height as f32,
+            ]);
+        }
+
+        let left_down = self.world_to_minimap(view.left_down());
+        let right_up = self.world_to_minimap(view.right_up());
+        self.indicator_rect = Rectangle::new(left_down.x, left_down.y, right_up.x, right_up.y)
+            .intersect(rect)
+            .unwrap_or(Rectangle::new_half(rect.left_down(), Size::splat(0)));
+
+        world.queue_trigger(self.background, WidgetRectangle(rect));
+        world.queue_trigger(self.indicator, WidgetRectangle(self.indicator_rect));
+        world.queue_trigger(self.collider, WidgetRectangle(rect));
+    }
+
+    /// Where the minimap is, in the UI camera.
+    fn rect(&self) -> Rectangle {
+        let right = self.screen.right() - MINIMAP_MARGIN;
+        let down = self.screen.down() + MINIMAP_MARGIN;
+        Rectangle::new(
+            right - MINIMAP_SIZE.w as i32,
+            down,
+            right,
+            down + MINIMAP_SIZE.h as i32,
+        )
+    }
+
+    fn world_to_minimap(&self, point: Position) -> Position {
+        world_to_minimap(self.frame, self.rect(), self.scale, point)
+    }
+
+    fn minimap_to_world(&self, point: PositionFract) -> PositionFract {
+        minimap_to_world(self.frame, self.rect(), self.scale, point)
+    }
+
+    fn attach_pointer(&mut self, world: &World, this: Handle<Self>) {
+        world.observer(self.collider, move |event: &PointerHit, world| {
+            let mut minimap = world.fetch_mut(this).unwrap();
+            let target = minimap.minimap_to_world(event.position);
+
+            let inside = event.position.floor().within(minimap.indicator_rect);
+
+            let main = world.single_fetch::<MainCamera>().unwrap();
+            world.enter(main.0, || {
+                let mut camera_utils = world.single_fetch_mut::<CameraUtils>().unwrap();
+                let camera = world.single_fetch::<Camera>().unwrap();
+                let (center, zoom) = (camera.center, camera.zoom);
+                drop(camera);
+
+                match event.status {
+                    PointerHitStatus::Press if inside => {
+                        minimap.dragging = Some(center - target);
+                    }
+                    PointerHitStatus::Press => {
+                        camera_utils.fly_to(world, target, zoom);
+                    }
+                    PointerHitStatus::Moving => {
+                        if let Some(offset) = minimap.dragging {
+                            camera_utils.move_to(world, target + offset);
+                        }
+                    }
+                    PointerHitStatus::Release => {
+                        minimap.dragging = None;
+                        Minimap::request_layout(world, this);
+                    }
+                }
+            });
+        });
+    }
+}
+
+impl Element for Minimap {
+    fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        world.dependency(self.camera, this);
+        world.dependency(self.background, this);
+        world.dependency(self.indicator, this);
+        world.dependency(self.collider, this);
+
+        let Ok(lnwindow) = world.single::<Lnwindow>() else {
+            return;
+        };
+        let stroke = world.single::<StrokeLayer>().unwrap();
+        world.enter(self.camera, || {
+            world.option(ViewOptions {
+                refs: vec![lnwindow.untyped(), stroke.untyped()],
+            });
+            world.queue(move |world| {
+                let overview = world.insert(StrokeView::overview(world, MINIMAP_ORDER + 1));
+                world.fetch_mut(this).unwrap().overview = Some(overview);
+                Minimap::request_layout(world, this);
+            });
+        });
+
+        let ob = world.observer(lnwindow, move |&WidgetRectangle(rect), world| {
+            world.fetch_mut(this).unwrap().screen = rect;
+            Minimap::request_layout(world, this);
+        });
+        world.dependency(ob, this);
+
+        let main = world.single_fetch::<MainCamera>().unwrap();
+        let ob = world.observer(main.0, move |_: &CameraPositionChanged, world| {
+            Minimap::request_layout(world, this);
+        });
+        world.dependency(ob, this);
+
+        let ob = world.observer(stroke, move |StrokeChanged, world| {
+            world.fetch_mut(this).unwrap().content_outdated = true;
+        });
+        world.dependency(ob, this);
+
+        let timer = world.insert(Timer::new(Duration::from_secs(1)));
+        world.observer(timer, move |TimerHit, world| {
+            let mut minimap = world.fetch_mut(this).unwrap();
+            if minimap.content_outdated {
+                minimap.content_outdated = false;
+                minimap.content = Minimap::load_content(world);
+                Minimap::request_layout(world, this);
+            }
+        });
+        world.dependency(timer, this);
+
+        self.attach_pointer(world, this);
+    }
+}
+
+/// Where `point` of the `frame` in the world is shown on the minimap at `rect`.
+fn world_to_minimap(frame: Rectangle, rect: Rectangle, scale: f64, point: Position) -> Position {
+    let point = PositionFract::new(Fract::new(point.x, 0), Fract::new(point.y, 0));
+    let delta = point - rect_center(frame);
+    let minimap = rect_center(rect);
+    let x = minimap.x.into_f64() + delta.x.into_f64() * scale;
+    let y = minimap.y.into_f64() + delta.y.into_f64() * scale;
+    Position::new(x.round() as i32, y.round() as i32)
+}
+
+/// The point of the world shown at `point` of the minimap, see [`world_to_minimap`].
+fn minimap_to_world(
+    frame: Rectangle,
+    rect: Rectangle,
+    scale: f64,
+    point: PositionFract,
+) -> PositionFract {
+    let center = rect_center(frame);
+    let minimap = rect_center(rect);
+    center + (point - minimap) * Fract::from_f64(scale.recip())
+}
+
+/// Counted from the origin, which stays right for a rectangle over the wrap seam.
+fn rect_center(rect: Rectangle) -> PositionFract {
+    PositionFract::new(
+        Fract::new(rect.left(), 0) + Fract::from_f64(rect.width() as f64 / 2.0),
+        Fract::new(rect.down(), 0) + Fract::from_f64(rect.height() as f64 / 2.0),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let rect = Rectangle::new(100, -300, 340, -140);
+        let frames = [
+            Rectangle::new(-5000, -2000, 7000, 3000),
+            Rectangle::new(10, 10, 50, 30),
+            // over the wrap seam
+            Rectangle {
+                origin: Position::new(i32::MAX - 1000, i32::MIN + 10),
+                extend: Size::new(3000, 800),
+            },
+        ];
+
+        for frame in frames {
+            let scale = f64::min(
+                rect.width() as f64 / frame.width() as f64,
+                rect.height() as f64 / frame.height() as f64,
+            );
+
+            // the corners of the frame are within the minimap
+            for corner in [frame.left_down(), frame.right_up()] {
+                let point = world_to_minimap(frame, rect, scale, corner);
+                let inflated = Rectangle::new_half(
+                    rect_center(rect).round(),
+                    Size::new(rect.width() / 2 + 1, rect.height() / 2 + 1),
+                );
+                assert!(point.within(inflated), "{corner} of {frame} at {point}");
+            }
+
+            for (dx, dy) in [(0, 0), (3, 7), (-40, 25), (120, -80)] {
+                let on_minimap = rect_center(rect).round() + Position::new(dx, dy);
+                let point =
+                    PositionFract::new(Fract::new(on_minimap.x, 0), Fract::new(on_minimap.y, 0));
+                let world = minimap_to_world(frame, rect, scale, point);
+                let back = world_to_minimap(frame, rect, scale, world.round());
+                // off by the world unit it is rounded to at most
+                let error = (scale / 2.0).ceil() as i32 + 1;
+                assert!(
+                    (back.x - on_minimap.x).abs() <= error
+                        && (back.y - on_minimap.y).abs() <= error,
+                    "{on_minimap} through {frame} back at {back}",
+                );
+            }
+        }
+    }
+}
@@ -5,6 +5,7 @@ use crate::{
     layout::transform::{Transform, TransformValue},
     measures::Rectangle,
     stroke::StrokeLayer,
+    theme::{Attach, ThemeManager},
     widgets::{
         WidgetAnimatedRectangle, WidgetClick, WidgetEnabled, WidgetHsla, WidgetRectangle,
         button::{Button, ButtonDrag, ButtonDragStatus},
@@ -33,6 +34,9 @@ impl ColorPicker {
             order: 0,
             ..Default::default()
         });
+        if let Ok(theme) = world.single::<ThemeManager>() {
+            world.queue_trigger(theme, Attach::here(world, main_panel));
+        }
 
         world.insert(Transform {
             value: TransformValue::scale(0.7, 0.7),
@@ -4,22 +4,41 @@ struct Camera {
     center_fract: vec2u,
     zoom: i32,
     zoom_fract: u32,
+    rotation: vec2f,
+    flip: f32,
 }
 
 @group(0) @binding(0) var<uniform> camera: Camera;
 
+// flip, then rotate counter-clockwise
+fn camera_rotate(camera_space: vec2f) -> vec2f {
+    let flipped = vec2f(camera_space.x * camera.flip, camera_space.y);
+    return vec2f(
+        flipped.x * camera.rotation.x - flipped.y * camera.rotation.y,
+        flipped.x * camera.rotation.y + flipped.y * camera.rotation.x,
+    );
+}
+
+fn camera_unrotate(screen_space: vec2f) -> vec2f {
+    let rotated = vec2f(
+        screen_space.x * camera.rotation.x + screen_space.y * camera.rotation.y,
+        screen_space.y * camera.rotation.x - screen_space.x * camera.rotation.y,
+    );
+    return vec2f(rotated.x * camera.flip, rotated.y);
+}
+
 fn world_to_clip(world_space: vec2i) -> vec2f {
     let camera_space = world_space - camera.center;
     let camera_scale = pow(2.0, f32(camera.zoom) + f32(camera.zoom_fract) * 0x1p-32);
-    let clip_space = (vec2f(camera_space) - vec2f(camera.center_fract) * vec2f(0x1p-32))
-        / vec2f(camera.size) * camera_scale * 2.0;
+    let clip_space = camera_rotate((vec2f(camera_space) - vec2f(camera.center_fract) * vec2f(0x1p-32))
+        * camera_scale) / vec2f(camera.size) * 2.0;
 
     return clip_space;
 }
 
 fn clip_to_world(clip_space: vec2f) -> vec2f {
     let camera_scale = pow(2.0, f32(camera.zoom) + f32(camera.zoom_fract) * 0x1p-32);
-    let camera_space = clip_space * vec2f(camera.size) / camera_scale / 2.0 + vec2f(camera.center_fract) * vec2f(0x1p-32);
+    let camera_space = camera_unrotate(clip_space * vec2f(camera.size) / 2.0) / camera_scale + vec2f(camera.center_fract) * vec2f(0x1p-32);
     let world_space = camera_space + vec2f(camera.center);
 
     return world_space;
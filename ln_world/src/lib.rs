@@ -1,8 +1,11 @@
+pub mod trace;
+
 use std::{
     any::{Any, TypeId, type_name},
     cell::{Cell, RefCell},
     fmt,
     hash::{Hash, Hasher},
+    io,
     marker::PhantomData,
     ops::{Deref, DerefMut},
     sync::mpsc::{Receiver, Sender, channel},
@@ -11,6 +14,8 @@ use std::{
 use hashbrown::{HashMap, HashSet};
 use smallvec::SmallVec;
 
+use crate::trace::Recorder;
+
 // Definition //
 
 /// A shared form of objects in the [`World`].
@@ -128,6 +133,11 @@ pub struct World {
 
     location: Cell<Handle>,
     dependencies: RefCell<Dependencies>,
+    events: RefCell<HashMap<TypeId, EventInspector>>,
+    recorder: RefCell<Option<Recorder>>,
+    batches: RefCell<HashMap<TypeId, Box<dyn Any>>>,
+    broadcasts: RefCell<HashMap<TypeId, HashSet<Handle>>>,
+    stopped: Cell<bool>,
 
     queue: Receiver<WorldCommand>,
     commander: Sender<WorldCommand>,
@@ -138,6 +148,8 @@ struct Storage<T: Element>(HashMap<Handle, T>);
 trait StorageGeneral: Any {
     fn remove(&mut self, handle: Handle);
     fn when_remove(&mut self, world: &World, handle: Handle);
+    fn type_name(&self) -> &'static str;
+    fn len(&self) -> usize;
 }
 
 impl<T: Element> StorageGeneral for Storage<T> {
@@ -145,6 +157,14 @@ impl<T: Element> StorageGeneral for Storage<T> {
         self.0.remove(&handle);
     }
 
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
     fn when_remove(&mut self, world: &World, handle: Handle) {
         let elem = self.0.get_mut(&handle).unwrap();
         T::when_remove(elem, world, handle.cast());
@@ -207,6 +227,11 @@ impl World {
             removed: RefCell::default(),
             location: Cell::new(INITELEM),
             dependencies: RefCell::default(),
+            events: RefCell::default(),
+            recorder: RefCell::default(),
+            batches: RefCell::default(),
+            broadcasts: RefCell::default(),
+            stopped: Cell::new(false),
             queue,
             commander,
         }
@@ -228,9 +253,12 @@ impl World {
         // write immediate record
         let mut inserted = self.inserted.borrow_mut();
         inserted.insert(handle.cast());
+        drop(inserted);
 
-        // delay execution
         let location = self.location.get();
+        self.trace(|rec| rec.element(false, handle.cast(), location, type_name::<T>()));
+
+        // delay execution
         self.queue(move |world| {
             // get type table ready
             let storage = world.storages.entry(TypeId::of::<T>()).or_insert_with(|| {
@@ -266,6 +294,11 @@ impl World {
         // SAFETY: we have checked the mutability
         let type_id = *self.typetable.get(&handle.cast()).unwrap();
         let storage = self.storages.get(&type_id).unwrap().as_ref() as *const _;
+
+        let view = *self.viewtable.get(&handle.cast()).unwrap();
+        let name = self.storages.get(&type_id).unwrap().type_name();
+        self.trace(|rec| rec.element(true, handle.cast(), view, name));
+
         let storage = storage as *mut dyn StorageGeneral;
         unsafe { (*storage).when_remove(self, handle.cast()) };
 
@@ -411,6 +444,10 @@ impl World {
     pub fn flush(&mut self) {
         let origin = self.location.get();
         let buf = self.queue.try_iter().collect::<Vec<_>>();
+        if !buf.is_empty() {
+            self.trace(|rec| rec.flush(buf.len()));
+        }
+
         for cmd in buf {
             self.location.set(cmd.location);
             (cmd.action)(self);
@@ -662,6 +699,15 @@ impl World {
         &self,
         target: Handle<impl ?Sized + 'static>,
         action: impl FnMut(&E, &World) + 'static,
+    ) -> Handle {
+        self.observer_with(target, ObserverOptions::default(), action)
+    }
+
+    pub fn observer_with<E: 'static>(
+        &self,
+        target: Handle<impl ?Sized + 'static>,
+        options: ObserverOptions,
+        action: impl FnMut(&E, &World) + 'static,
     ) -> Handle {
         let here = self.location.get();
         let handle = self.enter(INITELEM, || {
@@ -669,6 +715,7 @@ impl World {
                 action: Box::new(action),
                 view: here,
                 target: target.cast(),
+                options,
             })
         });
 
@@ -677,14 +724,167 @@ impl World {
 
     /// Will immediately triggered and acquire mutable access to `target`.
     pub fn trigger<E: 'static>(&self, target: Handle<impl ?Sized + 'static>, event: &E) -> usize {
+        self.trace(|rec| rec.trigger(false, target.cast(), self.here(), event));
+
+        let stopped = self.stopped.replace(false);
+        let cnt = self.notify(target.cast(), event, Phase::Target);
+        self.stopped.set(stopped);
+        cnt
+    }
+
+    pub fn queue_trigger<E: 'static>(&self, target: Handle<impl ?Sized + 'static>, event: E) {
+        self.trace(|rec| rec.trigger(true, target.cast(), self.here(), &event));
+        self.queue(move |world| {
+            world.trigger(target, &event);
+        });
+    }
+
+    /// Trigger along the dependency parents of `target`. Parents listening with `capture`
+    /// are notified first, farthest first, then the target, then parents listening with
+    /// `bubble`, nearest first. Each element is visited once.
+    pub fn dispatch<E: 'static>(&self, target: Handle<impl ?Sized + 'static>, event: &E) -> usize {
+        self.trace(|rec| rec.trigger(false, target.cast(), self.here(), event));
+
+        // breadth-first, so the nearest parents come first
+        let mut path = vec![target.cast()];
+        let dependencies = self.dependencies.borrow();
+        let mut idx = 0;
+        while let Some(&handle) = path.get(idx) {
+            for &parent in dependencies
+                .0
+                .get(&handle)
+                .into_iter()
+                .flat_map(|x| &x.parents)
+            {
+                if !path.contains(&parent) {
+                    path.push(parent);
+                }
+            }
+            idx += 1;
+        }
+        drop(dependencies);
+
+        let stopped = self.stopped.replace(false);
+        let mut cnt = 0;
+        let phases = (path[1..].iter().rev().map(|&x| (x, Phase::Capture)))
+            .chain([(path[0], Phase::Target)])
+            .chain(path[1..].iter().map(|&x| (x, Phase::Bubble)));
+        for (handle, phase) in phases {
+            cnt += self.notify(handle, event, phase);
+            if self.stopped.get() {
+                break;
+            }
+        }
+
+        self.stopped.set(stopped);
+        cnt
+    }
+
+    /// Trigger on `target` and then all of its dependency children, depth-first. Stopping
+    /// the propagation skips the children of the element being notified.
+    ///
+    /// Broadcasting the same type of event from an observer joins the running broadcast,
+    /// so every element is notified at most once, whoever passes the event on.
+    pub fn broadcast<E: 'static>(&self, target: Handle<impl ?Sized + 'static>, event: &E) -> usize {
+        self.trace(|rec| rec.trigger(false, target.cast(), self.here(), event));
+
+        let ty = TypeId::of::<E>();
+        let outermost = !self.broadcasts.borrow().contains_key(&ty);
+        if outermost {
+            self.broadcasts.borrow_mut().insert(ty, HashSet::new());
+        }
+
+        let stopped = self.stopped.get();
+        let mut stack = vec![target.cast()];
+        let mut cnt = 0;
+        while let Some(handle) = stack.pop() {
+            let mut broadcasts = self.broadcasts.borrow_mut();
+            if !broadcasts.get_mut(&ty).unwrap().insert(handle) {
+                continue;
+            }
+            drop(broadcasts);
+
+            self.stopped.set(false);
+            cnt += self.notify(handle, event, Phase::Target);
+            if self.stopped.get() {
+                continue;
+            }
+
+            let dependencies = self.dependencies.borrow();
+            if let Some(deps) = dependencies.0.get(&handle) {
+                stack.extend(deps.children.iter().rev());
+            }
+        }
+
+        if outermost {
+            self.broadcasts.borrow_mut().remove(&ty);
+        }
+        self.stopped.set(stopped);
+        cnt
+    }
+
+    /// Stop the current [`World::dispatch`] or [`World::broadcast`] after the observers of
+    /// the current element are finished.
+    pub fn stop_propagation(&self) {
+        self.stopped.set(true);
+    }
+
+    /// Deliver at the next `flush` together with other batched events of the same type.
+    /// A batched event that has not been delivered is replaced by a newer one for the same
+    /// target.
+    pub fn batch_trigger<E: 'static>(&self, target: Handle<impl ?Sized + 'static>, event: E) {
+        self.trace(|rec| rec.trigger(true, target.cast(), self.here(), &event));
+
+        let mut batches = self.batches.borrow_mut();
+        let batch = (batches.entry(TypeId::of::<E>()))
+            .or_insert_with(|| Box::new(Batch::<E>::default()))
+            .downcast_mut::<Batch<E>>()
+            .unwrap();
+
+        if let Some(&slot) = batch.slots.get(&target.cast()) {
+            batch.events[slot].1 = event;
+            return;
+        }
+
+        batch.slots.insert(target.cast(), batch.events.len());
+        batch.events.push((target.cast(), event));
+        if batch.events.len() == 1 {
+            drop(batches);
+            self.queue(|world| {
+                let mut batches = world.batches.borrow_mut();
+                let batch = (batches.get_mut(&TypeId::of::<E>()))
+                    .and_then(|x| x.downcast_mut::<Batch<E>>())
+                    .map(std::mem::take)
+                    .unwrap_or_default();
+                drop(batches);
+
+                for (target, event) in batch.events {
+                    world.trigger(target, &event);
+                }
+            });
+        }
+    }
+
+    fn notify<E: 'static>(&self, target: Handle, event: &E, phase: Phase) -> usize {
         let mut cnt = 0;
         self.enter(INITELEM, || {
             if let Ok(observers) = self.single_fetch::<Observers<E>>()
-                && let Some(observers) = observers.members.get(&target.cast())
+                && let Some(observers) = observers.members.get(&target)
             {
-                for mut observer in observers.iter().filter_map(|x| self.fetch_mut(*x).ok()) {
-                    self.enter(observer.view, || (observer.action)(event, self));
-                    cnt += 1;
+                let observers = observers
+                    .iter()
+                    .filter_map(|(_, x)| self.fetch_mut(*x).ok());
+                for mut observer in observers {
+                    let accepted = match phase {
+                        Phase::Capture => observer.options.capture,
+                        Phase::Target => true,
+                        Phase::Bubble => observer.options.bubble,
+                    };
+
+                    if accepted {
+                        self.enter(observer.view, || (observer.action)(event, self));
+                        cnt += 1;
+                    }
                 }
             }
         });
@@ -692,12 +892,6 @@ impl World {
         cnt
     }
 
-    pub fn queue_trigger<E: 'static>(&self, target: Handle<impl ?Sized + 'static>, event: E) {
-        self.queue(move |world| {
-            world.trigger(target, &event);
-        });
-    }
-
     // dependency //
 
     /// Declare a dependency relationship. When the `other` Element is removed, this element
@@ -720,6 +914,105 @@ impl World {
         let child_deps = dependencies.0.entry(child).or_default();
         child_deps.parents.push(parent);
     }
+
+    // inspection //
+
+    /// Take a snapshot of the world for debugging. Only flushed elements are included.
+    /// Elements that are mutably borrowed right now are reported but never read.
+    pub fn inspect(&self) -> Inspection {
+        let mut inspection = Inspection::default();
+
+        // borrowed goes first, the rest of the inspection may fetch
+        let occupied = self.occupied.borrow();
+        for (&handle, &cnt) in occupied.iter().filter(|(_, cnt)| **cnt != 0) {
+            inspection.borrowed.push(InspectBorrow {
+                handle,
+                type_name: self.type_name_of(handle).unwrap_or("?"),
+                state: match cnt {
+                    ..0 => BorrowState::Mutable,
+                    _ => BorrowState::Shared(cnt as usize),
+                },
+            });
+        }
+        drop(occupied);
+
+        for storage in self.storages.values() {
+            let len = storage.len();
+            if len != 0 {
+                inspection.types.push((storage.type_name(), len));
+            }
+        }
+
+        let dependencies = self.dependencies.borrow();
+        let mut views = HashMap::<Handle, usize>::new();
+        for (&handle, &view) in self.viewtable.iter() {
+            *views.entry(view).or_default() += 1;
+
+            let deps = dependencies.0.get(&handle);
+            inspection.elements.push(InspectElement {
+                handle,
+                type_name: self.type_name_of(handle).unwrap_or("?"),
+                view,
+                parents: deps.map(|x| x.parents.to_vec()).unwrap_or_default(),
+                children: deps.map(|x| x.children.to_vec()).unwrap_or_default(),
+            });
+        }
+        drop(dependencies);
+
+        for (handle, elements) in views {
+            inspection.views.push(InspectView {
+                handle,
+                type_name: match handle == INITELEM {
+                    true => "root",
+                    false => self.type_name_of(handle).unwrap_or("?"),
+                },
+                refs: (self.options.get(&handle))
+                    .map(|opt| opt.refs.clone())
+                    .unwrap_or_default(),
+                elements,
+            });
+        }
+
+        let events = self.events.borrow().values().copied().collect::<Vec<_>>();
+        for inspector in events {
+            inspector(self, &mut inspection.observers);
+        }
+
+        inspection.elements.sort_by_key(|x| x.handle.0);
+        inspection.views.sort_by_key(|x| x.handle.0);
+        inspection.types.sort();
+        inspection.observers.sort_by_key(|x| (x.target.0, x.event));
+        inspection.borrowed.sort_by_key(|x| x.handle.0);
+        inspection
+    }
+
+    // recording //
+
+    /// Start writing insertions, removals, triggers and flushes into `recorder`. The
+    /// previous recorder is returned.
+    pub fn record(&self, recorder: Recorder) -> Option<Recorder> {
+        self.recorder.borrow_mut().replace(recorder)
+    }
+
+    pub fn stop_recording(&self) -> Option<Recorder> {
+        self.recorder.borrow_mut().take()
+    }
+
+    fn trace(&self, f: impl FnOnce(&mut Recorder) -> io::Result<()>) {
+        let mut recorder = self.recorder.borrow_mut();
+        if let Some(rec) = recorder.as_mut()
+            && let Err(err) = f(rec)
+        {
+            log::error!("world recording stopped: {err}");
+            recorder.take();
+        }
+    }
+
+    /// Type name of a flushed element.
+    pub fn type_name_of(&self, handle: Handle<impl ?Sized>) -> Option<&'static str> {
+        let type_id = self.typetable.get(&handle.cast())?;
+        Some(self.storages.get(type_id)?.type_name())
+    }
 }
 
 impl Default for World {
@@ -849,9 +1142,32 @@ impl Commander {
 
 // Observer & Trigger //
 
+type EventInspector = fn(&World, &mut Vec<InspectObservers>);
+
+/// How an observer takes part in triggering, see [`World::observer_with`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ObserverOptions {
+    /// Observers with higher priority are notified first.
+    pub priority: isize,
+    /// Also notified when a dependency child is dispatched, before the child.
+    pub capture: bool,
+    /// Also notified when a dependency child is dispatched, after the child.
+    pub bubble: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Phase {
+    Capture,
+    Target,
+    Bubble,
+}
+
+/// Sorted by priority, descending.
+type ObserverList<E> = SmallVec<[(isize, Handle<Observer<E>>); 1]>;
+
 #[derive(Default)]
 struct Observers<E> {
-    members: HashMap<Handle, SmallVec<[Handle<Observer<E>>; 1]>>,
+    members: HashMap<Handle, ObserverList<E>>,
 }
 
 #[expect(clippy::type_complexity)]
@@ -859,16 +1175,50 @@ struct Observer<E> {
     action: Box<dyn FnMut(&E, &World)>,
     view: Handle,
     target: Handle,
+    options: ObserverOptions,
 }
 
 impl<E: 'static> Element for Observers<E> {}
 
+impl<E: 'static> Observers<E> {
+    fn inspect(world: &World, buf: &mut Vec<InspectObservers>) {
+        world.enter(INITELEM, || {
+            let Ok(handle) = world.single::<Self>() else {
+                return;
+            };
+
+            // being registered right now
+            let occupied = world.occupied.borrow();
+            if occupied.get(&handle.cast()).is_some_and(|cnt| *cnt < 0) {
+                return;
+            }
+            drop(occupied);
+
+            let observers = world.fetch(handle).unwrap();
+            for (&target, members) in &observers.members {
+                buf.push(InspectObservers {
+                    target,
+                    event: type_name::<E>(),
+                    count: members.len(),
+                });
+            }
+        });
+    }
+}
+
 impl<E: 'static> Element for Observer<E> {
     fn when_insert(&mut self, world: &World, this: Handle<Self>) {
+        let priority = self.options.priority;
+        let register = |members: &mut ObserverList<E>| {
+            let idx = (members.iter())
+                .position(|(x, _)| *x < priority)
+                .unwrap_or(members.len());
+            members.insert(idx, (priority, this));
+        };
+
         match world.single_fetch_mut::<Observers<E>>() {
             Ok(mut observers) => {
-                let observers = observers.members.entry(self.target).or_default();
-                observers.push(this);
+                register(observers.members.entry(self.target).or_default());
             }
             Err(WorldError::SingletonNoSuch(_)) => {
                 let mut observers = Observers::<E> {
@@ -876,9 +1226,11 @@ impl<E: 'static> Element for Observer<E> {
                 };
 
                 log::trace!("register events: {}", type_name::<E>());
+                let mut events = world.events.borrow_mut();
+                events.insert(TypeId::of::<E>(), Observers::<E>::inspect);
+                drop(events);
 
-                let observer = observers.members.entry(self.target).or_default();
-                observer.push(this);
+                register(observers.members.entry(self.target).or_default());
                 world.insert(observers);
             }
             Err(err) => {
@@ -895,12 +1247,169 @@ impl<E: 'static> Element for Observer<E> {
 #[derive(Default)]
 struct Dependencies(HashMap<Handle, Dependency>);
 
+/// Events of one type waiting for [`World::batch_trigger`] to deliver them, in the order
+/// their targets were first sent one.
+struct Batch<E> {
+    events: Vec<(Handle, E)>,
+    slots: HashMap<Handle, usize>,
+}
+
+impl<E> Default for Batch<E> {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            slots: HashMap::new(),
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 struct Dependency {
     parents: SmallVec<[Handle; 1]>,
     children: SmallVec<[Handle; 4]>,
 }
 
+// Inspection //
+
+/// A snapshot taken by [`World::inspect`], printable as a plain text report.
+#[derive(Debug, Clone, Default)]
+pub struct Inspection {
+    /// All elements, sorted by handle.
+    pub elements: Vec<InspectElement>,
+    /// Views holding at least one element.
+    pub views: Vec<InspectView>,
+    /// Element types and the size of their storages, see [`World::size_hint`].
+    pub types: Vec<(&'static str, usize)>,
+    /// Observer counts grouped by target and event type.
+    pub observers: Vec<InspectObservers>,
+    /// Elements with living [`Ref`] or [`RefMut`].
+    pub borrowed: Vec<InspectBorrow>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InspectElement {
+    pub handle: Handle,
+    pub type_name: &'static str,
+    pub view: Handle,
+    pub parents: Vec<Handle>,
+    pub children: Vec<Handle>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InspectView {
+    pub handle: Handle,
+    pub type_name: &'static str,
+    pub refs: Vec<Handle>,
+    pub elements: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct InspectObservers {
+    pub target: Handle,
+    pub event: &'static str,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct InspectBorrow {
+    pub handle: Handle,
+    pub type_name: &'static str,
+    pub state: BorrowState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorrowState {
+    Shared(usize),
+    Mutable,
+}
+
+impl Inspection {
+    pub fn element(&self, handle: Handle<impl ?Sized>) -> Option<&InspectElement> {
+        let idx = (self.elements)
+            .binary_search_by_key(&handle.0, |x| x.handle.0)
+            .ok()?;
+        Some(&self.elements[idx])
+    }
+
+    /// Elements not depending on anything, where the dependency trees start.
+    pub fn roots(&self) -> impl Iterator<Item = &InspectElement> {
+        self.elements.iter().filter(|x| x.parents.is_empty())
+    }
+
+    fn fmt_tree(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        elem: &InspectElement,
+        depth: usize,
+        visited: &mut HashSet<Handle>,
+    ) -> fmt::Result {
+        let indent = depth * 2 + 2;
+        if !visited.insert(elem.handle) {
+            return writeln!(f, "{:indent$}{} (loop)", "", elem.handle);
+        }
+
+        writeln!(f, "{:indent$}{} {}", "", elem.handle, elem.type_name)?;
+        for &child in &elem.children {
+            if let Some(child) = self.element(child) {
+                self.fmt_tree(f, child, depth + 1, visited)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "views:")?;
+        for view in &self.views {
+            write!(
+                f,
+                "  {} {} ({})",
+                view.handle, view.type_name, view.elements
+            )?;
+            for refs in &view.refs {
+                write!(f, " -> {refs}")?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "types:")?;
+        for (name, cnt) in &self.types {
+            writeln!(f, "  {name} x{cnt}")?;
+        }
+
+        writeln!(f, "dependencies:")?;
+        let mut visited = HashSet::new();
+        for root in self.roots().filter(|x| !x.children.is_empty()) {
+            self.fmt_tree(f, root, 0, &mut visited)?;
+        }
+
+        writeln!(f, "observers:")?;
+        for observers in &self.observers {
+            writeln!(
+                f,
+                "  {} {} x{}",
+                observers.target, observers.event, observers.count
+            )?;
+        }
+
+        writeln!(f, "borrowed:")?;
+        for borrow in &self.borrowed {
+            match borrow.state {
+                BorrowState::Shared(cnt) => {
+                    writeln!(f, "  {} {} &x{cnt}", borrow.handle, borrow.type_name)?
+                }
+                BorrowState::Mutable => {
+                    writeln!(f, "  {} {} &mut", borrow.handle, borrow.type_name)?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1167,4 +1676,262 @@ mod test {
         assert_eq!(&*world.fetch(left).unwrap(), &TestInserter(11));
         assert_eq!(&*world.fetch(right).unwrap(), &TestGoodInserter(12));
     }
+
+    #[test]
+    fn inspection() {
+        let mut world = World::default();
+
+        let view = world.insert(TestBlanker);
+        let parent = world.enter(view, || world.insert(TestInserter(1)));
+        let child = world.enter(view, || world.insert(TestGoodInserter(2)));
+
+        world.flush();
+
+        world.dependency(child, parent);
+        world.observer(parent, |TestEvent(_), _| {});
+        world.observer(parent, |TestEvent(_), _| {});
+
+        world.flush();
+
+        let borrow = world.enter(view, || world.fetch_mut(child).unwrap());
+        let inspection = world.inspect();
+        drop(borrow);
+
+        let elem = inspection.element(child).unwrap();
+        assert_eq!(elem.type_name, type_name::<TestGoodInserter>());
+        assert_eq!(elem.view, view.untyped());
+        assert_eq!(elem.parents, vec![parent.untyped()]);
+
+        let in_view = inspection.views.iter().find(|x| x.handle == view.untyped());
+        assert_eq!(in_view.unwrap().elements, 2);
+
+        let types = &inspection.types;
+        assert!(types.contains(&(type_name::<TestInserter>(), 1)));
+
+        let observers = &inspection.observers[..];
+        assert_eq!(observers.len(), 1);
+        assert_eq!(observers[0].target, parent.untyped());
+        assert_eq!(observers[0].event, type_name::<TestEvent>());
+        assert_eq!(observers[0].count, 2);
+
+        let borrowed = &inspection.borrowed[..];
+        assert_eq!(borrowed.len(), 1);
+        assert_eq!(borrowed[0].state, BorrowState::Mutable);
+
+        assert!(inspection.to_string().contains(type_name::<TestEvent>()));
+    }
+
+    #[test]
+    fn observer_priority() {
+        let mut world = World::default();
+
+        let target = world.insert(TestInserter(0));
+        world.flush();
+
+        for (priority, digit) in [(0, 1), (10, 2), (0, 3), (-5, 4)] {
+            let options = ObserverOptions {
+                priority,
+                ..Default::default()
+            };
+            world.observer_with(target, options, move |TestEvent(_), world| {
+                let mut this = world.fetch_mut(target).unwrap();
+                this.0 = this.0 * 10 + digit;
+            });
+        }
+
+        world.flush();
+        world.trigger(target, &TestEvent(0));
+
+        assert_eq!(world.fetch(target).unwrap().0, 2134);
+    }
+
+    #[test]
+    fn propagation() {
+        let mut world = World::default();
+
+        let log = world.insert(TestInserter(0));
+        let root = world.insert(TestBlanker);
+        let parent = world.insert(TestBlanker);
+        let child = world.insert(TestBlanker);
+
+        world.flush();
+
+        world.dependency(parent, root);
+        world.dependency(child, parent);
+
+        let capture = ObserverOptions {
+            capture: true,
+            ..Default::default()
+        };
+        let bubble = ObserverOptions {
+            bubble: true,
+            ..Default::default()
+        };
+
+        let record = move |digit| {
+            move |&TestEvent(stop): &TestEvent, world: &World| {
+                let mut log = world.fetch_mut(log).unwrap();
+                log.0 = log.0 * 10 + digit;
+                if stop == digit {
+                    world.stop_propagation();
+                }
+            }
+        };
+
+        world.observer_with(root, capture, record(1));
+        world.observer_with(parent, capture, record(2));
+        world.observer(child, record(3));
+        world.observer_with(parent, bubble, record(4));
+        world.observer_with(root, bubble, record(5));
+        world.observer(root, record(6));
+
+        world.flush();
+
+        world.dispatch(child, &TestEvent(0));
+        assert_eq!(world.fetch(log).unwrap().0, 12345);
+
+        world.fetch_mut(log).unwrap().0 = 0;
+        world.dispatch(child, &TestEvent(3));
+        assert_eq!(world.fetch(log).unwrap().0, 123);
+
+        world.fetch_mut(log).unwrap().0 = 0;
+        world.broadcast(root, &TestEvent(2));
+        assert_eq!(world.fetch(log).unwrap().0, 15624);
+    }
+
+    #[test]
+    fn nested_broadcast() {
+        let mut world = World::default();
+
+        let log = world.insert(TestInserter(0));
+        let root = world.insert(TestBlanker);
+        let side = world.insert(TestBlanker);
+        let child = world.insert(TestBlanker);
+
+        world.flush();
+
+        // reached both below the root and from the side
+        world.dependency(child, root);
+        world.dependency(child, side);
+
+        let record = move |digit| {
+            move |_: &TestEvent, world: &World| {
+                let mut log = world.fetch_mut(log).unwrap();
+                log.0 = log.0 * 10 + digit;
+            }
+        };
+
+        world.observer(root, record(1));
+        world.observer(root, move |event: &TestEvent, world| {
+            world.broadcast(side, event);
+        });
+        world.observer(side, record(2));
+        world.observer(child, record(3));
+
+        world.flush();
+
+        world.broadcast(root, &TestEvent(0));
+        assert_eq!(world.fetch(log).unwrap().0, 123);
+
+        // a later broadcast starts over
+        world.fetch_mut(log).unwrap().0 = 0;
+        world.broadcast(root, &TestEvent(0));
+        assert_eq!(world.fetch(log).unwrap().0, 123);
+    }
+
+    #[test]
+    fn batch_trigger() {
+        let mut world = World::default();
+
+        let target = world.insert(TestInserter(0));
+        let other = world.insert(TestInserter(0));
+        world.flush();
+
+        for handle in [target, other] {
+            world.observer(handle, move |&TestEvent(i), world| {
+                let mut this = world.fetch_mut(handle).unwrap();
+                this.0 = this.0 * 10 + i;
+            });
+        }
+
+        world.flush();
+
+        world.batch_trigger(target, TestEvent(1));
+        world.batch_trigger(other, TestEvent(2));
+        world.batch_trigger(target, TestEvent(3));
+        world.flush();
+
+        assert_eq!(world.fetch(target).unwrap().0, 3);
+        assert_eq!(world.fetch(other).unwrap().0, 2);
+
+        world.batch_trigger(target, TestEvent(4));
+        world.flush();
+
+        assert_eq!(world.fetch(target).unwrap().0, 34);
+    }
+
+    #[derive(Clone, Default)]
+    struct TestWriter(std::rc::Rc<RefCell<Vec<u8>>>);
+    impl io::Write for TestWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recording() {
+        use crate::trace::{TraceReader, TraceRecord};
+
+        let mut world = World::default();
+        let writer = TestWriter::default();
+        let recorder = Recorder::new(writer.clone()).unwrap();
+        world.record(recorder.with_payload::<TestEvent>());
+
+        let target = world.insert(TestInserter(1));
+        world.flush();
+
+        world.trigger(target, &TestEvent(7));
+        world.queue_trigger(target, TestEvent(8));
+        world.remove(target).unwrap();
+        world.flush();
+
+        world.stop_recording().unwrap().finish().unwrap();
+
+        let buf = writer.0.borrow();
+        let records = TraceReader::new(&buf[..])
+            .unwrap()
+            .map(|x| x.unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(records.len(), 7);
+        assert!(matches!(
+            &records[0],
+            TraceRecord::Insert { handle, element, .. }
+            if *handle == target.0 && element == type_name::<TestInserter>()
+        ));
+        assert!(matches!(records[1], TraceRecord::Flush { commands: 1, .. }));
+        assert!(matches!(
+            &records[2],
+            TraceRecord::Trigger { target: t, payload: Some(payload), .. }
+            if *t == target.0 && payload == "TestEvent(7)"
+        ));
+        assert!(matches!(&records[3], TraceRecord::QueueTrigger { .. }));
+        assert!(matches!(&records[4], TraceRecord::Remove { .. }));
+        assert!(matches!(records[5], TraceRecord::Flush { commands: 2, .. }));
+        assert!(matches!(
+            &records[6],
+            TraceRecord::Trigger { payload: Some(payload), .. }
+            if payload == "TestEvent(8)"
+        ));
+
+        // a name claiming far more than it has
+        let mut broken = trace::TRACE_MAGIC.to_vec();
+        broken.extend([0, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+        let err = TraceReader::new(&broken[..]).unwrap().next().unwrap();
+        assert_eq!(err.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
 }
@@ -0,0 +1,339 @@
+//! Opt-in recording of world activities, see [`World::record`].
+//!
+//! The file starts with [`TRACE_MAGIC`], followed by records. Every record is a tag byte
+//! and LEB128 integers. Type names are only written once, later records refer to them
+//! by index.
+
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use hashbrown::HashMap;
+
+use crate::Handle;
+#[cfg(doc)]
+use crate::World;
+
+pub const TRACE_MAGIC: &[u8; 5] = b"LNWT\x01";
+
+const TAG_NAME: u8 = 0;
+const TAG_TRIGGER: u8 = 1;
+const TAG_QUEUE_TRIGGER: u8 = 2;
+const TAG_INSERT: u8 = 3;
+const TAG_REMOVE: u8 = 4;
+const TAG_FLUSH: u8 = 5;
+
+/// Longest name or payload read back, a longer one is taken for a broken trace.
+const MAX_STRING: u64 = 1 << 20;
+
+type PayloadFormatter = fn(&dyn Any) -> String;
+
+/// Writes world activities into a trace file.
+pub struct Recorder {
+    writer: Box<dyn Write>,
+    start: Instant,
+    names: HashMap<&'static str, u64>,
+    payloads: HashMap<TypeId, PayloadFormatter>,
+}
+
+/// A record read back by [`TraceReader`]. Handles are kept as raw indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceRecord {
+    Trigger {
+        time: Duration,
+        target: usize,
+        view: usize,
+        event: String,
+        payload: Option<String>,
+    },
+    QueueTrigger {
+        time: Duration,
+        target: usize,
+        view: usize,
+        event: String,
+        payload: Option<String>,
+    },
+    Insert {
+        time: Duration,
+        handle: usize,
+        view: usize,
+        element: String,
+    },
+    Remove {
+        time: Duration,
+        handle: usize,
+        view: usize,
+        element: String,
+    },
+    Flush {
+        time: Duration,
+        commands: usize,
+    },
+}
+
+pub struct TraceReader<R: Read> {
+    reader: R,
+    names: Vec<String>,
+}
+
+impl Recorder {
+    pub fn new(mut writer: impl Write + 'static) -> io::Result<Recorder> {
+        writer.write_all(TRACE_MAGIC)?;
+        Ok(Recorder {
+            writer: Box::new(writer),
+            start: Instant::now(),
+            names: HashMap::new(),
+            payloads: HashMap::new(),
+        })
+    }
+
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Recorder> {
+        Recorder::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Also write the `Debug` form of events typed `E`.
+    pub fn with_payload<E: fmt::Debug + 'static>(mut self) -> Self {
+        self.payloads.insert(TypeId::of::<E>(), |event| {
+            format!("{:?}", event.downcast_ref::<E>().unwrap())
+        });
+        self
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub(crate) fn trigger<E: 'static>(
+        &mut self,
+        queued: bool,
+        target: Handle,
+        view: Handle,
+        event: &E,
+    ) -> io::Result<()> {
+        let name = self.name(std::any::type_name::<E>())?;
+        let payload = (self.payloads)
+            .get(&TypeId::of::<E>())
+            .map(|format| format(event));
+
+        self.head(match queued {
+            true => TAG_QUEUE_TRIGGER,
+            false => TAG_TRIGGER,
+        })?;
+        self.varint(target.0 as u64)?;
+        self.varint(view.0 as u64)?;
+        self.varint(name)?;
+        match payload {
+            Some(payload) => {
+                self.varint(payload.len() as u64 + 1)?;
+                self.writer.write_all(payload.as_bytes())
+            }
+            None => self.varint(0),
+        }
+    }
+
+    pub(crate) fn element(
+        &mut self,
+        removed: bool,
+        handle: Handle,
+        view: Handle,
+        element: &'static str,
+    ) -> io::Result<()> {
+        let name = self.name(element)?;
+        self.head(match removed {
+            true => TAG_REMOVE,
+            false => TAG_INSERT,
+        })?;
+        self.varint(handle.0 as u64)?;
+        self.varint(view.0 as u64)?;
+        self.varint(name)
+    }
+
+    pub(crate) fn flush(&mut self, commands: usize) -> io::Result<()> {
+        self.head(TAG_FLUSH)?;
+        self.varint(commands as u64)
+    }
+
+    fn name(&mut self, name: &'static str) -> io::Result<u64> {
+        if let Some(&idx) = self.names.get(name) {
+            return Ok(idx);
+        }
+
+        let idx = self.names.len() as u64;
+        self.names.insert(name, idx);
+        self.writer.write_all(&[TAG_NAME])?;
+        self.varint(name.len() as u64)?;
+        self.writer.write_all(name.as_bytes())?;
+        Ok(idx)
+    }
+
+    fn head(&mut self, tag: u8) -> io::Result<()> {
+        self.writer.write_all(&[tag])?;
+        self.varint(self.start.elapsed().as_micros() as u64)
+    }
+
+    fn varint(&mut self, mut value: u64) -> io::Result<()> {
+        let mut buf = [0; 10];
+        let mut len = 0;
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                buf[len] = byte;
+                len += 1;
+                break;
+            }
+            buf[len] = byte | 0x80;
+            len += 1;
+        }
+        self.writer.write_all(&buf[..len])
+    }
+}
+
+impl<R: Read> TraceReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; TRACE_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != TRACE_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a world trace",
+            ));
+        }
+
+        Ok(TraceReader {
+            reader,
+            names: Vec::new(),
+        })
+    }
+
+    fn next_record(&mut self) -> io::Result<Option<TraceRecord>> {
+        loop {
+            let mut tag = [0];
+            if self.reader.read(&mut tag)? == 0 {
+                return Ok(None);
+            }
+
+            if tag[0] == TAG_NAME {
+                let name = self.string()?;
+                self.names.push(name);
+                continue;
+            }
+
+            let time = Duration::from_micros(self.varint()?);
+            return Ok(Some(match tag[0] {
+                TAG_TRIGGER | TAG_QUEUE_TRIGGER => {
+                    let target = self.varint()? as usize;
+                    let view = self.varint()? as usize;
+                    let event = self.name()?;
+                    let payload = match self.varint()? {
+                        0 => None,
+                        len => Some(self.bytes(len - 1)?),
+                    };
+
+                    match tag[0] {
+                        TAG_TRIGGER => TraceRecord::Trigger {
+                            time,
+                            target,
+                            view,
+                            event,
+                            payload,
+                        },
+                        _ => TraceRecord::QueueTrigger {
+                            time,
+                            target,
+                            view,
+                            event,
+                            payload,
+                        },
+                    }
+                }
+                TAG_INSERT | TAG_REMOVE => {
+                    let handle = self.varint()? as usize;
+                    let view = self.varint()? as usize;
+                    let element = self.name()?;
+
+                    match tag[0] {
+                        TAG_INSERT => TraceRecord::Insert {
+                            time,
+                            handle,
+                            view,
+                            element,
+                        },
+                        _ => TraceRecord::Remove {
+                            time,
+                            handle,
+                            view,
+                            element,
+                        },
+                    }
+                }
+                TAG_FLUSH => TraceRecord::Flush {
+                    time,
+                    commands: self.varint()? as usize,
+                },
+                tag => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unknown trace tag {tag}"),
+                    ));
+                }
+            }));
+        }
+    }
+
+    fn name(&mut self) -> io::Result<String> {
+        let idx = self.varint()? as usize;
+        (self.names.get(idx).cloned()).ok_or(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("undefined trace name {idx}"),
+        ))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.varint()?;
+        self.bytes(len)
+    }
+
+    fn bytes(&mut self, len: u64) -> io::Result<String> {
+        if len > MAX_STRING {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("trace string of {len} bytes"),
+            ));
+        }
+
+        let mut buf = vec![0; len as usize];
+        self.reader.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let mut byte = [0];
+            self.reader.read_exact(&mut byte)?;
+            value |= ((byte[0] & 0x7F) as u64) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "varint overflow",
+        ))
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = io::Result<TraceRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}